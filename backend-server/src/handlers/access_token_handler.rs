@@ -0,0 +1,111 @@
+use crate::handlers::auth_handler::extract_session_user_id;
+use crate::models::access_token::CreateAccessTokenRequest;
+use crate::repositories::access_token_repo::AccessTokenRepository;
+use crate::repositories::member_repo::MemberRepository;
+use crate::repositories::workspace_repo::WorkspaceRepository;
+use crate::services::access_token_service::AccessTokenService;
+use crate::state::SharedState;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+fn unauthorized() -> axum::response::Response {
+    (
+        axum::http::StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({ "error": "Unauthorized" })),
+    )
+        .into_response()
+}
+
+pub async fn list_access_tokens_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => return unauthorized(),
+    };
+    let token_repo = AccessTokenRepository::new(&state.db);
+
+    match AccessTokenService::list(&token_repo, &user_id).await {
+        Ok(tokens) => {
+            Json(serde_json::json!({ "success": true, "tokens": tokens })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// Creates a token. The plaintext `token` is only ever returned by this call.
+pub async fn create_access_token_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<CreateAccessTokenRequest>,
+) -> axum::response::Response {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => return unauthorized(),
+    };
+    let token_repo = AccessTokenRepository::new(&state.db);
+    let workspace_repo = WorkspaceRepository::new(&state.db);
+    let member_repo = MemberRepository::new(&state.db);
+
+    match AccessTokenService::create(
+        &token_repo,
+        &workspace_repo,
+        &member_repo,
+        &user_id,
+        payload,
+    )
+    .await
+    {
+        Ok((token, plaintext)) => Json(serde_json::json!({
+            "success": true,
+            "token": plaintext,
+            "access_token": AccessTokenService::to_json(&token),
+        }))
+        .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn revoke_access_token_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(token_id): Path<String>,
+) -> axum::response::Response {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => return unauthorized(),
+    };
+
+    match AccessTokenRepository::new(&state.db)
+        .revoke(&user_id, &token_id)
+        .await
+    {
+        Ok(true) => Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(false) => (
+            axum::http::StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Token not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+        )
+            .into_response(),
+    }
+}
@@ -1,14 +1,17 @@
+use crate::handlers::member_handler::verify_workspace_access;
 use crate::models::data::Attachment;
+use crate::models::member::WorkspaceRole;
 use crate::repositories::data_repo::DataRepository;
 use crate::state::AppState;
 use aws_sdk_s3::primitives::ByteStream;
 use axum::{
     body::Body,
     extract::{Multipart, Path, State},
-    http::{header, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
+use axum_extra::extract::cookie::CookieJar;
 use chrono::Utc;
 use mongodb::bson::{doc, oid::ObjectId};
 use std::sync::Arc;
@@ -17,17 +20,18 @@ use uuid::Uuid;
 pub async fn upload_attachment(
     State(state): State<Arc<AppState>>,
     Path((ws_id_str, task_id_str)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
     mut multipart: Multipart,
-) -> impl IntoResponse {
-    let ws_id = match ObjectId::parse_str(&ws_id_str) {
-        Ok(id) => id,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({"error": "Invalid workspace ID"})),
-            )
-        }
-    };
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id_str, WorkspaceRole::Editor)
+            .await
+        {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let ws_id = access.workspace_id;
     let task_id = match ObjectId::parse_str(&task_id_str) {
         Ok(id) => id,
         Err(_) => {
@@ -35,6 +39,7 @@ pub async fn upload_attachment(
                 StatusCode::BAD_REQUEST,
                 Json(serde_json::json!({"error": "Invalid task ID"})),
             )
+                .into_response()
         }
     };
 
@@ -46,6 +51,7 @@ pub async fn upload_attachment(
                 StatusCode::SERVICE_UNAVAILABLE,
                 Json(serde_json::json!({"error": "Storage is disabled"})),
             )
+                .into_response()
         }
     };
 
@@ -57,12 +63,14 @@ pub async fn upload_attachment(
                 StatusCode::NOT_FOUND,
                 Json(serde_json::json!({"error": "Task not found"})),
             )
+                .into_response()
         }
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": e.to_string()})),
             )
+                .into_response()
         }
     };
 
@@ -70,7 +78,8 @@ pub async fn upload_attachment(
         return (
             StatusCode::FORBIDDEN,
             Json(serde_json::json!({"error": "Task does not belong to this workspace"})),
-        );
+        )
+            .into_response();
     }
 
     let mut attached_files = Vec::new();
@@ -112,7 +121,7 @@ pub async fn upload_attachment(
                     mime_type,
                     size: file_bytes.len() as i64,
                     uploaded_at: Utc::now().to_rfc3339(),
-                    uploader_id: "system".to_string(), // In a real app we'd extract user_id from token
+                    uploader_id: access.user_id.to_hex(),
                 };
                 attached_files.push(attachment);
             }
@@ -121,7 +130,8 @@ pub async fn upload_attachment(
                 return (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     Json(serde_json::json!({"error": "Storage error"})),
-                );
+                )
+                    .into_response();
             }
         }
     }
@@ -130,7 +140,8 @@ pub async fn upload_attachment(
         return (
             StatusCode::BAD_REQUEST,
             Json(serde_json::json!({"error": "No valid files received"})),
-        );
+        )
+            .into_response();
     }
 
     let mut current_attachments = task.attachments.unwrap_or_default();
@@ -143,7 +154,8 @@ pub async fn upload_attachment(
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": "Failed to update db"})),
-            );
+            )
+                .into_response();
         }
     }
 
@@ -151,12 +163,22 @@ pub async fn upload_attachment(
         StatusCode::OK,
         Json(serde_json::json!({"success": true, "attachments": attached_files})),
     )
+        .into_response()
 }
 
 pub async fn download_attachment(
     State(state): State<Arc<AppState>>,
     Path(file_key): Path<String>, // format: {ws_id}/{task_id}/{file_uuid}
+    headers: HeaderMap,
+    jar: CookieJar,
 ) -> impl IntoResponse {
+    let ws_id_str = file_key.split('/').next().unwrap_or_default();
+    if let Err(resp) =
+        verify_workspace_access(&state, &headers, &jar, ws_id_str, WorkspaceRole::Viewer).await
+    {
+        return resp;
+    }
+
     let storage = state.storage_snapshot().await;
     let client = match &storage.client {
         Some(c) => c,
@@ -200,16 +222,17 @@ pub async fn download_attachment(
 pub async fn delete_attachment(
     State(state): State<Arc<AppState>>,
     Path((ws_id_str, task_id_str, attachment_id)): Path<(String, String, String)>,
-) -> impl IntoResponse {
-    let ws_id = match ObjectId::parse_str(&ws_id_str) {
-        Ok(id) => id,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({"error": "Invalid workspace ID"})),
-            )
-        }
-    };
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id_str, WorkspaceRole::Editor)
+            .await
+        {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let ws_id = access.workspace_id;
     let task_id = match ObjectId::parse_str(&task_id_str) {
         Ok(id) => id,
         Err(_) => {
@@ -217,6 +240,7 @@ pub async fn delete_attachment(
                 StatusCode::BAD_REQUEST,
                 Json(serde_json::json!({"error": "Invalid task ID"})),
             )
+                .into_response()
         }
     };
 
@@ -228,6 +252,7 @@ pub async fn delete_attachment(
                 StatusCode::NOT_FOUND,
                 Json(serde_json::json!({"error": "Task not found"})),
             )
+                .into_response()
         }
     };
 
@@ -235,7 +260,8 @@ pub async fn delete_attachment(
         return (
             StatusCode::FORBIDDEN,
             Json(serde_json::json!({"error": "Forbidden"})),
-        );
+        )
+            .into_response();
     }
 
     let mut attachments = task.attachments.unwrap_or_default();
@@ -267,11 +293,12 @@ pub async fn delete_attachment(
             let _ = data_repo.update_task(&task_id, &ws_id, updates).await;
         }
 
-        (StatusCode::OK, Json(serde_json::json!({"success": true})))
+        (StatusCode::OK, Json(serde_json::json!({"success": true}))).into_response()
     } else {
         (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({"error": "Attachment not found"})),
         )
+            .into_response()
     }
 }
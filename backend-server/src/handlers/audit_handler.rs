@@ -0,0 +1,106 @@
+use crate::handlers::auth_handler::extract_session_claims;
+use crate::models::audit::AuditEventQuery;
+use crate::repositories::audit_repo::AuditRepository;
+use crate::state::SharedState;
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+/// Accepts any RFC3339 offset and rewrites it the way `created_at` is stored, so string
+/// comparison in MongoDB orders correctly.
+fn normalize_timestamp(value: Option<String>) -> Result<Option<String>, String> {
+    match value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        None => Ok(None),
+        Some(v) => chrono::DateTime::parse_from_rfc3339(v)
+            .map(|at| Some(at.with_timezone(&chrono::Utc).to_rfc3339()))
+            .map_err(|_| format!("Invalid timestamp: {}", v)),
+    }
+}
+
+/// Admin: `GET /api/admin/audit-events?action=&actor_id=&target_type=&target_id=&from=&to=&page=&limit=`
+pub async fn list_audit_events_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Query(mut query): Query<AuditEventQuery>,
+) -> axum::response::Response {
+    match extract_session_claims(&state, &headers, &jar).await {
+        Some(claims) if claims.role == "admin" => {}
+        Some(_) => {
+            return (
+                axum::http::StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Admin access required" })),
+            )
+                .into_response()
+        }
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    }
+
+    match (
+        normalize_timestamp(query.from.take()),
+        normalize_timestamp(query.to.take()),
+    ) {
+        (Ok(from), Ok(to)) => {
+            query.from = from;
+            query.to = to;
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        }
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let page = query.page.unwrap_or(1).max(1);
+
+    match AuditRepository::new(&state.db)
+        .find_page(&query, (page - 1) * limit, limit as i64)
+        .await
+    {
+        Ok((events, total)) => {
+            let events: Vec<_> = events
+                .into_iter()
+                .map(|e| {
+                    serde_json::json!({
+                        "id": e.event_id,
+                        "action": e.action,
+                        "actor_id": e.actor_id,
+                        "actor_email": e.actor_email,
+                        "target_type": e.target_type,
+                        "target_id": e.target_id,
+                        "before": e.before,
+                        "after": e.after,
+                        "ip": e.ip,
+                        "created_at": e.created_at,
+                    })
+                })
+                .collect();
+            Json(serde_json::json!({
+                "success": true,
+                "events": events,
+                "total": total,
+                "page": page,
+                "limit": limit,
+                "pages": total.div_ceil(limit).max(1),
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+        )
+            .into_response(),
+    }
+}
@@ -1,29 +1,68 @@
 use axum::{
     extract::{Json, Query, State},
     response::IntoResponse,
+    Extension,
 };
-use axum_extra::extract::cookie::CookieJar;
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use mongodb::bson::oid::ObjectId;
 
+use crate::models::access_token::ACCESS_TOKEN_PREFIX;
 use crate::models::auth::{
-    AuthRequest, Claims, InviteRequest, SetupPasswordPayload, SetupPasswordRequest,
+    AuthRequest, Claims, ForgotPasswordRequest, InviteRequest, LoginTwoFactorRequest,
+    ResetPasswordPayload, SetupPasswordPayload, SetupPasswordRequest, TokenQuery,
     UpdateProfileRequest,
 };
+use crate::models::member::WorkspaceRole;
+use crate::models::session::ClientInfo;
+use crate::models::user::User;
+use crate::repositories::access_token_repo::AccessTokenRepository;
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::auth_token_repo::AuthTokenRepository;
+use crate::repositories::login_attempt_repo::LoginAttemptRepository;
+use crate::repositories::member_repo::MemberRepository;
 use crate::repositories::profile_repo::ProfileRepository;
+use crate::repositories::security_repo::SecurityRepository;
+use crate::repositories::session_repo::SessionRepository;
 use crate::repositories::user_repo::UserRepository;
 use crate::repositories::workspace_repo::WorkspaceRepository;
+use crate::services::access_token_service::AccessTokenService;
+use crate::services::account_service::AccountService;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
 use crate::services::auth_service::AuthService;
+use crate::services::client_ip_service::ClientIp;
+use crate::services::login_throttle_service::{LoginBlocked, LoginThrottleService};
+use crate::services::member_service::MemberService;
+use crate::services::session_service::SessionService;
+use crate::services::two_factor_service::TwoFactorService;
 use crate::state::SharedState;
 
+const REFRESH_COOKIE: &str = "_khun_ph_refresh";
+/// The refresh cookie is only ever sent to the endpoint that uses it
+const REFRESH_COOKIE_PATH: &str = "/api/auth/refresh";
+
+/// The rotating refresh token lives only in this cookie, out of reach of page scripts.
+/// An empty token with no lifetime clears it.
+fn refresh_cookie(token: String, max_age_seconds: i64) -> Cookie<'static> {
+    Cookie::build((REFRESH_COOKIE, token))
+        .path(REFRESH_COOKIE_PATH)
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Strict)
+        .max_age(time::Duration::seconds(max_age_seconds))
+        .build()
+}
+
 pub async fn invite_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
     Json(payload): Json<InviteRequest>,
 ) -> axum::response::Response {
     let user_repo = UserRepository::new(&state.db);
     let profile_repo = ProfileRepository::new(&state.db);
+    let mut actor_id = None;
 
     // If no users exist yet, allow the first invite only with a valid setup token
     let user_count = match user_repo.count().await {
@@ -44,7 +83,7 @@ pub async fn invite_handler(
             ).into_response();
         }
     } else {
-        let claims = match extract_claims(&headers, &jar, &state.jwt_secret) {
+        let claims = match extract_claims(&state, &headers, &jar).await {
             Some(c) => c,
             None => {
                 return (
@@ -62,10 +101,34 @@ pub async fn invite_handler(
             )
                 .into_response();
         }
+        actor_id = Some(claims.sub);
     }
 
-    match AuthService::invite(&user_repo, &profile_repo, payload).await {
+    let email = payload.email.clone();
+    match AuthService::invite(&user_repo, &profile_repo, &state.auth_settings, payload).await {
         Ok(token) => {
+            let invited_id = match user_repo.find_by_email(&email).await {
+                Ok(Some(user)) => user.id,
+                _ => None,
+            };
+            let after = match &invited_id {
+                Some(id) => AuditService::user_snapshot(&user_repo, &profile_repo, id).await,
+                None => None,
+            };
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &user_repo,
+                &AuditActor::new(actor_id.as_deref(), &client_ip),
+                AuditRecord {
+                    action: "user.invite",
+                    target_type: "user",
+                    target_id: invited_id.map(|id| id.to_hex()),
+                    before: None,
+                    after,
+                },
+            )
+            .await;
+
             if token == "ACTV" {
                 axum::Json(serde_json::json!({
                     "success": true,
@@ -74,6 +137,17 @@ pub async fn invite_handler(
                 }))
                 .into_response()
             } else {
+                // The link is also returned below, so a mail failure shouldn't fail the invite
+                if let Err(e) = AccountService::send_setup_email(
+                    &state.mailer,
+                    &state.auth_settings,
+                    &email,
+                    &token,
+                )
+                .await
+                {
+                    tracing::warn!("Failed to send setup email to {}: {}", email, e);
+                }
                 axum::Json(serde_json::json!({
                     "success": true,
                     "message": "Invitation created successfully",
@@ -126,57 +200,444 @@ pub async fn get_setup_info_handler(
     }
 }
 
-pub async fn login_handler(
+/// Admin-only: issue a new setup link for a user who never finished setup.
+pub async fn resend_setup_handler(
     State(state): State<SharedState>,
-    Json(payload): Json<AuthRequest>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    axum::extract::Path(id): axum::extract::Path<String>,
 ) -> axum::response::Response {
+    let claims = match extract_claims(&state, &headers, &jar).await {
+        Some(c) => c,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+
+    if claims.role != "admin" {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            axum::Json(serde_json::json!({ "error": "Admin access required" })),
+        )
+            .into_response();
+    }
+
     let user_repo = UserRepository::new(&state.db);
-    let profile_repo = ProfileRepository::new(&state.db);
 
-    match AuthService::login(&user_repo, &profile_repo, payload, &state.jwt_secret).await {
-        Ok((id, user_id, email, role, token, profile)) => axum::Json(serde_json::json!({
+    match AccountService::resend_setup(&user_repo, &state.mailer, &state.auth_settings, &id).await {
+        Ok(token) => axum::Json(serde_json::json!({
             "success": true,
-            "id": id,
-            "user_id": user_id,
-            "email": email,
-            "role": role,
-            "token": token,
-            "profile": profile
+            "setup_link": format!("/setup-password?token={}", token)
         }))
         .into_response(),
         Err(e) => (
-            axum::http::StatusCode::UNAUTHORIZED,
+            axum::http::StatusCode::BAD_REQUEST,
             axum::Json(serde_json::json!({ "error": e })),
         )
             .into_response(),
     }
 }
 
-pub async fn logout_handler() -> axum::response::Response {
-    axum::Json(serde_json::json!({ "success": true })).into_response()
+pub async fn forgot_password_handler(
+    State(state): State<SharedState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> axum::response::Response {
+    let user_repo = UserRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
+
+    if let Err(e) = AccountService::forgot_password(
+        &user_repo,
+        &token_repo,
+        &state.mailer,
+        &state.auth_settings,
+        &payload.email,
+    )
+    .await
+    {
+        tracing::error!("Failed to process password reset request: {}", e);
+    }
+
+    // Same answer whether or not the account exists
+    axum::Json(serde_json::json!({
+        "success": true,
+        "message": "If an account exists for this email, a reset link has been sent"
+    }))
+    .into_response()
 }
 
-pub async fn me_handler(
+pub async fn get_reset_info_handler(
     State(state): State<SharedState>,
-    jar: CookieJar,
+    axum::extract::Query(payload): axum::extract::Query<TokenQuery>,
+) -> axum::response::Response {
+    let user_repo = UserRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
+
+    match AccountService::get_reset_info(&user_repo, &token_repo, &payload.token).await {
+        Ok(email) => {
+            axum::Json(serde_json::json!({ "success": true, "email": email })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn reset_password_handler(
+    State(state): State<SharedState>,
+    Json(payload): Json<ResetPasswordPayload>,
+) -> axum::response::Response {
+    let user_repo = UserRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
+    let session_repo = SessionRepository::new(&state.db);
+
+    match AccountService::reset_password(
+        &user_repo,
+        &token_repo,
+        &session_repo,
+        &payload.token,
+        &payload.password,
+    )
+    .await
+    {
+        Ok(email) => {
+            axum::Json(serde_json::json!({ "success": true, "email": email })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn verify_email_handler(
+    State(state): State<SharedState>,
+    Json(payload): Json<TokenQuery>,
+) -> axum::response::Response {
+    let user_repo = UserRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
+
+    match AccountService::confirm_email_change(&user_repo, &token_repo, &payload.token).await {
+        Ok(email) => {
+            axum::Json(serde_json::json!({ "success": true, "email": email })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn login_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     headers: axum::http::HeaderMap,
+    Json(payload): Json<AuthRequest>,
 ) -> axum::response::Response {
-    let auth_header = headers.get("Authorization").and_then(|h| h.to_str().ok());
+    let user_repo = UserRepository::new(&state.db);
+    let attempt_repo = LoginAttemptRepository::new(&state.db);
+    let email = payload.email.clone();
 
-    // First try to get token from Authorization: Bearer <token>
-    let token = if let Some(header) = auth_header {
-        if header.starts_with("Bearer ") {
-            Some(header[7..].to_string())
-        } else {
-            None
+    if let Err(blocked) =
+        LoginThrottleService::check(&attempt_repo, &email, client_ip.0.as_deref()).await
+    {
+        return login_blocked_response(&blocked);
+    }
+
+    let user = match AuthService::authenticate(&user_repo, payload).await {
+        Ok(u) => u,
+        Err(e) => {
+            LoginThrottleService::record_failure(
+                &attempt_repo,
+                &state.login_throttle,
+                &email,
+                client_ip.0.as_deref(),
+            )
+            .await;
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": e })),
+            )
+                .into_response();
         }
+    };
+
+    finish_login(&state, client_info(&headers, &client_ip), user).await
+}
+
+fn login_blocked_response(blocked: &LoginBlocked) -> axum::response::Response {
+    let message = if blocked.locked {
+        "Too many failed login attempts. Sign-in is temporarily locked"
     } else {
-        // Fallback to cookie
-        jar.get("_khun_ph_token").map(|c| c.value().to_string())
+        "Too many failed login attempts. Please wait before trying again"
+    };
+    (
+        axum::http::StatusCode::TOO_MANY_REQUESTS,
+        [(
+            axum::http::header::RETRY_AFTER,
+            blocked.retry_after_seconds.to_string(),
+        )],
+        axum::Json(serde_json::json!({
+            "error": message,
+            "retry_after": blocked.retry_after_seconds,
+            "locked": blocked.locked,
+        })),
+    )
+        .into_response()
+}
+
+/// Second step of every login once the first factor (password or SSO) has been accepted:
+/// either a 2FA challenge or the session itself.
+pub async fn finish_login(
+    state: &SharedState,
+    client: ClientInfo,
+    mut user: User,
+) -> axum::response::Response {
+    let user_repo = UserRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
+
+    let policy = match SecurityRepository::new(&state.db).get_policy().await {
+        Ok(p) => p,
+        Err(e) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+            )
+                .into_response()
+        }
+    };
+
+    if user.two_factor.enabled || policy.requires_two_factor(&user.role) {
+        return match TwoFactorService::start_login_challenge(&user_repo, &token_repo, &mut user)
+            .await
+        {
+            Ok(challenge) => {
+                let (secret, otpauth_uri) = challenge.setup.unzip();
+                axum::Json(serde_json::json!({
+                    "success": true,
+                    "two_factor_required": user.two_factor.enabled,
+                    "two_factor_setup_required": !user.two_factor.enabled,
+                    "challenge_token": challenge.challenge_token,
+                    "expires_in": challenge.expires_in,
+                    "secret": secret,
+                    "otpauth_uri": otpauth_uri,
+                }))
+                .into_response()
+            }
+            Err(e) => (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": e })),
+            )
+                .into_response(),
+        };
+    }
+
+    issue_login_session(state, client, &user, None).await
+}
+
+pub async fn login_two_factor_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<LoginTwoFactorRequest>,
+) -> axum::response::Response {
+    let user_repo = UserRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
+    let attempt_repo = LoginAttemptRepository::new(&state.db);
+
+    let mut user = match TwoFactorService::login_challenge_user(
+        &user_repo,
+        &token_repo,
+        &payload.challenge_token,
+    )
+    .await
+    {
+        Ok(u) => u,
+        Err(e) => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        }
     };
 
-    let token_str = match token {
-        Some(t) => t,
+    // Wrong codes count against the same account and IP as wrong passwords, so starting a
+    // new challenge with the password doesn't buy more guesses
+    if let Err(blocked) =
+        LoginThrottleService::check(&attempt_repo, &user.email, client_ip.0.as_deref()).await
+    {
+        return login_blocked_response(&blocked);
+    }
+
+    match TwoFactorService::complete_login_challenge(&user_repo, &token_repo, &mut user, &payload)
+        .await
+    {
+        Ok(recovery_codes) => {
+            issue_login_session(
+                &state,
+                client_info(&headers, &client_ip),
+                &user,
+                recovery_codes,
+            )
+            .await
+        }
+        Err(e) => {
+            LoginThrottleService::record_failure(
+                &attempt_repo,
+                &state.login_throttle,
+                &user.email,
+                client_ip.0.as_deref(),
+            )
+            .await;
+            (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn issue_login_session(
+    state: &SharedState,
+    client: ClientInfo,
+    user: &User,
+    recovery_codes: Option<Vec<String>>,
+) -> axum::response::Response {
+    let profile_repo = ProfileRepository::new(&state.db);
+    let session_repo = SessionRepository::new(&state.db);
+
+    match AuthService::start_session(
+        &profile_repo,
+        &session_repo,
+        user,
+        &state.auth_settings,
+        &state.jwt_secret,
+        client,
+    )
+    .await
+    {
+        Ok((tokens, profile)) => {
+            // Only a login that got all the way here clears the failures counted against it
+            LoginThrottleService::record_success(
+                &LoginAttemptRepository::new(&state.db),
+                &user.email,
+            )
+            .await;
+            let mut body = serde_json::json!({
+                "success": true,
+                "id": user.id.map(|id| id.to_hex()),
+                "user_id": user.user_id,
+                "email": user.email,
+                "role": user.role,
+                "token": tokens.access_token,
+                "expires_in": tokens.expires_in,
+                "session_id": tokens.session_id,
+                "profile": profile
+            });
+            // Only present right after enrolling during login; shown to the user once
+            if let Some(codes) = recovery_codes {
+                body["recovery_codes"] = serde_json::json!(codes);
+            }
+            let cookie = refresh_cookie(
+                tokens.refresh_token,
+                state.auth_settings.refresh_token_ttl_seconds,
+            );
+            (CookieJar::new().add(cookie), axum::Json(body)).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// Reads the refresh token from its cookie and sets the rotated one in its place.
+pub async fn refresh_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let refresh_token = match jar.get(REFRESH_COOKIE).map(|c| c.value().to_string()) {
+        Some(t) if !t.is_empty() => t,
+        _ => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Missing refresh token" })),
+            )
+                .into_response()
+        }
+    };
+
+    let user_repo = UserRepository::new(&state.db);
+    let session_repo = SessionRepository::new(&state.db);
+
+    match SessionService::refresh(
+        &session_repo,
+        &user_repo,
+        &refresh_token,
+        &state.auth_settings,
+        &state.jwt_secret,
+    )
+    .await
+    {
+        Ok(tokens) => {
+            let cookie = refresh_cookie(
+                tokens.refresh_token,
+                state.auth_settings.refresh_token_ttl_seconds,
+            );
+            (
+                jar.add(cookie),
+                axum::Json(serde_json::json!({
+                    "success": true,
+                    "token": tokens.access_token,
+                    "expires_in": tokens.expires_in,
+                    "session_id": tokens.session_id,
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::UNAUTHORIZED,
+            jar.add(refresh_cookie(String::new(), 0)),
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn logout_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    // Revoke the current session server-side; the token may already be invalid, which is fine
+    if let Some(sid) = decode_token(&headers, &jar, &state.jwt_secret).and_then(|c| c.sid) {
+        let session_repo = SessionRepository::new(&state.db);
+        let _ = session_repo.revoke(&sid).await;
+    }
+    (
+        jar.add(refresh_cookie(String::new(), 0)),
+        axum::Json(serde_json::json!({ "success": true })),
+    )
+        .into_response()
+}
+
+pub async fn me_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let claims = match extract_claims(&state, &headers, &jar).await {
+        Some(c) => c,
         None => {
             return (
                 axum::http::StatusCode::UNAUTHORIZED,
@@ -186,12 +647,8 @@ pub async fn me_handler(
         }
     };
 
-    let token_data = match decode::<Claims>(
-        &token_str,
-        &DecodingKey::from_secret(state.jwt_secret.as_ref()),
-        &Validation::default(),
-    ) {
-        Ok(c) => c,
+    let user_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(id) => id,
         Err(_) => {
             return (
                 axum::http::StatusCode::UNAUTHORIZED,
@@ -200,8 +657,6 @@ pub async fn me_handler(
                 .into_response()
         }
     };
-
-    let user_id = ObjectId::parse_str(&token_data.claims.sub).unwrap();
     let user_repo = UserRepository::new(&state.db);
     let profile_repo = ProfileRepository::new(&state.db);
 
@@ -212,6 +667,16 @@ pub async fn me_handler(
                 .await
                 .ok()
                 .flatten();
+            let impersonator = match claims
+                .impersonator
+                .as_deref()
+                .and_then(|id| ObjectId::parse_str(id).ok())
+            {
+                Some(admin_id) => user_repo.find_by_id(&admin_id).await.ok().flatten().map(
+                    |admin| serde_json::json!({ "id": admin_id.to_hex(), "email": admin.email }),
+                ),
+                None => None,
+            };
 
             axum::Json(serde_json::json!({
                 "success": true,
@@ -220,6 +685,11 @@ pub async fn me_handler(
                 "email": user.email,
                 "role": user.role,
                 "discord_id": user.discord_id,
+                "two_factor_enabled": user.two_factor.enabled,
+                "sso_linked": !user.external_identities.is_empty(),
+                "has_password": user.password_hash.is_some(),
+                "session_id": claims.sid,
+                "impersonator": impersonator,
                 "profile": profile
             }))
             .into_response()
@@ -237,13 +707,131 @@ pub async fn me_handler(
     }
 }
 
+pub async fn list_sessions_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let claims = match extract_session_claims(&state, &headers, &jar).await {
+        Some(c) => c,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+    let user_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(id) => id,
+        Err(_) => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+
+    let session_repo = SessionRepository::new(&state.db);
+    match SessionService::list_sessions(&session_repo, &user_id, claims.sid.as_deref()).await {
+        Ok(sessions) => {
+            axum::Json(serde_json::json!({ "success": true, "sessions": sessions })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn revoke_session_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    axum::extract::Path(session_id): axum::extract::Path<String>,
+) -> axum::response::Response {
+    let user_id = match extract_session_claims(&state, &headers, &jar)
+        .await
+        .and_then(|c| ObjectId::parse_str(&c.sub).ok())
+    {
+        Some(id) => id,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+
+    let session_repo = SessionRepository::new(&state.db);
+    match session_repo.revoke_for_user(&user_id, &session_id).await {
+        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(false) => (
+            axum::http::StatusCode::NOT_FOUND,
+            axum::Json(serde_json::json!({ "error": "Session not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// Signs out every device except the one making the request.
+pub async fn revoke_other_sessions_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let claims = match extract_session_claims(&state, &headers, &jar).await {
+        Some(c) => c,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+    let user_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(id) => id,
+        Err(_) => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+
+    let session_repo = SessionRepository::new(&state.db);
+    match session_repo
+        .revoke_all_for_user(&user_id, claims.sid.as_deref())
+        .await
+    {
+        Ok(count) => {
+            axum::Json(serde_json::json!({ "success": true, "revoked": count })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
 pub async fn list_users_handler(
     State(state): State<SharedState>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
     Query(query): Query<ListUsersQuery>,
 ) -> axum::response::Response {
-    let claims = match extract_claims(&headers, &jar, &state.jwt_secret) {
+    let claims = match extract_session_claims(&state, &headers, &jar).await {
         Some(c) => c,
         None => {
             return (
@@ -255,7 +843,7 @@ pub async fn list_users_handler(
     };
 
     // Admin can always list users.
-    // Non-admin can list users only if they own the requested workspace.
+    // Non-admin can list users only if they administer the requested workspace.
     if claims.role != "admin" {
         let requester_id = match ObjectId::parse_str(&claims.sub) {
             Ok(id) => id,
@@ -309,11 +897,16 @@ pub async fn list_users_handler(
             }
         };
 
-        if workspace.owner_id != requester_id {
+        let member_repo = MemberRepository::new(&state.db);
+        let role = MemberService::resolve_role(&member_repo, &workspace, &requester_id)
+            .await
+            .ok()
+            .flatten();
+        if !role.is_some_and(|r| r.allows(WorkspaceRole::Admin)) {
             return (
                 axum::http::StatusCode::FORBIDDEN,
                 axum::Json(
-                    serde_json::json!({ "error": "Only admin or workspace owner can list users" }),
+                    serde_json::json!({ "error": "Only admin or workspace admins can list users" }),
                 ),
             )
                 .into_response();
@@ -340,23 +933,166 @@ pub struct ListUsersQuery {
     pub workspace_id: Option<String>,
 }
 
-pub fn extract_user_id(
+pub async fn extract_user_id(
+    state: &SharedState,
     headers: &axum::http::HeaderMap,
     jar: &CookieJar,
-    secret: &str,
 ) -> Option<ObjectId> {
-    let claims = extract_claims(headers, jar, secret)?;
+    let claims = extract_claims(state, headers, jar).await?;
     ObjectId::parse_str(&claims.sub).ok()
 }
 
-pub fn extract_claims(
+/// Validates the access token and checks that its session is still live and the user active.
+/// The returned role is the user's current role, not the one baked into the token.
+/// Personal access tokens (`khp_...`) are accepted too; their claims carry the token scope.
+pub async fn extract_claims(
+    state: &SharedState,
     headers: &axum::http::HeaderMap,
     jar: &CookieJar,
-    secret: &str,
 ) -> Option<Claims> {
+    claims_for_token(state, &raw_token(headers, jar)?).await
+}
+
+/// Subprotocol a browser offers on the WebSocket upgrade to carry its token, since it can't
+/// set `Authorization` there: `new WebSocket(url, ["bearer", token])`.
+pub const WS_AUTH_PROTOCOL: &str = "bearer";
+
+/// `extract_claims` for the `/ws` upgrade. Besides the cookie and `Authorization` header,
+/// accepts the token as the value following `bearer` in `Sec-WebSocket-Protocol`.
+pub async fn extract_ws_claims(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Option<Claims> {
+    claims_for_token(state, &raw_ws_token(headers, jar)?).await
+}
+
+fn raw_ws_token(headers: &axum::http::HeaderMap, jar: &CookieJar) -> Option<String> {
+    raw_token(headers, jar).or_else(|| {
+        let protocols = headers
+            .get(axum::http::header::SEC_WEBSOCKET_PROTOCOL)?
+            .to_str()
+            .ok()?;
+        let mut values = protocols.split(',').map(str::trim);
+        values.find(|v| *v == WS_AUTH_PROTOCOL)?;
+        values.next().map(str::to_string)
+    })
+}
+
+/// The token a WebSocket upgrade or event stream request was made with, kept so the
+/// connection can be re-checked with `recheck_connection_token` while it lasts.
+pub fn connection_token(headers: &axum::http::HeaderMap, jar: &CookieJar) -> Option<String> {
+    raw_ws_token(headers, jar)
+}
+
+/// Re-checks the token a long-lived connection was opened with: its session or personal
+/// access token must still be live and its user active. The access token's own expiry is
+/// ignored, since the connection outlives it; revoking the session is what ends it.
+pub async fn recheck_connection_token(state: &SharedState, raw: &str) -> Option<Claims> {
+    if raw.starts_with(ACCESS_TOKEN_PREFIX) {
+        return claims_for_token(state, raw).await;
+    }
+
+    let mut validation = Validation::default();
+    validation.validate_exp = false;
+    let claims = decode::<Claims>(
+        raw,
+        &DecodingKey::from_secret(state.jwt_secret.as_ref()),
+        &validation,
+    )
+    .ok()?
+    .claims;
+    live_session_claims(state, claims).await
+}
+
+async fn claims_for_token(state: &SharedState, raw: &str) -> Option<Claims> {
+    if raw.starts_with(ACCESS_TOKEN_PREFIX) {
+        return AccessTokenService::authenticate(
+            &AccessTokenRepository::new(&state.db),
+            &UserRepository::new(&state.db),
+            raw,
+        )
+        .await;
+    }
+
+    live_session_claims(state, decode_jwt(raw, &state.jwt_secret)?).await
+}
+
+async fn live_session_claims(state: &SharedState, mut claims: Claims) -> Option<Claims> {
+    let sid = claims.sid.as_deref()?;
+
+    let session_repo = SessionRepository::new(&state.db);
+    let session = session_repo.find_active(sid).await.ok().flatten()?;
+    if session.user_id.to_hex() != claims.sub {
+        return None;
+    }
+
+    let user_repo = UserRepository::new(&state.db);
+    let user = user_repo
+        .find_by_id(&session.user_id)
+        .await
+        .ok()
+        .flatten()?;
+    if !user.is_active {
+        return None;
+    }
+
+    // Impersonation tokens only work on the session the admin opened, and only while that
+    // admin is still an active admin
+    if session.impersonator_id.map(|id| id.to_hex()) != claims.impersonator {
+        return None;
+    }
+    if let Some(admin_id) = &session.impersonator_id {
+        let admin = user_repo.find_by_id(admin_id).await.ok().flatten()?;
+        if !admin.is_active || admin.role != "admin" {
+            return None;
+        }
+    }
+
+    claims.role = user.role;
+    Some(claims)
+}
+
+/// Signature and expiry check only, without the session lookup. For middleware that needs
+/// to know whether a request is made while impersonating; handlers still do the full check.
+/// Reads the token wherever `extract_ws_claims` would, so socket upgrades are seen too.
+pub fn peek_claims(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Option<Claims> {
+    let raw = raw_ws_token(headers, jar)?;
+    if raw.starts_with(ACCESS_TOKEN_PREFIX) {
+        return None;
+    }
+    decode_jwt(&raw, &state.jwt_secret)
+}
+
+/// Like `extract_claims`, but only for browser sessions. Used by account and session
+/// management endpoints that a personal access token must not reach.
+pub async fn extract_session_claims(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Option<Claims> {
+    extract_claims(state, headers, jar)
+        .await
+        .filter(|c| c.access_token.is_none())
+}
+
+pub async fn extract_session_user_id(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Option<ObjectId> {
+    let claims = extract_session_claims(state, headers, jar).await?;
+    ObjectId::parse_str(&claims.sub).ok()
+}
+
+fn raw_token(headers: &axum::http::HeaderMap, jar: &CookieJar) -> Option<String> {
     let auth_header = headers.get("Authorization").and_then(|h| h.to_str().ok());
 
-    let token = if let Some(header) = auth_header {
+    if let Some(header) = auth_header {
         if header.starts_with("Bearer ") {
             Some(header[7..].to_string())
         } else {
@@ -364,12 +1100,12 @@ pub fn extract_claims(
         }
     } else {
         jar.get("_khun_ph_token").map(|c| c.value().to_string())
-    };
-
-    let token_str = token?;
+    }
+}
 
+fn decode_jwt(token: &str, secret: &str) -> Option<Claims> {
     decode::<Claims>(
-        &token_str,
+        token,
         &DecodingKey::from_secret(secret.as_ref()),
         &Validation::default(),
     )
@@ -377,13 +1113,30 @@ pub fn extract_claims(
     .map(|data| data.claims)
 }
 
+/// Signature/expiry check only, without touching the database.
+fn decode_token(headers: &axum::http::HeaderMap, jar: &CookieJar, secret: &str) -> Option<Claims> {
+    decode_jwt(&raw_token(headers, jar)?, secret)
+}
+
+pub fn client_info(headers: &axum::http::HeaderMap, client_ip: &ClientIp) -> ClientInfo {
+    let user_agent = headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.chars().take(256).collect());
+    ClientInfo {
+        user_agent,
+        ip: client_ip.0.clone(),
+    }
+}
+
 pub async fn delete_user_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
     axum::extract::Path(id): axum::extract::Path<String>,
 ) -> axum::response::Response {
-    let claims = match extract_claims(&headers, &jar, &state.jwt_secret) {
+    let claims = match extract_claims(&state, &headers, &jar).await {
         Some(c) => c,
         None => {
             return (
@@ -405,8 +1158,44 @@ pub async fn delete_user_handler(
     let user_repo = UserRepository::new(&state.db);
     let profile_repo = ProfileRepository::new(&state.db);
 
-    match AuthService::delete_user(&user_repo, &profile_repo, &id).await {
-        Ok(_) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+    let member_repo = MemberRepository::new(&state.db);
+
+    let session_repo = SessionRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
+    let access_token_repo = AccessTokenRepository::new(&state.db);
+
+    let before = match ObjectId::parse_str(&id) {
+        Ok(oid) => AuditService::user_snapshot(&user_repo, &profile_repo, &oid).await,
+        Err(_) => None,
+    };
+
+    match AuthService::delete_user(
+        &user_repo,
+        &profile_repo,
+        &member_repo,
+        &session_repo,
+        &token_repo,
+        &access_token_repo,
+        &id,
+    )
+    .await
+    {
+        Ok(_) => {
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &user_repo,
+                &AuditActor::new(Some(&claims.sub), &client_ip),
+                AuditRecord {
+                    action: "user.delete",
+                    target_type: "user",
+                    target_id: Some(id),
+                    before,
+                    after: None,
+                },
+            )
+            .await;
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Err(e) => (
             axum::http::StatusCode::BAD_REQUEST,
             axum::Json(serde_json::json!({ "error": e })),
@@ -421,8 +1210,8 @@ pub async fn update_me_handler(
     headers: axum::http::HeaderMap,
     Json(payload): Json<UpdateProfileRequest>,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
-        Some(id) => id,
+    let claims = match extract_session_claims(&state, &headers, &jar).await {
+        Some(c) => c,
         None => {
             return (
                 axum::http::StatusCode::UNAUTHORIZED,
@@ -431,12 +1220,66 @@ pub async fn update_me_handler(
                 .into_response()
         }
     };
+    let user_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(id) => id,
+        Err(_) => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
 
     let user_repo = UserRepository::new(&state.db);
     let profile_repo = ProfileRepository::new(&state.db);
+    let session_repo = SessionRepository::new(&state.db);
+    let token_repo = AuthTokenRepository::new(&state.db);
 
-    match AuthService::update_profile(&user_repo, &profile_repo, &user_id, payload).await {
-        Ok(_) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+    // Email changes only take effect after the new address is confirmed
+    let new_email = match (
+        payload.email.as_deref(),
+        user_repo.find_by_id(&user_id).await,
+    ) {
+        (Some(email), Ok(Some(user))) if !email.trim().eq_ignore_ascii_case(&user.email) => {
+            Some(email.trim().to_string())
+        }
+        _ => None,
+    };
+    if let Some(email) = &new_email {
+        if let Err(e) = AccountService::request_email_change(
+            &user_repo,
+            &token_repo,
+            &state.mailer,
+            &state.auth_settings,
+            &user_id,
+            email,
+        )
+        .await
+        {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({ "error": e })),
+            )
+                .into_response();
+        }
+    }
+
+    match AuthService::update_profile(
+        &user_repo,
+        &profile_repo,
+        &session_repo,
+        &user_id,
+        claims.sid.as_deref(),
+        payload,
+    )
+    .await
+    {
+        Ok(_) => axum::Json(serde_json::json!({
+            "success": true,
+            "email_verification_sent": new_email.is_some()
+        }))
+        .into_response(),
         Err(e) => (
             axum::http::StatusCode::BAD_REQUEST,
             axum::Json(serde_json::json!({ "error": e })),
@@ -447,12 +1290,13 @@ pub async fn update_me_handler(
 
 pub async fn update_user_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
     axum::extract::Path(id): axum::extract::Path<String>,
     Json(payload): Json<crate::models::auth::UpdateUserRequest>,
 ) -> axum::response::Response {
-    let claims = match extract_claims(&headers, &jar, &state.jwt_secret) {
+    let claims = match extract_claims(&state, &headers, &jar).await {
         Some(c) => c,
         None => {
             return (
@@ -474,8 +1318,54 @@ pub async fn update_user_handler(
     let user_repo = UserRepository::new(&state.db);
     let profile_repo = ProfileRepository::new(&state.db);
 
-    match AuthService::update_user(&user_repo, &profile_repo, &id, payload).await {
-        Ok(_) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+    let session_repo = SessionRepository::new(&state.db);
+    let policy = match SecurityRepository::new(&state.db).get_policy().await {
+        Ok(p) => p,
+        Err(e) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+            )
+                .into_response()
+        }
+    };
+
+    let oid = ObjectId::parse_str(&id).ok();
+    let before = match &oid {
+        Some(oid) => AuditService::user_snapshot(&user_repo, &profile_repo, oid).await,
+        None => None,
+    };
+
+    match AuthService::update_user(
+        &user_repo,
+        &profile_repo,
+        &session_repo,
+        &policy,
+        &id,
+        payload,
+    )
+    .await
+    {
+        Ok(_) => {
+            let after = match &oid {
+                Some(oid) => AuditService::user_snapshot(&user_repo, &profile_repo, oid).await,
+                None => None,
+            };
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &user_repo,
+                &AuditActor::new(Some(&claims.sub), &client_ip),
+                AuditRecord {
+                    action: "user.update",
+                    target_type: "user",
+                    target_id: Some(id),
+                    before,
+                    after,
+                },
+            )
+            .await;
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Err(e) => (
             axum::http::StatusCode::BAD_REQUEST,
             axum::Json(serde_json::json!({ "error": e })),
@@ -9,11 +9,16 @@ use mongodb::bson::{doc, oid::ObjectId, Document};
 use std::collections::HashSet;
 use uuid::Uuid;
 
-use crate::handlers::auth_handler::extract_user_id;
+use crate::handlers::auth_handler::{extract_claims, extract_user_id};
+use crate::handlers::member_handler::verify_workspace_access;
 use crate::models::data::*;
 use crate::models::data::{CommentDocument, CommentImage};
+use crate::models::member::WorkspaceRole;
+use crate::models::room::{ChangeAction, RoomEvent};
 use crate::repositories::data_repo::DataRepository;
+use crate::repositories::member_repo::MemberRepository;
 use crate::repositories::workspace_repo::WorkspaceRepository;
+use crate::services::room_bus_service::publish_to_room;
 use crate::state::SharedState;
 use futures::StreamExt;
 
@@ -26,68 +31,6 @@ fn is_duplicate_key_error(error: &mongodb::error::Error) -> bool {
     error.to_string().contains("E11000")
 }
 
-/// Helper: verify user owns workspace and return workspace_id as ObjectId
-async fn verify_workspace_access(
-    state: &SharedState,
-    headers: &HeaderMap,
-    jar: &CookieJar,
-    ws_id_str: &str,
-) -> Result<ObjectId, axum::response::Response> {
-    // Auth check
-    let user_id = extract_user_id(headers, jar, &state.jwt_secret).ok_or_else(|| {
-        (
-            axum::http::StatusCode::UNAUTHORIZED,
-            axum::Json(serde_json::json!({ "error": "Not logged in" })),
-        )
-            .into_response()
-    })?;
-
-    // Parse workspace_id
-    let ws_oid = ObjectId::parse_str(ws_id_str).map_err(|_| {
-        (
-            axum::http::StatusCode::BAD_REQUEST,
-            axum::Json(serde_json::json!({ "error": "Invalid workspace ID" })),
-        )
-            .into_response()
-    })?;
-
-    // Check ownership
-    let ws_repo = WorkspaceRepository::new(&state.db);
-    let ws = ws_repo.find_by_id(&ws_oid).await.map_err(|_| {
-        (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            axum::Json(serde_json::json!({ "error": "Database error" })),
-        )
-            .into_response()
-    })?;
-
-    match ws {
-        Some(w) => {
-            if w.owner_id == user_id {
-                return Ok(ws_oid);
-            }
-            if let Some(id) = w.id {
-                let data_repo = DataRepository::new(&state.db);
-                if let Ok(assigned) = data_repo.find_assigned_workspaces(&user_id.to_hex()).await {
-                    if assigned.contains(&id) {
-                        return Ok(ws_oid);
-                    }
-                }
-            }
-            Err((
-                axum::http::StatusCode::FORBIDDEN,
-                axum::Json(serde_json::json!({ "error": "Access denied to this workspace" })),
-            )
-                .into_response())
-        }
-        None => Err((
-            axum::http::StatusCode::NOT_FOUND,
-            axum::Json(serde_json::json!({ "error": "Workspace not found" })),
-        )
-            .into_response()),
-    }
-}
-
 async fn verify_task_belongs_to_workspace(
     repo: &DataRepository,
     ws_oid: &ObjectId,
@@ -186,8 +129,16 @@ pub async fn list_tasks(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -222,8 +173,11 @@ pub async fn list_my_tasks(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
-        Some(id) => id,
+    let (user_id, token_scope) = match extract_claims(&state, &headers, &jar)
+        .await
+        .and_then(|c| Some((ObjectId::parse_str(&c.sub).ok()?, c.access_token)))
+    {
+        Some(caller) => caller,
         None => {
             return (
                 axum::http::StatusCode::UNAUTHORIZED,
@@ -235,15 +189,15 @@ pub async fn list_my_tasks(
 
     let workspace_repo = WorkspaceRepository::new(&state.db);
     let data_repo = DataRepository::new(&state.db);
-    let assigned_ws_ids = data_repo
-        .find_assigned_workspaces(&user_id.to_hex())
+    let member_ws_ids = MemberRepository::new(&state.db)
+        .find_workspace_ids_by_user(&user_id)
         .await
         .unwrap_or_default();
 
     let workspaces = match crate::services::workspace_service::WorkspaceService::get_user_workspaces(
         &workspace_repo,
         &user_id,
-        assigned_ws_ids,
+        member_ws_ids,
     )
     .await
     {
@@ -257,7 +211,15 @@ pub async fn list_my_tasks(
         }
     };
 
-    let workspace_ids: Vec<ObjectId> = workspaces.iter().filter_map(|ws| ws.id).collect();
+    let workspace_ids: Vec<ObjectId> = workspaces
+        .iter()
+        .filter_map(|ws| ws.id)
+        .filter(|id| {
+            token_scope
+                .as_ref()
+                .is_none_or(|scope| scope.allows_workspace(id))
+        })
+        .collect();
     if workspace_ids.is_empty() {
         return axum::Json(PaginatedTaskResponse {
             success: true,
@@ -405,8 +367,16 @@ pub async fn get_next_task_number(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -431,10 +401,19 @@ pub async fn create_task(
     jar: CookieJar,
     Json(payload): Json<CreateTaskRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let resolved_start_date = match payload
         .start_date
@@ -507,6 +486,17 @@ pub async fn create_task(
 
         match repo.create_task(task).await {
             Ok(created) => {
+                publish_to_room(
+                    &state,
+                    &access.workspace.room_code,
+                    RoomEvent::TaskChanged {
+                        action: ChangeAction::Created,
+                        task_id: created.id.map(|id| id.to_hex()).unwrap_or_default(),
+                        task: serde_json::to_value(&created).ok(),
+                        user_id: access.user_id.to_hex(),
+                    },
+                );
+
                 let ws_repo = WorkspaceRepository::new(&state.db);
                 if let Ok(Some(ws)) = ws_repo.find_by_id(&ws_oid).await {
                     crate::services::notification_service::notify_task_created(
@@ -545,10 +535,19 @@ pub async fn update_task(
     jar: CookieJar,
     Json(payload): Json<UpdateTaskRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let task_oid = match ObjectId::parse_str(&task_id) {
         Ok(id) => id,
@@ -707,6 +706,18 @@ pub async fn update_task(
             }
 
             let updated_task = repo.find_task_by_id(&task_oid).await.ok().flatten();
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::TaskChanged {
+                    action: ChangeAction::Updated,
+                    task_id: task_id.clone(),
+                    task: updated_task
+                        .as_ref()
+                        .and_then(|t| serde_json::to_value(t).ok()),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "task": updated_task })).into_response()
         }
         Ok(false) => (
@@ -728,10 +739,19 @@ pub async fn delete_task(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let task_oid = match ObjectId::parse_str(&task_id) {
         Ok(id) => id,
@@ -788,7 +808,19 @@ pub async fn delete_task(
     }
 
     match repo.delete_task(&task_oid, &ws_oid).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::TaskChanged {
+                    action: ChangeAction::Deleted,
+                    task_id,
+                    task: None,
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Task not found" })),
@@ -809,8 +841,16 @@ pub async fn list_task_comments(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
     let task_oid = match ObjectId::parse_str(&task_id) {
@@ -862,10 +902,14 @@ pub async fn create_task_comment(
     jar: CookieJar,
     mut multipart: Multipart,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
-        Err(resp) => return resp,
-    };
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Commenter)
+            .await
+        {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let ws_oid = access.workspace_id;
     let task_oid = match ObjectId::parse_str(&task_id) {
         Ok(id) => id,
         Err(_) => {
@@ -876,7 +920,7 @@ pub async fn create_task_comment(
                 .into_response()
         }
     };
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
+    let user_id = match extract_user_id(&state, &headers, &jar).await {
         Some(id) => id.to_hex(),
         None => {
             return (
@@ -1015,6 +1059,17 @@ pub async fn create_task_comment(
     };
     match repo.create_comment(comment).await {
         Ok(created) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::CommentChanged {
+                    action: ChangeAction::Created,
+                    task_id,
+                    comment_id: created.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    comment: serde_json::to_value(&created).ok(),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "comment": created })).into_response()
         }
         Err(e) => (
@@ -1032,8 +1087,16 @@ pub async fn list_comment_images(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
     let task_oid = match ObjectId::parse_str(&task_id) {
@@ -1097,10 +1160,14 @@ pub async fn delete_task_comment(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
-        Err(resp) => return resp,
-    };
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Commenter)
+            .await
+        {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let ws_oid = access.workspace_id;
     let task_oid = match ObjectId::parse_str(&task_id) {
         Ok(id) => id,
         Err(_) => {
@@ -1146,6 +1213,13 @@ pub async fn delete_task_comment(
                 .into_response()
         }
     };
+    if comment.created_by != access.user_id.to_hex() && !access.role.allows(WorkspaceRole::Admin) {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            axum::Json(serde_json::json!({ "error": "You can only delete your own comments" })),
+        )
+            .into_response();
+    }
 
     let storage = state.storage_snapshot().await;
     if let Some(client) = &storage.client {
@@ -1163,7 +1237,20 @@ pub async fn delete_task_comment(
     }
 
     match repo.delete_comment(&ws_oid, &task_oid, &comment_oid).await {
-        Ok(Some(_)) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(Some(_)) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::CommentChanged {
+                    action: ChangeAction::Deleted,
+                    task_id,
+                    comment_id,
+                    comment: None,
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(None) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Comment not found" })),
@@ -1184,10 +1271,14 @@ pub async fn update_task_comment(
     jar: CookieJar,
     Json(payload): Json<UpdateCommentRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
-        Err(resp) => return resp,
-    };
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Commenter)
+            .await
+        {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let ws_oid = access.workspace_id;
     let task_oid = match ObjectId::parse_str(&task_id) {
         Ok(id) => id,
         Err(_) => {
@@ -1212,6 +1303,33 @@ pub async fn update_task_comment(
     if let Err(resp) = verify_task_belongs_to_workspace(&repo, &ws_oid, &task_oid).await {
         return resp;
     }
+    match repo
+        .find_comment_by_id(&ws_oid, &task_oid, &comment_oid)
+        .await
+    {
+        Ok(Some(comment)) if comment.created_by != access.user_id.to_hex() => {
+            return (
+                axum::http::StatusCode::FORBIDDEN,
+                axum::Json(serde_json::json!({ "error": "You can only edit your own comments" })),
+            )
+                .into_response()
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return (
+                axum::http::StatusCode::NOT_FOUND,
+                axum::Json(serde_json::json!({ "error": "Comment not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": format!("{}", e) })),
+            )
+                .into_response()
+        }
+    }
     match repo
         .update_comment_content(
             &ws_oid,
@@ -1221,7 +1339,25 @@ pub async fn update_task_comment(
         )
         .await
     {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            let comment = repo
+                .find_comment_by_id(&ws_oid, &task_oid, &comment_oid)
+                .await
+                .ok()
+                .flatten();
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::CommentChanged {
+                    action: ChangeAction::Updated,
+                    task_id,
+                    comment_id,
+                    comment: comment.and_then(|c| serde_json::to_value(c).ok()),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Comment not found" })),
@@ -1242,10 +1378,14 @@ pub async fn toggle_task_comment_reaction(
     jar: CookieJar,
     Json(payload): Json<ToggleCommentReactionRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
-        Err(resp) => return resp,
-    };
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Commenter)
+            .await
+        {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let ws_oid = access.workspace_id;
     let task_oid = match ObjectId::parse_str(&task_id) {
         Ok(id) => id,
         Err(_) => {
@@ -1266,7 +1406,7 @@ pub async fn toggle_task_comment_reaction(
                 .into_response()
         }
     };
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
+    let user_id = match extract_user_id(&state, &headers, &jar).await {
         Some(id) => id.to_hex(),
         None => {
             return (
@@ -1296,6 +1436,17 @@ pub async fn toggle_task_comment_reaction(
         .await
     {
         Ok(Some(comment)) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::CommentChanged {
+                    action: ChangeAction::Updated,
+                    task_id,
+                    comment_id,
+                    comment: serde_json::to_value(&comment).ok(),
+                    user_id,
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "comment": comment })).into_response()
         }
         Ok(None) => (
@@ -1319,8 +1470,16 @@ pub async fn list_projects(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -1344,10 +1503,19 @@ pub async fn create_project(
     jar: CookieJar,
     Json(payload): Json<CreateProjectRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let project = ProjectDocument {
         id: None,
@@ -1360,6 +1528,16 @@ pub async fn create_project(
     let repo = DataRepository::new(&state.db);
     match repo.create_project(project).await {
         Ok(created) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::ProjectChanged {
+                    action: ChangeAction::Created,
+                    project_id: created.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    project: serde_json::to_value(&created).ok(),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "project": created })).into_response()
         }
         Err(e) => (
@@ -1377,10 +1555,19 @@ pub async fn update_project(
     jar: CookieJar,
     Json(payload): Json<UpdateProjectRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let proj_oid = match ObjectId::parse_str(&project_id) {
         Ok(id) => id,
@@ -1414,7 +1601,24 @@ pub async fn update_project(
 
     let repo = DataRepository::new(&state.db);
     match repo.update_project(&proj_oid, &ws_oid, updates).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            let project = repo
+                .find_project_by_id(&proj_oid, &ws_oid)
+                .await
+                .ok()
+                .flatten();
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::ProjectChanged {
+                    action: ChangeAction::Updated,
+                    project_id,
+                    project: project.and_then(|p| serde_json::to_value(p).ok()),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Project not found" })),
@@ -1434,10 +1638,19 @@ pub async fn delete_project(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let proj_oid = match ObjectId::parse_str(&project_id) {
         Ok(id) => id,
@@ -1452,7 +1665,19 @@ pub async fn delete_project(
 
     let repo = DataRepository::new(&state.db);
     match repo.delete_project(&proj_oid, &ws_oid).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::ProjectChanged {
+                    action: ChangeAction::Deleted,
+                    project_id,
+                    project: None,
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Project not found" })),
@@ -1472,8 +1697,16 @@ pub async fn get_project_stats(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -1523,8 +1756,16 @@ pub async fn list_assignees(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -1604,8 +1845,16 @@ pub async fn get_assignee_stats(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -1657,10 +1906,19 @@ pub async fn create_assignee(
     jar: CookieJar,
     Json(payload): Json<CreateAssigneeRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let mut discord_id = payload.discord_id;
 
@@ -1691,6 +1949,16 @@ pub async fn create_assignee(
     let repo = DataRepository::new(&state.db);
     match repo.create_assignee(assignee).await {
         Ok(created) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::AssigneeChanged {
+                    action: ChangeAction::Created,
+                    assignee_id: created.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    assignee: serde_json::to_value(&created).ok(),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "assignee": created })).into_response()
         }
         Err(e) => (
@@ -1708,10 +1976,19 @@ pub async fn update_assignee(
     jar: CookieJar,
     Json(payload): Json<UpdateAssigneeRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let assignee_oid = match ObjectId::parse_str(&assignee_id) {
         Ok(id) => id,
@@ -1772,7 +2049,24 @@ pub async fn update_assignee(
 
     let repo = DataRepository::new(&state.db);
     match repo.update_assignee(&assignee_oid, &ws_oid, updates).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            let assignee = repo
+                .find_assignee_by_id(&assignee_oid, &ws_oid)
+                .await
+                .ok()
+                .flatten();
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::AssigneeChanged {
+                    action: ChangeAction::Updated,
+                    assignee_id,
+                    assignee: assignee.and_then(|a| serde_json::to_value(a).ok()),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Assignee not found" })),
@@ -1792,10 +2086,19 @@ pub async fn delete_assignee(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let assignee_oid = match ObjectId::parse_str(&assignee_id) {
         Ok(id) => id,
@@ -1810,7 +2113,19 @@ pub async fn delete_assignee(
 
     let repo = DataRepository::new(&state.db);
     match repo.delete_assignee(&assignee_oid, &ws_oid).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::AssigneeChanged {
+                    action: ChangeAction::Deleted,
+                    assignee_id,
+                    assignee: None,
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Assignee not found" })),
@@ -1830,8 +2145,16 @@ pub async fn list_assignee_groups(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -1855,10 +2178,19 @@ pub async fn create_assignee_group(
     jar: CookieJar,
     Json(payload): Json<CreateAssigneeGroupRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let trimmed_name = payload.name.trim();
     if trimmed_name.is_empty() {
@@ -1900,6 +2232,16 @@ pub async fn create_assignee_group(
 
     match repo.create_assignee_group(group).await {
         Ok(created) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::AssigneeGroupChanged {
+                    action: ChangeAction::Created,
+                    group_id: created.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    group: serde_json::to_value(&created).ok(),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "group": created })).into_response()
         }
         Err(e) => (
@@ -1917,10 +2259,19 @@ pub async fn update_assignee_group(
     jar: CookieJar,
     Json(payload): Json<UpdateAssigneeGroupRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let group_oid = match ObjectId::parse_str(&group_id) {
         Ok(id) => id,
@@ -1976,6 +2327,16 @@ pub async fn update_assignee_group(
         .await
     {
         Ok(Some(updated)) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::AssigneeGroupChanged {
+                    action: ChangeAction::Updated,
+                    group_id,
+                    group: serde_json::to_value(&updated).ok(),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "group": updated })).into_response()
         }
         Ok(None) => (
@@ -1997,10 +2358,19 @@ pub async fn delete_assignee_group(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let group_oid = match ObjectId::parse_str(&group_id) {
         Ok(id) => id,
@@ -2015,7 +2385,19 @@ pub async fn delete_assignee_group(
 
     let repo = DataRepository::new(&state.db);
     match repo.delete_assignee_group(&group_oid, &ws_oid).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::AssigneeGroupChanged {
+                    action: ChangeAction::Deleted,
+                    group_id,
+                    group: None,
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Group not found" })),
@@ -2037,8 +2419,16 @@ pub async fn list_sprints(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
@@ -2062,10 +2452,19 @@ pub async fn create_sprint(
     jar: CookieJar,
     Json(payload): Json<CreateSprintRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let sprint = SprintDocument {
         id: None,
@@ -2082,6 +2481,16 @@ pub async fn create_sprint(
     let repo = DataRepository::new(&state.db);
     match repo.create_sprint(sprint).await {
         Ok(created) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::SprintChanged {
+                    action: ChangeAction::Created,
+                    sprint_id: created.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    sprint: serde_json::to_value(&created).ok(),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
             axum::Json(serde_json::json!({ "success": true, "sprint": created })).into_response()
         }
         Err(e) => (
@@ -2099,10 +2508,19 @@ pub async fn update_sprint(
     jar: CookieJar,
     Json(payload): Json<UpdateSprintRequest>,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let sprint_oid = match ObjectId::parse_str(&sprint_id) {
         Ok(id) => id,
@@ -2148,7 +2566,24 @@ pub async fn update_sprint(
 
     let repo = DataRepository::new(&state.db);
     match repo.update_sprint(&sprint_oid, &ws_oid, updates).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            let sprint = repo
+                .find_sprint_by_id(&sprint_oid, &ws_oid)
+                .await
+                .ok()
+                .flatten();
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::SprintChanged {
+                    action: ChangeAction::Updated,
+                    sprint_id,
+                    sprint: sprint.and_then(|s| serde_json::to_value(s).ok()),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Sprint not found" })),
@@ -2168,10 +2603,19 @@ pub async fn delete_sprint(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
         Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let sprint_oid = match ObjectId::parse_str(&sprint_id) {
         Ok(id) => id,
@@ -2186,7 +2630,19 @@ pub async fn delete_sprint(
 
     let repo = DataRepository::new(&state.db);
     match repo.delete_sprint(&sprint_oid, &ws_oid).await {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::SprintChanged {
+                    action: ChangeAction::Deleted,
+                    sprint_id,
+                    sprint: None,
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Sprint not found" })),
@@ -2205,8 +2661,16 @@ pub async fn daily_report(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let ws_oid = match verify_workspace_access(&state, &headers, &jar, &ws_id).await {
-        Ok(id) => id,
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
         Err(resp) => return resp,
     };
 
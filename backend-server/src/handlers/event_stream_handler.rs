@@ -0,0 +1,403 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+use crate::handlers::auth_handler::connection_token;
+use crate::handlers::member_handler::verify_workspace_access;
+use crate::handlers::ws_handler::{
+    access_removed, enter_room, handle_client_message, publish_presence, refresh_role,
+    room_event_message, snapshot_message, touch_peer, EnteredRoom, JoinedRoom, PeerSink, WsCaller,
+};
+use crate::models::{
+    member::WorkspaceRole,
+    message::{ClientMessage, ErrorCode, ServerMessage, SystemEvent},
+    room::RoomEvent,
+};
+use crate::services::event_feed_service::{
+    FeedEntry, FeedEvent, FeedSubscription, RoomFeed, RoomFeeds, StreamSession,
+};
+use crate::services::presence_service::PresenceService;
+use crate::services::room_service::{ensure_room_exists, remove_peer};
+use crate::services::ws_limit_service::ConnectionLimiter;
+use crate::state::SharedState;
+
+/// Messages posted for a stream that it hasn't handled yet
+const INBOX_CAPACITY: usize = 32;
+
+fn error_response(status: StatusCode, message: &str) -> axum::response::Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// `GET /api/workspaces/:ws_id/events` follows the workspace's room as server-sent events,
+/// for clients whose network blocks WebSocket upgrades. Each event's data is the JSON a
+/// WebSocket peer would get. The stream joins the room as its own peer and reports the peer id
+/// in `connected`; a client that reconnects with `Last-Event-ID` gets the room events it
+/// missed instead of the whole document, if they are still held.
+pub async fn stream_events(
+    State(state): State<SharedState>,
+    Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(response) => return response,
+    };
+
+    let room_code = access.workspace.room_code.clone();
+    if let Err(e) = ensure_room_exists(&state, &room_code).await {
+        return error_response(StatusCode::NOT_FOUND, &e);
+    }
+    let Some(feed) = RoomFeeds::get(&state, &room_code) else {
+        return error_response(StatusCode::NOT_FOUND, "Room not found");
+    };
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok());
+    let subscription = feed.subscribe(last_event_id);
+
+    let user = PresenceService::resolve_user(&state.db, &access.user_id).await;
+    let caller = WsCaller::new(access.user_id, connection_token(&headers, &jar), user);
+    let entered = match enter_room(&state, &caller, &room_code, &None, None).await {
+        Ok(entered) => entered,
+        Err(e) => return error_response(StatusCode::NOT_FOUND, &e),
+    };
+
+    let (out_tx, out_rx) = mpsc::channel(state.room_metrics.channel_capacity);
+    let (inbox_tx, inbox_rx) = mpsc::channel(INBOX_CAPACITY);
+    state.event_streams.insert(
+        caller.peer_id.clone(),
+        StreamSession {
+            user_id: access.user_id,
+            workspace_id: access.workspace_id,
+            inbox: inbox_tx,
+        },
+    );
+    info!(
+        "📡 Event stream opened: {} (user {}) in room {}",
+        caller.peer_id, caller.user_id, room_code
+    );
+
+    let joined = JoinedRoom::new(room_code, access.role);
+    tokio::spawn(run_stream(
+        state.clone(),
+        caller,
+        joined,
+        feed,
+        subscription,
+        entered,
+        out_tx,
+        inbox_rx,
+    ));
+
+    let stream = futures::stream::unfold(out_rx, |mut out_rx| async move {
+        let event = out_rx.recv().await?;
+        Some((Ok::<_, Infallible>(event), out_rx))
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// `POST /api/workspaces/:ws_id/events/:peer_id` hands a client message to the caller's
+/// stream, which handles it like a WebSocket message would be. Replies and errors arrive on
+/// the stream. Streams live on one instance, so this has to reach the same one.
+pub async fn post_event(
+    State(state): State<SharedState>,
+    Path((ws_id, peer_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    body: String,
+) -> axum::response::Response {
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(response) => return response,
+    };
+
+    if body.len() > state.ws_limits.max_message_bytes {
+        return error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            &format!(
+                "Messages are limited to {} bytes",
+                state.ws_limits.max_message_bytes
+            ),
+        );
+    }
+    let msg = match serde_json::from_str::<ClientMessage>(&body) {
+        Ok(ClientMessage::Join { .. }) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "Event streams join their workspace's room when they open",
+            )
+        }
+        Ok(msg) => msg,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid message format: {}", e),
+            )
+        }
+    };
+
+    let inbox = match state.event_streams.get(&peer_id) {
+        Some(session)
+            if session.user_id == access.user_id && session.workspace_id == access.workspace_id =>
+        {
+            session.inbox.clone()
+        }
+        _ => return error_response(StatusCode::NOT_FOUND, "Event stream not found"),
+    };
+    match inbox.try_send(msg) {
+        Ok(()) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({ "status": "accepted" })),
+        )
+            .into_response(),
+        Err(mpsc::error::TrySendError::Full(_)) => error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many messages, slow down",
+        ),
+        Err(mpsc::error::TrySendError::Closed(_)) => {
+            error_response(StatusCode::NOT_FOUND, "Event stream not found")
+        }
+    }
+}
+
+/// The events a stream sends until its client goes away, it leaves, or the room closes.
+#[allow(clippy::too_many_arguments)]
+async fn run_stream(
+    state: SharedState,
+    caller: WsCaller,
+    joined: JoinedRoom,
+    feed: Arc<RoomFeed>,
+    subscription: FeedSubscription,
+    entered: EnteredRoom,
+    out: mpsc::Sender<Event>,
+    mut inbox: mpsc::Receiver<ClientMessage>,
+) {
+    let FeedSubscription { mut rx, replay } = subscription;
+    let room_code = joined.room_code.clone();
+    let mut joined = Some(joined);
+    // Replaced by the numbered feed; only `leave` touches it
+    let mut room_rx = None;
+    let mut system_rx = state.system_tx.subscribe();
+    let mut limiter = ConnectionLimiter::new(state.ws_limits);
+    let mut touch_interval = tokio::time::interval(state.ws_heartbeat.interval);
+
+    let peers = state
+        .room_bus
+        .room_peers(&state.db, &room_code, entered.local_peers)
+        .await;
+    let mut opening = vec![
+        ServerMessage::RoomInfo {
+            room_code: room_code.clone(),
+            host_id: entered.host_id,
+            peers,
+        },
+        ServerMessage::Connected {
+            peer_id: caller.peer_id.clone(),
+            room_code: room_code.clone(),
+        },
+    ];
+    // A resumed stream already has the document up to its last event
+    let replay = match replay {
+        Some(entries) => entries,
+        None => {
+            opening.extend(entered.sync);
+            Vec::new()
+        }
+    };
+    let mut open = true;
+    for msg in &opening {
+        open &= PeerSink::Stream(&out).send(msg).await.is_ok();
+    }
+    let mut last_seq = 0;
+    for entry in replay {
+        last_seq = entry.seq;
+        open &= send_entry(&out, &state, &room_code, &feed, entry, &caller.peer_id).await;
+    }
+
+    while open {
+        let presence_due = joined.as_ref().and_then(JoinedRoom::presence_due);
+
+        tokio::select! {
+            entry = rx.recv() => {
+                match entry {
+                    Ok(entry) if entry.seq <= last_seq => {}
+                    Ok(entry) => {
+                        last_seq = entry.seq;
+                        open = send_entry(&out, &state, &room_code, &feed, entry, &caller.peer_id).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        state.room_metrics.record_lag(skipped);
+                        warn!("🐢 Event stream {} missed {} room events, resyncing", caller.peer_id, skipped);
+                        open = send_resync(&out, &state, &room_code, skipped, None).await;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+
+            msg = inbox.recv() => {
+                let Some(msg) = msg else { break };
+                let outcome = match limiter.check_rate(&msg) {
+                    Err(code) => Err((code, "Too many messages, slow down".to_string())),
+                    Ok(()) => match handle_client_message(
+                        &mut PeerSink::Stream(&out),
+                        &state,
+                        &caller,
+                        &msg,
+                        &mut joined,
+                        &mut room_rx,
+                    )
+                    .await
+                    {
+                        Ok(true) => break,
+                        Ok(false) => Ok(()),
+                        Err(e) => Err((ErrorCode::RequestFailed, e)),
+                    },
+                };
+
+                if let Err((code, message)) = outcome {
+                    warn!("❌ Rejected message from {}: {}", caller.peer_id, message);
+                    let error_msg = ServerMessage::Error { code, message };
+                    let _ = PeerSink::Stream(&out).send(&error_msg).await;
+
+                    if code != ErrorCode::RequestFailed && limiter.strike() {
+                        info!(
+                            "🚫 Event stream {} kept breaking message limits, closing",
+                            caller.peer_id
+                        );
+                        break;
+                    }
+                }
+            }
+
+            _ = async {
+                match presence_due {
+                    Some(due) => tokio::time::sleep_until(due).await,
+                    None => futures::future::pending().await,
+                }
+            } => {
+                if let Some(room) = joined.as_mut() {
+                    publish_presence(&state, room, &caller.peer_id);
+                }
+            }
+
+            // The open stream is the heartbeat; the keep-alive comments find dead connections
+            _ = touch_interval.tick() => {
+                if !touch_peer(&state, &room_code, &caller.peer_id, chrono::Utc::now()) {
+                    info!("💀 Event stream {} was evicted from its room, closing", caller.peer_id);
+                    break;
+                }
+                if let Some(room) = joined.as_mut() {
+                    if !refresh_role(&state, &caller, room).await {
+                        info!("🚫 Event stream {} lost access to room {}, closing", caller.peer_id, room_code);
+                        let _ = PeerSink::Stream(&out).send(&access_removed()).await;
+                        break;
+                    }
+                }
+            }
+
+            _ = out.closed() => {
+                info!("🔌 Event stream closed by client: {}", caller.peer_id);
+                break;
+            }
+
+            sys_msg = system_rx.recv() => {
+                if let Ok(SystemEvent::Shutdown) = sys_msg {
+                    info!("🛑 Server shutting down, closing event stream: {}", caller.peer_id);
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(room) = joined {
+        remove_peer(&state, &room.room_code, &caller.peer_id);
+    }
+    state.event_streams.remove(&caller.peer_id);
+    info!("📡 Event stream ended: {}", caller.peer_id);
+}
+
+/// Sends a numbered room event. False once the stream should end: the client is gone, the
+/// room closed or the peer was kicked.
+async fn send_entry(
+    out: &mpsc::Sender<Event>,
+    state: &SharedState,
+    room_code: &str,
+    feed: &RoomFeed,
+    entry: FeedEntry,
+    peer_id: &str,
+) -> bool {
+    let id = feed.event_id(entry.seq);
+    let event = match entry.event {
+        FeedEvent::Room(event) => Arc::unwrap_or_clone(event),
+        FeedEvent::Missed(skipped) => {
+            return send_resync(out, state, room_code, skipped, Some(id)).await
+        }
+    };
+
+    let ends = matches!(event, RoomEvent::RoomClosed { .. })
+        || matches!(&event, RoomEvent::PeerKicked { peer_id: kicked, .. } if kicked == peer_id);
+    let Some(msg) = room_event_message(event, peer_id) else {
+        return true;
+    };
+    let Ok(json) = serde_json::to_string(&msg) else {
+        return true;
+    };
+    out.send(Event::default().id(id).data(json)).await.is_ok() && !ends
+}
+
+/// Tells a stream that fell behind to start over from a full snapshot, like a lagging socket.
+async fn send_resync(
+    out: &mpsc::Sender<Event>,
+    state: &SharedState,
+    room_code: &str,
+    skipped: u64,
+    id: Option<String>,
+) -> bool {
+    let sync = match state.rooms.get(room_code) {
+        Some(room) => snapshot_message(room.document.as_ref()),
+        None => return false,
+    };
+    let mut sink = PeerSink::Stream(out);
+    if sink.send(&ServerMessage::Resync { skipped }).await.is_err() {
+        return false;
+    }
+    // The snapshot carries the id, so a reconnect resumes after it
+    let Ok(json) = serde_json::to_string(&sync) else {
+        return false;
+    };
+    let event = match id {
+        Some(id) => Event::default().id(id).data(json),
+        None => Event::default().data(json),
+    };
+    out.send(event).await.is_ok()
+}
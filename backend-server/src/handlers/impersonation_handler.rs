@@ -0,0 +1,346 @@
+use crate::handlers::auth_handler::{
+    client_info, extract_claims, extract_session_claims, peek_claims,
+};
+use crate::models::auth::ImpersonateRequest;
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::session_repo::SessionRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
+use crate::services::client_ip_service::ClientIp;
+use crate::services::session_service::SessionService;
+use crate::state::SharedState;
+use axum::{
+    extract::{MatchedPath, Path, Request, State},
+    http::Method,
+    middleware::Next,
+    response::IntoResponse,
+    Extension, Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use mongodb::bson::oid::ObjectId;
+
+const STOP_ROUTE: &str = "/api/auth/impersonation/stop";
+
+/// Admin, user-management, storage and account-security endpoints are off limits while
+/// acting as someone else, as are deleting a workspace and rotating its room code.
+fn blocked_while_impersonating(method: &Method, route: &str) -> bool {
+    if route.starts_with("/api/admin/")
+        || route.starts_with("/api/auth/users")
+        || route == "/api/auth/invite"
+    {
+        return true;
+    }
+    if *method == Method::GET || *method == Method::HEAD {
+        return false;
+    }
+    route == "/api/auth/me"
+        || route.starts_with("/api/auth/me/")
+        || (route == "/api/workspaces/:id" && *method == Method::DELETE)
+        || route == "/api/workspaces/:id/room-code/rotate"
+}
+
+fn forbidden(message: &str) -> axum::response::Response {
+    (
+        axum::http::StatusCode::FORBIDDEN,
+        Json(serde_json::json!({ "error": message })),
+    )
+        .into_response()
+}
+
+/// Route layer: refuses blocked endpoints for impersonation tokens and writes every other
+/// non-GET request made with one to the audit log, attributed to the admin.
+pub async fn impersonation_guard(
+    State(state): State<SharedState>,
+    req: Request,
+    next: Next,
+) -> axum::response::Response {
+    let jar = CookieJar::from_headers(req.headers());
+    let (user_id, admin_id) = match peek_claims(&state, req.headers(), &jar) {
+        Some(claims) => match claims.impersonator {
+            Some(admin_id) => (claims.sub, admin_id),
+            None => return next.run(req).await,
+        },
+        None => return next.run(req).await,
+    };
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| path.clone());
+    let client_ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .cloned()
+        .unwrap_or_default();
+
+    let blocked = blocked_while_impersonating(&method, &route);
+    let (action, response) = if blocked {
+        (
+            "impersonation.blocked",
+            forbidden("Not allowed while impersonating another user"),
+        )
+    } else {
+        ("impersonation.request", next.run(req).await)
+    };
+
+    let read_only = method == Method::GET || method == Method::HEAD || method == Method::OPTIONS;
+    if blocked || (!read_only && route != STOP_ROUTE) {
+        AuditService::record(
+            &AuditRepository::new(&state.db),
+            &UserRepository::new(&state.db),
+            &AuditActor::new(Some(&admin_id), &client_ip),
+            AuditRecord {
+                action,
+                target_type: "user",
+                target_id: Some(user_id),
+                before: None,
+                after: Some(serde_json::json!({
+                    "method": method.as_str(),
+                    "path": path,
+                    "status": response.status().as_u16(),
+                })),
+            },
+        )
+        .await;
+    }
+    response
+}
+
+/// Admin: `POST /api/auth/users/:id/impersonate` returns a short-lived token for acting as
+/// the user. Admin accounts can't be impersonated.
+pub async fn start_impersonation_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<String>,
+    payload: Option<Json<ImpersonateRequest>>,
+) -> axum::response::Response {
+    let claims = match extract_session_claims(&state, &headers, &jar).await {
+        Some(c) => c,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+    if claims.role != "admin" || claims.impersonator.is_some() {
+        return forbidden("Admin access required");
+    }
+    let admin_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(oid) => oid,
+        Err(_) => return forbidden("Admin access required"),
+    };
+    let target_id = match ObjectId::parse_str(&id) {
+        Ok(oid) => oid,
+        Err(_) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid user ID" })),
+            )
+                .into_response()
+        }
+    };
+    if target_id == admin_id {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "You can't impersonate yourself" })),
+        )
+            .into_response();
+    }
+
+    let user_repo = UserRepository::new(&state.db);
+    let user = match user_repo.find_by_id(&target_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return (
+                axum::http::StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "User not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+            )
+                .into_response()
+        }
+    };
+    if user.role == "admin" {
+        return forbidden("Admin accounts can't be impersonated");
+    }
+    if !user.is_active {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "User is not active" })),
+        )
+            .into_response();
+    }
+
+    let reason = payload
+        .and_then(|Json(p)| p.reason)
+        .map(|r| r.trim().chars().take(500).collect::<String>())
+        .filter(|r| !r.is_empty());
+
+    let session_repo = SessionRepository::new(&state.db);
+    match SessionService::issue_impersonation(
+        &session_repo,
+        &user,
+        &admin_id,
+        &state.auth_settings,
+        &state.jwt_secret,
+        client_info(&headers, &client_ip),
+    )
+    .await
+    {
+        Ok((token, session)) => {
+            tracing::warn!(
+                "🎭 Admin {} is impersonating {} until {}",
+                claims.sub,
+                user.email,
+                session.expires_at
+            );
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &user_repo,
+                &AuditActor::new(Some(&claims.sub), &client_ip),
+                AuditRecord {
+                    action: "impersonation.start",
+                    target_type: "user",
+                    target_id: Some(id),
+                    before: None,
+                    after: Some(serde_json::json!({
+                        "session_id": session.session_id,
+                        "expires_at": session.expires_at,
+                        "reason": reason,
+                    })),
+                },
+            )
+            .await;
+
+            Json(serde_json::json!({
+                "success": true,
+                "token": token,
+                "expires_in": state.auth_settings.impersonation_ttl_seconds,
+                "expires_at": session.expires_at,
+                "session_id": session.session_id,
+                "impersonating": {
+                    "id": target_id.to_hex(),
+                    "email": user.email,
+                },
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// `POST /api/auth/impersonation/stop`, called with the impersonation token. Ends the
+/// impersonation session; the admin's own session is untouched.
+pub async fn stop_impersonation_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let claims = match extract_claims(&state, &headers, &jar).await {
+        Some(c) => c,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response()
+        }
+    };
+    let (admin_id, session_id) = match (&claims.impersonator, &claims.sid) {
+        (Some(admin_id), Some(sid)) => (admin_id.clone(), sid.clone()),
+        _ => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Not impersonating" })),
+            )
+                .into_response()
+        }
+    };
+
+    if let Err(e) = SessionRepository::new(&state.db).revoke(&session_id).await {
+        return (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+        )
+            .into_response();
+    }
+
+    AuditService::record(
+        &AuditRepository::new(&state.db),
+        &UserRepository::new(&state.db),
+        &AuditActor::new(Some(&admin_id), &client_ip),
+        AuditRecord {
+            action: "impersonation.stop",
+            target_type: "user",
+            target_id: Some(claims.sub),
+            before: None,
+            after: Some(serde_json::json!({ "session_id": session_id })),
+        },
+    )
+    .await;
+
+    Json(serde_json::json!({ "success": true })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_admin_and_account_security_routes() {
+        assert!(blocked_while_impersonating(
+            &Method::DELETE,
+            "/api/auth/users/:id"
+        ));
+        assert!(blocked_while_impersonating(
+            &Method::GET,
+            "/api/admin/audit-events"
+        ));
+        assert!(blocked_while_impersonating(
+            &Method::PUT,
+            "/api/admin/storage/config"
+        ));
+        assert!(blocked_while_impersonating(
+            &Method::POST,
+            "/api/auth/me/tokens"
+        ));
+        assert!(blocked_while_impersonating(&Method::PUT, "/api/auth/me"));
+        assert!(blocked_while_impersonating(
+            &Method::DELETE,
+            "/api/workspaces/:id"
+        ));
+        assert!(blocked_while_impersonating(
+            &Method::POST,
+            "/api/workspaces/:id/room-code/rotate"
+        ));
+
+        assert!(!blocked_while_impersonating(&Method::GET, "/api/auth/me"));
+        assert!(!blocked_while_impersonating(&Method::GET, "/api/my/tasks"));
+        assert!(!blocked_while_impersonating(
+            &Method::PUT,
+            "/api/workspaces/:id"
+        ));
+        assert!(!blocked_while_impersonating(
+            &Method::POST,
+            "/api/workspaces/:ws_id/tasks"
+        ));
+        assert!(!blocked_while_impersonating(&Method::POST, STOP_ROUTE));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::handlers::auth_handler::extract_session_claims;
+use crate::models::login_attempt::account_key;
+use crate::repositories::login_attempt_repo::LoginAttemptRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::state::SharedState;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use mongodb::bson::oid::ObjectId;
+
+async fn ensure_admin(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Option<axum::response::Response> {
+    match extract_session_claims(state, headers, jar).await {
+        Some(claims) if claims.role == "admin" => None,
+        Some(_) => Some(
+            (
+                axum::http::StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Admin access required" })),
+            )
+                .into_response(),
+        ),
+        None => Some(
+            (
+                axum::http::StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Unauthorized" })),
+            )
+                .into_response(),
+        ),
+    }
+}
+
+fn database_error(e: mongodb::error::Error) -> axum::response::Response {
+    (
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+    )
+        .into_response()
+}
+
+/// Admin: accounts and IPs that currently can't log in because of failed attempts.
+pub async fn list_lockouts_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    if let Some(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+
+    match LoginAttemptRepository::new(&state.db).find_blocked().await {
+        Ok(attempts) => {
+            let lockouts: Vec<_> = attempts
+                .into_iter()
+                .map(|a| {
+                    serde_json::json!({
+                        "key": a.key,
+                        "failures": a.failures,
+                        "last_failed_at": a.last_failed_at,
+                        "blocked_until": a.blocked_until,
+                        "locked": a.locked,
+                    })
+                })
+                .collect();
+            Json(serde_json::json!({ "success": true, "lockouts": lockouts })).into_response()
+        }
+        Err(e) => database_error(e),
+    }
+}
+
+/// Admin: clears one counter by key (`account:<email>` or `ip:<address>`).
+pub async fn clear_lockout_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(key): Path<String>,
+) -> axum::response::Response {
+    if let Some(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+
+    match LoginAttemptRepository::new(&state.db).clear(&key).await {
+        Ok(true) => Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(false) => (
+            axum::http::StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "No failed logins recorded for this key" })),
+        )
+            .into_response(),
+        Err(e) => database_error(e),
+    }
+}
+
+/// Admin: lets a user log in again right away after a lockout.
+pub async fn unlock_user_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<String>,
+) -> axum::response::Response {
+    if let Some(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+    let oid = match ObjectId::parse_str(&id) {
+        Ok(oid) => oid,
+        Err(_) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid user ID" })),
+            )
+                .into_response()
+        }
+    };
+    let user = match UserRepository::new(&state.db).find_by_id(&oid).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return (
+                axum::http::StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "User not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => return database_error(e),
+    };
+
+    match LoginAttemptRepository::new(&state.db)
+        .clear(&account_key(&user.email))
+        .await
+    {
+        Ok(was_locked) => Json(serde_json::json!({
+            "success": true,
+            "was_locked": was_locked,
+        }))
+        .into_response(),
+        Err(e) => database_error(e),
+    }
+}
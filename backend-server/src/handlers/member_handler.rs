@@ -0,0 +1,365 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use axum_extra::extract::cookie::CookieJar;
+use mongodb::bson::oid::ObjectId;
+
+use crate::handlers::auth_handler::extract_claims;
+use crate::models::member::{InviteMemberRequest, UpdateMemberRoleRequest, WorkspaceRole};
+use crate::models::workspace::Workspace;
+use crate::repositories::member_repo::MemberRepository;
+use crate::repositories::profile_repo::ProfileRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::repositories::workspace_repo::WorkspaceRepository;
+use crate::services::member_service::MemberService;
+use crate::state::SharedState;
+
+pub struct WorkspaceAccess {
+    pub workspace_id: ObjectId,
+    pub user_id: ObjectId,
+    pub role: WorkspaceRole,
+    pub workspace: Workspace,
+}
+
+/// Helper: verify the caller is a member of the workspace with at least `required` role.
+/// With a personal access token the workspace must be in the token's scope and the role is
+/// capped by the token permission.
+pub async fn verify_workspace_access(
+    state: &SharedState,
+    headers: &HeaderMap,
+    jar: &CookieJar,
+    ws_id_str: &str,
+    required: WorkspaceRole,
+) -> Result<WorkspaceAccess, axum::response::Response> {
+    let claims = extract_claims(state, headers, jar).await;
+    let (user_id, token_scope) = claims
+        .and_then(|c| Some((ObjectId::parse_str(&c.sub).ok()?, c.access_token)))
+        .ok_or_else(|| {
+            (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Not logged in" })),
+            )
+                .into_response()
+        })?;
+
+    let ws_oid = ObjectId::parse_str(ws_id_str).map_err(|_| {
+        (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": "Invalid workspace ID" })),
+        )
+            .into_response()
+    })?;
+
+    if let Some(scope) = &token_scope {
+        if !scope.allows_workspace(&ws_oid) {
+            return Err((
+                axum::http::StatusCode::FORBIDDEN,
+                axum::Json(
+                    serde_json::json!({ "error": "Access token is not scoped to this workspace" }),
+                ),
+            )
+                .into_response());
+        }
+    }
+
+    let ws_repo = WorkspaceRepository::new(&state.db);
+    let workspace = match ws_repo.find_by_id(&ws_oid).await {
+        Ok(Some(w)) => w,
+        Ok(None) => {
+            return Err((
+                axum::http::StatusCode::NOT_FOUND,
+                axum::Json(serde_json::json!({ "error": "Workspace not found" })),
+            )
+                .into_response())
+        }
+        Err(_) => {
+            return Err((
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": "Database error" })),
+            )
+                .into_response())
+        }
+    };
+
+    let member_repo = MemberRepository::new(&state.db);
+    let role = MemberService::resolve_role(&member_repo, &workspace, &user_id)
+        .await
+        .map_err(|e| {
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        })?
+        .map(|role| match &token_scope {
+            Some(scope) => scope.cap_role(role),
+            None => role,
+        });
+
+    match role {
+        Some(role) if role.allows(required) => Ok(WorkspaceAccess {
+            workspace_id: ws_oid,
+            user_id,
+            role,
+            workspace,
+        }),
+        Some(_) => Err((
+            axum::http::StatusCode::FORBIDDEN,
+            axum::Json(serde_json::json!({
+                "error": format!("This action requires the {} role or higher", required.as_str())
+            })),
+        )
+            .into_response()),
+        None => Err((
+            axum::http::StatusCode::FORBIDDEN,
+            axum::Json(serde_json::json!({ "error": "Access denied to this workspace" })),
+        )
+            .into_response()),
+    }
+}
+
+fn invalid_member_id() -> axum::response::Response {
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        axum::Json(serde_json::json!({ "error": "Invalid user ID" })),
+    )
+        .into_response()
+}
+
+fn forbidden(message: &str) -> axum::response::Response {
+    (
+        axum::http::StatusCode::FORBIDDEN,
+        axum::Json(serde_json::json!({ "error": message })),
+    )
+        .into_response()
+}
+
+pub async fn list_members(
+    State(state): State<SharedState>,
+    Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(resp) => return resp,
+    };
+
+    let member_repo = MemberRepository::new(&state.db);
+    let user_repo = UserRepository::new(&state.db);
+    let profile_repo = ProfileRepository::new(&state.db);
+
+    match MemberService::list_members(&member_repo, &user_repo, &profile_repo, &access.workspace)
+        .await
+    {
+        Ok(members) => {
+            axum::Json(serde_json::json!({ "success": true, "members": members })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn invite_member(
+    State(state): State<SharedState>,
+    Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Json(payload): Json<InviteMemberRequest>,
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Admin).await {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+
+    if payload.role == WorkspaceRole::Admin && access.role != WorkspaceRole::Owner {
+        return forbidden("Only the workspace owner can add admins");
+    }
+
+    let member_repo = MemberRepository::new(&state.db);
+    let user_repo = UserRepository::new(&state.db);
+
+    match MemberService::invite(
+        &member_repo,
+        &user_repo,
+        &access.workspace,
+        &access.user_id,
+        payload,
+    )
+    .await
+    {
+        Ok(member) => axum::Json(serde_json::json!({
+            "success": true,
+            "member": {
+                "user_id": member.user_id.to_hex(),
+                "role": member.role,
+                "invited_by": member.invited_by.map(|id| id.to_hex()),
+                "created_at": member.created_at,
+            }
+        }))
+        .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn update_member_role(
+    State(state): State<SharedState>,
+    Path((ws_id, member_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Json(payload): Json<UpdateMemberRoleRequest>,
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Admin).await {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let member_oid = match ObjectId::parse_str(&member_id) {
+        Ok(id) => id,
+        Err(_) => return invalid_member_id(),
+    };
+
+    if member_oid == access.workspace.owner_id {
+        return forbidden("The workspace owner's role cannot be changed");
+    }
+    if payload.role == WorkspaceRole::Owner {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": "Cannot assign the owner role" })),
+        )
+            .into_response();
+    }
+
+    let member_repo = MemberRepository::new(&state.db);
+    let current = match member_repo
+        .find_member(&access.workspace_id, &member_oid)
+        .await
+    {
+        Ok(Some(m)) => m,
+        Ok(None) => {
+            return (
+                axum::http::StatusCode::NOT_FOUND,
+                axum::Json(serde_json::json!({ "error": "Member not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": format!("{}", e) })),
+            )
+                .into_response()
+        }
+    };
+
+    if (current.role == WorkspaceRole::Admin || payload.role == WorkspaceRole::Admin)
+        && access.role != WorkspaceRole::Owner
+    {
+        return forbidden("Only the workspace owner can manage admins");
+    }
+
+    match member_repo
+        .update_role(&access.workspace_id, &member_oid, payload.role)
+        .await
+    {
+        Ok(true) => {
+            axum::Json(serde_json::json!({ "success": true, "role": payload.role })).into_response()
+        }
+        Ok(false) => (
+            axum::http::StatusCode::NOT_FOUND,
+            axum::Json(serde_json::json!({ "error": "Member not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": format!("{}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn remove_member(
+    State(state): State<SharedState>,
+    Path((ws_id, member_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    // Any member may leave on their own; removing someone else requires admin.
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(resp) => return resp,
+    };
+    let member_oid = match ObjectId::parse_str(&member_id) {
+        Ok(id) => id,
+        Err(_) => return invalid_member_id(),
+    };
+
+    if member_oid == access.workspace.owner_id {
+        return forbidden("The workspace owner cannot be removed");
+    }
+
+    let member_repo = MemberRepository::new(&state.db);
+    if member_oid != access.user_id {
+        if !access.role.allows(WorkspaceRole::Admin) {
+            return forbidden("This action requires the admin role or higher");
+        }
+        match member_repo
+            .find_member(&access.workspace_id, &member_oid)
+            .await
+        {
+            Ok(Some(target)) => {
+                if target.role == WorkspaceRole::Admin && access.role != WorkspaceRole::Owner {
+                    return forbidden("Only the workspace owner can remove admins");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return (
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    axum::Json(serde_json::json!({ "error": format!("{}", e) })),
+                )
+                    .into_response()
+            }
+        }
+    }
+
+    match member_repo.delete(&access.workspace_id, &member_oid).await {
+        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(false) => (
+            axum::http::StatusCode::NOT_FOUND,
+            axum::Json(serde_json::json!({ "error": "Member not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": format!("{}", e) })),
+        )
+            .into_response(),
+    }
+}
@@ -1,23 +1,37 @@
+use crate::handlers::member_handler::verify_workspace_access;
+use crate::models::member::WorkspaceRole;
 use crate::models::milestone::{CreateMilestoneRequest, UpdateMilestoneRequest};
+use crate::models::room::{ChangeAction, RoomEvent};
 use crate::repositories::milestone_repo::MilestoneRepository;
 use crate::services::milestone_service::MilestoneService;
+use crate::services::room_bus_service::publish_to_room;
 use crate::state::AppState;
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
-use mongodb::bson::oid::ObjectId;
+use axum_extra::extract::cookie::CookieJar;
 use std::sync::Arc;
 
 pub async fn list_milestones(
     State(state): State<Arc<AppState>>,
     Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
 ) -> impl IntoResponse {
-    let ws_oid = match ObjectId::parse_str(&ws_id) {
-        Ok(oid) => oid,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid workspace ID").into_response(),
+    let ws_oid = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access.workspace_id,
+        Err(resp) => return resp,
     };
 
     let repo = MilestoneRepository::new(&state.db);
@@ -32,18 +46,41 @@ pub async fn list_milestones(
 pub async fn create_milestone(
     State(state): State<Arc<AppState>>,
     Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
     Json(req): Json<CreateMilestoneRequest>,
 ) -> impl IntoResponse {
-    let ws_oid = match ObjectId::parse_str(&ws_id) {
-        Ok(oid) => oid,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid workspace ID").into_response(),
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let repo = MilestoneRepository::new(&state.db);
     let service = MilestoneService::new(repo);
 
     match service.create_milestone(ws_oid, req).await {
-        Ok(milestone) => (StatusCode::CREATED, Json(milestone)).into_response(),
+        Ok(milestone) => {
+            publish_to_room(
+                &state,
+                &access.workspace.room_code,
+                RoomEvent::MilestoneChanged {
+                    action: ChangeAction::Created,
+                    milestone_id: milestone.id.clone(),
+                    milestone: serde_json::to_value(&milestone).ok(),
+                    user_id: access.user_id.to_hex(),
+                },
+            );
+            (StatusCode::CREATED, Json(milestone)).into_response()
+        }
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
@@ -51,19 +88,48 @@ pub async fn create_milestone(
 pub async fn update_milestone(
     State(state): State<Arc<AppState>>,
     Path((ws_id, milestone_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
     Json(req): Json<UpdateMilestoneRequest>,
 ) -> impl IntoResponse {
-    let ws_oid = match ObjectId::parse_str(&ws_id) {
-        Ok(oid) => oid,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid workspace ID").into_response(),
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let repo = MilestoneRepository::new(&state.db);
     let service = MilestoneService::new(repo);
 
-    match service.update_milestone(milestone_id, &ws_oid, req).await {
+    match service
+        .update_milestone(milestone_id.clone(), &ws_oid, req)
+        .await
+    {
         Ok(found) => {
             if found {
+                let milestone = MilestoneRepository::new(&state.db)
+                    .find_by_id(&milestone_id)
+                    .await
+                    .ok()
+                    .flatten();
+                publish_to_room(
+                    &state,
+                    &access.workspace.room_code,
+                    RoomEvent::MilestoneChanged {
+                        action: ChangeAction::Updated,
+                        milestone_id,
+                        milestone: milestone.and_then(|m| serde_json::to_value(m).ok()),
+                        user_id: access.user_id.to_hex(),
+                    },
+                );
                 StatusCode::OK.into_response()
             } else {
                 StatusCode::NOT_FOUND.into_response()
@@ -76,18 +142,42 @@ pub async fn update_milestone(
 pub async fn delete_milestone(
     State(state): State<Arc<AppState>>,
     Path((ws_id, milestone_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
 ) -> impl IntoResponse {
-    let ws_oid = match ObjectId::parse_str(&ws_id) {
-        Ok(oid) => oid,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid workspace ID").into_response(),
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Editor,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(resp) => return resp,
     };
+    let ws_oid = access.workspace_id;
 
     let repo = MilestoneRepository::new(&state.db);
     let service = MilestoneService::new(repo);
 
-    match service.delete_milestone(milestone_id, &ws_oid).await {
+    match service
+        .delete_milestone(milestone_id.clone(), &ws_oid)
+        .await
+    {
         Ok(found) => {
             if found {
+                publish_to_room(
+                    &state,
+                    &access.workspace.room_code,
+                    RoomEvent::MilestoneChanged {
+                        action: ChangeAction::Deleted,
+                        milestone_id,
+                        milestone: None,
+                        user_id: access.user_id.to_hex(),
+                    },
+                );
                 StatusCode::NO_CONTENT.into_response()
             } else {
                 StatusCode::NOT_FOUND.into_response()
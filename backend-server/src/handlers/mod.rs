@@ -1,9 +1,20 @@
+pub mod access_token_handler;
 pub mod attachment_handler;
+pub mod audit_handler;
 pub mod auth_handler;
 pub mod checklist_template_handler;
 pub mod data_handler;
+pub mod event_stream_handler;
+pub mod impersonation_handler;
+pub mod login_throttle_handler;
+pub mod member_handler;
 pub mod milestone_handler;
+pub mod oidc_handler;
+pub mod room_admin_handler;
 pub mod room_handler;
+pub mod room_snapshot_handler;
 pub mod storage_handler;
+pub mod two_factor_handler;
 pub mod workspace_handler;
+pub mod workspace_invite_handler;
 pub mod ws_handler;
@@ -0,0 +1,184 @@
+use crate::handlers::auth_handler::{client_info, extract_session_user_id, finish_login};
+use crate::models::oidc::OidcCallbackRequest;
+use crate::repositories::oidc_state_repo::OidcStateRepository;
+use crate::repositories::profile_repo::ProfileRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::client_ip_service::ClientIp;
+use crate::services::oidc_service::{OidcOutcome, OidcService};
+use crate::state::SharedState;
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+use axum_extra::extract::cookie::CookieJar;
+
+fn not_configured() -> axum::response::Response {
+    (
+        axum::http::StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "Single sign-on is not configured" })),
+    )
+        .into_response()
+}
+
+fn unauthorized() -> axum::response::Response {
+    (
+        axum::http::StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({ "error": "Unauthorized" })),
+    )
+        .into_response()
+}
+
+/// Public: tells the login page whether to show the SSO button.
+pub async fn oidc_config_handler(State(state): State<SharedState>) -> axum::response::Response {
+    Json(serde_json::json!({
+        "success": true,
+        "enabled": state.oidc.is_some(),
+        "provider_name": state.oidc.as_ref().map(|p| p.settings().provider_name.clone()),
+    }))
+    .into_response()
+}
+
+pub async fn oidc_authorize_handler(State(state): State<SharedState>) -> axum::response::Response {
+    let provider = match state.oidc.as_ref() {
+        Some(p) => p,
+        None => return not_configured(),
+    };
+    let state_repo = OidcStateRepository::new(&state.db);
+
+    match OidcService::begin(provider, &state_repo, None).await {
+        Ok(url) => {
+            Json(serde_json::json!({ "success": true, "authorization_url": url })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// The frontend posts the `code` and `state` it received from the provider. Responds like
+/// `/api/auth/login` (session or 2FA challenge), or with `linked: true` for a link request.
+pub async fn oidc_callback_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<OidcCallbackRequest>,
+) -> axum::response::Response {
+    let provider = match state.oidc.as_ref() {
+        Some(p) => p,
+        None => return not_configured(),
+    };
+    let caller = extract_session_user_id(&state, &headers, &jar).await;
+    let state_repo = OidcStateRepository::new(&state.db);
+    let user_repo = UserRepository::new(&state.db);
+    let profile_repo = ProfileRepository::new(&state.db);
+
+    match OidcService::complete(
+        provider,
+        &state_repo,
+        &user_repo,
+        &profile_repo,
+        caller,
+        &payload,
+    )
+    .await
+    {
+        Ok(OidcOutcome::SignedIn(user)) => {
+            finish_login(&state, client_info(&headers, &client_ip), user).await
+        }
+        Ok(OidcOutcome::Linked(user)) => Json(serde_json::json!({
+            "success": true,
+            "linked": true,
+            "email": user.email,
+        }))
+        .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// Starts the provider round-trip that links the signed-in account to an external identity.
+pub async fn link_oidc_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let provider = match state.oidc.as_ref() {
+        Some(p) => p,
+        None => return not_configured(),
+    };
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => return unauthorized(),
+    };
+    let state_repo = OidcStateRepository::new(&state.db);
+
+    match OidcService::begin(provider, &state_repo, Some(user_id)).await {
+        Ok(url) => {
+            Json(serde_json::json!({ "success": true, "authorization_url": url })).into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn unlink_oidc_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => return unauthorized(),
+    };
+    let user_repo = UserRepository::new(&state.db);
+    let user = match user_repo.find_by_id(&user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return unauthorized(),
+        Err(e) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+            )
+                .into_response()
+        }
+    };
+    // Accounts created through SSO have no password to fall back on
+    if user.password_hash.is_none() {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Set a password before unlinking single sign-on" })),
+        )
+            .into_response();
+    }
+
+    let mut removed = false;
+    for identity in &user.external_identities {
+        match user_repo
+            .unlink_external_identity(&user_id, &identity.issuer)
+            .await
+        {
+            Ok(changed) => removed |= changed,
+            Err(e) => {
+                return (
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+                )
+                    .into_response()
+            }
+        }
+    }
+    if !removed {
+        return (
+            axum::http::StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "No linked identity" })),
+        )
+            .into_response();
+    }
+    Json(serde_json::json!({ "success": true })).into_response()
+}
@@ -0,0 +1,330 @@
+use crate::handlers::auth_handler::extract_session_claims;
+use crate::models::auth::Claims;
+use crate::models::room::{AdminRoomActionRequest, PeerInfo, Room};
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
+use crate::services::client_ip_service::ClientIp;
+use crate::services::room_bus_service::close_room;
+use crate::services::room_service::{ensure_room_exists, evict_room, kick_peer};
+use crate::state::SharedState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+const MAX_REASON_LEN: usize = 200;
+
+async fn ensure_admin(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Result<Claims, axum::response::Response> {
+    match extract_session_claims(state, headers, jar).await {
+        Some(claims) if claims.role == "admin" => Ok(claims),
+        Some(_) => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Admin access required" })),
+        )
+            .into_response()),
+        None => Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Unauthorized" })),
+        )
+            .into_response()),
+    }
+}
+
+fn room_not_found() -> axum::response::Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "Room not found" })),
+    )
+        .into_response()
+}
+
+async fn record_room_audit(
+    state: &SharedState,
+    claims: &Claims,
+    client_ip: &ClientIp,
+    action: &'static str,
+    room_code: &str,
+    after: Option<serde_json::Value>,
+) {
+    AuditService::record(
+        &AuditRepository::new(&state.db),
+        &UserRepository::new(&state.db),
+        &AuditActor::new(Some(&claims.sub), client_ip),
+        AuditRecord {
+            action,
+            target_type: "room",
+            target_id: Some(room_code.to_string()),
+            before: None,
+            after,
+        },
+    )
+    .await;
+}
+
+/// The reason shown to peers, or `fallback` when none was given.
+fn reason_or(payload: AdminRoomActionRequest, fallback: &str) -> Result<String, String> {
+    match payload.reason.as_deref().map(str::trim) {
+        Some(reason) if reason.chars().count() > MAX_REASON_LEN => Err(format!(
+            "Reason is limited to {} characters",
+            MAX_REASON_LEN
+        )),
+        Some("") | None => Ok(fallback.to_string()),
+        Some(reason) => Ok(reason.to_string()),
+    }
+}
+
+fn room_summary(state: &SharedState, room_code: &str, room: &Room) -> serde_json::Value {
+    let (document_size, task_count) = match room.document.as_ref() {
+        Some(doc) => (doc.export().len(), doc.tasks().len()),
+        None => (0, 0),
+    };
+    serde_json::json!({
+        "room_code": room_code,
+        "room_id": room.id,
+        "host_id": room.host_id,
+        "peer_count": room.peers.len(),
+        "created_at": room.created_at,
+        "last_sync": room.last_sync,
+        "empty_since": room.empty_since,
+        "last_snapshot": room.last_snapshot,
+        "document_loaded": room.document.is_some(),
+        "document_size": document_size,
+        "task_count": task_count,
+        "unsaved": state.document_persister.pending_document(room_code).is_some(),
+    })
+}
+
+/// Admin: rooms loaded on this instance, most recently synced first. `peer_count` only
+/// counts peers connected here.
+pub async fn list_rooms_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    if let Err(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+
+    let mut rooms: Vec<(chrono::DateTime<chrono::Utc>, serde_json::Value)> = state
+        .rooms
+        .iter()
+        .map(|entry| {
+            (
+                entry.last_sync,
+                room_summary(&state, entry.key(), entry.value()),
+            )
+        })
+        .collect();
+    rooms.sort_by_key(|(last_sync, _)| std::cmp::Reverse(*last_sync));
+    let rooms: Vec<serde_json::Value> = rooms.into_iter().map(|(_, room)| room).collect();
+
+    Json(serde_json::json!({
+        "success": true,
+        "count": rooms.len(),
+        "rooms": rooms,
+    }))
+    .into_response()
+}
+
+/// Admin: one room with its peers, including those connected to other instances.
+pub async fn get_room_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(room_code): Path<String>,
+) -> axum::response::Response {
+    if let Err(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+
+    let (summary, local_peers) = match state.rooms.get(&room_code) {
+        Some(room) => {
+            let peers: Vec<PeerInfo> = room.peers.iter().map(|e| e.value().clone()).collect();
+            (room_summary(&state, &room_code, &room), peers)
+        }
+        None => return room_not_found(),
+    };
+    let local_ids: Vec<String> = local_peers.iter().map(|peer| peer.id.clone()).collect();
+    let peers: Vec<serde_json::Value> = state
+        .room_bus
+        .room_peers(&state.db, &room_code, local_peers)
+        .await
+        .into_iter()
+        .map(|peer| {
+            let local = local_ids.contains(&peer.id);
+            let mut value = serde_json::to_value(peer).unwrap_or_default();
+            value["local"] = serde_json::Value::Bool(local);
+            value
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "success": true,
+        "room": summary,
+        "peers": peers,
+    }))
+    .into_response()
+}
+
+/// Admin: disconnects a peer, which receives `kicked` with the reason first. It may rejoin.
+pub async fn kick_peer_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path((room_code, peer_id)): Path<(String, String)>,
+    Json(payload): Json<AdminRoomActionRequest>,
+) -> axum::response::Response {
+    let claims = match ensure_admin(&state, &headers, &jar).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    let reason = match reason_or(payload, "Removed by an administrator") {
+        Ok(reason) => reason,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        }
+    };
+
+    let local_peers: Vec<PeerInfo> = match state.rooms.get(&room_code) {
+        Some(room) => room.peers.iter().map(|e| e.value().clone()).collect(),
+        None => Vec::new(),
+    };
+    let peers = state
+        .room_bus
+        .room_peers(&state.db, &room_code, local_peers)
+        .await;
+    if !peers.iter().any(|peer| peer.id == peer_id) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Peer not found in this room" })),
+        )
+            .into_response();
+    }
+
+    kick_peer(&state, &room_code, &peer_id, &reason);
+    record_room_audit(
+        &state,
+        &claims,
+        &client_ip,
+        "room.peer_kick",
+        &room_code,
+        Some(serde_json::json!({ "peer_id": peer_id, "reason": reason })),
+    )
+    .await;
+    Json(serde_json::json!({ "success": true })).into_response()
+}
+
+/// Admin: closes the room on every instance. Peers receive `room_closed` and are
+/// disconnected; queued document writes are still saved.
+pub async fn close_room_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(room_code): Path<String>,
+    Json(payload): Json<AdminRoomActionRequest>,
+) -> axum::response::Response {
+    let claims = match ensure_admin(&state, &headers, &jar).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    let reason = match reason_or(payload, "closed_by_admin") {
+        Ok(reason) => reason,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        }
+    };
+    if !state.rooms.contains_key(&room_code) {
+        return room_not_found();
+    }
+
+    close_room(&state, &room_code, &reason);
+    record_room_audit(
+        &state,
+        &claims,
+        &client_ip,
+        "room.close",
+        &room_code,
+        Some(serde_json::json!({ "reason": reason })),
+    )
+    .await;
+    Json(serde_json::json!({ "success": true })).into_response()
+}
+
+/// Admin: saves and unloads an empty room's document, so the next join reads it from the
+/// database. Rooms with peers have to be closed first.
+pub async fn evict_room_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(room_code): Path<String>,
+) -> axum::response::Response {
+    let claims = match ensure_admin(&state, &headers, &jar).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    if !state.rooms.contains_key(&room_code) {
+        return room_not_found();
+    }
+
+    match evict_room(&state, &room_code).await {
+        Ok(true) => {
+            record_room_audit(&state, &claims, &client_ip, "room.evict", &room_code, None).await;
+            Json(serde_json::json!({ "success": true })).into_response()
+        }
+        Ok(false) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "Room has connected peers; close it before evicting"
+            })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// Admin: loads a room and its persisted document into memory ahead of the first join.
+pub async fn preload_room_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(room_code): Path<String>,
+) -> axum::response::Response {
+    if let Err(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+    if ensure_room_exists(&state, &room_code).await.is_err() {
+        return room_not_found();
+    }
+
+    match state.rooms.get(&room_code) {
+        Some(room) => Json(serde_json::json!({
+            "success": true,
+            "room": room_summary(&state, &room_code, &room),
+        }))
+        .into_response(),
+        None => room_not_found(),
+    }
+}
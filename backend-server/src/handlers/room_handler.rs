@@ -1,29 +1,79 @@
 use axum::{
     extract::{Json, Path, State},
+    http::HeaderMap,
     response::IntoResponse,
 };
+use axum_extra::extract::cookie::CookieJar;
 use dashmap::DashMap;
+use mongodb::bson::oid::ObjectId;
 use tokio::sync::broadcast;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::handlers::auth_handler::extract_claims;
+use crate::models::member::WorkspaceRole;
 use crate::models::room::{CreateRoomRequest, PeerInfo, Room};
 use crate::repositories::room_repo::RoomRepository;
-use crate::services::room_service::{generate_random_id, generate_room_code};
+use crate::services::room_service::{generate_room_code, resolve_room_role};
+use crate::services::snapshot_service::SnapshotService;
 use crate::state::SharedState;
 
+/// Checks the caller could join the room. Callers who aren't logged in get 401; non-members get
+/// the same 404 as an unknown code, so a code alone reveals nothing.
+async fn room_access(
+    state: &SharedState,
+    headers: &HeaderMap,
+    jar: &CookieJar,
+    room_code: &str,
+) -> Result<(), axum::response::Response> {
+    let claims = match extract_claims(state, headers, jar).await {
+        Some(c) => c,
+        None => {
+            return Err((
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Not logged in" })),
+            )
+                .into_response())
+        }
+    };
+    let Ok(user_id) = ObjectId::parse_str(&claims.sub) else {
+        return Err((
+            axum::http::StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({ "error": "Invalid token" })),
+        )
+            .into_response());
+    };
+
+    match resolve_room_role(state, &user_id, claims.access_token.as_ref(), room_code).await {
+        Ok(role) if role.allows(WorkspaceRole::Viewer) => Ok(()),
+        _ => Err(room_not_found()),
+    }
+}
+
+fn room_not_found() -> axum::response::Response {
+    (
+        axum::http::StatusCode::NOT_FOUND,
+        axum::Json(serde_json::json!({
+            "success": false,
+            "error": "Room not found"
+        })),
+    )
+        .into_response()
+}
+
 pub async fn create_room(
     State(state): State<SharedState>,
+    headers: HeaderMap,
+    jar: CookieJar,
     payload: Option<Json<CreateRoomRequest>>,
-) -> impl IntoResponse {
-    let (requested_code, requested_host_id) = if let Some(Json(req)) = payload {
-        (req.desired_room_code, req.desired_host_id)
-    } else {
-        (None, None)
-    };
-
+) -> axum::response::Response {
+    let requested_code = payload.and_then(|Json(req)| req.desired_room_code);
     let room_code = requested_code.unwrap_or_else(generate_room_code);
 
+    if let Err(resp) = room_access(&state, &headers, &jar, &room_code).await {
+        return resp;
+    }
+
     if let Some(room) = state.rooms.get(&room_code) {
         return axum::Json(serde_json::json!({
             "success": true,
@@ -32,8 +82,9 @@ pub async fn create_room(
             "host_id": room.host_id,
             "websocket_url": format!("ws://localhost:3001/ws"),
             "restored": true,
-            "has_document": room.document_state.is_some()
-        }));
+            "has_document": room.document.is_some()
+        }))
+        .into_response();
     }
 
     let room_repo = RoomRepository::new(&state.db);
@@ -45,66 +96,95 @@ pub async fn create_room(
         }
     };
 
-    let document_state = existing_room_doc.and_then(|d| {
-        d.get("document")
-            .and_then(|v| v.as_str().map(|s| s.to_string()))
-    });
+    let stored = existing_room_doc
+        .and_then(|d| d.get("document")?.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let document = match SnapshotService::load_stored(&state.db, &room_code, &stored).await {
+        Ok(document) => document,
+        Err(e) => {
+            warn!("Could not load room {}: {}", room_code, e);
+            return axum::Json(serde_json::json!({
+                "success": false,
+                "error": "Room document could not be loaded"
+            }))
+            .into_response();
+        }
+    };
 
     let room_id = Uuid::new_v4().to_string();
-    let host_id = requested_host_id.unwrap_or_else(|| format!("host_{}", generate_random_id()));
 
-    let (tx, _) = broadcast::channel(256);
+    let (tx, _) = broadcast::channel(state.room_metrics.channel_capacity);
 
     let room = Room {
         id: room_id.clone(),
-        host_id: host_id.clone(),
+        // The first peer to join becomes host
+        host_id: None,
         created_at: chrono::Utc::now(),
         tx,
         peers: DashMap::new(),
-        document_state,
+        document,
         last_sync: chrono::Utc::now(),
+        last_snapshot: None,
         empty_since: Some(chrono::Utc::now()),
     };
 
     state.rooms.insert(room_code.clone(), room);
 
-    info!("🆕 Room created: {} (host: {})", room_code, host_id);
+    info!("🆕 Room created: {}", room_code);
 
     axum::Json(serde_json::json!({
         "success": true,
         "room_code": room_code,
         "room_id": room_id,
-        "host_id": host_id,
+        "host_id": null,
         "websocket_url": format!("ws://localhost:3001/ws"),
     }))
+    .into_response()
 }
 
+/// Peers carry who they are and what they have open, so only people who could join the room
+/// see them.
 pub async fn get_room_info(
     Path(room_code): Path<String>,
     State(state): State<SharedState>,
-) -> impl IntoResponse {
-    let _ = crate::services::room_service::ensure_room_exists(&state, &room_code).await;
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    if let Err(resp) = room_access(&state, &headers, &jar, &room_code).await {
+        return resp;
+    }
+    if crate::services::room_service::ensure_room_exists(&state, &room_code)
+        .await
+        .is_err()
+    {
+        return room_not_found();
+    }
 
-    match state.rooms.get(&room_code) {
+    let (host_id, created_at, local_peers) = match state.rooms.get(&room_code) {
         Some(room) => {
             let peers: Vec<PeerInfo> = room
                 .peers
                 .iter()
                 .map(|entry| entry.value().clone())
                 .collect();
-
-            axum::Json(serde_json::json!({
-                "success": true,
-                "room_code": room_code,
-                "host_id": room.host_id,
-                "peers": peers,
-                "created_at": room.created_at,
-                "peer_count": peers.len(),
-            }))
+            (room.host_id.clone(), room.created_at, peers)
         }
-        None => axum::Json(serde_json::json!({
-            "success": false,
-            "error": "Room not found"
-        })),
-    }
+        None => return room_not_found(),
+    };
+
+    // Includes peers connected to other instances when the room bus is shared
+    let peers = state
+        .room_bus
+        .room_peers(&state.db, &room_code, local_peers)
+        .await;
+
+    axum::Json(serde_json::json!({
+        "success": true,
+        "room_code": room_code,
+        "host_id": host_id,
+        "peers": peers,
+        "created_at": created_at,
+        "peer_count": peers.len(),
+    }))
+    .into_response()
 }
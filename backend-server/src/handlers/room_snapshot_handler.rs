@@ -0,0 +1,192 @@
+use crate::handlers::member_handler::verify_workspace_access;
+use crate::models::member::WorkspaceRole;
+use crate::models::room_snapshot::{RoomSnapshot, SnapshotAuthor};
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::room_snapshot_repo::RoomSnapshotRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
+use crate::services::client_ip_service::ClientIp;
+use crate::services::snapshot_service::SnapshotService;
+use crate::state::SharedState;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use mongodb::bson::oid::ObjectId;
+
+fn server_error(e: String) -> axum::response::Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": e })),
+    )
+        .into_response()
+}
+
+fn snapshot_json(snapshot: &RoomSnapshot) -> serde_json::Value {
+    serde_json::json!({
+        "id": snapshot.id.map(|id| id.to_hex()),
+        "author_peer_id": snapshot.author_peer_id,
+        "author_user_id": snapshot.author_user_id,
+        "reason": snapshot.reason,
+        "size": snapshot.size,
+        "task_count": snapshot.task_count,
+        "created_at": snapshot.created_at,
+    })
+}
+
+/// Looks up one of the room's snapshots, or the response to send when there is none.
+async fn find_snapshot(
+    state: &SharedState,
+    room_code: &str,
+    snapshot_id: &str,
+) -> Result<RoomSnapshot, axum::response::Response> {
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Snapshot not found" })),
+        )
+            .into_response()
+    };
+    let Ok(id) = ObjectId::parse_str(snapshot_id) else {
+        return Err(not_found());
+    };
+    match RoomSnapshotRepository::new(&state.db)
+        .find_by_id(room_code, &id)
+        .await
+    {
+        Ok(Some(snapshot)) => Ok(snapshot),
+        Ok(None) => Err(not_found()),
+        Err(e) => Err(server_error(format!("Database error: {}", e))),
+    }
+}
+
+/// `GET /api/workspaces/:ws_id/snapshots`, newest first. Documents are left out.
+pub async fn list_snapshots(
+    State(state): State<SharedState>,
+    Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(resp) => return resp,
+    };
+
+    match RoomSnapshotRepository::new(&state.db)
+        .find_by_room(&access.workspace.room_code)
+        .await
+    {
+        Ok(snapshots) => Json(serde_json::json!({
+            "success": true,
+            "snapshots": snapshots.iter().map(snapshot_json).collect::<Vec<_>>(),
+        }))
+        .into_response(),
+        Err(e) => server_error(format!("Database error: {}", e)),
+    }
+}
+
+/// `GET /api/workspaces/:ws_id/snapshots/:snapshot_id/diff`: what changed since the snapshot.
+pub async fn diff_snapshot(
+    State(state): State<SharedState>,
+    Path((ws_id, snapshot_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access = match verify_workspace_access(
+        &state,
+        &headers,
+        &jar,
+        &ws_id,
+        WorkspaceRole::Viewer,
+    )
+    .await
+    {
+        Ok(access) => access,
+        Err(resp) => return resp,
+    };
+    let room_code = &access.workspace.room_code;
+
+    let snapshot = match find_snapshot(&state, room_code, &snapshot_id).await {
+        Ok(snapshot) => snapshot,
+        Err(resp) => return resp,
+    };
+    let current = match SnapshotService::current_document(&state, room_code).await {
+        Ok(current) => current,
+        Err(e) => return server_error(e),
+    };
+
+    match SnapshotService::diff(&current, &snapshot.document) {
+        Ok(diff) => Json(serde_json::json!({
+            "success": true,
+            "snapshot": snapshot_json(&snapshot),
+            "diff": diff,
+        }))
+        .into_response(),
+        Err(e) => server_error(e),
+    }
+}
+
+/// `POST /api/workspaces/:ws_id/snapshots/:snapshot_id/restore` (admin or owner). Connected
+/// peers receive the restored document as a `document_sync`.
+pub async fn restore_snapshot(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    Path((ws_id, snapshot_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Admin).await {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+    let room_code = &access.workspace.room_code;
+
+    let snapshot = match find_snapshot(&state, room_code, &snapshot_id).await {
+        Ok(snapshot) => snapshot,
+        Err(resp) => return resp,
+    };
+    let author = SnapshotAuthor {
+        peer_id: "server".to_string(),
+        user_id: Some(access.user_id.to_hex()),
+    };
+
+    match SnapshotService::restore(&state, room_code, &snapshot, &author).await {
+        Ok(restored_task_ids) => {
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &UserRepository::new(&state.db),
+                &AuditActor::new(Some(&access.user_id.to_hex()), &client_ip),
+                AuditRecord {
+                    action: "workspace.snapshot_restore",
+                    target_type: "workspace",
+                    target_id: Some(ws_id),
+                    before: None,
+                    after: Some(serde_json::json!({
+                        "snapshot_id": snapshot_id,
+                        "snapshot_created_at": snapshot.created_at,
+                        "restored_task_ids": restored_task_ids,
+                    })),
+                },
+            )
+            .await;
+            Json(serde_json::json!({
+                "success": true,
+                "restored_task_ids": restored_task_ids,
+            }))
+            .into_response()
+        }
+        Err(e) => server_error(e),
+    }
+}
@@ -1,15 +1,20 @@
 use crate::handlers::auth_handler::extract_claims;
+use crate::models::auth::Claims;
 use crate::models::storage::{StorageConfigDocument, StorageProvider, UpdateStorageConfigRequest};
+use crate::repositories::audit_repo::AuditRepository;
 use crate::repositories::storage_repo::StorageRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
+use crate::services::client_ip_service::ClientIp;
 use crate::services::storage_service::{
     build_active_storage, decrypt_storage_config, default_storage_config_doc,
-    encrypt_value_if_present,
+    encrypt_value_if_present, ActiveStorage,
 };
 use crate::state::SharedState;
 use axum::{
     extract::{Path, Query, State},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
 use axum_extra::extract::cookie::CookieJar;
 use serde::{Deserialize, Serialize};
@@ -44,12 +49,12 @@ fn normalize_optional(value: Option<String>) -> Option<String> {
     })
 }
 
-fn ensure_admin(
+async fn ensure_admin(
     headers: &axum::http::HeaderMap,
     jar: &CookieJar,
     state: &SharedState,
-) -> Result<(), axum::response::Response> {
-    let claims = match extract_claims(headers, jar, &state.jwt_secret) {
+) -> Result<Claims, axum::response::Response> {
+    let claims = match extract_claims(state, headers, jar).await {
         Some(c) => c,
         None => {
             return Err((
@@ -68,7 +73,32 @@ fn ensure_admin(
             .into_response());
     }
 
-    Ok(())
+    Ok(claims)
+}
+
+/// Credentials are left out; they are encrypted with a random nonce and can't be compared.
+fn storage_audit_snapshot(storage: &ActiveStorage) -> serde_json::Value {
+    serde_json::json!({
+        "provider": storage.provider,
+        "bucket": storage.bucket,
+        "region": storage.region,
+        "endpoint": storage.endpoint,
+    })
+}
+
+async fn record_storage_audit(
+    state: &SharedState,
+    claims: &Claims,
+    client_ip: &ClientIp,
+    record: AuditRecord,
+) {
+    AuditService::record(
+        &AuditRepository::new(&state.db),
+        &UserRepository::new(&state.db),
+        &AuditActor::new(Some(&claims.sub), client_ip),
+        record,
+    )
+    .await;
 }
 
 pub async fn get_storage_config_handler(
@@ -76,7 +106,7 @@ pub async fn get_storage_config_handler(
     jar: CookieJar,
     headers: axum::http::HeaderMap,
 ) -> axum::response::Response {
-    if let Err(response) = ensure_admin(&headers, &jar, &state) {
+    if let Err(response) = ensure_admin(&headers, &jar, &state).await {
         return response;
     }
 
@@ -117,13 +147,15 @@ pub async fn get_storage_config_handler(
 
 pub async fn update_storage_config_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
     Json(payload): Json<UpdateStorageConfigRequest>,
 ) -> axum::response::Response {
-    if let Err(response) = ensure_admin(&headers, &jar, &state) {
-        return response;
-    }
+    let claims = match ensure_admin(&headers, &jar, &state).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
 
     let bucket = normalize_optional(payload.bucket);
     let region = normalize_optional(payload.region);
@@ -177,6 +209,7 @@ pub async fn update_storage_config_handler(
     };
 
     let next_storage = build_active_storage(Some(&config)).await;
+    let previous_storage = state.storage_snapshot().await;
     let repo = StorageRepository::new(&state.db);
     if let Err(error) = repo.save_storage_config(&config).await {
         tracing::error!("Failed to save storage config: {:?}", error);
@@ -188,6 +221,19 @@ pub async fn update_storage_config_handler(
     }
 
     state.replace_storage(next_storage.clone()).await;
+    record_storage_audit(
+        &state,
+        &claims,
+        &client_ip,
+        AuditRecord {
+            action: "storage.config_update",
+            target_type: "storage_config",
+            target_id: None,
+            before: Some(storage_audit_snapshot(&previous_storage)),
+            after: Some(storage_audit_snapshot(&next_storage)),
+        },
+    )
+    .await;
 
     Json(serde_json::json!({
         "success": true,
@@ -204,12 +250,14 @@ pub async fn update_storage_config_handler(
 
 pub async fn reset_storage_config_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
 ) -> axum::response::Response {
-    if let Err(response) = ensure_admin(&headers, &jar, &state) {
-        return response;
-    }
+    let claims = match ensure_admin(&headers, &jar, &state).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
 
     let repo = StorageRepository::new(&state.db);
     if let Err(error) = repo.delete_storage_config().await {
@@ -221,8 +269,22 @@ pub async fn reset_storage_config_handler(
             .into_response();
     }
 
+    let previous_storage = state.storage_snapshot().await;
     let next_storage = build_active_storage(None).await;
     state.replace_storage(next_storage.clone()).await;
+    record_storage_audit(
+        &state,
+        &claims,
+        &client_ip,
+        AuditRecord {
+            action: "storage.config_reset",
+            target_type: "storage_config",
+            target_id: None,
+            before: Some(storage_audit_snapshot(&previous_storage)),
+            after: Some(storage_audit_snapshot(&next_storage)),
+        },
+    )
+    .await;
 
     Json(serde_json::json!({
         "success": true,
@@ -241,7 +303,7 @@ pub async fn get_storage_stats_handler(
     jar: CookieJar,
     headers: axum::http::HeaderMap,
 ) -> axum::response::Response {
-    if let Err(response) = ensure_admin(&headers, &jar, &state) {
+    if let Err(response) = ensure_admin(&headers, &jar, &state).await {
         return response;
     }
 
@@ -328,7 +390,7 @@ pub async fn list_storage_objects_handler(
     headers: axum::http::HeaderMap,
     Query(query): Query<StorageObjectsQuery>,
 ) -> axum::response::Response {
-    if let Err(response) = ensure_admin(&headers, &jar, &state) {
+    if let Err(response) = ensure_admin(&headers, &jar, &state).await {
         return response;
     }
 
@@ -416,13 +478,15 @@ pub async fn list_storage_objects_handler(
 
 pub async fn delete_storage_object_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
     Path(key): Path<String>,
 ) -> axum::response::Response {
-    if let Err(response) = ensure_admin(&headers, &jar, &state) {
-        return response;
-    }
+    let claims = match ensure_admin(&headers, &jar, &state).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
 
     let storage = state.storage_snapshot().await;
     let client = match &storage.client {
@@ -443,7 +507,22 @@ pub async fn delete_storage_object_handler(
         .send()
         .await
     {
-        Ok(_) => Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(_) => {
+            record_storage_audit(
+                &state,
+                &claims,
+                &client_ip,
+                AuditRecord {
+                    action: "storage.object_delete",
+                    target_type: "storage_object",
+                    target_id: Some(key.clone()),
+                    before: Some(serde_json::json!({ "bucket": storage.bucket, "key": key })),
+                    after: None,
+                },
+            )
+            .await;
+            Json(serde_json::json!({ "success": true })).into_response()
+        }
         Err(error) => {
             tracing::error!("Failed to delete storage object {}: {:?}", key, error);
             (
@@ -457,13 +536,15 @@ pub async fn delete_storage_object_handler(
 
 pub async fn bulk_delete_storage_objects_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     jar: CookieJar,
     headers: axum::http::HeaderMap,
     Json(payload): Json<BulkDeleteStorageObjectsRequest>,
 ) -> axum::response::Response {
-    if let Err(response) = ensure_admin(&headers, &jar, &state) {
-        return response;
-    }
+    let claims = match ensure_admin(&headers, &jar, &state).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
 
     let storage = state.storage_snapshot().await;
     let client = match &storage.client {
@@ -492,7 +573,7 @@ pub async fn bulk_delete_storage_objects_handler(
             .into_response();
     }
 
-    let mut deleted = 0usize;
+    let mut deleted_keys: Vec<String> = Vec::new();
     let mut failed: Vec<String> = Vec::new();
 
     for key in &keys {
@@ -503,13 +584,34 @@ pub async fn bulk_delete_storage_objects_handler(
             .send()
             .await
         {
-            Ok(_) => deleted += 1,
+            Ok(_) => deleted_keys.push(key.clone()),
             Err(error) => {
                 tracing::error!("Failed to delete storage object {}: {:?}", key, error);
                 failed.push(key.clone());
             }
         }
     }
+    let deleted = deleted_keys.len();
+
+    // Partial failures are recorded too; whatever was deleted is gone either way
+    if deleted > 0 {
+        record_storage_audit(
+            &state,
+            &claims,
+            &client_ip,
+            AuditRecord {
+                action: "storage.objects_bulk_delete",
+                target_type: "storage_object",
+                target_id: None,
+                before: Some(serde_json::json!({
+                    "bucket": storage.bucket,
+                    "keys": deleted_keys,
+                })),
+                after: None,
+            },
+        )
+        .await;
+    }
 
     if !failed.is_empty() {
         return (
@@ -0,0 +1,318 @@
+use crate::handlers::auth_handler::extract_session_claims;
+use crate::models::auth::{Claims, DisableTwoFactorRequest, TwoFactorCodeRequest};
+use crate::models::security::UpdateSecurityPolicyRequest;
+use crate::models::user::User;
+use crate::repositories::security_repo::SecurityRepository;
+use crate::repositories::session_repo::SessionRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::two_factor_service::TwoFactorService;
+use crate::state::SharedState;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use mongodb::bson::oid::ObjectId;
+
+async fn current_user(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Result<(Claims, User), axum::response::Response> {
+    let unauthorized = || {
+        (
+            axum::http::StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Unauthorized" })),
+        )
+            .into_response()
+    };
+    let claims = extract_session_claims(state, headers, jar)
+        .await
+        .ok_or_else(unauthorized)?;
+    let oid = ObjectId::parse_str(&claims.sub).map_err(|_| unauthorized())?;
+    match UserRepository::new(&state.db).find_by_id(&oid).await {
+        Ok(Some(user)) => Ok((claims, user)),
+        Ok(None) => Err(unauthorized()),
+        Err(e) => Err((
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+        )
+            .into_response()),
+    }
+}
+
+async fn ensure_admin(
+    state: &SharedState,
+    headers: &axum::http::HeaderMap,
+    jar: &CookieJar,
+) -> Result<(Claims, User), axum::response::Response> {
+    let (claims, user) = current_user(state, headers, jar).await?;
+    if claims.role != "admin" {
+        return Err((
+            axum::http::StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Admin access required" })),
+        )
+            .into_response());
+    }
+    Ok((claims, user))
+}
+
+fn bad_request(e: String) -> axum::response::Response {
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": e })),
+    )
+        .into_response()
+}
+
+fn database_error(e: mongodb::error::Error) -> axum::response::Response {
+    (
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": format!("Database error: {}", e) })),
+    )
+        .into_response()
+}
+
+pub async fn get_two_factor_status_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let (_, user) = match current_user(&state, &headers, &jar).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+    let policy = match SecurityRepository::new(&state.db).get_policy().await {
+        Ok(p) => p,
+        Err(e) => return database_error(e),
+    };
+
+    Json(serde_json::json!({
+        "success": true,
+        "enabled": user.two_factor.enabled,
+        "enabled_at": user.two_factor.enabled_at,
+        "recovery_codes_remaining": user.two_factor.recovery_code_hashes.len(),
+        "required": policy.requires_two_factor(&user.role),
+    }))
+    .into_response()
+}
+
+pub async fn setup_two_factor_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    let (_, mut user) = match current_user(&state, &headers, &jar).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+    let user_repo = UserRepository::new(&state.db);
+
+    match TwoFactorService::begin_setup(&user_repo, &mut user).await {
+        Ok((secret, otpauth_uri)) => Json(serde_json::json!({
+            "success": true,
+            "secret": secret,
+            "otpauth_uri": otpauth_uri,
+        }))
+        .into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+pub async fn enable_two_factor_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> axum::response::Response {
+    let (claims, mut user) = match current_user(&state, &headers, &jar).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+    let user_repo = UserRepository::new(&state.db);
+
+    match TwoFactorService::confirm_setup(&user_repo, &mut user, &payload.code).await {
+        Ok(recovery_codes) => {
+            // Other devices signed in with just a password have to log in again
+            if let Some(oid) = user.id {
+                let _ = SessionRepository::new(&state.db)
+                    .revoke_all_for_user(&oid, claims.sid.as_deref())
+                    .await;
+            }
+            Json(serde_json::json!({
+                "success": true,
+                "recovery_codes": recovery_codes,
+            }))
+            .into_response()
+        }
+        Err(e) => bad_request(e),
+    }
+}
+
+pub async fn disable_two_factor_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<DisableTwoFactorRequest>,
+) -> axum::response::Response {
+    let (_, mut user) = match current_user(&state, &headers, &jar).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+    let policy = match SecurityRepository::new(&state.db).get_policy().await {
+        Ok(p) => p,
+        Err(e) => return database_error(e),
+    };
+    if policy.requires_two_factor(&user.role) {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            Json(
+                serde_json::json!({ "error": "Two-factor authentication is required for admins" }),
+            ),
+        )
+            .into_response();
+    }
+
+    let user_repo = UserRepository::new(&state.db);
+    match TwoFactorService::disable(
+        &user_repo,
+        &mut user,
+        &payload.password,
+        payload.code.as_deref(),
+        payload.recovery_code.as_deref(),
+    )
+    .await
+    {
+        Ok(_) => Json(serde_json::json!({ "success": true })).into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+pub async fn regenerate_recovery_codes_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> axum::response::Response {
+    let (_, user) = match current_user(&state, &headers, &jar).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+    let user_repo = UserRepository::new(&state.db);
+
+    match TwoFactorService::regenerate_recovery_codes(&user_repo, &user, &payload.code).await {
+        Ok(recovery_codes) => Json(serde_json::json!({
+            "success": true,
+            "recovery_codes": recovery_codes,
+        }))
+        .into_response(),
+        Err(e) => bad_request(e),
+    }
+}
+
+/// Admin: clears another user's 2FA and signs them out. Under the admin policy they will
+/// be asked to enroll again on their next login.
+pub async fn reset_user_two_factor_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<String>,
+) -> axum::response::Response {
+    if let Err(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+    let user_repo = UserRepository::new(&state.db);
+
+    match TwoFactorService::reset(&user_repo, &id).await {
+        Ok(user) => {
+            if let Some(oid) = user.id {
+                let _ = SessionRepository::new(&state.db)
+                    .revoke_all_for_user(&oid, None)
+                    .await;
+            }
+            Json(serde_json::json!({ "success": true })).into_response()
+        }
+        Err(e) => bad_request(e),
+    }
+}
+
+pub async fn get_security_policy_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    if let Err(response) = ensure_admin(&state, &headers, &jar).await {
+        return response;
+    }
+    match SecurityRepository::new(&state.db).get_policy().await {
+        Ok(policy) => {
+            Json(serde_json::json!({ "success": true, "policy": policy })).into_response()
+        }
+        Err(e) => database_error(e),
+    }
+}
+
+pub async fn update_security_policy_handler(
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<UpdateSecurityPolicyRequest>,
+) -> axum::response::Response {
+    let (_, admin) = match ensure_admin(&state, &headers, &jar).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+    let security_repo = SecurityRepository::new(&state.db);
+    let mut policy = match security_repo.get_policy().await {
+        Ok(p) => p,
+        Err(e) => return database_error(e),
+    };
+
+    let turning_on =
+        payload.require_admin_two_factor == Some(true) && !policy.require_admin_two_factor;
+    // Don't let an admin lock themselves out of the settings they just changed
+    if turning_on && !admin.two_factor.enabled {
+        return bad_request(
+            "Enable two-factor authentication on your own account first".to_string(),
+        );
+    }
+
+    if let Some(required) = payload.require_admin_two_factor {
+        policy.require_admin_two_factor = required;
+    }
+    policy.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    policy.updated_by = admin.id.map(|id| id.to_hex());
+
+    if let Err(e) = security_repo.save_policy(&policy).await {
+        return database_error(e);
+    }
+
+    // Admins without 2FA have to log in again and enroll
+    let mut signed_out = 0;
+    if turning_on {
+        let user_repo = UserRepository::new(&state.db);
+        let session_repo = SessionRepository::new(&state.db);
+        let users = match user_repo.find_all().await {
+            Ok(users) => users,
+            Err(e) => return database_error(e),
+        };
+        for user in users
+            .iter()
+            .filter(|u| u.role == "admin" && !u.two_factor.enabled)
+        {
+            if let Some(oid) = user.id {
+                if session_repo.revoke_all_for_user(&oid, None).await.is_ok() {
+                    signed_out += 1;
+                }
+            }
+        }
+    }
+
+    Json(serde_json::json!({
+        "success": true,
+        "policy": policy,
+        "signed_out_admins": signed_out,
+    }))
+    .into_response()
+}
@@ -2,25 +2,46 @@ use axum::{
     extract::{Json, Path, State},
     http::HeaderMap,
     response::IntoResponse,
+    Extension,
 };
 use axum_extra::extract::cookie::CookieJar;
 
-use crate::handlers::auth_handler::extract_user_id;
-use crate::models::workspace::{CreateWorkspaceRequest, UpdateWorkspaceRequest};
+use crate::handlers::auth_handler::{extract_claims, extract_session_user_id};
+use crate::models::access_token::AccessTokenScope;
+use crate::models::workspace::{CreateWorkspaceRequest, UpdateWorkspaceRequest, Workspace};
+use crate::repositories::audit_repo::AuditRepository;
 use crate::repositories::data_repo::DataRepository;
+use crate::repositories::member_repo::MemberRepository;
 use crate::repositories::room_repo::RoomRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::repositories::workspace_invite_repo::WorkspaceInviteRepository;
 use crate::repositories::workspace_repo::WorkspaceRepository;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
+use crate::services::client_ip_service::ClientIp;
+use crate::services::member_service::MemberService;
 use crate::services::workspace_service::WorkspaceService;
 use crate::state::SharedState;
 use mongodb::bson::oid::ObjectId;
 
+/// Personal access tokens only see the workspaces they were scoped to.
+fn within_token_scope(scope: &Option<AccessTokenScope>, workspace: &Workspace) -> bool {
+    match (scope, workspace.id) {
+        (Some(scope), Some(id)) => scope.allows_workspace(&id),
+        (Some(_), None) => false,
+        (None, _) => true,
+    }
+}
+
 pub async fn get_workspaces_handler(
     State(state): State<SharedState>,
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
-        Some(id) => id,
+    let (user_id, token_scope) = match extract_claims(&state, &headers, &jar)
+        .await
+        .and_then(|c| Some((ObjectId::parse_str(&c.sub).ok()?, c.access_token)))
+    {
+        Some(caller) => caller,
         None => {
             return (
                 axum::http::StatusCode::UNAUTHORIZED,
@@ -31,16 +52,16 @@ pub async fn get_workspaces_handler(
     };
 
     let workspace_repo = WorkspaceRepository::new(&state.db);
-    let data_repo = DataRepository::new(&state.db);
-    let assigned_ws_ids = data_repo
-        .find_assigned_workspaces(&user_id.to_hex())
+    let member_ws_ids = MemberRepository::new(&state.db)
+        .find_workspace_ids_by_user(&user_id)
         .await
         .unwrap_or_default();
 
-    match WorkspaceService::get_user_workspaces(&workspace_repo, &user_id, assigned_ws_ids).await {
+    match WorkspaceService::get_user_workspaces(&workspace_repo, &user_id, member_ws_ids).await {
         Ok(workspaces) => {
             let workspaces_json: Vec<_> = workspaces
                 .into_iter()
+                .filter(|w| within_token_scope(&token_scope, w))
                 .map(|w| {
                     serde_json::json!({
                         "id": w.id.map(|id| id.to_hex()).unwrap_or_default(),
@@ -71,8 +92,11 @@ pub async fn get_workspaces_stats_handler(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
-        Some(id) => id,
+    let (user_id, token_scope) = match extract_claims(&state, &headers, &jar)
+        .await
+        .and_then(|c| Some((ObjectId::parse_str(&c.sub).ok()?, c.access_token)))
+    {
+        Some(caller) => caller,
         None => {
             return (
                 axum::http::StatusCode::UNAUTHORIZED,
@@ -84,8 +108,8 @@ pub async fn get_workspaces_stats_handler(
 
     let workspace_repo = WorkspaceRepository::new(&state.db);
     let data_repo = DataRepository::new(&state.db);
-    let assigned_ws_ids = data_repo
-        .find_assigned_workspaces(&user_id.to_hex())
+    let member_ws_ids = MemberRepository::new(&state.db)
+        .find_workspace_ids_by_user(&user_id)
         .await
         .unwrap_or_default();
 
@@ -93,7 +117,7 @@ pub async fn get_workspaces_stats_handler(
         match crate::services::workspace_service::WorkspaceService::get_user_workspaces(
             &workspace_repo,
             &user_id,
-            assigned_ws_ids,
+            member_ws_ids,
         )
         .await
         {
@@ -107,7 +131,11 @@ pub async fn get_workspaces_stats_handler(
             }
         };
 
-    let ws_ids: Vec<ObjectId> = workspaces.iter().filter_map(|w| w.id).collect();
+    let ws_ids: Vec<ObjectId> = workspaces
+        .iter()
+        .filter(|w| within_token_scope(&token_scope, w))
+        .filter_map(|w| w.id)
+        .collect();
 
     let my_assignee_map = data_repo
         .find_user_assignee_ids_by_workspace_ids(&user_id.to_hex(), &ws_ids)
@@ -148,7 +176,7 @@ pub async fn create_workspace_handler(
     jar: CookieJar,
     Json(payload): Json<CreateWorkspaceRequest>,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
         Some(id) => id,
         None => {
             return (
@@ -160,7 +188,9 @@ pub async fn create_workspace_handler(
     };
 
     let workspace_repo = WorkspaceRepository::new(&state.db);
-    match WorkspaceService::create_workspace(&workspace_repo, &user_id, payload).await {
+    let member_repo = MemberRepository::new(&state.db);
+    match WorkspaceService::create_workspace(&workspace_repo, &member_repo, &user_id, payload).await
+    {
         Ok(workspace) => {
             let workspace_json = serde_json::json!({
                 "id": workspace.id.map(|id| id.to_hex()).unwrap_or_default(),
@@ -190,7 +220,7 @@ pub async fn update_workspace_handler(
     jar: CookieJar,
     Json(payload): Json<UpdateWorkspaceRequest>,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
         Some(id) => id,
         None => {
             return (
@@ -255,11 +285,12 @@ pub async fn update_workspace_handler(
 
 pub async fn delete_workspace_handler(
     State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
     Path(id): Path<String>,
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
         Some(id) => id,
         None => {
             return (
@@ -284,7 +315,7 @@ pub async fn delete_workspace_handler(
     let workspace_repo = WorkspaceRepository::new(&state.db);
 
     // Only the owner can delete a workspace
-    match workspace_repo.find_by_id(&workspace_id).await {
+    let workspace = match workspace_repo.find_by_id(&workspace_id).await {
         Ok(Some(ws)) if ws.owner_id != user_id => {
             return (axum::http::StatusCode::FORBIDDEN, axum::Json(serde_json::json!({ "error": "Only the workspace owner can delete this workspace" }))).into_response();
         }
@@ -302,20 +333,44 @@ pub async fn delete_workspace_handler(
             )
                 .into_response();
         }
-        _ => {}
-    }
+        Ok(Some(ws)) => ws,
+    };
 
     let room_repo = RoomRepository::new(&state.db);
+    let member_repo = MemberRepository::new(&state.db);
+    let invite_repo = WorkspaceInviteRepository::new(&state.db);
     match WorkspaceService::delete_workspace(
         &workspace_repo,
         &room_repo,
-        &state.rooms,
+        &member_repo,
+        &invite_repo,
+        &state,
         &user_id,
         &workspace_id,
     )
     .await
     {
-        Ok(true) => axum::Json(serde_json::json!({ "success": true })).into_response(),
+        Ok(true) => {
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &UserRepository::new(&state.db),
+                &AuditActor::new(Some(&user_id.to_hex()), &client_ip),
+                AuditRecord {
+                    action: "workspace.delete",
+                    target_type: "workspace",
+                    target_id: Some(id),
+                    before: Some(serde_json::json!({
+                        "name": workspace.name,
+                        "short_name": workspace.short_name,
+                        "room_code": workspace.room_code,
+                        "owner_id": workspace.owner_id.to_hex(),
+                    })),
+                    after: None,
+                },
+            )
+            .await;
+            axum::Json(serde_json::json!({ "success": true })).into_response()
+        }
         Ok(false) => (
             axum::http::StatusCode::NOT_FOUND,
             axum::Json(serde_json::json!({ "error": "Workspace not found" })),
@@ -329,14 +384,116 @@ pub async fn delete_workspace_handler(
     }
 }
 
+/// Owner: `POST /api/workspaces/:id/room-code/rotate` replaces the room code. Anyone still
+/// connected with the old code is disconnected and has to reopen the workspace.
+pub async fn rotate_room_code_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                axum::Json(serde_json::json!({ "error": "Not logged in" })),
+            )
+                .into_response()
+        }
+    };
+
+    let workspace_id = match ObjectId::parse_str(&id) {
+        Ok(oid) => oid,
+        Err(_) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({ "error": "Invalid workspace ID syntax" })),
+            )
+                .into_response()
+        }
+    };
+
+    let workspace_repo = WorkspaceRepository::new(&state.db);
+    let workspace = match workspace_repo.find_by_id(&workspace_id).await {
+        Ok(Some(ws)) if ws.owner_id != user_id => {
+            return (
+                axum::http::StatusCode::FORBIDDEN,
+                axum::Json(serde_json::json!({
+                    "error": "Only the workspace owner can rotate the room code"
+                })),
+            )
+                .into_response();
+        }
+        Ok(Some(ws)) => ws,
+        Ok(None) => {
+            return (
+                axum::http::StatusCode::NOT_FOUND,
+                axum::Json(serde_json::json!({ "error": "Workspace not found" })),
+            )
+                .into_response();
+        }
+        Err(_) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({ "error": "Database error" })),
+            )
+                .into_response();
+        }
+    };
+
+    let room_repo = RoomRepository::new(&state.db);
+    match WorkspaceService::rotate_room_code(
+        &workspace_repo,
+        &room_repo,
+        &state,
+        &user_id,
+        &workspace,
+    )
+    .await
+    {
+        Ok(Some(room_code)) => {
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &UserRepository::new(&state.db),
+                &AuditActor::new(Some(&user_id.to_hex()), &client_ip),
+                AuditRecord {
+                    action: "workspace.room_code_rotate",
+                    target_type: "workspace",
+                    target_id: Some(id),
+                    before: None,
+                    after: None,
+                },
+            )
+            .await;
+            axum::Json(serde_json::json!({ "success": true, "room_code": room_code }))
+                .into_response()
+        }
+        Ok(None) => (
+            axum::http::StatusCode::NOT_FOUND,
+            axum::Json(serde_json::json!({ "error": "Workspace not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
 pub async fn check_workspace_access_handler(
     State(state): State<SharedState>,
     Path(room_code): Path<String>,
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
-        Some(id) => id,
+    let (user_id, token_scope) = match extract_claims(&state, &headers, &jar)
+        .await
+        .and_then(|c| Some((ObjectId::parse_str(&c.sub).ok()?, c.access_token)))
+    {
+        Some(caller) => caller,
         None => {
             return (
                 axum::http::StatusCode::UNAUTHORIZED,
@@ -365,7 +522,7 @@ pub async fn check_workspace_access_handler(
 
     let ws = cursor.next().await.and_then(|r| r.ok());
 
-    match ws {
+    match ws.filter(|w| within_token_scope(&token_scope, w)) {
         Some(w) => {
             let workspace_json = serde_json::json!({
                 "id": w.id.map(|id| id.to_hex()).unwrap_or_default(),
@@ -377,28 +534,34 @@ pub async fn check_workspace_access_handler(
                 "room_code": w.room_code.clone(),
             });
 
-            if w.owner_id == user_id {
-                return axum::Json(serde_json::json!({
-                    "success": true,
-                    "has_access": true,
-                    "workspace": workspace_json
-                }))
-                .into_response();
-            }
-            if let Some(id) = w.id {
-                let data_repo = crate::repositories::data_repo::DataRepository::new(&state.db);
-                if let Ok(assigned) = data_repo.find_assigned_workspaces(&user_id.to_hex()).await {
-                    if assigned.contains(&id) {
-                        return axum::Json(serde_json::json!({
-                            "success": true,
-                            "has_access": true,
-                            "workspace": workspace_json
-                        }))
-                        .into_response();
-                    }
+            let member_repo = MemberRepository::new(&state.db);
+            match MemberService::resolve_role(&member_repo, &w, &user_id).await {
+                Ok(Some(role)) => {
+                    let role = match &token_scope {
+                        Some(scope) => scope.cap_role(role),
+                        None => role,
+                    };
+                    return axum::Json(serde_json::json!({
+                        "success": true,
+                        "has_access": true,
+                        "role": role,
+                        "workspace": workspace_json
+                    }))
+                    .into_response();
+                }
+                // Non-members get the same answer as for an unknown code, so a room code
+                // can't be used to probe for workspaces; joining goes through invite links.
+                Ok(None) => {}
+                Err(e) => {
+                    return (
+                        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        axum::Json(serde_json::json!({ "error": e })),
+                    )
+                        .into_response()
                 }
             }
-            axum::Json(serde_json::json!({ "success": true, "has_access": false })).into_response()
+            axum::Json(serde_json::json!({ "success": false, "error": "Workspace not found" }))
+                .into_response()
         }
         None => axum::Json(serde_json::json!({ "success": false, "error": "Workspace not found" }))
             .into_response(),
@@ -411,7 +574,7 @@ pub async fn get_notification_config_handler(
     headers: HeaderMap,
     jar: CookieJar,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
         Some(id) => id,
         None => {
             return (
@@ -467,7 +630,7 @@ pub async fn update_notification_config_handler(
     jar: CookieJar,
     Json(payload): Json<crate::models::workspace::UpdateNotificationConfigRequest>,
 ) -> axum::response::Response {
-    let user_id = match extract_user_id(&headers, &jar, &state.jwt_secret) {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
         Some(id) => id,
         None => {
             return (
@@ -0,0 +1,260 @@
+use crate::handlers::auth_handler::extract_session_user_id;
+use crate::handlers::member_handler::verify_workspace_access;
+use crate::models::member::WorkspaceRole;
+use crate::models::workspace_invite::CreateWorkspaceInviteRequest;
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::member_repo::MemberRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::repositories::workspace_invite_repo::WorkspaceInviteRepository;
+use crate::repositories::workspace_repo::WorkspaceRepository;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
+use crate::services::client_ip_service::ClientIp;
+use crate::services::member_service::MemberService;
+use crate::services::workspace_invite_service::WorkspaceInviteService;
+use crate::state::SharedState;
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::IntoResponse,
+    Extension, Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+fn unauthorized() -> axum::response::Response {
+    (
+        axum::http::StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({ "error": "Not logged in" })),
+    )
+        .into_response()
+}
+
+fn invalid_invite() -> axum::response::Response {
+    (
+        axum::http::StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "This invite link is invalid or has expired" })),
+    )
+        .into_response()
+}
+
+fn server_error(e: String) -> axum::response::Response {
+    (
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": e })),
+    )
+        .into_response()
+}
+
+/// `GET /api/workspaces/:ws_id/invites` (admin or owner). Tokens are never listed.
+pub async fn list_invites_handler(
+    State(state): State<SharedState>,
+    Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Admin).await {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+
+    match WorkspaceInviteRepository::new(&state.db)
+        .find_by_workspace(&access.workspace_id)
+        .await
+    {
+        Ok(invites) => Json(serde_json::json!({
+            "success": true,
+            "invites": invites.iter().map(WorkspaceInviteService::to_json).collect::<Vec<_>>(),
+        }))
+        .into_response(),
+        Err(e) => server_error(format!("Database error: {}", e)),
+    }
+}
+
+/// `POST /api/workspaces/:ws_id/invites`. The link is only ever returned by this call.
+pub async fn create_invite_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    Path(ws_id): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Json(payload): Json<CreateWorkspaceInviteRequest>,
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Admin).await {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+
+    if payload.role == Some(WorkspaceRole::Admin) && access.role != WorkspaceRole::Owner {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Only the workspace owner can add admins" })),
+        )
+            .into_response();
+    }
+
+    let invite_repo = WorkspaceInviteRepository::new(&state.db);
+    match WorkspaceInviteService::create(
+        &invite_repo,
+        &access.workspace_id,
+        &access.user_id,
+        payload,
+    )
+    .await
+    {
+        Ok((invite, token)) => {
+            let invite_json = WorkspaceInviteService::to_json(&invite);
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &UserRepository::new(&state.db),
+                &AuditActor::new(Some(&access.user_id.to_hex()), &client_ip),
+                AuditRecord {
+                    action: "workspace.invite_create",
+                    target_type: "workspace",
+                    target_id: Some(ws_id),
+                    before: None,
+                    after: Some(invite_json.clone()),
+                },
+            )
+            .await;
+
+            Json(serde_json::json!({
+                "success": true,
+                "token": token,
+                "url": WorkspaceInviteService::link(&state.auth_settings.app_base_url, &token),
+                "invite": invite_json,
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// `DELETE /api/workspaces/:ws_id/invites/:invite_id`. Members who already joined stay.
+pub async fn revoke_invite_handler(
+    State(state): State<SharedState>,
+    Extension(client_ip): Extension<ClientIp>,
+    Path((ws_id, invite_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let access =
+        match verify_workspace_access(&state, &headers, &jar, &ws_id, WorkspaceRole::Admin).await {
+            Ok(access) => access,
+            Err(resp) => return resp,
+        };
+
+    match WorkspaceInviteRepository::new(&state.db)
+        .revoke(&access.workspace_id, &invite_id)
+        .await
+    {
+        Ok(true) => {
+            AuditService::record(
+                &AuditRepository::new(&state.db),
+                &UserRepository::new(&state.db),
+                &AuditActor::new(Some(&access.user_id.to_hex()), &client_ip),
+                AuditRecord {
+                    action: "workspace.invite_revoke",
+                    target_type: "workspace",
+                    target_id: Some(ws_id),
+                    before: None,
+                    after: Some(serde_json::json!({ "invite_id": invite_id })),
+                },
+            )
+            .await;
+            Json(serde_json::json!({ "success": true })).into_response()
+        }
+        Ok(false) => (
+            axum::http::StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Invite not found" })),
+        )
+            .into_response(),
+        Err(e) => server_error(format!("Database error: {}", e)),
+    }
+}
+
+/// `GET /api/invites/:token` shows what the link leads to before it is accepted.
+pub async fn preview_invite_handler(
+    State(state): State<SharedState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => return unauthorized(),
+    };
+
+    let invite_repo = WorkspaceInviteRepository::new(&state.db);
+    let workspace_repo = WorkspaceRepository::new(&state.db);
+    let (invite, workspace) =
+        match WorkspaceInviteService::find_usable(&invite_repo, &workspace_repo, &token).await {
+            Ok(Some(found)) => found,
+            Ok(None) => return invalid_invite(),
+            Err(e) => return server_error(e),
+        };
+
+    let member_repo = MemberRepository::new(&state.db);
+    let current_role = match MemberService::resolve_role(&member_repo, &workspace, &user_id).await {
+        Ok(role) => role,
+        Err(e) => return server_error(e),
+    };
+
+    Json(serde_json::json!({
+        "success": true,
+        "workspace": {
+            "name": workspace.name,
+            "short_name": workspace.short_name,
+            "color": workspace.color,
+            "icon": workspace.icon,
+        },
+        "role": invite.role,
+        "expires_at": invite.expires_at,
+        "already_member": current_role.is_some(),
+    }))
+    .into_response()
+}
+
+/// `POST /api/invites/:token/accept` joins the workspace with the invite's role.
+pub async fn accept_invite_handler(
+    State(state): State<SharedState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let user_id = match extract_session_user_id(&state, &headers, &jar).await {
+        Some(id) => id,
+        None => return unauthorized(),
+    };
+
+    let invite_repo = WorkspaceInviteRepository::new(&state.db);
+    let workspace_repo = WorkspaceRepository::new(&state.db);
+    let member_repo = MemberRepository::new(&state.db);
+    match WorkspaceInviteService::accept(
+        &invite_repo,
+        &workspace_repo,
+        &member_repo,
+        &token,
+        &user_id,
+    )
+    .await
+    {
+        Ok(Some(accepted)) => Json(serde_json::json!({
+            "success": true,
+            "already_member": accepted.already_member,
+            "role": accepted.role,
+            "workspace": {
+                "id": accepted.workspace.id.map(|id| id.to_hex()),
+                "name": accepted.workspace.name,
+                "room_code": accepted.workspace.room_code,
+            },
+        }))
+        .into_response(),
+        Ok(None) => invalid_invite(),
+        Err(e) => server_error(e),
+    }
+}
@@ -1,95 +1,282 @@
 use axum::{
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
     extract::State,
-    response::IntoResponse,
+    http::HeaderMap,
+    response::{sse::Event, IntoResponse},
+    Extension, Json,
 };
-use tokio::sync::broadcast;
+use axum_extra::extract::cookie::CookieJar;
+use mongodb::bson::oid::ObjectId;
+use std::sync::atomic::Ordering;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{Duration, Instant};
 use tracing::{info, warn};
+use wasm_crdt::{CrdtDocument, VersionVector};
 
+use crate::handlers::auth_handler::{
+    connection_token, extract_ws_claims, recheck_connection_token, WS_AUTH_PROTOCOL,
+};
 use crate::models::{
-    message::{ClientMessage, ServerMessage, SystemEvent},
-    room::{PeerInfo, RoomEvent},
+    member::WorkspaceRole,
+    message::{ClientMessage, ErrorCode, ServerMessage, SystemEvent},
+    room::{PeerInfo, PeerUser, RoomEvent},
+    room_snapshot::SnapshotAuthor,
 };
-use crate::repositories::room_repo::RoomRepository;
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::audit_service::{AuditActor, AuditRecord, AuditService};
+use crate::services::client_ip_service::ClientIp;
+use crate::services::document_service::DocumentService;
+use crate::services::presence_service::{PresenceService, PRESENCE_THROTTLE_MS};
+use crate::services::room_bus_service::elect_host;
+use crate::services::room_service::{generate_random_id, remove_peer, resolve_room_role};
+use crate::services::snapshot_service::SnapshotService;
+use crate::services::ws_frame_service::{decode_frame, Wire, FRAME_PROTOCOL};
+use crate::services::ws_limit_service::{log_preview, ConnectionLimiter};
 use crate::state::SharedState;
 
+/// Who is on the other end of the socket or event stream. The peer id is assigned by the
+/// server.
+pub(crate) struct WsCaller {
+    pub(crate) peer_id: String,
+    pub(crate) user_id: ObjectId,
+    /// Token the connection was opened with, re-checked while it lasts
+    credential: Option<String>,
+    user: Option<PeerUser>,
+    /// Admin acting as the user; their changes over the socket go to the audit log
+    impersonator: Option<String>,
+    client_ip: ClientIp,
+}
+
+impl WsCaller {
+    pub(crate) fn new(
+        user_id: ObjectId,
+        credential: Option<String>,
+        user: Option<PeerUser>,
+    ) -> Self {
+        Self {
+            peer_id: format!("peer_{}", generate_random_id()),
+            user_id,
+            credential,
+            user,
+            impersonator: None,
+            client_ip: ClientIp::default(),
+        }
+    }
+
+    fn snapshot_author(&self) -> SnapshotAuthor {
+        SnapshotAuthor {
+            peer_id: self.peer_id.clone(),
+            user_id: Some(self.user_id.to_hex()),
+        }
+    }
+}
+
+/// Where replies to a peer go: its WebSocket, or its server-sent event stream.
+pub(crate) enum PeerSink<'a> {
+    Socket(&'a mut WebSocket, Wire),
+    Stream(&'a mpsc::Sender<Event>),
+}
+
+impl PeerSink<'_> {
+    pub(crate) async fn send(&mut self, msg: &ServerMessage) -> Result<(), String> {
+        match self {
+            PeerSink::Socket(socket, wire) => socket
+                .send(wire.encode(msg))
+                .await
+                .map_err(|e| e.to_string()),
+            PeerSink::Stream(tx) => {
+                let json = serde_json::to_string(msg).map_err(|e| e.to_string())?;
+                tx.send(Event::default().data(json))
+                    .await
+                    .map_err(|_| "Event stream closed".to_string())
+            }
+        }
+    }
+}
+
+/// The room the socket has joined and the caller's role there, resolved at join time and
+/// re-checked by `refresh_role`.
+pub(crate) struct JoinedRoom {
+    pub(crate) room_code: String,
+    role: WorkspaceRole,
+    presence_sent_at: Option<Instant>,
+    /// A presence change is waiting for the throttle window to pass
+    presence_pending: bool,
+}
+
+impl JoinedRoom {
+    pub(crate) fn new(room_code: String, role: WorkspaceRole) -> Self {
+        Self {
+            room_code,
+            role,
+            presence_sent_at: None,
+            presence_pending: false,
+        }
+    }
+
+    pub(crate) fn presence_due(&self) -> Option<Instant> {
+        if !self.presence_pending {
+            return None;
+        }
+        let throttle = Duration::from_millis(PRESENCE_THROTTLE_MS);
+        Some(
+            self.presence_sent_at
+                .map_or_else(Instant::now, |at| at + throttle),
+        )
+    }
+}
+
+/// `GET /ws` authenticates like the REST API before upgrading; browsers pass their token
+/// with the `bearer` subprotocol.
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<SharedState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    Extension(client_ip): Extension<ClientIp>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> axum::response::Response {
+    let mut caller = match extract_ws_claims(&state, &headers, &jar)
+        .await
+        .and_then(|c| {
+            let mut caller = WsCaller::new(
+                ObjectId::parse_str(&c.sub).ok()?,
+                connection_token(&headers, &jar),
+                None,
+            );
+            caller.impersonator = c.impersonator;
+            caller.client_ip = client_ip;
+            Some(caller)
+        }) {
+        Some(caller) => caller,
+        None => {
+            return (
+                axum::http::StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Not logged in" })),
+            )
+                .into_response()
+        }
+    };
+
+    caller.user = PresenceService::resolve_user(&state.db, &caller.user_id).await;
+    let (wire, protocol) = Wire::negotiate(&headers, &[FRAME_PROTOCOL, WS_AUTH_PROTOCOL]);
+
+    // Hard stop at the protocol level; messages between the two limits get a typed error
+    ws.protocols(protocol)
+        .max_message_size(state.ws_limits.max_message_bytes.saturating_mul(2))
+        .on_upgrade(move |socket| handle_socket(socket, state, caller, wire))
 }
 
-async fn handle_socket(mut socket: WebSocket, state: SharedState) {
-    let mut current_room: Option<String> = None;
-    let mut current_peer_id: Option<String> = None;
+async fn handle_socket(mut socket: WebSocket, state: SharedState, caller: WsCaller, wire: Wire) {
+    let mut joined: Option<JoinedRoom> = None;
     let mut room_rx: Option<broadcast::Receiver<RoomEvent>> = None;
 
     let mut system_rx = state.system_tx.subscribe();
 
-    info!("🔌 New WebSocket connection");
+    state.ws_connections.fetch_add(1, Ordering::Relaxed);
+    let heartbeat = state.ws_heartbeat;
+    let mut ping_interval =
+        tokio::time::interval_at(Instant::now() + heartbeat.interval, heartbeat.interval);
+    let mut last_seen = chrono::Utc::now();
+    let mut limiter = ConnectionLimiter::new(state.ws_limits);
+
+    info!(
+        "🔌 New WebSocket connection: {} (user {})",
+        caller.peer_id, caller.user_id
+    );
 
     loop {
+        let presence_due = joined.as_ref().and_then(JoinedRoom::presence_due);
+
         tokio::select! {
             msg = socket.recv() => {
                 match msg {
                     Some(Ok(msg)) => {
-                        match msg {
+                        // Any frame, pongs included, shows the peer is still there
+                        last_seen = chrono::Utc::now();
+                        let too_large = |code| {
+                            (code, format!(
+                                "Messages are limited to {} bytes",
+                                state.ws_limits.max_message_bytes
+                            ))
+                        };
+                        let incoming = match msg {
                             Message::Text(text) => {
-                                if text.len() < 200 {
-                                    info!("📨 Received: {}", text);
-                                } else {
-                                    info!("📨 Received (len={}): {}...", text.len(), &text[0..50]);
-                                }
-
-                                match serde_json::from_str::<ClientMessage>(&text) {
-                                    Ok(client_msg) => {
-                                        match handle_client_message(
-                                            &mut socket,
-                                            &state,
-                                            &client_msg,
-                                            &mut current_room,
-                                            &mut current_peer_id,
-                                            &mut room_rx,
-                                        )
-                                        .await
-                                        {
-                                            Ok(should_close) => {
-                                                if should_close {
-                                                    break;
-                                                }
-                                            }
-                                            Err(e) => {
-                                                warn!("Error handling message: {}", e);
-                                                let error_msg = ServerMessage::Error {
-                                                    message: e.to_string(),
-                                                };
-                                                let _ = socket
-                                                    .send(Message::Text(
-                                                        serde_json::to_string(&error_msg).unwrap(),
-                                                    ))
-                                                    .await;
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        warn!("❌ Invalid message format: {}", e);
-                                        let error_msg = ServerMessage::Error {
-                                            message: format!("Invalid message format: {}", e),
-                                        };
-                                        let _ = socket
-                                            .send(Message::Text(
-                                                serde_json::to_string(&error_msg).unwrap(),
-                                            ))
-                                            .await;
-                                    }
-                                }
+                                limiter.check_size(text.len()).map_err(too_large).map(|()| text)
                             }
+                            // Binary frames are checked compressed, then again once inflated
+                            Message::Binary(frame) => limiter
+                                .check_size(frame.len())
+                                .map_err(too_large)
+                                .and_then(|()| {
+                                    decode_frame(&frame, state.ws_limits.max_message_bytes)
+                                }),
                             Message::Close(_) => {
                                 info!("🔌 Client closed connection");
                                 break;
                             }
-                            _ => {}
+                            _ => continue,
+                        };
+
+                        if let Ok(text) = &incoming {
+                            if text.len() < 200 {
+                                info!("📨 Received: {}", text);
+                            } else {
+                                let preview = log_preview(text, 50);
+                                info!("📨 Received (len={}): {}...", text.len(), preview);
+                            }
+                        }
+
+                        let outcome = match incoming {
+                            Err(rejected) => Err(rejected),
+                            Ok(text) => match serde_json::from_str::<ClientMessage>(&text) {
+                                Err(e) => Err((
+                                    ErrorCode::InvalidMessage,
+                                    format!("Invalid message format: {}", e),
+                                )),
+                                Ok(client_msg) => match limiter.check_rate(&client_msg) {
+                                    Err(code) => {
+                                        Err((code, "Too many messages, slow down".to_string()))
+                                    }
+                                    Ok(()) => match handle_client_message(
+                                        &mut PeerSink::Socket(&mut socket, wire),
+                                        &state,
+                                        &caller,
+                                        &client_msg,
+                                        &mut joined,
+                                        &mut room_rx,
+                                    )
+                                    .await
+                                    {
+                                        Ok(true) => break,
+                                        Ok(false) => Ok(()),
+                                        Err(e) => Err((ErrorCode::RequestFailed, e)),
+                                    },
+                                },
+                            },
+                        };
+
+                        if let Err((code, message)) = outcome {
+                            warn!("❌ Rejected message from {}: {}", caller.peer_id, message);
+                            let error_msg = ServerMessage::Error { code, message };
+                            let _ = socket
+                                .send(wire.encode(&error_msg))
+                                .await;
+
+                            // Failed requests are normal; malformed, oversized or too many are not
+                            if code != ErrorCode::RequestFailed && limiter.strike() {
+                                info!(
+                                    "🚫 Peer {} kept breaking message limits, disconnecting",
+                                    caller.peer_id
+                                );
+                                let _ = socket
+                                    .send(Message::Close(Some(CloseFrame {
+                                        code: close_code::POLICY,
+                                        reason: "Too many rejected messages".into(),
+                                    })))
+                                    .await;
+                                break;
+                            }
                         }
                     }
                     Some(Err(e)) => {
@@ -110,17 +297,75 @@ async fn handle_socket(mut socket: WebSocket, state: SharedState) {
                     futures::future::pending().await
                 }
             } => {
-                if let Ok(event) = event {
-                    if let Err(e) = forward_room_event(&mut socket, event, current_peer_id.as_ref()).await {
-                        warn!("Failed to forward room event: {}", e);
+                match event {
+                    Ok(event) => {
+                        let closed = matches!(event, RoomEvent::RoomClosed { .. });
+                        let kicked =
+                            matches!(&event, RoomEvent::PeerKicked { peer_id, .. } if *peer_id == caller.peer_id);
+                        if let Err(e) = forward_room_event(&mut socket, wire, event, &caller.peer_id).await {
+                            warn!("Failed to forward room event: {}", e);
+                        }
+                        if closed || kicked {
+                            info!("🚪 Room closed or peer kicked, disconnecting peer: {}", caller.peer_id);
+                            let _ = socket.send(Message::Close(None)).await;
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        room_rx = None;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        state.room_metrics.record_lag(skipped);
+                        warn!("🐢 Peer {} missed {} room events, resyncing", caller.peer_id, skipped);
+                        if let Some(room) = joined.as_ref() {
+                            if let Err(e) = send_resync(&mut socket, wire, &state, &room.room_code, skipped).await {
+                                warn!("Failed to resync peer {}: {}", caller.peer_id, e);
+                            }
+                        }
+                    }
+                }
+            }
+
+            _ = async {
+                match presence_due {
+                    Some(due) => tokio::time::sleep_until(due).await,
+                    None => futures::future::pending().await,
+                }
+            } => {
+                if let Some(room) = joined.as_mut() {
+                    publish_presence(&state, room, &caller.peer_id);
+                }
+            }
+
+            _ = ping_interval.tick() => {
+                let silent = chrono::Utc::now().signed_duration_since(last_seen);
+                if silent.to_std().is_ok_and(|silent| silent >= heartbeat.timeout) {
+                    info!("💀 Peer {} missed heartbeats, disconnecting", caller.peer_id);
+                    break;
+                }
+                if let Some(room) = joined.as_mut() {
+                    if !touch_peer(&state, &room.room_code, &caller.peer_id, last_seen) {
+                        info!("💀 Peer {} was evicted from its room, disconnecting", caller.peer_id);
+                        break;
+                    }
+                    if !refresh_role(&state, &caller, room).await {
+                        info!("🚫 Peer {} lost access to room {}, disconnecting", caller.peer_id, room.room_code);
+                        let _ = socket.send(wire.encode(&access_removed())).await;
+                        let _ = socket.send(Message::Close(None)).await;
+                        break;
                     }
                 }
+                let ping = tokio::time::timeout(heartbeat.timeout, socket.send(Message::Ping(Vec::new())));
+                if !matches!(ping.await, Ok(Ok(()))) {
+                    info!("💀 Could not ping peer {}, disconnecting", caller.peer_id);
+                    break;
+                }
             }
 
             sys_msg = system_rx.recv() => {
                 match sys_msg {
                     Ok(SystemEvent::Shutdown) => {
-                        info!("🛑 Server shutting down, closing connection for peer: {:?}", current_peer_id);
+                        info!("🛑 Server shutting down, closing connection for peer: {}", caller.peer_id);
                         let _ = socket.send(Message::Close(None)).await;
                         break;
                     }
@@ -130,259 +375,698 @@ async fn handle_socket(mut socket: WebSocket, state: SharedState) {
         }
     }
 
-    if let (Some(room_code), Some(peer_id)) = (current_room, current_peer_id) {
-        leave_room(&state, &room_code, &peer_id).await;
+    if let Some(room) = joined {
+        remove_peer(&state, &room.room_code, &caller.peer_id);
     }
+    state.ws_connections.fetch_sub(1, Ordering::Relaxed);
 }
 
 async fn forward_room_event(
     socket: &mut WebSocket,
+    wire: Wire,
     event: RoomEvent,
-    current_peer_id: Option<&String>,
+    current_peer_id: &str,
 ) -> Result<(), String> {
-    let server_msg = match event {
+    if let Some(msg) = room_event_message(event, current_peer_id) {
+        socket
+            .send(wire.encode(&msg))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// What a peer is told about a room event, if anything. Peers don't get their own changes back.
+pub(crate) fn room_event_message(event: RoomEvent, current_peer_id: &str) -> Option<ServerMessage> {
+    match event {
         RoomEvent::PeerJoined { peer } => Some(ServerMessage::PeerJoined { peer }),
         RoomEvent::PeerLeft { peer_id } => Some(ServerMessage::PeerLeft { peer_id }),
         RoomEvent::DataSync { from, data } => {
-            if Some(&from) == current_peer_id {
+            if from == current_peer_id {
                 None
             } else {
                 Some(ServerMessage::Data { from, data })
             }
         }
-        RoomEvent::DocumentUpdate { from, document } => {
-            if Some(&from) == current_peer_id {
+        RoomEvent::DocumentUpdate {
+            from,
+            document,
+            version,
+        } => {
+            if from == current_peer_id {
                 None
             } else {
                 info!("📄 Document update from {}, broadcasting to peers", from);
-                Some(ServerMessage::DocumentSync { document })
+                Some(ServerMessage::DocumentSync { document, version })
             }
         }
-        RoomEvent::HostChanged { new_host_id } => {
-            info!("👑 Host changed to: {}", new_host_id);
-            None
+        RoomEvent::OperationsUpdate {
+            from,
+            operations,
+            version,
+        } => {
+            if from == current_peer_id {
+                None
+            } else {
+                Some(ServerMessage::Operations {
+                    operations,
+                    version,
+                })
+            }
         }
-    };
+        RoomEvent::PresenceUpdate { peer_id, presence } => {
+            if peer_id == current_peer_id {
+                None
+            } else {
+                Some(ServerMessage::PresenceUpdate { peer_id, presence })
+            }
+        }
+        RoomEvent::HostChanged { new_host_id } => Some(ServerMessage::HostChanged {
+            host_id: new_host_id,
+        }),
+        RoomEvent::RoomClosed { reason } => Some(ServerMessage::RoomClosed { reason }),
+        // The others learn from the `peer_left` that follows
+        RoomEvent::PeerKicked { peer_id, reason } => {
+            if peer_id == current_peer_id {
+                Some(ServerMessage::Kicked { reason })
+            } else {
+                None
+            }
+        }
+        // Changes made through the REST API go to everyone, including the author's own tabs
+        RoomEvent::TaskChanged {
+            action,
+            task_id,
+            task,
+            user_id,
+        } => Some(ServerMessage::TaskChanged {
+            action,
+            task_id,
+            task,
+            user_id,
+        }),
+        RoomEvent::CommentChanged {
+            action,
+            task_id,
+            comment_id,
+            comment,
+            user_id,
+        } => Some(ServerMessage::CommentChanged {
+            action,
+            task_id,
+            comment_id,
+            comment,
+            user_id,
+        }),
+        RoomEvent::SprintChanged {
+            action,
+            sprint_id,
+            sprint,
+            user_id,
+        } => Some(ServerMessage::SprintChanged {
+            action,
+            sprint_id,
+            sprint,
+            user_id,
+        }),
+        RoomEvent::ProjectChanged {
+            action,
+            project_id,
+            project,
+            user_id,
+        } => Some(ServerMessage::ProjectChanged {
+            action,
+            project_id,
+            project,
+            user_id,
+        }),
+        RoomEvent::AssigneeChanged {
+            action,
+            assignee_id,
+            assignee,
+            user_id,
+        } => Some(ServerMessage::AssigneeChanged {
+            action,
+            assignee_id,
+            assignee,
+            user_id,
+        }),
+        RoomEvent::AssigneeGroupChanged {
+            action,
+            group_id,
+            group,
+            user_id,
+        } => Some(ServerMessage::AssigneeGroupChanged {
+            action,
+            group_id,
+            group,
+            user_id,
+        }),
+        RoomEvent::MilestoneChanged {
+            action,
+            milestone_id,
+            milestone,
+            user_id,
+        } => Some(ServerMessage::MilestoneChanged {
+            action,
+            milestone_id,
+            milestone,
+            user_id,
+        }),
+        RoomEvent::ChecklistTemplateChanged {
+            action,
+            template_id,
+            template,
+            user_id,
+        } => Some(ServerMessage::ChecklistTemplateChanged {
+            action,
+            template_id,
+            template,
+            user_id,
+        }),
+    }
+}
 
-    if let Some(msg) = server_msg {
-        let json = serde_json::to_string(&msg).map_err(|e| e.to_string())?;
-        socket
-            .send(Message::Text(json))
-            .await
-            .map_err(|e| e.to_string())?;
+/// Error for a message the caller's role doesn't allow.
+fn require_role(room: &JoinedRoom, required: WorkspaceRole) -> Result<(), String> {
+    if room.role.allows(required) {
+        Ok(())
+    } else {
+        Err(format!(
+            "This action requires the {} role or higher",
+            required.as_str()
+        ))
     }
+}
 
-    Ok(())
+/// The caller's role in a room as of now. Membership, roles, sessions and access tokens can
+/// all change while a connection is open; `None` once the caller has lost access.
+async fn current_role(
+    state: &SharedState,
+    caller: &WsCaller,
+    room_code: &str,
+) -> Option<WorkspaceRole> {
+    let claims = recheck_connection_token(state, caller.credential.as_deref()?).await?;
+    if claims.sub != caller.user_id.to_hex() {
+        return None;
+    }
+    resolve_room_role(
+        state,
+        &caller.user_id,
+        claims.access_token.as_ref(),
+        room_code,
+    )
+    .await
+    .ok()
 }
 
-async fn handle_client_message(
-    socket: &mut WebSocket,
+/// Brings the joined room's role up to date. False once the caller may no longer be there.
+pub(crate) async fn refresh_role(
+    state: &SharedState,
+    caller: &WsCaller,
+    room: &mut JoinedRoom,
+) -> bool {
+    match current_role(state, caller, &room.room_code).await {
+        Some(role) => {
+            room.role = role;
+            true
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn access_removed() -> ServerMessage {
+    ServerMessage::Kicked {
+        reason: "Your access to this room was removed".to_string(),
+    }
+}
+
+/// Name of a message that changes the room's data, if it is one.
+fn data_change(msg: &ClientMessage) -> Option<&'static str> {
+    match msg {
+        ClientMessage::Broadcast { .. } => Some("broadcast"),
+        ClientMessage::SyncDocument { .. } => Some("sync_document"),
+        ClientMessage::PushOperations { .. } => Some("push_operations"),
+        _ => None,
+    }
+}
+
+/// Writes a change made over the socket by an impersonating admin to the audit log, like
+/// `impersonation_guard` does for REST requests.
+async fn audit_impersonated(
     state: &SharedState,
+    caller: &WsCaller,
+    room_code: &str,
+    message: &str,
+) {
+    let Some(admin_id) = caller.impersonator.as_deref() else {
+        return;
+    };
+    AuditService::record(
+        &AuditRepository::new(&state.db),
+        &UserRepository::new(&state.db),
+        &AuditActor::new(Some(admin_id), &caller.client_ip),
+        AuditRecord {
+            action: "impersonation.request",
+            target_type: "user",
+            target_id: Some(caller.user_id.to_hex()),
+            before: None,
+            after: Some(serde_json::json!({
+                "method": "WS",
+                "path": "/ws",
+                "message": message,
+                "room_code": room_code,
+            })),
+        },
+    )
+    .await;
+}
+
+pub(crate) async fn handle_client_message(
+    sink: &mut PeerSink<'_>,
+    state: &SharedState,
+    caller: &WsCaller,
     msg: &ClientMessage,
-    current_room: &mut Option<String>,
-    current_peer_id: &mut Option<String>,
+    joined: &mut Option<JoinedRoom>,
     room_rx: &mut Option<broadcast::Receiver<RoomEvent>>,
 ) -> Result<bool, String> {
+    if let (Some(room), Some(change)) = (joined.as_mut(), data_change(msg)) {
+        if !refresh_role(state, caller, room).await {
+            sink.send(&access_removed()).await?;
+            return Ok(true);
+        }
+        audit_impersonated(state, caller, &room.room_code, change).await;
+    }
+
     match msg {
         ClientMessage::Join {
             room_code,
-            peer_id,
-            is_host,
             metadata,
+            version,
         } => {
-            if let Err(e) =
-                crate::services::room_service::ensure_room_exists(state, room_code).await
-            {
-                return Err(e);
-            }
-
-            if let Some(mut room) = state.rooms.get_mut(room_code) {
-                if room.empty_since.is_some() {
-                    room.empty_since = None;
-                    info!("🔄 Room revived: {}", room_code);
-                }
-
-                *room_rx = Some(room.tx.subscribe());
-
-                let peer_info = PeerInfo {
-                    id: peer_id.clone(),
-                    joined_at: chrono::Utc::now(),
-                    is_host: *is_host,
-                    metadata: metadata.clone(),
-                };
-
-                room.peers.insert(peer_id.clone(), peer_info.clone());
-
-                let event = RoomEvent::PeerJoined { peer: peer_info };
-                let _ = room.tx.send(event);
+            let role = current_role(state, caller, room_code)
+                .await
+                .ok_or_else(|| "Room not found".to_string())?;
+            crate::services::room_service::ensure_room_exists(state, room_code).await?;
 
-                let peers: Vec<PeerInfo> = room
-                    .peers
-                    .iter()
-                    .map(|entry| entry.value().clone())
-                    .collect();
+            if let Some(previous) = joined.take() {
+                *room_rx = None;
+                remove_peer(state, &previous.room_code, &caller.peer_id);
+            }
 
-                let response = ServerMessage::RoomInfo {
-                    room_code: room_code.clone(),
-                    host_id: room.host_id.clone(),
-                    peers,
-                };
-                socket
-                    .send(Message::Text(serde_json::to_string(&response).unwrap()))
-                    .await
-                    .map_err(|e| e.to_string())?;
+            let entered = enter_room(state, caller, room_code, metadata, version.as_ref()).await?;
+            *room_rx = Some(entered.events);
+            let host_id = entered.host_id;
 
-                let connected = ServerMessage::Connected {
-                    peer_id: peer_id.clone(),
-                    room_code: room_code.clone(),
-                };
-                socket
-                    .send(Message::Text(serde_json::to_string(&connected).unwrap()))
-                    .await
-                    .map_err(|e| e.to_string())?;
+            let peers = state
+                .room_bus
+                .room_peers(&state.db, room_code, entered.local_peers)
+                .await;
+            let is_host = host_id.as_deref() == Some(caller.peer_id.as_str());
+            let response = ServerMessage::RoomInfo {
+                room_code: room_code.clone(),
+                host_id,
+                peers,
+            };
+            sink.send(&response).await?;
 
-                *current_room = Some(room_code.clone());
-                *current_peer_id = Some(peer_id.clone());
+            let connected = ServerMessage::Connected {
+                peer_id: caller.peer_id.clone(),
+                room_code: room_code.clone(),
+            };
+            sink.send(&connected).await?;
 
-                info!(
-                    "👤 Peer joined: {} in room {} (host: {})",
-                    current_peer_id.as_ref().unwrap(),
-                    current_room.as_ref().unwrap(),
-                    is_host
-                );
+            *joined = Some(JoinedRoom::new(room_code.clone(), role));
 
-                if let Some(doc) = &room.document_state {
-                    let sync = ServerMessage::DocumentSync {
-                        document: doc.clone(),
-                    };
-                    socket
-                        .send(Message::Text(serde_json::to_string(&sync).unwrap()))
-                        .await
-                        .map_err(|e| e.to_string())?;
-                }
+            info!(
+                "👤 Peer joined: {} in room {} as {} (host: {})",
+                caller.peer_id,
+                room_code,
+                role.as_str(),
+                is_host
+            );
 
-                Ok(false)
-            } else {
-                Err("Room not found".to_string())
+            if let Some(sync) = entered.sync {
+                sink.send(&sync).await?;
             }
+
+            Ok(false)
         }
 
         ClientMessage::Leave => {
             *room_rx = None;
-            if let (Some(room_code), Some(peer_id)) = (current_room.take(), current_peer_id.take())
-            {
-                leave_room(state, &room_code, &peer_id).await;
+            if let Some(room) = joined.take() {
+                remove_peer(state, &room.room_code, &caller.peer_id);
                 return Ok(true);
             }
             Ok(false)
         }
 
         ClientMessage::Broadcast { data } => {
-            if let (Some(room_code), Some(peer_id)) =
-                (current_room.as_ref(), current_peer_id.as_ref())
-            {
-                if let Some(room) = state.rooms.get(room_code) {
+            if let Some(joined) = joined.as_ref() {
+                // Change notifications; anyone who can change data (comments included) sends them
+                require_role(joined, WorkspaceRole::Commenter)?;
+                if let Some(room) = state.rooms.get(&joined.room_code) {
                     let event = RoomEvent::DataSync {
-                        from: peer_id.clone(),
+                        from: caller.peer_id.clone(),
                         data: data.clone(),
                     };
-                    let _ = room.tx.send(event);
+                    state
+                        .room_bus
+                        .publish(Some(&room.tx), &joined.room_code, event);
                 }
             }
             Ok(false)
         }
 
         ClientMessage::SyncDocument { document } => {
-            if let (Some(room_code), Some(peer_id)) =
-                (current_room.as_ref(), current_peer_id.as_ref())
-            {
-                if let Some(mut room) = state.rooms.get_mut(room_code) {
-                    room.document_state = Some(document.clone());
-                    room.last_sync = chrono::Utc::now();
-
-                    let event = RoomEvent::DocumentUpdate {
-                        from: peer_id.clone(),
-                        document: document.clone(),
-                    };
-                    let _ = room.tx.send(event);
-
-                    let room_repo = RoomRepository::new(&state.db);
-                    let room_code_clone = room_code.clone();
-                    let document_clone = document.clone();
-                    tokio::spawn(async move {
-                        let _ = room_repo
-                            .upsert_document(&room_code_clone, &document_clone)
-                            .await;
-                    });
-
-                    info!("📄 Document synced by {} in room {}", peer_id, room_code);
-                }
+            if let Some(joined) = joined.as_ref() {
+                require_role(joined, WorkspaceRole::Editor)?;
+                let room_code = &joined.room_code;
+                let (merged, version, snapshot) = match state.rooms.get_mut(room_code) {
+                    Some(mut room) => {
+                        // Merging under the room's entry lock keeps concurrent syncs in order
+                        DocumentService::merge(&mut room.document, document)?;
+                        let now = chrono::Utc::now();
+                        let snapshot_due = SnapshotService::due(&mut room, now);
+                        let Some(doc) = room.document.as_ref() else {
+                            return Ok(false);
+                        };
+                        let merged = doc.export();
+                        let version = doc.version().clone();
+                        let snapshot = snapshot_due.then(|| {
+                            SnapshotService::capture(
+                                room_code,
+                                doc,
+                                &caller.snapshot_author(),
+                                "sync",
+                            )
+                        });
+                        room.last_sync = now;
+
+                        let event = RoomEvent::DocumentUpdate {
+                            from: caller.peer_id.clone(),
+                            document: merged.clone(),
+                            version: version.clone(),
+                        };
+                        state.room_bus.publish(Some(&room.tx), room_code, event);
+                        (merged, version, snapshot)
+                    }
+                    None => return Ok(false),
+                };
+
+                state
+                    .document_persister
+                    .mark(room_code, merged.clone(), snapshot);
+
+                // The sender gets the merged result too, since it may include edits it lacked
+                let sync = ServerMessage::DocumentSync {
+                    document: merged,
+                    version,
+                };
+                sink.send(&sync).await?;
+
+                info!(
+                    "📄 Document merged from {} in room {}",
+                    caller.peer_id, room_code
+                );
             }
             Ok(false)
         }
 
-        ClientMessage::RequestSync => {
-            if let Some(room_code) = current_room.as_ref() {
-                if let Some(room) = state.rooms.get(room_code) {
-                    if let Some(doc) = &room.document_state {
-                        let sync = ServerMessage::DocumentSync {
-                            document: doc.clone(),
+        ClientMessage::PushOperations { operations } => {
+            if let Some(joined) = joined.as_ref() {
+                require_role(joined, WorkspaceRole::Editor)?;
+                let room_code = &joined.room_code;
+                let (merged, snapshot) = match state.rooms.get_mut(room_code) {
+                    Some(mut room) => {
+                        let applied =
+                            DocumentService::apply(&mut room.document, operations.clone());
+                        if applied.is_empty() {
+                            return Ok(false);
+                        }
+                        let now = chrono::Utc::now();
+                        let snapshot_due = SnapshotService::due(&mut room, now);
+                        let Some(doc) = room.document.as_ref() else {
+                            return Ok(false);
                         };
-                        socket
-                            .send(Message::Text(serde_json::to_string(&sync).unwrap()))
-                            .await
-                            .map_err(|e| e.to_string())?;
-                        info!(
-                            "📄 Sent document to peer upon request in room {}",
-                            room_code
-                        );
-                    } else {
-                        let sync = ServerMessage::DocumentSync {
-                            document: String::new(),
+                        let merged = doc.export();
+                        let snapshot = snapshot_due.then(|| {
+                            SnapshotService::capture(
+                                room_code,
+                                doc,
+                                &caller.snapshot_author(),
+                                "sync",
+                            )
+                        });
+                        let event = RoomEvent::OperationsUpdate {
+                            from: caller.peer_id.clone(),
+                            operations: applied,
+                            version: doc.version().clone(),
                         };
-                        socket
-                            .send(Message::Text(serde_json::to_string(&sync).unwrap()))
-                            .await
-                            .map_err(|e| e.to_string())?;
-                        info!("📄 Sent empty document (no data yet) in room {}", room_code);
+                        room.last_sync = now;
+                        state.room_bus.publish(Some(&room.tx), room_code, event);
+                        (merged, snapshot)
                     }
+                    None => return Ok(false),
+                };
+
+                state.document_persister.mark(room_code, merged, snapshot);
+            }
+            Ok(false)
+        }
+
+        ClientMessage::RequestOperations { version } => {
+            if let Some(joined) = joined.as_ref() {
+                let sync = match state.rooms.get(&joined.room_code) {
+                    Some(room) => catch_up_message(room.document.as_ref(), version),
+                    None => return Ok(false),
+                };
+                sink.send(&sync).await?;
+            }
+            Ok(false)
+        }
+
+        ClientMessage::UpdatePresence {
+            task_id,
+            field,
+            typing,
+            status,
+        } => {
+            if let Some(joined) = joined.as_mut() {
+                let presence = PresenceService::from_update(
+                    task_id.as_ref(),
+                    field.as_ref(),
+                    *typing,
+                    *status,
+                )?;
+                match state.rooms.get(&joined.room_code) {
+                    Some(room) => match room.peers.get_mut(&caller.peer_id) {
+                        Some(mut peer) => peer.presence = presence,
+                        None => return Ok(false),
+                    },
+                    None => return Ok(false),
+                }
+
+                joined.presence_pending = true;
+                if joined
+                    .presence_due()
+                    .is_some_and(|due| due <= Instant::now())
+                {
+                    publish_presence(state, joined, &caller.peer_id);
                 }
             }
             Ok(false)
         }
 
+        ClientMessage::RequestSync => {
+            if let Some(joined) = joined.as_ref() {
+                let room_code = &joined.room_code;
+                let sync = match state.rooms.get(room_code) {
+                    Some(room) => snapshot_message(room.document.as_ref()),
+                    None => return Ok(false),
+                };
+                sink.send(&sync).await?;
+                info!(
+                    "📄 Sent document to peer upon request in room {}",
+                    room_code
+                );
+            }
+            Ok(false)
+        }
+
         ClientMessage::Ping => {
             let pong = ServerMessage::Pong;
-            socket
-                .send(Message::Text(serde_json::to_string(&pong).unwrap()))
-                .await
-                .map_err(|e| e.to_string())?;
+            sink.send(&pong).await?;
             Ok(false)
         }
     }
 }
 
-async fn leave_room(state: &SharedState, room_code: &str, peer_id: &str) {
-    if let Some(mut room) = state.rooms.get_mut(room_code) {
-        room.peers.remove(peer_id);
+/// A peer's first look at the room it just entered.
+pub(crate) struct EnteredRoom {
+    pub(crate) host_id: Option<String>,
+    /// Peers connected to this instance, the caller included
+    pub(crate) local_peers: Vec<PeerInfo>,
+    pub(crate) events: broadcast::Receiver<RoomEvent>,
+    /// Catch-up from `version`, or the whole document; `None` while the room has none
+    pub(crate) sync: Option<ServerMessage>,
+}
 
-        let event = RoomEvent::PeerLeft {
-            peer_id: peer_id.to_string(),
+/// Adds the caller to a loaded room and tells the others. Whoever enters a room without a
+/// host is put up for election (see `elect_host`); clients can't claim it. Everything that
+/// needs the room entry is gathered first, so the lock isn't held across the election,
+/// the presence lookup or socket writes.
+pub(crate) async fn enter_room(
+    state: &SharedState,
+    caller: &WsCaller,
+    room_code: &str,
+    metadata: &Option<serde_json::Value>,
+    version: Option<&VersionVector>,
+) -> Result<EnteredRoom, String> {
+    let mut entered = {
+        let Some(mut room) = state.rooms.get_mut(room_code) else {
+            return Err("Room not found".to_string());
         };
-        let _ = room.tx.send(event);
+        if room.empty_since.is_some() {
+            room.empty_since = None;
+            info!("🔄 Room revived: {}", room_code);
+        }
 
-        info!("👤 Peer left: {} from room {}", peer_id, room_code);
+        let events = room.tx.subscribe();
 
-        if room.peers.is_empty() {
-            room.empty_since = Some(chrono::Utc::now());
-            if state.room_idle_timeout_seconds == 0 {
-                info!("🕒 Room {} is empty; keeping indefinitely", room_code);
-            } else {
-                info!(
-                    "🕒 Room {} is empty; keeping for {}s before cleanup",
-                    room_code, state.room_idle_timeout_seconds
-                );
-            }
+        let peer_info = PeerInfo {
+            id: caller.peer_id.clone(),
+            joined_at: chrono::Utc::now(),
+            is_host: false,
+            metadata: metadata.clone(),
+            user: caller.user.clone(),
+            presence: Default::default(),
+            last_seen: Some(chrono::Utc::now()),
+        };
+
+        room.peers.insert(caller.peer_id.clone(), peer_info.clone());
+
+        let event = RoomEvent::PeerJoined { peer: peer_info };
+        state.room_bus.publish(Some(&room.tx), room_code, event);
+
+        let sync = match version {
+            Some(since) => Some(catch_up_message(room.document.as_ref(), since)),
+            None => room
+                .document
+                .as_ref()
+                .map(|doc| snapshot_message(Some(doc))),
+        };
+
+        EnteredRoom {
+            host_id: room.host_id.clone(),
+            local_peers: Vec::new(),
+            events,
+            sync,
         }
+    };
+
+    if entered.host_id.is_none() {
+        entered.host_id = elect_host(state, room_code, &caller.peer_id).await;
+    }
+    // Read after the election so the caller's own `is_host` is current
+    if let Some(room) = state.rooms.get(room_code) {
+        entered.local_peers = room
+            .peers
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+    }
+    Ok(entered)
+}
+
+/// Tells a peer that fell behind the room's channel to start over from a full snapshot.
+async fn send_resync(
+    socket: &mut WebSocket,
+    wire: Wire,
+    state: &SharedState,
+    room_code: &str,
+    skipped: u64,
+) -> Result<(), String> {
+    let sync = match state.rooms.get(room_code) {
+        Some(room) => snapshot_message(room.document.as_ref()),
+        None => return Ok(()),
+    };
+
+    for msg in [ServerMessage::Resync { skipped }, sync] {
+        socket
+            .send(wire.encode(&msg))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Full copy of the room's document; empty if nothing was synced yet.
+pub(crate) fn snapshot_message(document: Option<&CrdtDocument>) -> ServerMessage {
+    match document {
+        Some(doc) => ServerMessage::DocumentSync {
+            document: doc.export(),
+            version: doc.version().clone(),
+        },
+        None => ServerMessage::DocumentSync {
+            document: String::new(),
+            version: VersionVector::new(),
+        },
+    }
+}
+
+/// What a peer at `since` needs: the operations it missed, or a snapshot when the room's
+/// history no longer reaches back that far.
+fn catch_up_message(document: Option<&CrdtDocument>, since: &VersionVector) -> ServerMessage {
+    match document {
+        Some(doc) => match doc.operations_since(since) {
+            Some(operations) => ServerMessage::Operations {
+                operations,
+                version: doc.version().clone(),
+            },
+            None => snapshot_message(Some(doc)),
+        },
+        None => ServerMessage::Operations {
+            operations: Vec::new(),
+            version: VersionVector::new(),
+        },
+    }
+}
+
+/// Records the peer's last frame for the reaper. False when the reaper already removed it.
+pub(crate) fn touch_peer(
+    state: &SharedState,
+    room_code: &str,
+    peer_id: &str,
+    last_seen: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    let Some(room) = state.rooms.get(room_code) else {
+        return false;
+    };
+    let Some(mut peer) = room.peers.get_mut(peer_id) else {
+        return false;
+    };
+    peer.last_seen = Some(last_seen);
+    true
+}
+
+/// Sends the peer's current presence to its room and restarts the throttle window.
+pub(crate) fn publish_presence(state: &SharedState, joined: &mut JoinedRoom, peer_id: &str) {
+    joined.presence_pending = false;
+    joined.presence_sent_at = Some(Instant::now());
+
+    if let Some(room) = state.rooms.get(&joined.room_code) {
+        let Some(presence) = room.peers.get(peer_id).map(|peer| peer.presence.clone()) else {
+            return;
+        };
+        let event = RoomEvent::PresenceUpdate {
+            peer_id: peer_id.to_string(),
+            presence,
+        };
+        state
+            .room_bus
+            .publish(Some(&room.tx), &joined.room_code, event);
     }
 }
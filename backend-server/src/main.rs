@@ -7,11 +7,38 @@ mod state;
 use crate::models::message::SystemEvent;
 use crate::models::profile::UserProfile;
 use crate::models::user::User;
+use crate::repositories::access_token_repo::AccessTokenRepository;
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::auth_token_repo::AuthTokenRepository;
 use crate::repositories::data_repo::DataRepository;
+use crate::repositories::login_attempt_repo::LoginAttemptRepository;
+use crate::repositories::member_repo::MemberRepository;
+use crate::repositories::oidc_state_repo::OidcStateRepository;
 use crate::repositories::profile_repo::ProfileRepository;
+use crate::repositories::room_event_repo::RoomEventRepository;
+use crate::repositories::room_host_repo::RoomHostRepository;
+use crate::repositories::room_presence_repo::RoomPresenceRepository;
+use crate::repositories::room_snapshot_repo::RoomSnapshotRepository;
+use crate::repositories::session_repo::SessionRepository;
 use crate::repositories::storage_repo::StorageRepository;
 use crate::repositories::user_repo::UserRepository;
-use crate::services::room_service::spawn_room_cleanup_task;
+use crate::repositories::workspace_invite_repo::WorkspaceInviteRepository;
+use crate::services::audit_service::{retention_days_from_env, spawn_audit_retention_task};
+use crate::services::auth_service::setup_token_expiry;
+use crate::services::client_ip_service::{ClientIp, ProxySettings};
+use crate::services::event_feed_service::RoomFeeds;
+use crate::services::login_throttle_service::LoginThrottleSettings;
+use crate::services::mail_service::Mailer;
+use crate::services::member_service::MemberService;
+use crate::services::oidc_service::{OidcProvider, OidcSettings};
+use crate::services::persist_service::{spawn_document_persister_task, DocumentPersister};
+use crate::services::presence_service::spawn_presence_expiry_task;
+use crate::services::room_bus_service::{spawn_room_bus_tasks, RoomEventBus};
+use crate::services::room_service::{
+    spawn_peer_reaper_task, spawn_room_cleanup_task, HeartbeatSettings, RoomMetrics,
+};
+use crate::services::session_service::AuthSettings;
+use crate::services::ws_limit_service::WsLimits;
 use crate::state::AppState;
 use axum::{
     extract::State,
@@ -23,34 +50,44 @@ use bcrypt::hash;
 use dashmap::DashMap;
 use dotenv::dotenv;
 use mongodb::Client;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tower_governor::{errors::GovernorError, key_extractor::KeyExtractor};
 use tracing::info;
 
+/// Keys the rate limiter by the address `resolve_client_ip` settled on, so it follows the
+/// same trusted-proxy rules as login throttling.
 #[derive(Clone, Copy)]
-struct IpHeaderKeyExtractor;
+struct ClientIpKeyExtractor;
 
-impl KeyExtractor for IpHeaderKeyExtractor {
+impl KeyExtractor for ClientIpKeyExtractor {
     type Key = String;
 
     fn extract<B>(&self, req: &axum::http::Request<B>) -> Result<Self::Key, GovernorError> {
-        req.headers()
-            .get("x-forwarded-for")
-            .and_then(|h| h.to_str().ok())
-            .and_then(|s| s.split(',').next())
-            .map(|s| s.trim().to_string())
-            .or_else(|| {
-                req.headers()
-                    .get("x-real-ip")
-                    .and_then(|h| h.to_str().ok())
-                    .map(|s| s.to_string())
-            })
-            .ok_or(GovernorError::UnableToExtractKey)
-            .or_else(|_| Ok("unknown".to_string()))
+        Ok(req
+            .extensions()
+            .get::<ClientIp>()
+            .and_then(|ip| ip.0.clone())
+            .unwrap_or_else(|| "unknown".to_string()))
     }
 }
 
+async fn resolve_client_ip(
+    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
+    mut req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let peer = req
+        .extensions()
+        .get::<axum::extract::ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    let ip = state.proxy.resolve(req.headers(), peer);
+    req.extensions_mut().insert(ClientIp(ip));
+    next.run(req).await
+}
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
@@ -72,10 +109,52 @@ async fn main() {
         );
     }
 
+    let ws_heartbeat = HeartbeatSettings::from_env();
+    info!(
+        "💓 WebSocket ping every {}s, peers dropped after {}s without a reply",
+        ws_heartbeat.interval.as_secs(),
+        ws_heartbeat.timeout.as_secs()
+    );
+
+    let ws_limits = WsLimits::from_env();
+    info!(
+        "🚦 WebSocket limits: {} bytes per message, {}/s broadcast, {}/s document sync",
+        ws_limits.max_message_bytes, ws_limits.broadcast_per_second, ws_limits.sync_per_second
+    );
+
+    let room_metrics = RoomMetrics::from_env();
+    info!(
+        "📨 Room event channel holds {} events per room",
+        room_metrics.channel_capacity
+    );
+
+    let document_persister = DocumentPersister::from_env();
+    info!(
+        "💾 Room documents saved at most every {}ms",
+        document_persister.interval.as_millis()
+    );
+
     let mongodb_uri =
         std::env::var("MONGODB_URI").unwrap_or_else(|_| "mongodb://localhost:27017".to_string());
     let jwt_secret =
         std::env::var("JWT_SECRET").unwrap_or_else(|_| "default_secret_keep_it_safe".to_string());
+    let auth_settings = AuthSettings::from_env();
+    info!(
+        "🔐 Access token TTL: {}s, refresh token TTL: {}s",
+        auth_settings.access_token_ttl_seconds, auth_settings.refresh_token_ttl_seconds
+    );
+    let proxy = ProxySettings::from_env();
+    info!("🌐 Client IP source: {}", proxy.describe());
+    let login_throttle = LoginThrottleSettings::from_env();
+    let mailer = Mailer::from_env();
+    info!("📧 Mail transport: {}", mailer.kind());
+    let oidc = OidcSettings::from_env(&auth_settings.app_base_url).map(|settings| {
+        info!(
+            "🪪 OIDC sign-in enabled: {} (auto-provision: {})",
+            settings.issuer_url, settings.auto_provision
+        );
+        OidcProvider::new(settings)
+    });
     let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "tracker-db".to_string());
 
     info!("🔌 Connecting to MongoDB...");
@@ -94,6 +173,58 @@ async fn main() {
     if let Err(error) = data_repo.ensure_task_indexes().await {
         tracing::warn!("Failed to ensure task indexes: {}", error);
     }
+    let member_repo = MemberRepository::new(&db);
+    if let Err(error) = member_repo.ensure_indexes().await {
+        tracing::warn!("Failed to ensure workspace member indexes: {}", error);
+    }
+    if let Err(error) = SessionRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure session indexes: {}", error);
+    }
+    if let Err(error) = AuthTokenRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure auth token indexes: {}", error);
+    }
+    if let Err(error) = AccessTokenRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure access token indexes: {}", error);
+    }
+    if let Err(error) = OidcStateRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure OIDC state indexes: {}", error);
+    }
+    if let Err(error) = LoginAttemptRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure login attempt indexes: {}", error);
+    }
+    if let Err(error) = AuditRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure audit event indexes: {}", error);
+    }
+    if let Err(error) = WorkspaceInviteRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure workspace invite indexes: {}", error);
+    }
+    if let Err(error) = RoomSnapshotRepository::new(&db).ensure_indexes().await {
+        tracing::warn!("Failed to ensure room snapshot indexes: {}", error);
+    }
+    let room_bus = RoomEventBus::from_env(&db);
+    info!("📡 Room event bus: {}", room_bus.kind());
+    if let RoomEventBus::Mongo(_) = room_bus {
+        if let Err(error) = RoomEventRepository::new(&db).ensure_indexes().await {
+            tracing::warn!("Failed to ensure room event indexes: {}", error);
+        }
+        if let Err(error) = RoomPresenceRepository::new(&db).ensure_indexes().await {
+            tracing::warn!("Failed to ensure room presence indexes: {}", error);
+        }
+        if let Err(error) = RoomHostRepository::new(&db).ensure_indexes().await {
+            tracing::warn!("Failed to ensure room host indexes: {}", error);
+        }
+    }
+    match UserRepository::new(&db)
+        .set_missing_setup_token_expiry(&setup_token_expiry(&auth_settings))
+        .await
+    {
+        Ok(count) if count > 0 => info!("🔑 Added expiry to {} pending setup tokens", count),
+        Ok(_) => {}
+        Err(error) => tracing::warn!("Failed to backfill setup token expiry: {}", error),
+    }
+    if let Err(error) = MemberService::backfill_from_assignees(&member_repo, &data_repo).await {
+        tracing::warn!("Failed to backfill workspace members: {}", error);
+    }
     let stored_storage_config = storage_repo.get_storage_config().await.ok().flatten();
     let active_storage =
         crate::services::storage_service::build_active_storage(stored_storage_config.as_ref())
@@ -102,15 +233,38 @@ async fn main() {
     let state = Arc::new(AppState {
         db,
         rooms: DashMap::new(),
+        room_bus,
         room_idle_timeout_seconds,
+        ws_heartbeat,
+        ws_limits,
+        ws_connections: AtomicUsize::new(0),
+        room_feeds: RoomFeeds::default(),
+        event_streams: DashMap::new(),
+        room_metrics,
+        document_persister,
         system_tx: system_tx.clone(),
         jwt_secret,
+        auth_settings,
+        mailer,
+        oidc,
+        proxy,
+        login_throttle,
         storage: tokio::sync::RwLock::new(active_storage),
     });
 
     if room_idle_timeout_seconds > 0 {
         spawn_room_cleanup_task(state.clone());
     }
+    spawn_room_bus_tasks(state.clone());
+    spawn_presence_expiry_task(state.clone());
+    spawn_peer_reaper_task(state.clone());
+    spawn_document_persister_task(state.clone());
+
+    let audit_retention_days = retention_days_from_env();
+    if audit_retention_days > 0 {
+        info!("📜 Audit events kept for {} days", audit_retention_days);
+    }
+    spawn_audit_retention_task(state.db.clone(), audit_retention_days);
 
     // Start automated notifications service
     crate::services::notification_service::spawn_notification_service_task(state.clone());
@@ -120,7 +274,7 @@ async fn main() {
 
     let governor_conf = Arc::new(
         tower_governor::governor::GovernorConfigBuilder::default()
-            .key_extractor(IpHeaderKeyExtractor)
+            .key_extractor(ClientIpKeyExtractor)
             .per_second(2)
             .burst_size(5)
             .finish()
@@ -142,23 +296,127 @@ async fn main() {
             "/api/auth/setup-password",
             post(handlers::auth_handler::setup_password_handler),
         )
+        .route(
+            "/api/auth/forgot-password",
+            post(handlers::auth_handler::forgot_password_handler),
+        )
+        .route(
+            "/api/auth/reset-info",
+            get(handlers::auth_handler::get_reset_info_handler),
+        )
+        .route(
+            "/api/auth/reset-password",
+            post(handlers::auth_handler::reset_password_handler),
+        )
+        .route(
+            "/api/auth/verify-email",
+            post(handlers::auth_handler::verify_email_handler),
+        )
         .route(
             "/api/auth/login",
             post(handlers::auth_handler::login_handler),
         )
+        .route(
+            "/api/auth/oidc/config",
+            get(handlers::oidc_handler::oidc_config_handler),
+        )
+        .route(
+            "/api/auth/oidc/authorize",
+            get(handlers::oidc_handler::oidc_authorize_handler),
+        )
+        .route(
+            "/api/auth/oidc/callback",
+            post(handlers::oidc_handler::oidc_callback_handler),
+        )
+        .route(
+            "/api/auth/login/2fa",
+            post(handlers::auth_handler::login_two_factor_handler),
+        )
         .route(
             "/api/auth/logout",
             post(handlers::auth_handler::logout_handler),
         )
+        .route(
+            "/api/auth/refresh",
+            post(handlers::auth_handler::refresh_handler),
+        )
         .route("/api/auth/me", get(handlers::auth_handler::me_handler))
         .route(
             "/api/auth/me",
             put(handlers::auth_handler::update_me_handler),
         )
+        .route(
+            "/api/auth/me/sessions",
+            get(handlers::auth_handler::list_sessions_handler),
+        )
+        .route(
+            "/api/auth/me/sessions",
+            delete(handlers::auth_handler::revoke_other_sessions_handler),
+        )
+        .route(
+            "/api/auth/me/sessions/:session_id",
+            delete(handlers::auth_handler::revoke_session_handler),
+        )
+        .route(
+            "/api/auth/me/2fa",
+            get(handlers::two_factor_handler::get_two_factor_status_handler),
+        )
+        .route(
+            "/api/auth/me/2fa/setup",
+            post(handlers::two_factor_handler::setup_two_factor_handler),
+        )
+        .route(
+            "/api/auth/me/2fa/enable",
+            post(handlers::two_factor_handler::enable_two_factor_handler),
+        )
+        .route(
+            "/api/auth/me/2fa/disable",
+            post(handlers::two_factor_handler::disable_two_factor_handler),
+        )
+        .route(
+            "/api/auth/me/2fa/recovery-codes",
+            post(handlers::two_factor_handler::regenerate_recovery_codes_handler),
+        )
+        .route(
+            "/api/auth/me/oidc/link",
+            post(handlers::oidc_handler::link_oidc_handler),
+        )
+        .route(
+            "/api/auth/me/oidc",
+            delete(handlers::oidc_handler::unlink_oidc_handler),
+        )
+        .route(
+            "/api/auth/me/tokens",
+            get(handlers::access_token_handler::list_access_tokens_handler),
+        )
+        .route(
+            "/api/auth/me/tokens",
+            post(handlers::access_token_handler::create_access_token_handler),
+        )
+        .route(
+            "/api/auth/me/tokens/:token_id",
+            delete(handlers::access_token_handler::revoke_access_token_handler),
+        )
         .route(
             "/api/auth/users",
             get(handlers::auth_handler::list_users_handler),
         )
+        .route(
+            "/api/auth/users/:id/impersonate",
+            post(handlers::impersonation_handler::start_impersonation_handler),
+        )
+        .route(
+            "/api/auth/impersonation/stop",
+            post(handlers::impersonation_handler::stop_impersonation_handler),
+        )
+        .route(
+            "/api/auth/users/:id/unlock",
+            post(handlers::login_throttle_handler::unlock_user_handler),
+        )
+        .route(
+            "/api/auth/users/:id/resend-setup",
+            post(handlers::auth_handler::resend_setup_handler),
+        )
         .route(
             "/api/auth/users/:id",
             put(handlers::auth_handler::update_user_handler),
@@ -167,6 +425,54 @@ async fn main() {
             "/api/auth/users/:id",
             delete(handlers::auth_handler::delete_user_handler),
         )
+        .route(
+            "/api/auth/users/:id/2fa",
+            delete(handlers::two_factor_handler::reset_user_two_factor_handler),
+        )
+        .route(
+            "/api/admin/security/lockouts",
+            get(handlers::login_throttle_handler::list_lockouts_handler),
+        )
+        .route(
+            "/api/admin/security/lockouts/:key",
+            delete(handlers::login_throttle_handler::clear_lockout_handler),
+        )
+        .route(
+            "/api/admin/audit-events",
+            get(handlers::audit_handler::list_audit_events_handler),
+        )
+        .route(
+            "/api/admin/rooms",
+            get(handlers::room_admin_handler::list_rooms_handler),
+        )
+        .route(
+            "/api/admin/rooms/:room_code",
+            get(handlers::room_admin_handler::get_room_handler),
+        )
+        .route(
+            "/api/admin/rooms/:room_code/peers/:peer_id/kick",
+            post(handlers::room_admin_handler::kick_peer_handler),
+        )
+        .route(
+            "/api/admin/rooms/:room_code/close",
+            post(handlers::room_admin_handler::close_room_handler),
+        )
+        .route(
+            "/api/admin/rooms/:room_code/evict",
+            post(handlers::room_admin_handler::evict_room_handler),
+        )
+        .route(
+            "/api/admin/rooms/:room_code/preload",
+            post(handlers::room_admin_handler::preload_room_handler),
+        )
+        .route(
+            "/api/admin/security/policy",
+            get(handlers::two_factor_handler::get_security_policy_handler),
+        )
+        .route(
+            "/api/admin/security/policy",
+            put(handlers::two_factor_handler::update_security_policy_handler),
+        )
         .route(
             "/api/admin/storage/config",
             get(handlers::storage_handler::get_storage_config_handler),
@@ -233,10 +539,52 @@ async fn main() {
             "/api/workspaces/:id",
             delete(handlers::workspace_handler::delete_workspace_handler),
         )
+        .route(
+            "/api/workspaces/:id/room-code/rotate",
+            post(handlers::workspace_handler::rotate_room_code_handler),
+        )
         .route(
             "/api/workspaces/access/:room_code",
             get(handlers::workspace_handler::check_workspace_access_handler),
         )
+        // Workspace member routes
+        .route(
+            "/api/workspaces/:ws_id/members",
+            get(handlers::member_handler::list_members),
+        )
+        .route(
+            "/api/workspaces/:ws_id/members",
+            post(handlers::member_handler::invite_member),
+        )
+        .route(
+            "/api/workspaces/:ws_id/members/:member_id",
+            put(handlers::member_handler::update_member_role),
+        )
+        .route(
+            "/api/workspaces/:ws_id/members/:member_id",
+            delete(handlers::member_handler::remove_member),
+        )
+        // Workspace invite links
+        .route(
+            "/api/workspaces/:ws_id/invites",
+            get(handlers::workspace_invite_handler::list_invites_handler),
+        )
+        .route(
+            "/api/workspaces/:ws_id/invites",
+            post(handlers::workspace_invite_handler::create_invite_handler),
+        )
+        .route(
+            "/api/workspaces/:ws_id/invites/:invite_id",
+            delete(handlers::workspace_invite_handler::revoke_invite_handler),
+        )
+        .route(
+            "/api/invites/:token",
+            get(handlers::workspace_invite_handler::preview_invite_handler),
+        )
+        .route(
+            "/api/invites/:token/accept",
+            post(handlers::workspace_invite_handler::accept_invite_handler),
+        )
         .route("/api/my/tasks", get(handlers::data_handler::list_my_tasks))
         // Data routes (workspace-scoped)
         .route(
@@ -401,7 +749,33 @@ async fn main() {
             "/api/workspaces/:ws_id/checklist-templates/:template_id",
             delete(handlers::checklist_template_handler::delete_checklist_template),
         )
+        // Room document history
+        .route(
+            "/api/workspaces/:ws_id/snapshots",
+            get(handlers::room_snapshot_handler::list_snapshots),
+        )
+        .route(
+            "/api/workspaces/:ws_id/snapshots/:snapshot_id/diff",
+            get(handlers::room_snapshot_handler::diff_snapshot),
+        )
+        .route(
+            "/api/workspaces/:ws_id/snapshots/:snapshot_id/restore",
+            post(handlers::room_snapshot_handler::restore_snapshot),
+        )
+        // WebSocket, and server-sent events for networks that block it
         .route("/ws", get(handlers::ws_handler::ws_handler))
+        .route(
+            "/api/workspaces/:ws_id/events",
+            get(handlers::event_stream_handler::stream_events),
+        )
+        .route(
+            "/api/workspaces/:ws_id/events/:peer_id",
+            post(handlers::event_stream_handler::post_event),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            handlers::impersonation_handler::impersonation_guard,
+        ))
         .layer(
             tower_http::cors::CorsLayer::new()
                 .allow_origin([
@@ -428,10 +802,15 @@ async fn main() {
                     axum::http::header::CONTENT_TYPE,
                     axum::http::header::AUTHORIZATION,
                     "x-setup-token".parse::<axum::http::HeaderName>().unwrap(),
+                    "last-event-id".parse::<axum::http::HeaderName>().unwrap(),
                 ])
                 .allow_credentials(true),
         )
-        .with_state(state);
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            resolve_client_ip,
+        ))
+        .with_state(state.clone());
 
     let port = std::env::var("PORT")
         .ok()
@@ -443,13 +822,19 @@ async fn main() {
     info!("🔗 WebSocket endpoint: ws://{}/ws", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(system_tx))
-        .await
-        .unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(system_tx, state.clone()))
+    .await
+    .unwrap();
+
+    // Syncs handled while the last connections were closing
+    state.document_persister.flush(&state.db).await;
 }
 
-async fn shutdown_signal(tx: broadcast::Sender<SystemEvent>) {
+async fn shutdown_signal(tx: broadcast::Sender<SystemEvent>, state: Arc<AppState>) {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
             .await
@@ -474,6 +859,10 @@ async fn shutdown_signal(tx: broadcast::Sender<SystemEvent>) {
 
     info!("🛑 Signal received, starting graceful shutdown...");
     let _ = tx.send(SystemEvent::Shutdown);
+
+    let dirty = state.document_persister.dirty_rooms();
+    let flushed = state.document_persister.flush(&state.db).await;
+    info!("💾 Saved {}/{} dirty room documents", flushed, dirty);
 }
 
 async fn root_handler() -> impl IntoResponse {
@@ -490,9 +879,22 @@ async fn root_handler() -> impl IntoResponse {
 }
 
 async fn health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    // Rooms are listed by id; their codes grant access to workspaces
+    let room_peers: Vec<serde_json::Value> = state
+        .rooms
+        .iter()
+        .map(|room| serde_json::json!({ "room_id": room.id, "peers": room.peers.len() }))
+        .collect();
+    let peers: usize = state.rooms.iter().map(|room| room.peers.len()).sum();
+
     axum::Json(serde_json::json!({
         "status": "healthy",
         "rooms": state.rooms.len(),
+        "connections": state.ws_connections.load(Ordering::Relaxed),
+        "event_streams": state.event_streams.len(),
+        "peers": peers,
+        "room_peers": room_peers,
+        "broadcast": state.room_metrics.snapshot(),
         "timestamp": chrono::Utc::now()
     }))
 }
@@ -523,8 +925,11 @@ async fn check_and_create_initial_admin(db: &mongodb::Database) {
                 password_hash: Some(password_hash),
                 created_at: chrono::Utc::now(),
                 setup_token: None,
+                setup_token_expires_at: None,
                 is_active: true,
                 discord_id: None,
+                two_factor: Default::default(),
+                external_identities: Vec::new(),
             };
 
             if let Err(e) = user_repo.create(new_user).await {
@@ -0,0 +1,106 @@
+use crate::models::member::WorkspaceRole;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Prefix that tells a personal access token apart from a session JWT.
+pub const ACCESS_TOKEN_PREFIX: &str = "khp_";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessTokenPermission {
+    Read,
+    Write,
+}
+
+impl AccessTokenPermission {
+    /// Highest workspace role a token with this permission can act as. Member and
+    /// workspace management stay session-only.
+    pub fn max_role(self) -> WorkspaceRole {
+        match self {
+            AccessTokenPermission::Read => WorkspaceRole::Viewer,
+            AccessTokenPermission::Write => WorkspaceRole::Editor,
+        }
+    }
+}
+
+/// Named token for scripts and CI. Only the SHA-256 hash of the secret is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalAccessToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub token_id: String,
+    pub user_id: ObjectId,
+    pub name: String,
+    pub token_hash: String,
+    /// First characters of the token, shown in lists so users can tell tokens apart
+    pub token_prefix: String,
+    pub workspace_ids: Vec<ObjectId>,
+    pub permission: AccessTokenPermission,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+}
+
+/// What a request authenticated with a personal access token may touch.
+#[derive(Debug, Clone)]
+pub struct AccessTokenScope {
+    pub workspace_ids: Vec<ObjectId>,
+    pub permission: AccessTokenPermission,
+}
+
+impl AccessTokenScope {
+    pub fn allows_workspace(&self, workspace_id: &ObjectId) -> bool {
+        self.workspace_ids.contains(workspace_id)
+    }
+
+    /// The member's role, capped at what the token permission allows.
+    pub fn cap_role(&self, role: WorkspaceRole) -> WorkspaceRole {
+        let max = self.permission.max_role();
+        if max.allows(role) {
+            role
+        } else {
+            max
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAccessTokenRequest {
+    pub name: String,
+    pub workspace_ids: Vec<String>,
+    pub permission: AccessTokenPermission,
+    /// Defaults to 90 days; `0` means the token never expires
+    pub expires_in_days: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_tokens_are_capped_at_viewer() {
+        let scope = AccessTokenScope {
+            workspace_ids: vec![],
+            permission: AccessTokenPermission::Read,
+        };
+        assert_eq!(scope.cap_role(WorkspaceRole::Owner), WorkspaceRole::Viewer);
+        assert_eq!(scope.cap_role(WorkspaceRole::Viewer), WorkspaceRole::Viewer);
+    }
+
+    #[test]
+    fn write_tokens_never_exceed_the_member_role() {
+        let scope = AccessTokenScope {
+            workspace_ids: vec![],
+            permission: AccessTokenPermission::Write,
+        };
+        assert_eq!(scope.cap_role(WorkspaceRole::Owner), WorkspaceRole::Editor);
+        assert_eq!(
+            scope.cap_role(WorkspaceRole::Commenter),
+            WorkspaceRole::Commenter
+        );
+    }
+}
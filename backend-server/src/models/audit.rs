@@ -0,0 +1,45 @@
+use mongodb::bson::{oid::ObjectId, Document};
+use serde::{Deserialize, Serialize};
+
+/// One security-relevant admin action. Entries are only ever inserted; the retention task is
+/// the only thing that removes them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub event_id: String,
+    /// Dotted verb such as `user.update` or `storage.config_reset`
+    pub action: String,
+    /// `None` when no user was logged in, e.g. the first invite made with the setup token
+    #[serde(default)]
+    pub actor_id: Option<String>,
+    /// Email at the time of the action, kept so the entry still reads after the actor is deleted
+    #[serde(default)]
+    pub actor_email: Option<String>,
+    pub target_type: String,
+    #[serde(default)]
+    pub target_id: Option<String>,
+    /// Only the fields that changed, with their old values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Document>,
+    /// Only the fields that changed, with their new values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Document>,
+    #[serde(default)]
+    pub ip: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventQuery {
+    pub action: Option<String>,
+    pub actor_id: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    /// RFC3339, inclusive
+    pub from: Option<String>,
+    /// RFC3339, exclusive
+    pub to: Option<String>,
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+}
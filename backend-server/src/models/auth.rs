@@ -1,3 +1,4 @@
+use crate::models::access_token::AccessTokenScope;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -5,6 +6,14 @@ pub struct Claims {
     pub sub: String, // user_id
     pub role: String,
     pub exp: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>, // session_id
+    /// Set when the request used a personal access token instead of a session
+    #[serde(skip)]
+    pub access_token: Option<AccessTokenScope>,
+    /// Admin user id when an admin is acting as `sub` through an impersonation token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -13,6 +22,26 @@ pub struct AuthRequest {
     pub password: String,
 }
 
+/// Second login step: `code` from the authenticator app, or a one-time `recovery_code`.
+#[derive(Deserialize)]
+pub struct LoginTwoFactorRequest {
+    pub challenge_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct InviteRequest {
     pub email: String,
@@ -25,6 +54,12 @@ pub struct InviteRequest {
     pub discord_id: Option<String>,
 }
 
+#[derive(Deserialize, Default)]
+pub struct ImpersonateRequest {
+    /// Shown in the audit log, e.g. the support ticket being looked into
+    pub reason: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct UpdateUserRequest {
     pub email: Option<String>,
@@ -48,8 +83,25 @@ pub struct SetupPasswordPayload {
     pub password: String,
 }
 
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordPayload {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenQuery {
+    pub token: String,
+}
+
 #[derive(Deserialize)]
 pub struct UpdateProfileRequest {
+    pub email: Option<String>,
     pub password: Option<String>,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
@@ -0,0 +1,40 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthTokenPurpose {
+    PasswordReset,
+    EmailChange,
+    /// Password was accepted; the second factor (or 2FA enrollment) is still pending
+    TwoFactorLogin,
+}
+
+impl AuthTokenPurpose {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuthTokenPurpose::PasswordReset => "password_reset",
+            AuthTokenPurpose::EmailChange => "email_change",
+            AuthTokenPurpose::TwoFactorLogin => "two_factor_login",
+        }
+    }
+}
+
+/// Single-use token (emailed link or login challenge). Only the SHA-256 hash is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub token_hash: String,
+    pub purpose: AuthTokenPurpose,
+    pub user_id: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_email: Option<String>,
+    pub created_at: String,
+    pub expires_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub used_at: Option<String>,
+    /// Wrong codes entered against a login challenge
+    #[serde(default)]
+    pub failed_attempts: u32,
+}
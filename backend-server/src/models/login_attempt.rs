@@ -0,0 +1,28 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Failed logins counted against one key: `account:<email>` or `ip:<address>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginAttempt {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub key: String,
+    pub failures: u32,
+    /// The counter starts over once the window since the first failure has passed
+    pub window_started_at: String,
+    pub last_failed_at: String,
+    /// No login is attempted for this key before this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_until: Option<String>,
+    /// The block is a lockout rather than a backoff delay
+    #[serde(default)]
+    pub locked: bool,
+}
+
+pub fn account_key(email: &str) -> String {
+    format!("account:{}", email.trim().to_lowercase())
+}
+
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
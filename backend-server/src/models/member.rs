@@ -0,0 +1,89 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceRole {
+    Owner,
+    Admin,
+    Editor,
+    Commenter,
+    Viewer,
+}
+
+impl WorkspaceRole {
+    fn rank(self) -> u8 {
+        match self {
+            WorkspaceRole::Owner => 4,
+            WorkspaceRole::Admin => 3,
+            WorkspaceRole::Editor => 2,
+            WorkspaceRole::Commenter => 1,
+            WorkspaceRole::Viewer => 0,
+        }
+    }
+
+    /// True if this role grants at least the permissions of `required`.
+    pub fn allows(self, required: WorkspaceRole) -> bool {
+        self.rank() >= required.rank()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Editor => "editor",
+            WorkspaceRole::Commenter => "commenter",
+            WorkspaceRole::Viewer => "viewer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub workspace_id: ObjectId,
+    pub user_id: ObjectId, // Link to User._id
+    pub role: WorkspaceRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invited_by: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteMemberRequest {
+    pub email: Option<String>,
+    pub user_id: Option<String>,
+    pub role: WorkspaceRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRoleRequest {
+    pub role: WorkspaceRole,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_hierarchy() {
+        assert!(WorkspaceRole::Owner.allows(WorkspaceRole::Admin));
+        assert!(WorkspaceRole::Editor.allows(WorkspaceRole::Commenter));
+        assert!(WorkspaceRole::Viewer.allows(WorkspaceRole::Viewer));
+        assert!(!WorkspaceRole::Viewer.allows(WorkspaceRole::Commenter));
+        assert!(!WorkspaceRole::Commenter.allows(WorkspaceRole::Editor));
+        assert!(!WorkspaceRole::Admin.allows(WorkspaceRole::Owner));
+    }
+
+    #[test]
+    fn test_role_serialization() {
+        let json = serde_json::to_string(&WorkspaceRole::Commenter).unwrap();
+        assert_eq!(json, "\"commenter\"");
+        let role: WorkspaceRole = serde_json::from_str("\"viewer\"").unwrap();
+        assert_eq!(role, WorkspaceRole::Viewer);
+    }
+}
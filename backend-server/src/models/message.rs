@@ -1,14 +1,16 @@
-use crate::models::room::PeerInfo;
+use crate::models::room::{ChangeAction, PeerInfo, Presence, PresenceStatus};
 use serde::{Deserialize, Serialize};
+use wasm_crdt::{Operation, VersionVector};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "action", rename_all = "snake_case")]
 pub enum ClientMessage {
     Join {
         room_code: String,
-        peer_id: String,
-        is_host: bool,
         metadata: Option<serde_json::Value>,
+        /// Set by a reconnecting peer to receive only the operations it missed
+        #[serde(default)]
+        version: Option<VersionVector>,
     },
     Leave,
     Broadcast {
@@ -18,6 +20,23 @@ pub enum ClientMessage {
         document: String,
     },
     RequestSync,
+    PushOperations {
+        operations: Vec<Operation>,
+    },
+    RequestOperations {
+        version: VersionVector,
+    },
+    /// Replaces the sender's presence; omitted fields are cleared
+    UpdatePresence {
+        #[serde(default)]
+        task_id: Option<String>,
+        #[serde(default)]
+        field: Option<String>,
+        #[serde(default)]
+        typing: bool,
+        #[serde(default)]
+        status: PresenceStatus,
+    },
     Ping,
 }
 
@@ -40,18 +59,102 @@ pub enum ServerMessage {
     },
     DocumentSync {
         document: String,
+        version: VersionVector,
+    },
+    Operations {
+        operations: Vec<Operation>,
+        version: VersionVector,
     },
     Error {
+        code: ErrorCode,
         message: String,
     },
     RoomInfo {
         room_code: String,
-        host_id: String,
+        host_id: Option<String>,
         peers: Vec<PeerInfo>,
     },
+    PresenceUpdate {
+        peer_id: String,
+        presence: Presence,
+    },
+    HostChanged {
+        host_id: Option<String>,
+    },
+    RoomClosed {
+        reason: String,
+    },
+    /// An admin removed this peer from the room; the connection closes after it
+    Kicked {
+        reason: String,
+    },
+    /// This peer missed `skipped` room events; a fresh `document_sync` follows
+    Resync {
+        skipped: u64,
+    },
+    TaskChanged {
+        action: ChangeAction,
+        task_id: String,
+        task: Option<serde_json::Value>,
+        user_id: String,
+    },
+    CommentChanged {
+        action: ChangeAction,
+        task_id: String,
+        comment_id: String,
+        comment: Option<serde_json::Value>,
+        user_id: String,
+    },
+    SprintChanged {
+        action: ChangeAction,
+        sprint_id: String,
+        sprint: Option<serde_json::Value>,
+        user_id: String,
+    },
+    ProjectChanged {
+        action: ChangeAction,
+        project_id: String,
+        project: Option<serde_json::Value>,
+        user_id: String,
+    },
+    AssigneeChanged {
+        action: ChangeAction,
+        assignee_id: String,
+        assignee: Option<serde_json::Value>,
+        user_id: String,
+    },
+    AssigneeGroupChanged {
+        action: ChangeAction,
+        group_id: String,
+        group: Option<serde_json::Value>,
+        user_id: String,
+    },
+    MilestoneChanged {
+        action: ChangeAction,
+        milestone_id: String,
+        milestone: Option<serde_json::Value>,
+        user_id: String,
+    },
+    ChecklistTemplateChanged {
+        action: ChangeAction,
+        template_id: String,
+        template: Option<serde_json::Value>,
+        user_id: String,
+    },
     Pong,
 }
 
+/// Machine-readable reason carried by `error` messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidMessage,
+    MessageTooLarge,
+    RateLimited,
+    /// The message was valid but couldn't be carried out (e.g. not allowed for the role)
+    RequestFailed,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum SystemEvent {
@@ -59,3 +162,33 @@ pub enum SystemEvent {
     RoomClosed { room_id: String },
     Shutdown,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rest_changes_serialize_for_clients() {
+        let msg = ServerMessage::CommentChanged {
+            action: ChangeAction::Deleted,
+            task_id: "t1".to_string(),
+            comment_id: "c1".to_string(),
+            comment: None,
+            user_id: "u1".to_string(),
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "comment_changed");
+        assert_eq!(json["action"], "deleted");
+        assert!(json["comment"].is_null());
+    }
+
+    #[test]
+    fn kicked_peer_gets_the_reason() {
+        let msg = ServerMessage::Kicked {
+            reason: "Spamming the board".to_string(),
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "kicked");
+        assert_eq!(json["reason"], "Spamming the board");
+    }
+}
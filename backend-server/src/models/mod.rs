@@ -1,9 +1,20 @@
+pub mod access_token;
+pub mod audit;
 pub mod auth;
+pub mod auth_token;
 pub mod data;
+pub mod login_attempt;
+pub mod member;
 pub mod message;
 pub mod milestone;
+pub mod oidc;
 pub mod profile;
 pub mod room;
+pub mod room_event;
+pub mod room_snapshot;
+pub mod security;
+pub mod session;
 pub mod storage;
 pub mod user;
 pub mod workspace;
+pub mod workspace_invite;
@@ -0,0 +1,33 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Identity at an external OpenID Connect provider, linked to a local user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalIdentity {
+    pub issuer: String,
+    pub subject: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub linked_at: String,
+}
+
+/// Pending authorization request, kept until the provider redirects back with a code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcLoginState {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub state_hash: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    /// Set when a signed-in user is linking their account instead of logging in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_user_id: Option<ObjectId>,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
@@ -2,16 +2,20 @@ use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
+use wasm_crdt::{CrdtDocument, Operation, VersionVector};
 
 #[derive(Debug)]
 pub struct Room {
     pub id: String,
-    pub host_id: String,
+    /// Peer id of the host, chosen by the server; `None` while nobody is connected
+    pub host_id: Option<String>,
     pub created_at: DateTime<Utc>,
     pub tx: broadcast::Sender<RoomEvent>,
     pub peers: DashMap<String, PeerInfo>,
-    pub document_state: Option<String>,
+    pub document: Option<CrdtDocument>,
     pub last_sync: DateTime<Utc>,
+    /// When the document was last snapshotted into the room's history
+    pub last_snapshot: Option<DateTime<Utc>>,
     pub empty_since: Option<DateTime<Utc>>,
 }
 
@@ -21,20 +25,149 @@ pub struct PeerInfo {
     pub joined_at: DateTime<Utc>,
     pub is_host: bool,
     pub metadata: Option<serde_json::Value>,
+    /// Resolved from the socket's credentials, so unlike `metadata` it can't be spoofed
+    #[serde(default)]
+    pub user: Option<PeerUser>,
+    #[serde(default)]
+    pub presence: Presence,
+    /// Last frame received from the peer, written on each heartbeat. Only set for peers
+    /// connected to this instance.
+    #[serde(skip)]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerUser {
+    pub id: String,
+    pub email: String,
+    pub nickname: Option<String>,
+}
+
+/// What a peer is doing right now. Clients refresh it while it holds; the server clears
+/// `typing` and then the task/field once the peer stops refreshing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Presence {
+    pub task_id: Option<String>,
+    pub field: Option<String>,
+    pub typing: bool,
+    pub status: PresenceStatus,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    #[default]
+    Active,
+    Idle,
+    Away,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum RoomEvent {
-    PeerJoined { peer: PeerInfo },
-    PeerLeft { peer_id: String },
-    DataSync { from: String, data: String },
-    DocumentUpdate { from: String, document: String },
-    HostChanged { new_host_id: String },
+    PeerJoined {
+        peer: PeerInfo,
+    },
+    PeerLeft {
+        peer_id: String,
+    },
+    DataSync {
+        from: String,
+        data: String,
+    },
+    DocumentUpdate {
+        from: String,
+        document: String,
+        version: VersionVector,
+    },
+    OperationsUpdate {
+        from: String,
+        operations: Vec<Operation>,
+        version: VersionVector,
+    },
+    HostChanged {
+        new_host_id: Option<String>,
+    },
+    PresenceUpdate {
+        peer_id: String,
+        presence: Presence,
+    },
+    RoomClosed {
+        reason: String,
+    },
+    PeerKicked {
+        peer_id: String,
+        reason: String,
+    },
+    TaskChanged {
+        action: ChangeAction,
+        task_id: String,
+        task: Option<serde_json::Value>,
+        user_id: String,
+    },
+    CommentChanged {
+        action: ChangeAction,
+        task_id: String,
+        comment_id: String,
+        comment: Option<serde_json::Value>,
+        user_id: String,
+    },
+    SprintChanged {
+        action: ChangeAction,
+        sprint_id: String,
+        sprint: Option<serde_json::Value>,
+        user_id: String,
+    },
+    ProjectChanged {
+        action: ChangeAction,
+        project_id: String,
+        project: Option<serde_json::Value>,
+        user_id: String,
+    },
+    AssigneeChanged {
+        action: ChangeAction,
+        assignee_id: String,
+        assignee: Option<serde_json::Value>,
+        user_id: String,
+    },
+    AssigneeGroupChanged {
+        action: ChangeAction,
+        group_id: String,
+        group: Option<serde_json::Value>,
+        user_id: String,
+    },
+    MilestoneChanged {
+        action: ChangeAction,
+        milestone_id: String,
+        milestone: Option<serde_json::Value>,
+        user_id: String,
+    },
+    ChecklistTemplateChanged {
+        action: ChangeAction,
+        template_id: String,
+        template: Option<serde_json::Value>,
+        user_id: String,
+    },
+}
+
+/// What a REST mutation did to the record it touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Body of the admin kick and close endpoints.
+#[derive(Debug, Deserialize)]
+pub struct AdminRoomActionRequest {
+    #[serde(default)]
+    pub reason: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct CreateRoomRequest {
     pub desired_room_code: Option<String>,
-    pub desired_host_id: Option<String>,
 }
@@ -0,0 +1,44 @@
+use crate::models::room::PeerInfo;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// A `RoomEvent` handed to the other server instances through `room_events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomEventRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_code: String,
+    /// Instance that published the event; it already delivered it locally
+    pub origin: String,
+    /// The event as JSON
+    pub event: String,
+    pub created_at: String,
+}
+
+/// A peer connected to one of the server instances.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomPresence {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_code: String,
+    pub peer_id: String,
+    pub instance_id: String,
+    pub peer: PeerInfo,
+    /// Refreshed by the instance's heartbeat; entries of a crashed instance go stale
+    pub seen_at: String,
+}
+
+/// The host of a room shared by several instances. Only the instance holding it may pass it
+/// on, and a new host is only claimed when there is none, so instances can't both elect one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomHost {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_code: String,
+    /// `None` between a host leaving and someone claiming the room
+    pub host_id: Option<String>,
+    /// Instance the host is connected to
+    pub instance_id: String,
+    /// Refreshed by that instance's heartbeat; a stale host can be claimed over
+    pub seen_at: String,
+}
@@ -0,0 +1,52 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// A copy of a room's CRDT document, taken as it is synced and before every restore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub room_code: String,
+    /// The exported document. Left out of listings, so it may be empty.
+    #[serde(default)]
+    pub document: String,
+    /// Peer whose sync produced the document; "server" for snapshots taken on a restore
+    pub author_peer_id: String,
+    pub author_user_id: Option<String>,
+    /// "sync", "before_restore", or "unreadable" for a stored document the server couldn't load
+    pub reason: String,
+    /// Size of `document` in bytes
+    pub size: i64,
+    pub task_count: i64,
+    pub created_at: String,
+}
+
+/// Who caused a snapshot to be taken.
+#[derive(Debug, Clone)]
+pub struct SnapshotAuthor {
+    pub peer_id: String,
+    pub user_id: Option<String>,
+}
+
+/// How the current document differs from a snapshot.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotDiff {
+    /// Tasks that exist now but not in the snapshot
+    pub added: Vec<u32>,
+    /// Tasks in the snapshot that have since been deleted
+    pub removed: Vec<u32>,
+    pub changed: Vec<TaskDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskDiff {
+    pub task_id: u32,
+    pub fields: Vec<FieldDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldDiff {
+    pub field: String,
+    pub snapshot: Option<String>,
+    pub current: Option<String>,
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+pub const SECURITY_POLICY_KEY: &str = "security_policy";
+
+fn default_key() -> String {
+    SECURITY_POLICY_KEY.to_string()
+}
+
+/// Deployment-wide security settings, stored in `app_settings` next to the storage config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityPolicyDocument {
+    #[serde(default = "default_key")]
+    pub key: String,
+    /// Every user with the `admin` role must use two-factor authentication to log in
+    #[serde(default)]
+    pub require_admin_two_factor: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+}
+
+impl Default for SecurityPolicyDocument {
+    fn default() -> Self {
+        Self {
+            key: default_key(),
+            require_admin_two_factor: false,
+            updated_at: None,
+            updated_by: None,
+        }
+    }
+}
+
+impl SecurityPolicyDocument {
+    pub fn requires_two_factor(&self, role: &str) -> bool {
+        self.require_admin_two_factor && role == "admin"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSecurityPolicyRequest {
+    pub require_admin_two_factor: Option<bool>,
+}
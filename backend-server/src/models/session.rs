@@ -0,0 +1,41 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// A logged-in device. The refresh token itself is never stored, only its SHA-256 hash,
+/// and the hash is replaced on every refresh.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub session_id: String,
+    pub user_id: ObjectId,
+    pub refresh_token_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    pub created_at: String,
+    pub last_used_at: String,
+    pub expires_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+    /// Admin who opened this session to act as the user. These sessions have no usable
+    /// refresh token and end at `expires_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator_id: Option<ObjectId>,
+}
+
+/// Where a login came from, recorded on the session so users can recognise their devices.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenPair {
+    pub session_id: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
@@ -1,3 +1,4 @@
+use crate::models::oidc::ExternalIdentity;
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
@@ -31,8 +32,33 @@ pub struct User {
     #[serde(default = "default_created_at")]
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub setup_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setup_token_expires_at: Option<String>,
     #[serde(default = "default_is_active")]
     pub is_active: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub discord_id: Option<String>,
+    #[serde(default)]
+    pub two_factor: TwoFactorSettings,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_identities: Vec<ExternalIdentity>,
+}
+
+/// TOTP second factor. `secret` and `pending_secret` are stored encrypted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TwoFactorSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Secret handed out by setup but not yet confirmed with a code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_secret: Option<String>,
+    #[serde(default)]
+    pub recovery_code_hashes: Vec<String>,
+    /// Last accepted time step, so a code can't be replayed inside its window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_step: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled_at: Option<String>,
 }
@@ -0,0 +1,96 @@
+use crate::models::member::WorkspaceRole;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Shareable link that lets any logged-in user join a workspace with `role`. Only the
+/// SHA-256 hash of the link token is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInvite {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub invite_id: String,
+    pub workspace_id: ObjectId,
+    pub token_hash: String,
+    /// Role given to whoever accepts the link; never `Owner`
+    pub role: WorkspaceRole,
+    pub created_by: ObjectId,
+    pub created_at: String,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// `None` means unlimited
+    #[serde(default)]
+    pub max_uses: Option<u32>,
+    #[serde(default)]
+    pub uses: u32,
+    #[serde(default)]
+    pub revoked_at: Option<String>,
+}
+
+impl WorkspaceInvite {
+    /// `active`, `revoked`, `expired` or `used_up`, as shown in the invite list.
+    pub fn status(&self, now: &str) -> &'static str {
+        if self.revoked_at.is_some() {
+            "revoked"
+        } else if self.expires_at.as_deref().is_some_and(|at| at <= now) {
+            "expired"
+        } else if self.max_uses.is_some_and(|max| self.uses >= max) {
+            "used_up"
+        } else {
+            "active"
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWorkspaceInviteRequest {
+    /// Defaults to `viewer`
+    pub role: Option<WorkspaceRole>,
+    /// Defaults to 7 days; `0` means the link never expires
+    pub expires_in_hours: Option<i64>,
+    /// Omit for unlimited uses
+    pub max_uses: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invite() -> WorkspaceInvite {
+        WorkspaceInvite {
+            id: None,
+            invite_id: "inv".to_string(),
+            workspace_id: ObjectId::new(),
+            token_hash: String::new(),
+            role: WorkspaceRole::Editor,
+            created_by: ObjectId::new(),
+            created_at: "2026-01-01T00:00:00+00:00".to_string(),
+            expires_at: Some("2026-01-08T00:00:00+00:00".to_string()),
+            max_uses: Some(2),
+            uses: 1,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn status_reflects_revocation_expiry_and_uses() {
+        let now = "2026-01-02T00:00:00+00:00";
+        assert_eq!(invite().status(now), "active");
+        assert_eq!(invite().status("2026-01-08T00:00:00+00:00"), "expired");
+        assert_eq!(
+            WorkspaceInvite {
+                uses: 2,
+                ..invite()
+            }
+            .status(now),
+            "used_up"
+        );
+        assert_eq!(
+            WorkspaceInvite {
+                revoked_at: Some(now.to_string()),
+                ..invite()
+            }
+            .status(now),
+            "revoked"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use crate::models::access_token::PersonalAccessToken;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::{FindOptions, IndexOptions},
+    Collection, Database, IndexModel,
+};
+
+/// Minimum gap between `last_used_at` writes so busy scripts don't write on every request.
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+#[derive(Clone)]
+pub struct AccessTokenRepository {
+    collection: Collection<PersonalAccessToken>,
+}
+
+impl AccessTokenRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("personal_access_tokens"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_hash = IndexModel::builder()
+            .keys(doc! { "token_hash": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_pat_hash_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_user = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_pat_user".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection
+            .create_indexes(vec![unique_hash, by_user], None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, token: &PersonalAccessToken) -> mongodb::error::Result<()> {
+        self.collection.insert_one(token, None).await?;
+        Ok(())
+    }
+
+    /// Looks up a token that is neither revoked nor expired.
+    pub async fn find_active_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> mongodb::error::Result<Option<PersonalAccessToken>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.collection
+            .find_one(
+                doc! {
+                    "token_hash": token_hash,
+                    "revoked_at": null,
+                    "$or": [
+                        { "expires_at": null },
+                        { "expires_at": { "$gt": now } },
+                    ],
+                },
+                None,
+            )
+            .await
+    }
+
+    pub async fn find_by_user(
+        &self,
+        user_id: &ObjectId,
+    ) -> mongodb::error::Result<Vec<PersonalAccessToken>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        let cursor = self
+            .collection
+            .find(doc! { "user_id": user_id, "revoked_at": null }, options)
+            .await?;
+        cursor.try_collect().await
+    }
+
+    pub async fn touch_last_used(&self, token_id: &str) -> mongodb::error::Result<()> {
+        let now = chrono::Utc::now();
+        let threshold =
+            (now - chrono::Duration::seconds(LAST_USED_RESOLUTION_SECONDS)).to_rfc3339();
+        self.collection
+            .update_one(
+                doc! {
+                    "token_id": token_id,
+                    "$or": [
+                        { "last_used_at": null },
+                        { "last_used_at": { "$lt": threshold } },
+                    ],
+                },
+                doc! { "$set": { "last_used_at": now.to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns false if the token doesn't exist, belongs to someone else or is already revoked.
+    pub async fn revoke(&self, user_id: &ObjectId, token_id: &str) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! { "token_id": token_id, "user_id": user_id, "revoked_at": null },
+                doc! { "$set": { "revoked_at": chrono::Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count == 1)
+    }
+
+    pub async fn delete_by_user(&self, user_id: &ObjectId) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        Ok(())
+    }
+}
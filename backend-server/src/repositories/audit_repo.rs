@@ -0,0 +1,118 @@
+use crate::models::audit::{AuditEvent, AuditEventQuery};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::{FindOptions, IndexOptions},
+    Collection, Database, IndexModel,
+};
+
+/// Append-only: there is deliberately no update method.
+#[derive(Clone)]
+pub struct AuditRepository {
+    collection: Collection<AuditEvent>,
+}
+
+impl AuditRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("audit_events"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let by_time = IndexModel::builder()
+            .keys(doc! { "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_audit_created_at".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_actor = IndexModel::builder()
+            .keys(doc! { "actor_id": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_audit_actor".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_target = IndexModel::builder()
+            .keys(doc! { "target_type": 1, "target_id": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_audit_target".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection
+            .create_indexes(vec![by_time, by_actor, by_target], None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn insert(&self, event: &AuditEvent) -> mongodb::error::Result<()> {
+        self.collection.insert_one(event, None).await?;
+        Ok(())
+    }
+
+    fn filter(query: &AuditEventQuery) -> Document {
+        let mut filter = doc! {};
+        if let Some(action) = &query.action {
+            filter.insert("action", action);
+        }
+        if let Some(actor_id) = &query.actor_id {
+            filter.insert("actor_id", actor_id);
+        }
+        if let Some(target_type) = &query.target_type {
+            filter.insert("target_type", target_type);
+        }
+        if let Some(target_id) = &query.target_id {
+            filter.insert("target_id", target_id);
+        }
+        let mut created_at = doc! {};
+        if let Some(from) = &query.from {
+            created_at.insert("$gte", from);
+        }
+        if let Some(to) = &query.to {
+            created_at.insert("$lt", to);
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
+        filter
+    }
+
+    /// Newest first. Returns the page and the total number of matching events.
+    pub async fn find_page(
+        &self,
+        query: &AuditEventQuery,
+        skip: u64,
+        limit: i64,
+    ) -> mongodb::error::Result<(Vec<AuditEvent>, u64)> {
+        let filter = Self::filter(query);
+        let total = self
+            .collection
+            .count_documents(filter.clone(), None)
+            .await?;
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(skip)
+            .limit(limit)
+            .build();
+        let events = self
+            .collection
+            .find(filter, options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok((events, total))
+    }
+
+    pub async fn delete_older_than(&self, cutoff: &str) -> mongodb::error::Result<u64> {
+        let res = self
+            .collection
+            .delete_many(doc! { "created_at": { "$lt": cutoff } }, None)
+            .await?;
+        Ok(res.deleted_count)
+    }
+}
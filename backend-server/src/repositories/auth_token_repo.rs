@@ -0,0 +1,146 @@
+use crate::models::auth_token::{AuthToken, AuthTokenPurpose};
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct AuthTokenRepository {
+    collection: Collection<AuthToken>,
+}
+
+impl AuthTokenRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("auth_tokens"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_hash = IndexModel::builder()
+            .keys(doc! { "token_hash": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_auth_tokens_hash_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_user = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "purpose": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_auth_tokens_user_purpose".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection
+            .create_indexes(vec![unique_hash, by_user], None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, token: &AuthToken) -> mongodb::error::Result<()> {
+        self.collection.insert_one(token, None).await?;
+        Ok(())
+    }
+
+    pub async fn find_valid(
+        &self,
+        token_hash: &str,
+        purpose: AuthTokenPurpose,
+    ) -> mongodb::error::Result<Option<AuthToken>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.collection
+            .find_one(
+                doc! {
+                    "token_hash": token_hash,
+                    "purpose": purpose.as_str(),
+                    "used_at": null,
+                    "expires_at": { "$gt": now },
+                },
+                None,
+            )
+            .await
+    }
+
+    /// Atomically marks a valid token as used and returns it. A second call with the
+    /// same token returns `None`.
+    pub async fn consume(
+        &self,
+        token_hash: &str,
+        purpose: AuthTokenPurpose,
+    ) -> mongodb::error::Result<Option<AuthToken>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.collection
+            .find_one_and_update(
+                doc! {
+                    "token_hash": token_hash,
+                    "purpose": purpose.as_str(),
+                    "used_at": null,
+                    "expires_at": { "$gt": &now },
+                },
+                doc! { "$set": { "used_at": &now } },
+                options,
+            )
+            .await
+    }
+
+    /// Counts a wrong code against a challenge and burns it once `max_attempts` is reached.
+    pub async fn record_failed_attempt(
+        &self,
+        token_hash: &str,
+        max_attempts: u32,
+    ) -> mongodb::error::Result<()> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let updated = self
+            .collection
+            .find_one_and_update(
+                doc! { "token_hash": token_hash, "used_at": null },
+                doc! { "$inc": { "failed_attempts": 1 } },
+                options,
+            )
+            .await?;
+        if let Some(token) = updated {
+            if token.failed_attempts >= max_attempts {
+                self.collection
+                    .update_one(
+                        doc! { "token_hash": token_hash },
+                        doc! { "$set": { "used_at": chrono::Utc::now().to_rfc3339() } },
+                        None,
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Burns any outstanding tokens of this purpose so only the newest link works.
+    pub async fn invalidate_for_user(
+        &self,
+        user_id: &ObjectId,
+        purpose: AuthTokenPurpose,
+    ) -> mongodb::error::Result<()> {
+        self.collection
+            .update_many(
+                doc! { "user_id": user_id, "purpose": purpose.as_str(), "used_at": null },
+                doc! { "$set": { "used_at": chrono::Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_user(&self, user_id: &ObjectId) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        Ok(())
+    }
+}
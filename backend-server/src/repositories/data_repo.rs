@@ -649,6 +649,16 @@ impl DataRepository {
         Ok(project)
     }
 
+    pub async fn find_project_by_id(
+        &self,
+        id: &ObjectId,
+        workspace_id: &ObjectId,
+    ) -> mongodb::error::Result<Option<ProjectDocument>> {
+        self.projects
+            .find_one(doc! { "_id": id, "workspace_id": workspace_id }, None)
+            .await
+    }
+
     pub async fn update_project(
         &self,
         id: &ObjectId,
@@ -832,22 +842,24 @@ impl DataRepository {
         Ok(results)
     }
 
-    pub async fn find_assigned_workspaces(
+    pub async fn find_user_linked_assignees(
         &self,
-        user_id_hex: &str,
-    ) -> mongodb::error::Result<Vec<ObjectId>> {
+    ) -> mongodb::error::Result<Vec<AssigneeDocument>> {
         let mut cursor = self
             .assignees
-            .find(doc! { "user_id": user_id_hex }, None)
+            .find(
+                doc! { "user_id": { "$exists": true, "$ne": Bson::Null } },
+                None,
+            )
             .await?;
-        let mut w_ids = Vec::new();
+        let mut assignees = Vec::new();
         while let Some(result) = cursor.next().await {
             match result {
-                Ok(doc) => w_ids.push(doc.workspace_id),
+                Ok(doc) => assignees.push(doc),
                 Err(e) => return Err(e),
             }
         }
-        Ok(w_ids)
+        Ok(assignees)
     }
 
     pub async fn find_user_assignee_ids_by_workspace_ids(
@@ -894,6 +906,16 @@ impl DataRepository {
         Ok(assignee)
     }
 
+    pub async fn find_assignee_by_id(
+        &self,
+        id: &ObjectId,
+        workspace_id: &ObjectId,
+    ) -> mongodb::error::Result<Option<AssigneeDocument>> {
+        self.assignees
+            .find_one(doc! { "_id": id, "workspace_id": workspace_id }, None)
+            .await
+    }
+
     pub async fn update_assignee(
         &self,
         id: &ObjectId,
@@ -1030,6 +1052,16 @@ impl DataRepository {
         Ok(sprint)
     }
 
+    pub async fn find_sprint_by_id(
+        &self,
+        id: &ObjectId,
+        workspace_id: &ObjectId,
+    ) -> mongodb::error::Result<Option<SprintDocument>> {
+        self.sprints
+            .find_one(doc! { "_id": id, "workspace_id": workspace_id }, None)
+            .await
+    }
+
     pub async fn update_sprint(
         &self,
         id: &ObjectId,
@@ -1153,7 +1185,6 @@ impl DataRepository {
         Ok(res.deleted_count > 0)
     }
 
-    #[allow(dead_code)]
     pub async fn find_checklist_template_by_id(
         &self,
         id: &str,
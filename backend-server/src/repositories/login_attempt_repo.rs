@@ -0,0 +1,108 @@
+use crate::models::login_attempt::LoginAttempt;
+use mongodb::{
+    bson::doc,
+    options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct LoginAttemptRepository {
+    collection: Collection<LoginAttempt>,
+}
+
+impl LoginAttemptRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("login_attempts"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_key = IndexModel::builder()
+            .keys(doc! { "key": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_login_attempts_key_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(unique_key, None).await?;
+        Ok(())
+    }
+
+    pub async fn find(&self, key: &str) -> mongodb::error::Result<Option<LoginAttempt>> {
+        self.collection.find_one(doc! { "key": key }, None).await
+    }
+
+    /// Counts one more failure and returns the updated counter. A counter whose window
+    /// started before `window_cutoff` starts over at one.
+    pub async fn record_failure(
+        &self,
+        key: &str,
+        window_cutoff: &str,
+    ) -> mongodb::error::Result<Option<LoginAttempt>> {
+        self.collection
+            .delete_one(
+                doc! { "key": key, "window_started_at": { "$lt": window_cutoff } },
+                None,
+            )
+            .await?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        self.collection
+            .find_one_and_update(
+                doc! { "key": key },
+                doc! {
+                    "$inc": { "failures": 1 },
+                    "$set": { "last_failed_at": &now },
+                    "$setOnInsert": { "window_started_at": &now, "locked": false },
+                },
+                FindOneAndUpdateOptions::builder()
+                    .upsert(true)
+                    .return_document(ReturnDocument::After)
+                    .build(),
+            )
+            .await
+    }
+
+    pub async fn set_block(
+        &self,
+        key: &str,
+        blocked_until: &str,
+        locked: bool,
+    ) -> mongodb::error::Result<()> {
+        self.collection
+            .update_one(
+                doc! { "key": key },
+                doc! { "$set": { "blocked_until": blocked_until, "locked": locked } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Keys that are blocked right now, most recent failure first.
+    pub async fn find_blocked(&self) -> mongodb::error::Result<Vec<LoginAttempt>> {
+        use futures::TryStreamExt;
+        let now = chrono::Utc::now().to_rfc3339();
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "last_failed_at": -1 })
+            .limit(200)
+            .build();
+        self.collection
+            .find(doc! { "blocked_until": { "$gt": now } }, options)
+            .await?
+            .try_collect()
+            .await
+    }
+
+    /// Returns false if there was nothing to clear.
+    pub async fn clear(&self, key: &str) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .delete_one(doc! { "key": key }, None)
+            .await?;
+        Ok(res.deleted_count == 1)
+    }
+}
@@ -0,0 +1,204 @@
+use crate::models::member::{WorkspaceMember, WorkspaceRole};
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    options::{IndexOptions, UpdateOptions},
+    Collection, Database, IndexModel,
+};
+
+const ASSIGNEE_BACKFILL_KEY: &str = "migration_members_from_assignees";
+
+#[derive(Clone)]
+pub struct MemberRepository {
+    collection: Collection<WorkspaceMember>,
+    settings: Collection<Document>,
+}
+
+impl MemberRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("workspace_members"),
+            settings: db.collection("app_settings"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_member = IndexModel::builder()
+            .keys(doc! { "workspace_id": 1, "user_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_workspace_members_ws_user_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_user = IndexModel::builder()
+            .keys(doc! { "user_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_workspace_members_user".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection
+            .create_indexes(vec![unique_member, by_user], None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find_member(
+        &self,
+        workspace_id: &ObjectId,
+        user_id: &ObjectId,
+    ) -> mongodb::error::Result<Option<WorkspaceMember>> {
+        self.collection
+            .find_one(
+                doc! { "workspace_id": workspace_id, "user_id": user_id },
+                None,
+            )
+            .await
+    }
+
+    pub async fn find_by_workspace(
+        &self,
+        workspace_id: &ObjectId,
+    ) -> mongodb::error::Result<Vec<WorkspaceMember>> {
+        let mut cursor = self
+            .collection
+            .find(doc! { "workspace_id": workspace_id }, None)
+            .await?;
+        let mut members = Vec::new();
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(doc) => members.push(doc),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(members)
+    }
+
+    pub async fn find_workspace_ids_by_user(
+        &self,
+        user_id: &ObjectId,
+    ) -> mongodb::error::Result<Vec<ObjectId>> {
+        let mut cursor = self
+            .collection
+            .find(doc! { "user_id": user_id }, None)
+            .await?;
+        let mut ws_ids = Vec::new();
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(doc) => ws_ids.push(doc.workspace_id),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(ws_ids)
+    }
+
+    pub async fn create(
+        &self,
+        mut member: WorkspaceMember,
+    ) -> mongodb::error::Result<WorkspaceMember> {
+        let now = chrono::Utc::now().to_rfc3339();
+        member.created_at = Some(now.clone());
+        member.updated_at = Some(now);
+        let res = self.collection.insert_one(member.clone(), None).await?;
+        if let Some(id) = res.inserted_id.as_object_id() {
+            member.id = Some(id);
+        }
+        Ok(member)
+    }
+
+    /// Inserts a membership only if the user has none yet; existing roles are left untouched.
+    pub async fn insert_if_missing(
+        &self,
+        workspace_id: &ObjectId,
+        user_id: &ObjectId,
+        role: WorkspaceRole,
+    ) -> mongodb::error::Result<bool> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let res = self
+            .collection
+            .update_one(
+                doc! { "workspace_id": workspace_id, "user_id": user_id },
+                doc! { "$setOnInsert": {
+                    "workspace_id": workspace_id,
+                    "user_id": user_id,
+                    "role": role.as_str(),
+                    "created_at": &now,
+                    "updated_at": &now,
+                } },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(res.upserted_id.is_some())
+    }
+
+    pub async fn update_role(
+        &self,
+        workspace_id: &ObjectId,
+        user_id: &ObjectId,
+        role: WorkspaceRole,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! { "workspace_id": workspace_id, "user_id": user_id },
+                doc! { "$set": {
+                    "role": role.as_str(),
+                    "updated_at": chrono::Utc::now().to_rfc3339(),
+                } },
+                None,
+            )
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    pub async fn delete(
+        &self,
+        workspace_id: &ObjectId,
+        user_id: &ObjectId,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .delete_one(
+                doc! { "workspace_id": workspace_id, "user_id": user_id },
+                None,
+            )
+            .await?;
+        Ok(res.deleted_count > 0)
+    }
+
+    pub async fn delete_by_workspace(&self, workspace_id: &ObjectId) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_many(doc! { "workspace_id": workspace_id }, None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_user(&self, user_id: &ObjectId) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn is_assignee_backfill_done(&self) -> mongodb::error::Result<bool> {
+        Ok(self
+            .settings
+            .find_one(doc! { "key": ASSIGNEE_BACKFILL_KEY }, None)
+            .await?
+            .is_some())
+    }
+
+    pub async fn mark_assignee_backfill_done(&self) -> mongodb::error::Result<()> {
+        self.settings
+            .update_one(
+                doc! { "key": ASSIGNEE_BACKFILL_KEY },
+                doc! { "$set": { "completed_at": chrono::Utc::now().to_rfc3339() } },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
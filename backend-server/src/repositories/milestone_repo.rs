@@ -60,7 +60,6 @@ impl MilestoneRepository {
         Ok(milestones)
     }
 
-    #[allow(dead_code)]
     pub async fn find_by_id(&self, id: &str) -> mongodb::error::Result<Option<Milestone>> {
         self.collection
             .find_one(doc! { "_id": Self::build_id_filter(id) }, None)
@@ -1,7 +1,20 @@
+pub mod access_token_repo;
+pub mod audit_repo;
+pub mod auth_token_repo;
 pub mod data_repo;
+pub mod login_attempt_repo;
+pub mod member_repo;
 pub mod milestone_repo;
+pub mod oidc_state_repo;
 pub mod profile_repo;
+pub mod room_event_repo;
+pub mod room_host_repo;
+pub mod room_presence_repo;
 pub mod room_repo;
+pub mod room_snapshot_repo;
+pub mod security_repo;
+pub mod session_repo;
 pub mod storage_repo;
 pub mod user_repo;
+pub mod workspace_invite_repo;
 pub mod workspace_repo;
@@ -0,0 +1,54 @@
+use crate::models::oidc::OidcLoginState;
+use mongodb::{bson::doc, options::IndexOptions, Collection, Database, IndexModel};
+
+#[derive(Clone)]
+pub struct OidcStateRepository {
+    collection: Collection<OidcLoginState>,
+}
+
+impl OidcStateRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("oidc_login_states"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_hash = IndexModel::builder()
+            .keys(doc! { "state_hash": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_oidc_states_hash_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(unique_hash, None).await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, state: &OidcLoginState) -> mongodb::error::Result<()> {
+        // Abandoned logins never come back, so clean them up as new ones start
+        let now = chrono::Utc::now().to_rfc3339();
+        self.collection
+            .delete_many(doc! { "expires_at": { "$lte": &now } }, None)
+            .await?;
+        self.collection.insert_one(state, None).await?;
+        Ok(())
+    }
+
+    /// Removes and returns the state if it exists and hasn't expired. A state can only be
+    /// used once.
+    pub async fn consume(
+        &self,
+        state_hash: &str,
+    ) -> mongodb::error::Result<Option<OidcLoginState>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.collection
+            .find_one_and_delete(
+                doc! { "state_hash": state_hash, "expires_at": { "$gt": now } },
+                None,
+            )
+            .await
+    }
+}
@@ -0,0 +1,64 @@
+use crate::models::room_event::RoomEventRecord;
+use mongodb::{
+    bson::doc,
+    change_stream::{event::ChangeStreamEvent, event::ResumeToken, ChangeStream},
+    options::{ChangeStreamOptions, IndexOptions},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct RoomEventRepository {
+    collection: Collection<RoomEventRecord>,
+}
+
+impl RoomEventRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("room_events"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let by_time = IndexModel::builder()
+            .keys(doc! { "created_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_room_event_created_at".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(by_time, None).await?;
+        Ok(())
+    }
+
+    pub async fn insert(&self, record: &RoomEventRecord) -> mongodb::error::Result<()> {
+        self.collection.insert_one(record, None).await?;
+        Ok(())
+    }
+
+    /// Change stream of events published by other instances. Needs a replica set.
+    pub async fn watch_others(
+        &self,
+        instance_id: &str,
+        resume_after: Option<ResumeToken>,
+    ) -> mongodb::error::Result<ChangeStream<ChangeStreamEvent<RoomEventRecord>>> {
+        let pipeline = vec![doc! {
+            "$match": {
+                "operationType": "insert",
+                "fullDocument.origin": { "$ne": instance_id },
+            }
+        }];
+        let options = ChangeStreamOptions::builder()
+            .start_after(resume_after)
+            .build();
+        self.collection.watch(pipeline, options).await
+    }
+
+    pub async fn delete_older_than(&self, cutoff: &str) -> mongodb::error::Result<u64> {
+        let res = self
+            .collection
+            .delete_many(doc! { "created_at": { "$lt": cutoff } }, None)
+            .await?;
+        Ok(res.deleted_count)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::models::room_event::RoomHost;
+use mongodb::{
+    bson::doc,
+    options::{IndexOptions, UpdateOptions},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct RoomHostRepository {
+    collection: Collection<RoomHost>,
+}
+
+impl RoomHostRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("room_hosts"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_room = IndexModel::builder()
+            .keys(doc! { "room_code": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_room_hosts_room_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(unique_room, None).await?;
+        Ok(())
+    }
+
+    /// Makes `peer_id` host if the room has none, or only one whose instance stopped checking
+    /// in before `stale_before`. Returns whoever hosts the room afterwards.
+    pub async fn claim(
+        &self,
+        room_code: &str,
+        peer_id: &str,
+        instance_id: &str,
+        now: &str,
+        stale_before: &str,
+    ) -> mongodb::error::Result<Option<String>> {
+        let claimed = self
+            .collection
+            .update_one(
+                doc! {
+                    "room_code": room_code,
+                    "$or": [{ "host_id": null }, { "seen_at": { "$lt": stale_before } }],
+                },
+                doc! {
+                    "$set": { "host_id": peer_id, "instance_id": instance_id, "seen_at": now },
+                },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await;
+        match claimed {
+            Ok(_) => {}
+            // The room has a live host, so the upsert ran into its document
+            Err(e) if e.to_string().contains("E11000") => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(self
+            .collection
+            .find_one(doc! { "room_code": room_code }, None)
+            .await?
+            .and_then(|host| host.host_id))
+    }
+
+    /// Passes the room's host on, or releases it with `None`. Does nothing unless the host is
+    /// held by `instance_id`.
+    pub async fn hand_over(
+        &self,
+        room_code: &str,
+        instance_id: &str,
+        host_id: Option<&str>,
+        now: &str,
+    ) -> mongodb::error::Result<()> {
+        self.collection
+            .update_one(
+                doc! { "room_code": room_code, "instance_id": instance_id },
+                doc! { "$set": { "host_id": host_id, "seen_at": now } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Keeps the hosts this instance still has from going stale.
+    pub async fn touch(
+        &self,
+        room_codes: &[String],
+        instance_id: &str,
+        now: &str,
+    ) -> mongodb::error::Result<()> {
+        self.collection
+            .update_many(
+                doc! { "room_code": { "$in": room_codes }, "instance_id": instance_id },
+                doc! { "$set": { "seen_at": now } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, room_code: &str) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_one(doc! { "room_code": room_code }, None)
+            .await?;
+        Ok(())
+    }
+}
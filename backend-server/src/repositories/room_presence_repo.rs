@@ -0,0 +1,119 @@
+use crate::models::room::Presence;
+use crate::models::room_event::RoomPresence;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::doc,
+    options::{IndexOptions, ReplaceOptions},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct RoomPresenceRepository {
+    collection: Collection<RoomPresence>,
+}
+
+impl RoomPresenceRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("room_presence"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_peer = IndexModel::builder()
+            .keys(doc! { "peer_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_room_presence_peer_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_room = IndexModel::builder()
+            .keys(doc! { "room_code": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_room_presence_room".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_instance = IndexModel::builder()
+            .keys(doc! { "instance_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_room_presence_instance".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection
+            .create_indexes(vec![unique_peer, by_room, by_instance], None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn upsert(&self, presence: &RoomPresence) -> mongodb::error::Result<()> {
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.collection
+            .replace_one(doc! { "peer_id": &presence.peer_id }, presence, options)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, peer_id: &str) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_one(doc! { "peer_id": peer_id }, None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_presence(
+        &self,
+        peer_id: &str,
+        presence: &Presence,
+    ) -> mongodb::error::Result<()> {
+        let presence = mongodb::bson::to_bson(presence)?;
+        self.collection
+            .update_one(
+                doc! { "peer_id": peer_id },
+                doc! { "$set": { "peer.presence": presence } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Peers in the room whose instance checked in after `seen_after`.
+    pub async fn find_by_room(
+        &self,
+        room_code: &str,
+        seen_after: &str,
+    ) -> mongodb::error::Result<Vec<RoomPresence>> {
+        let cursor = self
+            .collection
+            .find(
+                doc! { "room_code": room_code, "seen_at": { "$gt": seen_after } },
+                None,
+            )
+            .await?;
+        cursor.try_collect().await
+    }
+
+    pub async fn touch_instance(&self, instance_id: &str, now: &str) -> mongodb::error::Result<()> {
+        self.collection
+            .update_many(
+                doc! { "instance_id": instance_id },
+                doc! { "$set": { "seen_at": now } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_stale(&self, cutoff: &str) -> mongodb::error::Result<u64> {
+        let res = self
+            .collection
+            .delete_many(doc! { "seen_at": { "$lt": cutoff } }, None)
+            .await?;
+        Ok(res.deleted_count)
+    }
+}
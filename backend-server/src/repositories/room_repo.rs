@@ -40,6 +40,18 @@ impl RoomRepository {
         Ok(())
     }
 
+    /// Moves the persisted document to a new room code.
+    pub async fn rename(&self, room_code: &str, new_room_code: &str) -> mongodb::error::Result<()> {
+        self.collection
+            .update_many(
+                doc! { "room_code": room_code },
+                doc! { "$set": { "room_code": new_room_code } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
     pub async fn delete_by_room_code(&self, room_code: &str) -> mongodb::error::Result<()> {
         self.collection
             .delete_many(doc! { "room_code": room_code }, None)
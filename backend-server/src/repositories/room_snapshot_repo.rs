@@ -0,0 +1,88 @@
+use crate::models::room_snapshot::RoomSnapshot;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::{FindOptions, IndexOptions},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct RoomSnapshotRepository {
+    collection: Collection<RoomSnapshot>,
+}
+
+impl RoomSnapshotRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("room_snapshots"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let by_room = IndexModel::builder()
+            .keys(doc! { "room_code": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_room_snapshot_room".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(by_room, None).await?;
+        Ok(())
+    }
+
+    pub async fn insert(&self, snapshot: &RoomSnapshot) -> mongodb::error::Result<()> {
+        self.collection.insert_one(snapshot, None).await?;
+        Ok(())
+    }
+
+    /// Newest first, without the documents themselves.
+    pub async fn find_by_room(&self, room_code: &str) -> mongodb::error::Result<Vec<RoomSnapshot>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .projection(doc! { "document": 0 })
+            .build();
+        self.collection
+            .find(doc! { "room_code": room_code }, options)
+            .await?
+            .try_collect()
+            .await
+    }
+
+    pub async fn find_by_id(
+        &self,
+        room_code: &str,
+        id: &ObjectId,
+    ) -> mongodb::error::Result<Option<RoomSnapshot>> {
+        self.collection
+            .find_one(doc! { "_id": id, "room_code": room_code }, None)
+            .await
+    }
+
+    pub async fn delete_ids(&self, ids: &[ObjectId]) -> mongodb::error::Result<u64> {
+        let res = self
+            .collection
+            .delete_many(doc! { "_id": { "$in": ids } }, None)
+            .await?;
+        Ok(res.deleted_count)
+    }
+
+    /// Moves the history along with the document when a room code is rotated.
+    pub async fn rename(&self, room_code: &str, new_room_code: &str) -> mongodb::error::Result<()> {
+        self.collection
+            .update_many(
+                doc! { "room_code": room_code },
+                doc! { "$set": { "room_code": new_room_code } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_room_code(&self, room_code: &str) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_many(doc! { "room_code": room_code }, None)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::models::security::{SecurityPolicyDocument, SECURITY_POLICY_KEY};
+use mongodb::{bson::doc, options::ReplaceOptions, Collection, Database};
+
+/// Shares `app_settings` (and its unique `key` index) with `StorageRepository`.
+#[derive(Clone)]
+pub struct SecurityRepository {
+    collection: Collection<SecurityPolicyDocument>,
+}
+
+impl SecurityRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("app_settings"),
+        }
+    }
+
+    pub async fn get_policy(&self) -> mongodb::error::Result<SecurityPolicyDocument> {
+        Ok(self
+            .collection
+            .find_one(doc! { "key": SECURITY_POLICY_KEY }, None)
+            .await?
+            .unwrap_or_default())
+    }
+
+    pub async fn save_policy(&self, policy: &SecurityPolicyDocument) -> mongodb::error::Result<()> {
+        self.collection
+            .replace_one(
+                doc! { "key": SECURITY_POLICY_KEY },
+                policy,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
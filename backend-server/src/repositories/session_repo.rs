@@ -0,0 +1,195 @@
+use crate::models::session::Session;
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::{FindOptions, IndexOptions},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct SessionRepository {
+    collection: Collection<Session>,
+}
+
+impl SessionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("auth_sessions"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_session = IndexModel::builder()
+            .keys(doc! { "session_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_auth_sessions_session_id_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_user = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "last_used_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_auth_sessions_user_last_used".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection
+            .create_indexes(vec![unique_session, by_user], None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, session: &Session) -> mongodb::error::Result<()> {
+        self.collection.insert_one(session, None).await?;
+        Ok(())
+    }
+
+    /// Returns the session only if it is neither revoked nor expired.
+    pub async fn find_active(&self, session_id: &str) -> mongodb::error::Result<Option<Session>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.collection
+            .find_one(
+                doc! {
+                    "session_id": session_id,
+                    "revoked_at": null,
+                    "expires_at": { "$gt": now },
+                },
+                None,
+            )
+            .await
+    }
+
+    pub async fn find_active_by_user(
+        &self,
+        user_id: &ObjectId,
+    ) -> mongodb::error::Result<Vec<Session>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let options = FindOptions::builder()
+            .sort(doc! { "last_used_at": -1 })
+            .build();
+        let mut cursor = self
+            .collection
+            .find(
+                doc! {
+                    "user_id": user_id,
+                    "revoked_at": null,
+                    "expires_at": { "$gt": now },
+                },
+                options,
+            )
+            .await?;
+        let mut sessions = Vec::new();
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(doc) => sessions.push(doc),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Swaps the stored refresh token hash, but only if `current_hash` still matches.
+    /// Returns `false` when another refresh already consumed this token.
+    pub async fn rotate(
+        &self,
+        session_id: &str,
+        current_hash: &str,
+        next_hash: &str,
+        expires_at: &str,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! {
+                    "session_id": session_id,
+                    "refresh_token_hash": current_hash,
+                    "revoked_at": null,
+                },
+                doc! { "$set": {
+                    "refresh_token_hash": next_hash,
+                    "expires_at": expires_at,
+                    "last_used_at": chrono::Utc::now().to_rfc3339(),
+                } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count > 0)
+    }
+
+    pub async fn revoke(&self, session_id: &str) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! { "session_id": session_id, "revoked_at": null },
+                doc! { "$set": { "revoked_at": chrono::Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count > 0)
+    }
+
+    pub async fn revoke_for_user(
+        &self,
+        user_id: &ObjectId,
+        session_id: &str,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! { "session_id": session_id, "user_id": user_id, "revoked_at": null },
+                doc! { "$set": { "revoked_at": chrono::Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count > 0)
+    }
+
+    /// Revokes every active session of the user, optionally keeping one (the caller's own).
+    pub async fn revoke_all_for_user(
+        &self,
+        user_id: &ObjectId,
+        keep_session_id: Option<&str>,
+    ) -> mongodb::error::Result<u64> {
+        let mut filter = doc! { "user_id": user_id, "revoked_at": null };
+        if let Some(keep) = keep_session_id {
+            filter.insert("session_id", doc! { "$ne": keep });
+        }
+        let res = self
+            .collection
+            .update_many(
+                filter,
+                doc! { "$set": { "revoked_at": chrono::Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count)
+    }
+
+    /// Drops expired and revoked sessions of the user so the collection doesn't grow forever.
+    pub async fn delete_stale_for_user(&self, user_id: &ObjectId) -> mongodb::error::Result<()> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.collection
+            .delete_many(
+                doc! {
+                    "user_id": user_id,
+                    "$or": [
+                        { "revoked_at": { "$ne": null } },
+                        { "expires_at": { "$lte": now } },
+                    ],
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_user(&self, user_id: &ObjectId) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::oidc::ExternalIdentity;
 use crate::models::user::User;
 use mongodb::{
     bson::{doc, oid::ObjectId},
@@ -55,6 +56,131 @@ impl UserRepository {
         Ok(users)
     }
 
+    /// Gives legacy setup tokens (created before they had an expiry) a deadline.
+    pub async fn set_missing_setup_token_expiry(
+        &self,
+        expires_at: &str,
+    ) -> mongodb::error::Result<u64> {
+        let res = self
+            .collection
+            .update_many(
+                doc! {
+                    "setup_token": { "$ne": null },
+                    "setup_token_expires_at": { "$exists": false },
+                },
+                doc! { "$set": { "setup_token_expires_at": expires_at } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count)
+    }
+
+    /// Records a used TOTP step. Returns false if that step (or a later one) was already used.
+    pub async fn advance_totp_step(
+        &self,
+        id: &ObjectId,
+        step: i64,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! {
+                    "_id": id,
+                    "$or": [
+                        { "two_factor.last_used_step": { "$lt": step } },
+                        { "two_factor.last_used_step": null },
+                    ],
+                },
+                doc! { "$set": { "two_factor.last_used_step": step } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count == 1)
+    }
+
+    /// Removes a recovery code hash; returns false if it wasn't there (unknown or already used).
+    pub async fn consume_recovery_code(
+        &self,
+        id: &ObjectId,
+        code_hash: &str,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! { "_id": id, "two_factor.recovery_code_hashes": code_hash },
+                doc! { "$pull": { "two_factor.recovery_code_hashes": code_hash } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count == 1)
+    }
+
+    pub async fn set_recovery_codes(
+        &self,
+        id: &ObjectId,
+        code_hashes: Vec<String>,
+    ) -> mongodb::error::Result<()> {
+        self.collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "two_factor.recovery_code_hashes": code_hashes } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find_by_external_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> mongodb::error::Result<Option<User>> {
+        self.collection
+            .find_one(
+                doc! {
+                    "external_identities": {
+                        "$elemMatch": { "issuer": issuer, "subject": subject }
+                    }
+                },
+                None,
+            )
+            .await
+    }
+
+    /// Replaces any identity the user already had at the same provider.
+    pub async fn link_external_identity(
+        &self,
+        id: &ObjectId,
+        identity: &ExternalIdentity,
+    ) -> mongodb::error::Result<()> {
+        self.unlink_external_identity(id, &identity.issuer).await?;
+        let identity = mongodb::bson::to_document(identity)?;
+        self.collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$push": { "external_identities": identity } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unlink_external_identity(
+        &self,
+        id: &ObjectId,
+        issuer: &str,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$pull": { "external_identities": { "issuer": issuer } } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count == 1)
+    }
+
     pub async fn count(&self) -> mongodb::error::Result<u64> {
         self.collection.estimated_document_count(None).await
     }
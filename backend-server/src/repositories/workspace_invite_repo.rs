@@ -0,0 +1,140 @@
+use crate::models::workspace_invite::WorkspaceInvite;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    options::{FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Clone)]
+pub struct WorkspaceInviteRepository {
+    collection: Collection<WorkspaceInvite>,
+}
+
+impl WorkspaceInviteRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("workspace_invites"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let unique_hash = IndexModel::builder()
+            .keys(doc! { "token_hash": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .name(Some("idx_workspace_invite_hash_unique".to_string()))
+                    .build(),
+            )
+            .build();
+        let by_workspace = IndexModel::builder()
+            .keys(doc! { "workspace_id": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(Some("idx_workspace_invite_workspace".to_string()))
+                    .build(),
+            )
+            .build();
+        self.collection
+            .create_indexes(vec![unique_hash, by_workspace], None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, invite: &WorkspaceInvite) -> mongodb::error::Result<()> {
+        self.collection.insert_one(invite, None).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_workspace(
+        &self,
+        workspace_id: &ObjectId,
+    ) -> mongodb::error::Result<Vec<WorkspaceInvite>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        let cursor = self
+            .collection
+            .find(doc! { "workspace_id": workspace_id }, options)
+            .await?;
+        cursor.try_collect().await
+    }
+
+    pub async fn find_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> mongodb::error::Result<Option<WorkspaceInvite>> {
+        self.collection
+            .find_one(doc! { "token_hash": token_hash }, None)
+            .await
+    }
+
+    /// Takes one use of the invite if it is still active. Returns `None` when it is revoked,
+    /// expired or used up, so two people racing for the last use can't both get in.
+    pub async fn consume(
+        &self,
+        token_hash: &str,
+    ) -> mongodb::error::Result<Option<WorkspaceInvite>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.collection
+            .find_one_and_update(
+                doc! {
+                    "token_hash": token_hash,
+                    "revoked_at": null,
+                    "$and": [
+                        { "$or": [
+                            { "expires_at": null },
+                            { "expires_at": { "$gt": now } },
+                        ] },
+                        { "$or": [
+                            { "max_uses": null },
+                            { "$expr": { "$lt": ["$uses", "$max_uses"] } },
+                        ] },
+                    ],
+                },
+                doc! { "$inc": { "uses": 1 } },
+                options,
+            )
+            .await
+    }
+
+    /// Gives back a use taken by `consume` when the membership couldn't be created.
+    pub async fn release(&self, invite_id: &str) -> mongodb::error::Result<()> {
+        self.collection
+            .update_one(
+                doc! { "invite_id": invite_id, "uses": { "$gt": 0 } },
+                doc! { "$inc": { "uses": -1 } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns false if the invite doesn't exist in this workspace or is already revoked.
+    pub async fn revoke(
+        &self,
+        workspace_id: &ObjectId,
+        invite_id: &str,
+    ) -> mongodb::error::Result<bool> {
+        let res = self
+            .collection
+            .update_one(
+                doc! { "invite_id": invite_id, "workspace_id": workspace_id, "revoked_at": null },
+                doc! { "$set": { "revoked_at": chrono::Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await?;
+        Ok(res.modified_count == 1)
+    }
+
+    pub async fn delete_by_workspace(&self, workspace_id: &ObjectId) -> mongodb::error::Result<()> {
+        self.collection
+            .delete_many(doc! { "workspace_id": workspace_id }, None)
+            .await?;
+        Ok(())
+    }
+}
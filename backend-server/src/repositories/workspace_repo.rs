@@ -80,6 +80,23 @@ impl WorkspaceRepository {
         Ok(delete_res.deleted_count > 0)
     }
 
+    pub async fn update_room_code(
+        &self,
+        id: &ObjectId,
+        owner_id: &ObjectId,
+        room_code: &str,
+    ) -> mongodb::error::Result<bool> {
+        let update_res = self
+            .collection
+            .update_one(
+                doc! { "_id": id, "owner_id": owner_id },
+                doc! { "$set": { "room_code": room_code } },
+                None,
+            )
+            .await?;
+        Ok(update_res.matched_count > 0)
+    }
+
     pub async fn update_notification_config(
         &self,
         id: &ObjectId,
@@ -0,0 +1,158 @@
+use crate::models::access_token::{
+    AccessTokenScope, CreateAccessTokenRequest, PersonalAccessToken, ACCESS_TOKEN_PREFIX,
+};
+use crate::models::auth::Claims;
+use crate::repositories::{
+    access_token_repo::AccessTokenRepository, member_repo::MemberRepository,
+    user_repo::UserRepository, workspace_repo::WorkspaceRepository,
+};
+use crate::services::member_service::MemberService;
+use crate::services::session_service::{hash_token, random_secret};
+use mongodb::bson::oid::ObjectId;
+
+const DEFAULT_EXPIRY_DAYS: i64 = 90;
+const MAX_EXPIRY_DAYS: i64 = 365;
+const MAX_NAME_LENGTH: usize = 100;
+
+pub struct AccessTokenService;
+
+impl AccessTokenService {
+    /// Creates a token for `user_id`. The plaintext is returned once and never stored.
+    pub async fn create(
+        token_repo: &AccessTokenRepository,
+        workspace_repo: &WorkspaceRepository,
+        member_repo: &MemberRepository,
+        user_id: &ObjectId,
+        payload: CreateAccessTokenRequest,
+    ) -> Result<(PersonalAccessToken, String), String> {
+        let name = payload.name.trim().to_string();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(format!(
+                "Token name must be 1-{} characters",
+                MAX_NAME_LENGTH
+            ));
+        }
+        if payload.workspace_ids.is_empty() {
+            return Err("Select at least one workspace".to_string());
+        }
+
+        // The token can only reach workspaces the owner is currently a member of
+        let mut workspace_ids = Vec::new();
+        for id_str in &payload.workspace_ids {
+            let ws_oid =
+                ObjectId::parse_str(id_str).map_err(|_| "Invalid workspace ID".to_string())?;
+            let workspace = workspace_repo
+                .find_by_id(&ws_oid)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or_else(|| format!("Workspace {} not found", id_str))?;
+            if MemberService::resolve_role(member_repo, &workspace, user_id)
+                .await?
+                .is_none()
+            {
+                return Err(format!("You are not a member of workspace {}", id_str));
+            }
+            if !workspace_ids.contains(&ws_oid) {
+                workspace_ids.push(ws_oid);
+            }
+        }
+
+        let expires_in_days = payload.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+        if !(0..=MAX_EXPIRY_DAYS).contains(&expires_in_days) {
+            return Err(format!(
+                "expires_in_days must be between 0 and {}",
+                MAX_EXPIRY_DAYS
+            ));
+        }
+        let now = chrono::Utc::now();
+        let expires_at = (expires_in_days > 0)
+            .then(|| (now + chrono::Duration::days(expires_in_days)).to_rfc3339());
+
+        let plaintext = format!("{}{}", ACCESS_TOKEN_PREFIX, random_secret(40));
+        let token = PersonalAccessToken {
+            id: None,
+            token_id: uuid::Uuid::now_v7().to_string(),
+            user_id: *user_id,
+            name,
+            token_hash: hash_token(&plaintext),
+            token_prefix: plaintext
+                .chars()
+                .take(ACCESS_TOKEN_PREFIX.len() + 6)
+                .collect(),
+            workspace_ids,
+            permission: payload.permission,
+            created_at: now.to_rfc3339(),
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+        };
+        token_repo
+            .create(&token)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok((token, plaintext))
+    }
+
+    pub async fn list(
+        token_repo: &AccessTokenRepository,
+        user_id: &ObjectId,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let tokens = token_repo
+            .find_by_user(user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(tokens.iter().map(Self::to_json).collect())
+    }
+
+    pub fn to_json(token: &PersonalAccessToken) -> serde_json::Value {
+        serde_json::json!({
+            "token_id": token.token_id,
+            "name": token.name,
+            "token_prefix": token.token_prefix,
+            "workspace_ids": token.workspace_ids.iter().map(|id| id.to_hex()).collect::<Vec<_>>(),
+            "permission": token.permission,
+            "created_at": token.created_at,
+            "expires_at": token.expires_at,
+            "last_used_at": token.last_used_at,
+        })
+    }
+
+    /// Resolves a `khp_` bearer token to claims. The role is always `user`: deployment
+    /// admin rights are never reachable with a token.
+    pub async fn authenticate(
+        token_repo: &AccessTokenRepository,
+        user_repo: &UserRepository,
+        raw_token: &str,
+    ) -> Option<Claims> {
+        let token = token_repo
+            .find_active_by_hash(&hash_token(raw_token))
+            .await
+            .ok()
+            .flatten()?;
+        let user = user_repo.find_by_id(&token.user_id).await.ok().flatten()?;
+        if !user.is_active {
+            return None;
+        }
+
+        let _ = token_repo.touch_last_used(&token.token_id).await;
+
+        let exp = token
+            .expires_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at.timestamp() as usize)
+            .unwrap_or(usize::MAX);
+        Some(Claims {
+            sub: token.user_id.to_hex(),
+            role: "user".to_string(),
+            exp,
+            sid: None,
+            access_token: Some(AccessTokenScope {
+                workspace_ids: token.workspace_ids,
+                permission: token.permission,
+            }),
+            impersonator: None,
+        })
+    }
+}
@@ -0,0 +1,288 @@
+use crate::models::auth_token::{AuthToken, AuthTokenPurpose};
+use crate::repositories::{
+    auth_token_repo::AuthTokenRepository, session_repo::SessionRepository,
+    user_repo::UserRepository,
+};
+use crate::services::auth_service::setup_token_expiry;
+use crate::services::mail_service::{MailMessage, Mailer};
+use crate::services::session_service::{hash_token, random_secret, AuthSettings};
+use bcrypt::{hash, DEFAULT_COST};
+use mongodb::bson::oid::ObjectId;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Emailed account flows: invitation setup links, password reset and email change.
+pub struct AccountService;
+
+impl AccountService {
+    pub async fn send_setup_email(
+        mailer: &Mailer,
+        settings: &AuthSettings,
+        email: &str,
+        setup_token: &str,
+    ) -> Result<(), String> {
+        let link = format!(
+            "{}/setup-password?token={}",
+            settings.app_base_url, setup_token
+        );
+        mailer
+            .send(MailMessage {
+                to: email.to_string(),
+                subject: "Set up your Khun Phaen account".to_string(),
+                body: format!(
+                    "You have been invited to Khun Phaen.\n\nSet your password here:\n{}\n\nThis link expires in {} hours.",
+                    link,
+                    settings.setup_token_ttl_seconds / 3600
+                ),
+            })
+            .await
+    }
+
+    /// Issues a fresh setup token for a user who hasn't finished setup yet; the old link stops working.
+    pub async fn resend_setup(
+        user_repo: &UserRepository,
+        mailer: &Mailer,
+        settings: &AuthSettings,
+        id_str: &str,
+    ) -> Result<String, String> {
+        let oid = ObjectId::parse_str(id_str).map_err(|_| "Invalid User ID format".to_string())?;
+        let mut user = user_repo
+            .find_by_id(&oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "User not found".to_string())?;
+
+        if user.is_active || user.password_hash.is_some() {
+            return Err("User has already completed setup".to_string());
+        }
+
+        let token = random_secret(32);
+        user.setup_token = Some(token.clone());
+        user.setup_token_expires_at = Some(setup_token_expiry(settings));
+        user_repo
+            .update(&user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Self::send_setup_email(mailer, settings, &user.email, &token).await?;
+        Ok(token)
+    }
+
+    /// Always succeeds from the caller's point of view so the endpoint can't be used to
+    /// probe which emails have accounts.
+    pub async fn forgot_password(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        mailer: &Mailer,
+        settings: &AuthSettings,
+        email: &str,
+    ) -> Result<(), String> {
+        let user = match user_repo
+            .find_by_email(email.trim())
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+        {
+            Some(u) if u.is_active => u,
+            _ => return Ok(()),
+        };
+        let user_oid = user.id.ok_or("User has no id")?;
+
+        let token = Self::issue_token(
+            token_repo,
+            &user_oid,
+            AuthTokenPurpose::PasswordReset,
+            None,
+            settings.password_reset_ttl_seconds,
+        )
+        .await?;
+
+        let link = format!(
+            "{}/setup-password?token={}&type=reset",
+            settings.app_base_url, token
+        );
+        mailer
+            .send(MailMessage {
+                to: user.email,
+                subject: "Reset your Khun Phaen password".to_string(),
+                body: format!(
+                    "Someone asked to reset the password for your account.\n\nReset it here:\n{}\n\nThis link expires in {} minutes and can be used once. If you didn't ask for this, you can ignore this email.",
+                    link,
+                    settings.password_reset_ttl_seconds / 60
+                ),
+            })
+            .await
+    }
+
+    pub async fn get_reset_info(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        token: &str,
+    ) -> Result<String, String> {
+        let record = token_repo
+            .find_valid(&hash_token(token), AuthTokenPurpose::PasswordReset)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Invalid or expired token".to_string())?;
+        let user = user_repo
+            .find_by_id(&record.user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Invalid or expired token".to_string())?;
+        Ok(user.email)
+    }
+
+    /// Sets the new password and signs the user out everywhere.
+    pub async fn reset_password(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        session_repo: &SessionRepository,
+        token: &str,
+        password: &str,
+    ) -> Result<String, String> {
+        if password.len() < MIN_PASSWORD_LENGTH {
+            return Err(format!(
+                "Password must be at least {} characters",
+                MIN_PASSWORD_LENGTH
+            ));
+        }
+
+        let record = token_repo
+            .consume(&hash_token(token), AuthTokenPurpose::PasswordReset)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Invalid or expired token".to_string())?;
+
+        let mut user = user_repo
+            .find_by_id(&record.user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "User not found".to_string())?;
+
+        user.password_hash = Some(hash(password, DEFAULT_COST).map_err(|e| e.to_string())?);
+        user_repo
+            .update(&user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        session_repo
+            .revoke_all_for_user(&record.user_id, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(user.email)
+    }
+
+    /// Sends a confirmation link to `new_email`; the address only changes once it's clicked.
+    pub async fn request_email_change(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        mailer: &Mailer,
+        settings: &AuthSettings,
+        user_id: &ObjectId,
+        new_email: &str,
+    ) -> Result<(), String> {
+        let new_email = new_email.trim().to_string();
+        if new_email.is_empty() || !new_email.contains('@') {
+            return Err("Invalid email address".to_string());
+        }
+        if user_repo
+            .find_by_email(&new_email)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some()
+        {
+            return Err("Email is already in use".to_string());
+        }
+
+        let token = Self::issue_token(
+            token_repo,
+            user_id,
+            AuthTokenPurpose::EmailChange,
+            Some(new_email.clone()),
+            settings.email_change_ttl_seconds,
+        )
+        .await?;
+
+        let link = format!("{}/verify-email?token={}", settings.app_base_url, token);
+        mailer
+            .send(MailMessage {
+                to: new_email,
+                subject: "Confirm your new Khun Phaen email".to_string(),
+                body: format!(
+                    "Confirm this address for your Khun Phaen account:\n{}\n\nThis link expires in {} hours.",
+                    link,
+                    settings.email_change_ttl_seconds / 3600
+                ),
+            })
+            .await
+    }
+
+    pub async fn confirm_email_change(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        token: &str,
+    ) -> Result<String, String> {
+        let record = token_repo
+            .consume(&hash_token(token), AuthTokenPurpose::EmailChange)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Invalid or expired token".to_string())?;
+        let new_email = record
+            .new_email
+            .ok_or_else(|| "Invalid or expired token".to_string())?;
+
+        // Someone may have claimed the address after the link was sent
+        if user_repo
+            .find_by_email(&new_email)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some()
+        {
+            return Err("Email is already in use".to_string());
+        }
+
+        let mut user = user_repo
+            .find_by_id(&record.user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "User not found".to_string())?;
+        user.email = new_email.clone();
+        user_repo
+            .update(&user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(new_email)
+    }
+
+    async fn issue_token(
+        token_repo: &AuthTokenRepository,
+        user_id: &ObjectId,
+        purpose: AuthTokenPurpose,
+        new_email: Option<String>,
+        ttl_seconds: i64,
+    ) -> Result<String, String> {
+        token_repo
+            .invalidate_for_user(user_id, purpose)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let token = random_secret(48);
+        let now = chrono::Utc::now();
+        token_repo
+            .create(&AuthToken {
+                id: None,
+                token_hash: hash_token(&token),
+                purpose,
+                user_id: *user_id,
+                new_email,
+                created_at: now.to_rfc3339(),
+                expires_at: (now + chrono::Duration::seconds(ttl_seconds)).to_rfc3339(),
+                used_at: None,
+                failed_attempts: 0,
+            })
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(token)
+    }
+}
@@ -0,0 +1,200 @@
+use crate::models::audit::AuditEvent;
+use crate::repositories::audit_repo::AuditRepository;
+use crate::repositories::profile_repo::ProfileRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::client_ip_service::ClientIp;
+use mongodb::bson::{oid::ObjectId, Document};
+use serde_json::{Map, Value};
+use std::time::Duration as StdDuration;
+
+const DEFAULT_RETENTION_DAYS: i64 = 365;
+
+/// Days to keep audit events; 0 keeps them forever.
+pub fn retention_days_from_env() -> i64 {
+    std::env::var("AUDIT_RETENTION_DAYS")
+        .ok()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .map(|days| days.max(0))
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+pub fn spawn_audit_retention_task(db: mongodb::Database, retention_days: i64) {
+    if retention_days == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let repo = AuditRepository::new(&db);
+        let mut interval = tokio::time::interval(StdDuration::from_secs(3600));
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = (chrono::Utc::now() - chrono::Duration::days(retention_days)).to_rfc3339();
+            match repo.delete_older_than(&cutoff).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("🧹 Removed {} audit events older than {}", n, cutoff),
+                Err(e) => tracing::warn!("Failed to apply audit retention: {}", e),
+            }
+        }
+    });
+}
+
+/// Who performed an action and from where.
+pub struct AuditActor {
+    pub user_id: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl AuditActor {
+    pub fn new(user_id: Option<&str>, client_ip: &ClientIp) -> Self {
+        Self {
+            user_id: user_id.map(str::to_string),
+            ip: client_ip.0.clone(),
+        }
+    }
+}
+
+/// What happened. `before` / `after` are JSON objects; for updates only the fields that
+/// differ are stored.
+pub struct AuditRecord {
+    pub action: &'static str,
+    pub target_type: &'static str,
+    pub target_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+pub struct AuditService;
+
+impl AuditService {
+    /// Reduces two JSON objects to the top-level fields whose values differ. A field missing
+    /// on one side shows up as `null` there.
+    pub fn diff(before: &Value, after: &Value) -> (Map<String, Value>, Map<String, Value>) {
+        let empty = Map::new();
+        let old = before.as_object().unwrap_or(&empty);
+        let new = after.as_object().unwrap_or(&empty);
+
+        let mut old_changed = Map::new();
+        let mut new_changed = Map::new();
+        for key in old
+            .keys()
+            .chain(new.keys().filter(|k| !old.contains_key(*k)))
+        {
+            let a = old.get(key).unwrap_or(&Value::Null);
+            let b = new.get(key).unwrap_or(&Value::Null);
+            if a != b {
+                old_changed.insert(key.clone(), a.clone());
+                new_changed.insert(key.clone(), b.clone());
+            }
+        }
+        (old_changed, new_changed)
+    }
+
+    /// The user fields worth auditing. Never includes secrets.
+    pub async fn user_snapshot(
+        user_repo: &UserRepository,
+        profile_repo: &ProfileRepository,
+        id: &ObjectId,
+    ) -> Option<Value> {
+        let user = user_repo.find_by_id(id).await.ok().flatten()?;
+        let profile = profile_repo
+            .find_by_user_id(&user.user_id)
+            .await
+            .ok()
+            .flatten();
+        Some(serde_json::json!({
+            "email": user.email,
+            "role": user.role,
+            "is_active": user.is_active,
+            "discord_id": user.discord_id,
+            "first_name": profile.as_ref().and_then(|p| p.first_name.clone()),
+            "last_name": profile.as_ref().and_then(|p| p.last_name.clone()),
+            "nickname": profile.as_ref().and_then(|p| p.nickname.clone()),
+            "position": profile.as_ref().and_then(|p| p.position.clone()),
+        }))
+    }
+
+    fn to_document(map: Map<String, Value>) -> Option<Document> {
+        if map.is_empty() {
+            return None;
+        }
+        mongodb::bson::to_document(&map).ok()
+    }
+
+    /// Writes the entry. The action itself has already happened, so a failure here is logged
+    /// rather than returned.
+    pub async fn record(
+        audit_repo: &AuditRepository,
+        user_repo: &UserRepository,
+        actor: &AuditActor,
+        record: AuditRecord,
+    ) {
+        let (before, after) = match (record.before, record.after) {
+            (Some(before), Some(after)) => {
+                let (before, after) = Self::diff(&before, &after);
+                (Self::to_document(before), Self::to_document(after))
+            }
+            (before, after) => (
+                before.and_then(|v| v.as_object().cloned().and_then(Self::to_document)),
+                after.and_then(|v| v.as_object().cloned().and_then(Self::to_document)),
+            ),
+        };
+
+        let actor_email = match actor
+            .user_id
+            .as_deref()
+            .and_then(|id| ObjectId::parse_str(id).ok())
+        {
+            Some(oid) => user_repo
+                .find_by_id(&oid)
+                .await
+                .ok()
+                .flatten()
+                .map(|u| u.email),
+            None => None,
+        };
+
+        let event = AuditEvent {
+            id: None,
+            event_id: uuid::Uuid::now_v7().to_string(),
+            action: record.action.to_string(),
+            actor_id: actor.user_id.clone(),
+            actor_email,
+            target_type: record.target_type.to_string(),
+            target_id: record.target_id,
+            before,
+            after,
+            ip: actor.ip.clone(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        if let Err(e) = audit_repo.insert(&event).await {
+            tracing::error!("Failed to write audit event {}: {}", event.action, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_keeps_only_changed_fields() {
+        let before = json!({ "email": "a@x.com", "role": "user", "is_active": true });
+        let after = json!({ "email": "a@x.com", "role": "admin", "nickname": "Ann" });
+        let (old, new) = AuditService::diff(&before, &after);
+
+        assert_eq!(
+            Value::Object(old),
+            json!({ "role": "user", "is_active": true, "nickname": null })
+        );
+        assert_eq!(
+            Value::Object(new),
+            json!({ "role": "admin", "is_active": null, "nickname": "Ann" })
+        );
+
+        let (old, new) = AuditService::diff(&before, &before);
+        assert!(old.is_empty() && new.is_empty());
+    }
+}
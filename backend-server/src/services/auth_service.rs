@@ -1,18 +1,41 @@
 use crate::models::{
-    auth::AuthRequest, auth::Claims, auth::InviteRequest, auth::UpdateProfileRequest,
-    auth::UpdateUserRequest, profile::UserProfile, user::User,
+    auth::AuthRequest,
+    auth::InviteRequest,
+    auth::UpdateProfileRequest,
+    auth::UpdateUserRequest,
+    profile::UserProfile,
+    security::SecurityPolicyDocument,
+    session::{ClientInfo, TokenPair},
+    user::User,
 };
-use crate::repositories::{profile_repo::ProfileRepository, user_repo::UserRepository};
+use crate::repositories::{
+    access_token_repo::AccessTokenRepository, auth_token_repo::AuthTokenRepository,
+    member_repo::MemberRepository, profile_repo::ProfileRepository,
+    session_repo::SessionRepository, user_repo::UserRepository,
+};
+use crate::services::session_service::{random_secret, AuthSettings, SessionService};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{encode, EncodingKey, Header};
 use mongodb::bson::oid::ObjectId;
 
+pub fn setup_token_expiry(settings: &AuthSettings) -> String {
+    (chrono::Utc::now() + chrono::Duration::seconds(settings.setup_token_ttl_seconds)).to_rfc3339()
+}
+
+fn setup_token_is_valid(user: &User) -> bool {
+    user.setup_token_expires_at
+        .as_deref()
+        .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+        .map(|at| at > chrono::Utc::now())
+        .unwrap_or(false)
+}
+
 pub struct AuthService;
 
 impl AuthService {
     pub async fn invite(
         user_repo: &UserRepository,
         profile_repo: &ProfileRepository,
+        settings: &AuthSettings,
         payload: InviteRequest,
     ) -> Result<String, String> {
         let email = payload.email.clone();
@@ -25,6 +48,7 @@ impl AuthService {
         }
 
         let mut setup_token = None;
+        let mut setup_token_expires_at = None;
         let mut password_hash = None;
         let mut is_active = false;
 
@@ -36,12 +60,8 @@ impl AuthService {
         }
 
         if !is_active {
-            setup_token = Some(
-                rand::Rng::sample_iter(&mut rand::thread_rng(), &rand::distributions::Alphanumeric)
-                    .take(32)
-                    .map(char::from)
-                    .collect::<String>(),
-            );
+            setup_token = Some(random_secret(32));
+            setup_token_expires_at = Some(setup_token_expiry(settings));
         }
 
         let role = payload.role.unwrap_or_else(|| "user".to_string());
@@ -57,8 +77,11 @@ impl AuthService {
             password_hash,
             created_at: chrono::Utc::now(),
             setup_token: setup_token.clone(),
+            setup_token_expires_at,
             is_active,
             discord_id: payload.discord_id,
+            two_factor: Default::default(),
+            external_identities: Vec::new(),
         };
 
         user_repo
@@ -90,6 +113,7 @@ impl AuthService {
             .find_by_setup_token(token)
             .await
             .map_err(|e| format!("Database error: {}", e))?
+            .filter(setup_token_is_valid)
             .ok_or_else(|| "Invalid or expired token".to_string())?;
 
         Ok(user.email)
@@ -104,12 +128,14 @@ impl AuthService {
             .find_by_setup_token(token)
             .await
             .map_err(|e| format!("Database error: {}", e))?
+            .filter(setup_token_is_valid)
             .ok_or_else(|| "Invalid or expired token".to_string())?;
 
         let password_hash = hash(password, DEFAULT_COST).map_err(|e| e.to_string())?;
 
         user.password_hash = Some(password_hash);
         user.setup_token = None;
+        user.setup_token_expires_at = None;
         user.is_active = true;
 
         user_repo
@@ -120,12 +146,12 @@ impl AuthService {
         Ok(user.email)
     }
 
-    pub async fn login(
+    /// Password step of login. Whether a session is issued right away or a second factor
+    /// is needed is up to the caller.
+    pub async fn authenticate(
         user_repo: &UserRepository,
-        profile_repo: &ProfileRepository,
         payload: AuthRequest,
-        jwt_secret: &str,
-    ) -> Result<(String, String, String, String, String, Option<UserProfile>), String> {
+    ) -> Result<User, String> {
         let user = user_repo
             .find_by_email(&payload.email)
             .await
@@ -146,23 +172,19 @@ impl AuthService {
             return Err("Invalid email or password".to_string());
         }
 
-        let expiration = chrono::Utc::now()
-            .checked_add_signed(chrono::Duration::days(7))
-            .expect("valid timestamp")
-            .timestamp() as usize;
-
-        let claims = Claims {
-            sub: user.id.unwrap().to_hex(),
-            role: user.role.clone(),
-            exp: expiration,
-        };
+        Ok(user)
+    }
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(jwt_secret.as_ref()),
-        )
-        .map_err(|e| e.to_string())?;
+    pub async fn start_session(
+        profile_repo: &ProfileRepository,
+        session_repo: &SessionRepository,
+        user: &User,
+        settings: &AuthSettings,
+        jwt_secret: &str,
+        client: ClientInfo,
+    ) -> Result<(TokenPair, Option<UserProfile>), String> {
+        let tokens =
+            SessionService::issue(session_repo, user, settings, jwt_secret, client).await?;
 
         let profile = profile_repo
             .find_by_user_id(&user.user_id)
@@ -170,14 +192,7 @@ impl AuthService {
             .ok()
             .flatten();
 
-        Ok((
-            user.id.unwrap().to_hex(),
-            user.user_id,
-            user.email,
-            user.role,
-            token,
-            profile,
-        ))
+        Ok((tokens, profile))
     }
 
     pub async fn list_all_users(
@@ -204,7 +219,9 @@ impl AuthService {
                 "is_active": user.is_active,
                 "created_at": user.created_at,
                 "setup_token": user.setup_token,
+                "setup_token_expires_at": user.setup_token_expires_at,
                 "discord_id": user.discord_id,
+                "two_factor_enabled": user.two_factor.enabled,
                 "profile": profile
             }));
         }
@@ -215,6 +232,10 @@ impl AuthService {
     pub async fn delete_user(
         user_repo: &UserRepository,
         profile_repo: &ProfileRepository,
+        member_repo: &MemberRepository,
+        session_repo: &SessionRepository,
+        token_repo: &AuthTokenRepository,
+        access_token_repo: &AccessTokenRepository,
         id_str: &str,
     ) -> Result<(), String> {
         let oid = ObjectId::parse_str(id_str).map_err(|_| "Invalid User ID format".to_string())?;
@@ -231,6 +252,24 @@ impl AuthService {
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
+        // Drop workspace memberships, sessions, pending mail tokens and access tokens
+        member_repo
+            .delete_by_user(&oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        session_repo
+            .delete_by_user(&oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        token_repo
+            .delete_by_user(&oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        access_token_repo
+            .delete_by_user(&oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
         // Delete user
         user_repo
             .delete_by_id(&oid)
@@ -243,6 +282,8 @@ impl AuthService {
     pub async fn update_user(
         user_repo: &UserRepository,
         profile_repo: &ProfileRepository,
+        session_repo: &SessionRepository,
+        policy: &SecurityPolicyDocument,
         id_str: &str,
         payload: UpdateUserRequest,
     ) -> Result<(), String> {
@@ -272,6 +313,14 @@ impl AuthService {
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
+        // Deactivated users, and admins who now need 2FA but haven't enrolled, log in again
+        if !user.is_active || (policy.requires_two_factor(&user.role) && !user.two_factor.enabled) {
+            session_repo
+                .revoke_all_for_user(&oid, None)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+
         // Update profile too
         let mut profile = profile_repo
             .find_by_user_id(&user.user_id)
@@ -323,7 +372,9 @@ impl AuthService {
     pub async fn update_profile(
         user_repo: &UserRepository,
         profile_repo: &ProfileRepository,
+        session_repo: &SessionRepository,
         user_id_oid: &ObjectId,
+        current_session_id: Option<&str>,
         payload: UpdateProfileRequest,
     ) -> Result<(), String> {
         let mut user = user_repo
@@ -333,12 +384,14 @@ impl AuthService {
             .ok_or_else(|| "User not found".to_string())?;
 
         let mut user_updated = false;
+        let mut password_changed = false;
 
         if let Some(password) = payload.password {
             if !password.is_empty() {
                 let password_hash = hash(password, DEFAULT_COST).map_err(|e| e.to_string())?;
                 user.password_hash = Some(password_hash);
                 user_updated = true;
+                password_changed = true;
             }
         }
 
@@ -354,6 +407,14 @@ impl AuthService {
                 .map_err(|e| format!("Database error: {}", e))?;
         }
 
+        // A new password signs out every other device
+        if password_changed {
+            session_repo
+                .revoke_all_for_user(user_id_oid, current_session_id)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+
         // Update profile
         let mut profile = profile_repo
             .find_by_user_id(&user.user_id)
@@ -0,0 +1,116 @@
+use axum::http::HeaderMap;
+use std::env;
+use std::net::IpAddr;
+
+/// Client address for the current request, resolved once by middleware and stored as a
+/// request extension.
+#[derive(Debug, Clone, Default)]
+pub struct ClientIp(pub Option<String>);
+
+/// Which proxy header (if any) is trusted to carry the real client address.
+///
+/// Without `TRUSTED_PROXY_HEADER` the TCP peer address is used and forwarding headers are
+/// ignored, so a client can't pick its own rate-limit key.
+#[derive(Debug, Clone, Default)]
+pub struct ProxySettings {
+    /// Lower-case header name, e.g. `x-forwarded-for`, `x-real-ip` or `cf-connecting-ip`
+    pub header: Option<String>,
+    /// Number of trusted proxies that append to `X-Forwarded-For`. The client address is
+    /// taken that many entries from the right; anything further left is client-supplied.
+    pub hops: usize,
+}
+
+impl ProxySettings {
+    pub fn from_env() -> Self {
+        let header = env::var("TRUSTED_PROXY_HEADER")
+            .ok()
+            .map(|value| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty() && value != "none");
+        let hops = env::var("TRUSTED_PROXY_HOPS")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(1);
+        Self { header, hops }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.header {
+            Some(header) if header == "x-forwarded-for" => {
+                format!("{} ({} trusted hop(s))", header, self.hops)
+            }
+            Some(header) => header.clone(),
+            None => "socket peer address".to_string(),
+        }
+    }
+
+    pub fn resolve(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<String> {
+        let from_header = self.header.as_deref().and_then(|name| {
+            let values: Vec<&str> = headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .collect();
+            let candidate = if name == "x-forwarded-for" {
+                if self.hops == 0 || values.is_empty() {
+                    return None;
+                }
+                values[values.len().saturating_sub(self.hops)]
+            } else {
+                *values.last()?
+            };
+            candidate.parse::<IpAddr>().ok()
+        });
+        from_header.or(peer).map(|ip| ip.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn ignores_forwarding_headers_unless_trusted() {
+        let settings = ProxySettings::default();
+        let peer = "10.0.0.5".parse().ok();
+        let h = headers(&[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(settings.resolve(&h, peer), Some("10.0.0.5".to_string()));
+    }
+
+    #[test]
+    fn spoofed_forwarded_for_entries_are_skipped() {
+        let settings = ProxySettings {
+            header: Some("x-forwarded-for".to_string()),
+            hops: 1,
+        };
+        let peer = "10.0.0.5".parse().ok();
+        // Client sent "6.6.6.6"; our proxy appended the real address
+        let h = headers(&[("x-forwarded-for", "6.6.6.6, 203.0.113.7")]);
+        assert_eq!(settings.resolve(&h, peer), Some("203.0.113.7".to_string()));
+
+        let two_hops = ProxySettings {
+            hops: 2,
+            ..settings.clone()
+        };
+        let h = headers(&[("x-forwarded-for", "6.6.6.6, 203.0.113.7, 10.0.0.1")]);
+        assert_eq!(two_hops.resolve(&h, peer), Some("203.0.113.7".to_string()));
+
+        // Garbage or a missing header falls back to the peer
+        let h = headers(&[("x-forwarded-for", "not-an-ip")]);
+        assert_eq!(settings.resolve(&h, peer), Some("10.0.0.5".to_string()));
+        assert_eq!(
+            settings.resolve(&HeaderMap::new(), peer),
+            Some("10.0.0.5".to_string())
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use wasm_crdt::{CrdtDocument, Operation};
+
+/// Node id the server uses when it merges on behalf of a room. It only authors edits when a
+/// snapshot is restored.
+const SERVER_NODE_ID: &str = "server";
+
+pub struct DocumentService;
+
+impl DocumentService {
+    /// Loads a persisted room document, `None` if nothing was stored. Rooms persisted before
+    /// the server merged documents may hold anything; those are an error, so the caller can
+    /// keep the stored data before the room starts over.
+    pub fn load(stored: &str) -> Result<Option<CrdtDocument>, String> {
+        if stored.trim().is_empty() {
+            return Ok(None);
+        }
+        let mut document = CrdtDocument::new(SERVER_NODE_ID.to_string());
+        document
+            .import(stored)
+            .map_err(|e| format!("Unreadable room document: {}", e))?;
+        Ok(Some(document))
+    }
+
+    /// Merges a client's full `sync_document` into the room's document. `incoming` is either
+    /// an exported document (JSON object) or a list of operations (JSON array), matching the
+    /// client's `export` and `get_operations`.
+    pub fn merge(document: &mut Option<CrdtDocument>, incoming: &str) -> Result<(), String> {
+        let target = document.get_or_insert_with(|| CrdtDocument::new(SERVER_NODE_ID.to_string()));
+
+        // Both parse the whole input before touching the document
+        let result = if incoming.trim_start().starts_with('[') {
+            target.apply_operations(incoming)
+        } else {
+            target.merge(incoming)
+        };
+
+        if result.is_err() && target.version().is_empty() {
+            *document = None;
+        }
+        result
+    }
+
+    /// Applies a pushed batch and returns the operations that changed the room's document.
+    pub fn apply(
+        document: &mut Option<CrdtDocument>,
+        operations: Vec<Operation>,
+    ) -> Vec<Operation> {
+        document
+            .get_or_insert_with(|| CrdtDocument::new(SERVER_NODE_ID.to_string()))
+            .apply_remote_operations(operations)
+    }
+
+    /// Rewrites the room's document to an earlier export as new server edits, so the
+    /// restored values win over anything peers still hold. Returns the ids of tasks that were
+    /// deleted since and came back.
+    pub fn revert(document: &mut Option<CrdtDocument>, snapshot: &str) -> Result<Vec<u32>, String> {
+        document
+            .get_or_insert_with(|| CrdtDocument::new(SERVER_NODE_ID.to_string()))
+            .revert_to(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_crdt::VersionVector;
+
+    #[test]
+    fn load_rejects_unreadable_documents() {
+        assert!(DocumentService::load("").unwrap().is_none());
+        assert!(DocumentService::load("not a document").is_err());
+    }
+
+    #[test]
+    fn concurrent_syncs_keep_both_edits() {
+        let mut alice = CrdtDocument::new("alice".to_string());
+        alice.upsert_field(1, "title".to_string(), "Write docs".to_string());
+        let mut bob = CrdtDocument::new("bob".to_string());
+        bob.upsert_field(2, "title".to_string(), "Fix login".to_string());
+        bob.upsert_field(2, "status".to_string(), "done".to_string());
+
+        let mut room = None;
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+        let ops = serde_json::to_string(bob.operations()).unwrap();
+        DocumentService::merge(&mut room, &ops).unwrap();
+
+        let mut merged = DocumentService::load(&room.as_ref().unwrap().export())
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.tasks().len(), 2);
+        assert_eq!(merged.task(2).unwrap().fields["status"].value, "done");
+
+        // A stale copy of Alice's document must not undo Bob's later edit
+        alice.merge(&room.as_ref().unwrap().export()).unwrap();
+        alice.upsert_field(2, "status".to_string(), "reopened".to_string());
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+        DocumentService::merge(&mut room, &bob.export()).unwrap();
+        merged.import(&room.as_ref().unwrap().export()).unwrap();
+        assert_eq!(merged.task(2).unwrap().fields["status"].value, "reopened");
+
+        assert!(DocumentService::merge(&mut room, "not json").is_err());
+    }
+
+    #[test]
+    fn reconnecting_peer_gets_only_missed_operations() {
+        let mut alice = CrdtDocument::new("alice".to_string());
+        let mut bob = CrdtDocument::new("bob".to_string());
+        let mut room = None;
+
+        alice.upsert_field(1, "title".to_string(), "Write docs".to_string());
+        DocumentService::apply(&mut room, alice.operations().to_vec());
+        let missed = room
+            .as_ref()
+            .unwrap()
+            .operations_since(&VersionVector::new())
+            .unwrap();
+        bob.apply_remote_operations(missed);
+        let bob_version = bob.version().clone();
+
+        // Bob goes offline while Alice keeps editing
+        alice.clear_operations();
+        alice.upsert_field(1, "status".to_string(), "done".to_string());
+        alice.upsert_field(2, "title".to_string(), "Fix login".to_string());
+        let applied = DocumentService::apply(&mut room, alice.operations().to_vec());
+        assert_eq!(applied.len(), 2);
+        // Re-sending the same batch changes nothing
+        assert!(DocumentService::apply(&mut room, alice.operations().to_vec()).is_empty());
+
+        let room_doc = room.as_ref().unwrap();
+        let missed = room_doc.operations_since(&bob_version).unwrap();
+        assert_eq!(missed.len(), 2);
+        bob.apply_remote_operations(missed);
+        assert_eq!(bob.tasks().len(), 2);
+        assert_eq!(bob.version(), room_doc.version());
+
+        // After a restart the room has no history, so only a snapshot can catch Bob up
+        let reloaded = DocumentService::load(&room_doc.export()).unwrap().unwrap();
+        assert!(reloaded.operations_since(&bob_version).is_none());
+        assert_eq!(reloaded.operations_since(bob.version()).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn restored_snapshot_wins_over_stale_peers() {
+        let mut alice = CrdtDocument::new("alice".to_string());
+        alice.upsert_field(1, "title".to_string(), "Write docs".to_string());
+        alice.upsert_field(2, "title".to_string(), "Fix login".to_string());
+        let mut room = None;
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+        let snapshot = room.as_ref().unwrap().export();
+
+        alice.upsert_field(1, "title".to_string(), "Write better docs".to_string());
+        alice.upsert_field(3, "title".to_string(), "Ship it".to_string());
+        alice.delete_task(2);
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+
+        let restored = DocumentService::revert(&mut room, &snapshot).unwrap();
+        assert_eq!(restored, vec![2]);
+
+        // Alice's copy predates the restore, so merging it back must not undo it
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+        let doc = room.as_ref().unwrap();
+        assert_eq!(doc.task(1).unwrap().fields["title"].value, "Write docs");
+        assert_eq!(doc.task(2).unwrap().fields["title"].value, "Fix login");
+        assert!(doc.task(3).is_none());
+        assert_eq!(doc.tasks().len(), 2);
+
+        // Alice picks the restore up from the room, even though she deleted task 2 herself
+        alice.merge(&doc.export()).unwrap();
+        assert!(alice.task(2).is_some());
+    }
+
+    #[test]
+    fn restore_brings_back_a_wiped_board() {
+        let mut alice = CrdtDocument::new("alice".to_string());
+        for (id, title) in [(1, "Write docs"), (2, "Fix login"), (3, "Ship it")] {
+            alice.upsert_field(id, "title".to_string(), title.to_string());
+            alice.upsert_field(id, "status".to_string(), "todo".to_string());
+        }
+        let mut room = None;
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+        let snapshot = room.as_ref().unwrap().export();
+
+        // A buggy client deletes everything, sent both ways a client can sync
+        alice.clear_operations();
+        for id in 1..=3 {
+            alice.delete_task(id);
+        }
+        let ops = serde_json::to_string(alice.operations()).unwrap();
+        DocumentService::merge(&mut room, &ops).unwrap();
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+        assert!(room.as_ref().unwrap().tasks().is_empty());
+
+        let restored = DocumentService::revert(&mut room, &snapshot).unwrap();
+        assert_eq!(restored, vec![1, 2, 3]);
+
+        // The wiping client syncing its stale copy again changes nothing
+        DocumentService::merge(&mut room, &alice.export()).unwrap();
+        let board = |doc: &CrdtDocument| {
+            let mut tasks: Vec<(u32, String, String)> = doc
+                .tasks()
+                .iter()
+                .map(|task| {
+                    let field = |name: &str| task.fields[name].value.clone();
+                    (task.id, field("title"), field("status"))
+                })
+                .collect();
+            tasks.sort();
+            tasks
+        };
+        let expected = DocumentService::load(&snapshot).unwrap().unwrap();
+        assert_eq!(board(room.as_ref().unwrap()), board(&expected));
+
+        // Peers following operations get the tasks back too
+        let mut bob = CrdtDocument::new("bob".to_string());
+        bob.import(&snapshot).unwrap();
+        for id in 1..=3 {
+            bob.delete_task(id);
+        }
+        let missed = room
+            .as_ref()
+            .unwrap()
+            .operations_since(bob.version())
+            .unwrap();
+        bob.apply_remote_operations(missed);
+        assert_eq!(board(&bob), board(&expected));
+    }
+}
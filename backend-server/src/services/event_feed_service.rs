@@ -0,0 +1,230 @@
+use crate::models::message::ClientMessage;
+use crate::models::room::RoomEvent;
+use crate::state::SharedState;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use mongodb::bson::oid::ObjectId;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+/// Room events numbered for server-sent event streams, so a client that reconnects with
+/// `Last-Event-ID` gets what it missed. One feed follows each room that has streams; it
+/// keeps the last `capacity` events and ends with the room.
+#[derive(Default)]
+pub struct RoomFeeds {
+    feeds: DashMap<String, Arc<RoomFeed>>,
+}
+
+/// An open event stream, found by its peer id when the client posts a message for it.
+pub struct StreamSession {
+    pub user_id: ObjectId,
+    pub workspace_id: ObjectId,
+    pub inbox: mpsc::Sender<ClientMessage>,
+}
+
+pub struct RoomFeed {
+    /// The `Room::id` this feed follows. Ids from another feed of the same code can't resume.
+    pub room_id: String,
+    log: Mutex<FeedLog>,
+    tx: broadcast::Sender<FeedEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub seq: u64,
+    pub event: FeedEvent,
+}
+
+#[derive(Debug, Clone)]
+pub enum FeedEvent {
+    /// Shared, since every stream of the room gets a copy of the entry
+    Room(Arc<RoomEvent>),
+    /// The feed itself fell this many events behind the room; whoever reads it must resync
+    Missed(u64),
+}
+
+/// What a stream starts from: the live feed plus the events after its `Last-Event-ID`, or
+/// `None` when those are no longer held and the client needs the whole document.
+pub struct FeedSubscription {
+    pub rx: broadcast::Receiver<FeedEntry>,
+    pub replay: Option<Vec<FeedEntry>>,
+}
+
+struct FeedLog {
+    next_seq: u64,
+    capacity: usize,
+    entries: VecDeque<FeedEntry>,
+}
+
+impl FeedLog {
+    fn new(capacity: usize) -> Self {
+        Self {
+            next_seq: 1,
+            capacity: capacity.max(1),
+            entries: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, event: FeedEvent) -> FeedEntry {
+        let entry = FeedEntry {
+            seq: self.next_seq,
+            event,
+        };
+        self.next_seq += 1;
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry.clone());
+        entry
+    }
+
+    /// Everything after `seq`, if the log still reaches back that far without a gap.
+    fn since(&self, seq: u64) -> Option<Vec<FeedEntry>> {
+        if seq >= self.next_seq {
+            return None;
+        }
+        let oldest = self
+            .entries
+            .front()
+            .map_or(self.next_seq, |entry| entry.seq);
+        if seq + 1 < oldest {
+            return None;
+        }
+        let missed: Vec<FeedEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.seq > seq)
+            .cloned()
+            .collect();
+        if missed
+            .iter()
+            .any(|entry| matches!(entry.event, FeedEvent::Missed(_)))
+        {
+            return None;
+        }
+        Some(missed)
+    }
+}
+
+impl RoomFeed {
+    /// The `id:` of an entry on the wire.
+    pub fn event_id(&self, seq: u64) -> String {
+        format!("{}:{}", self.room_id, seq)
+    }
+
+    /// Follows the feed from `last_event_id`, or from now if the stream is new.
+    pub fn subscribe(&self, last_event_id: Option<&str>) -> FeedSubscription {
+        // Subscribing under the log lock leaves no gap between the replay and the live feed
+        let log = self.log.lock().unwrap();
+        let rx = self.tx.subscribe();
+        let replay = match last_event_id {
+            None => None,
+            Some(id) => id
+                .split_once(':')
+                .filter(|(room_id, _)| *room_id == self.room_id)
+                .and_then(|(_, seq)| seq.parse().ok())
+                .and_then(|seq| log.since(seq)),
+        };
+        FeedSubscription { rx, replay }
+    }
+}
+
+impl RoomFeeds {
+    /// The feed of a loaded room, started on first use. `None` if the room isn't loaded here.
+    pub fn get(state: &SharedState, room_code: &str) -> Option<Arc<RoomFeed>> {
+        let room = state.rooms.get(room_code)?;
+        let capacity = state.room_metrics.channel_capacity;
+        let started = || {
+            let (tx, _) = broadcast::channel(capacity);
+            Arc::new(RoomFeed {
+                room_id: room.id.clone(),
+                log: Mutex::new(FeedLog::new(capacity)),
+                tx,
+            })
+        };
+
+        // A feed left from an earlier room with the same code is replaced
+        let feed = match state.room_feeds.feeds.entry(room_code.to_string()) {
+            Entry::Occupied(entry) if entry.get().room_id == room.id => {
+                return Some(entry.get().clone())
+            }
+            Entry::Occupied(mut entry) => {
+                let feed = started();
+                entry.insert(feed.clone());
+                feed
+            }
+            Entry::Vacant(entry) => entry.insert(started()).clone(),
+        };
+        spawn_feed_task(
+            state.clone(),
+            room_code.to_string(),
+            feed.clone(),
+            room.tx.subscribe(),
+        );
+        Some(feed)
+    }
+}
+
+fn spawn_feed_task(
+    state: SharedState,
+    room_code: String,
+    feed: Arc<RoomFeed>,
+    mut rx: broadcast::Receiver<RoomEvent>,
+) {
+    tokio::spawn(async move {
+        info!("📰 Event feed started for room {}", room_code);
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => FeedEvent::Room(Arc::new(event)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    state.room_metrics.record_lag(skipped);
+                    warn!(
+                        "🐢 Event feed of room {} missed {} events",
+                        room_code, skipped
+                    );
+                    FeedEvent::Missed(skipped)
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let mut log = feed.log.lock().unwrap();
+            let entry = log.push(event);
+            let _ = feed.tx.send(entry);
+        }
+
+        state
+            .room_feeds
+            .feeds
+            .remove_if(&room_code, |_, current| Arc::ptr_eq(current, &feed));
+        info!("📰 Event feed ended for room {}", room_code);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_only_without_gaps() {
+        let mut log = FeedLog::new(3);
+        for n in 0..4 {
+            log.push(FeedEvent::Room(Arc::new(RoomEvent::PeerLeft {
+                peer_id: format!("peer_{}", n),
+            })));
+        }
+        // Holds 2..=4; resuming after 1 needs nothing that was dropped
+        let seqs = |entries: Vec<FeedEntry>| entries.iter().map(|e| e.seq).collect::<Vec<_>>();
+        assert_eq!(log.since(1).map(seqs), Some(vec![2, 3, 4]));
+        assert_eq!(log.since(4).map(seqs), Some(vec![]));
+        assert!(log.since(0).is_none());
+        assert!(log.since(5).is_none());
+
+        log.push(FeedEvent::Missed(2));
+        log.push(FeedEvent::Room(Arc::new(RoomEvent::PeerLeft {
+            peer_id: "peer_5".to_string(),
+        })));
+        assert!(log.since(4).is_none());
+        assert_eq!(log.since(5).map(seqs), Some(vec![6]));
+    }
+}
@@ -0,0 +1,297 @@
+use crate::models::login_attempt::{account_key, ip_key, LoginAttempt};
+use crate::repositories::login_attempt_repo::LoginAttemptRepository;
+use std::env;
+
+const DEFAULT_BACKOFF_AFTER: u32 = 3;
+const DEFAULT_BACKOFF_BASE_SECONDS: i64 = 1;
+const DEFAULT_BACKOFF_MAX_SECONDS: i64 = 300;
+const DEFAULT_ACCOUNT_LOCKOUT_AFTER: u32 = 10;
+// Many users can share one address (office NAT), so IPs get more room
+const DEFAULT_IP_BACKOFF_AFTER: u32 = 20;
+const DEFAULT_IP_LOCKOUT_AFTER: u32 = 50;
+const DEFAULT_LOCKOUT_SECONDS: i64 = 900;
+const DEFAULT_WINDOW_SECONDS: i64 = 3600;
+
+fn env_number<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+#[derive(Debug, Clone)]
+pub struct LoginThrottleSettings {
+    /// Failures against one account before each further attempt has to wait
+    pub backoff_after: u32,
+    /// Same for one IP address, counted across all accounts
+    pub ip_backoff_after: u32,
+    pub backoff_base_seconds: i64,
+    pub backoff_max_seconds: i64,
+    /// Failures against one account before it is locked; 0 disables the lockout
+    pub account_lockout_after: u32,
+    /// Failures from one IP (across all accounts) before it is locked; 0 disables it
+    pub ip_lockout_after: u32,
+    pub lockout_seconds: i64,
+    /// Counters reset this long after their first failure
+    pub window_seconds: i64,
+}
+
+impl Default for LoginThrottleSettings {
+    fn default() -> Self {
+        Self {
+            backoff_after: DEFAULT_BACKOFF_AFTER,
+            ip_backoff_after: DEFAULT_IP_BACKOFF_AFTER,
+            backoff_base_seconds: DEFAULT_BACKOFF_BASE_SECONDS,
+            backoff_max_seconds: DEFAULT_BACKOFF_MAX_SECONDS,
+            account_lockout_after: DEFAULT_ACCOUNT_LOCKOUT_AFTER,
+            ip_lockout_after: DEFAULT_IP_LOCKOUT_AFTER,
+            lockout_seconds: DEFAULT_LOCKOUT_SECONDS,
+            window_seconds: DEFAULT_WINDOW_SECONDS,
+        }
+    }
+}
+
+impl LoginThrottleSettings {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            backoff_after: env_number("LOGIN_BACKOFF_AFTER", defaults.backoff_after),
+            ip_backoff_after: env_number("LOGIN_IP_BACKOFF_AFTER", defaults.ip_backoff_after),
+            backoff_base_seconds: env_number(
+                "LOGIN_BACKOFF_BASE_SECONDS",
+                defaults.backoff_base_seconds,
+            )
+            .max(1),
+            backoff_max_seconds: env_number(
+                "LOGIN_BACKOFF_MAX_SECONDS",
+                defaults.backoff_max_seconds,
+            )
+            .max(1),
+            account_lockout_after: env_number(
+                "LOGIN_ACCOUNT_LOCKOUT_AFTER",
+                defaults.account_lockout_after,
+            ),
+            ip_lockout_after: env_number("LOGIN_IP_LOCKOUT_AFTER", defaults.ip_lockout_after),
+            lockout_seconds: env_number("LOGIN_LOCKOUT_SECONDS", defaults.lockout_seconds).max(1),
+            window_seconds: env_number("LOGIN_FAILURE_WINDOW_SECONDS", defaults.window_seconds)
+                .max(1),
+        }
+    }
+
+    /// How long a key must wait after its `failures`-th failure, and whether that wait is a
+    /// lockout. `None` means the next attempt may come right away.
+    pub fn block_after(
+        &self,
+        failures: u32,
+        backoff_after: u32,
+        lockout_after: u32,
+    ) -> Option<(i64, bool)> {
+        if lockout_after > 0 && failures >= lockout_after {
+            return Some((self.lockout_seconds, true));
+        }
+        if failures < backoff_after {
+            return None;
+        }
+        // 1x, 2x, 4x, ... the base delay, capped
+        let exponent = (failures - backoff_after).min(30);
+        let delay = self
+            .backoff_base_seconds
+            .saturating_mul(1i64 << exponent)
+            .min(self.backoff_max_seconds);
+        Some((delay, false))
+    }
+}
+
+/// A login that was refused before its password or code was even checked.
+#[derive(Debug)]
+pub struct LoginBlocked {
+    pub retry_after_seconds: i64,
+    pub locked: bool,
+}
+
+pub struct LoginThrottleService;
+
+impl LoginThrottleService {
+    fn keys(email: &str, ip: Option<&str>) -> Vec<String> {
+        let mut keys = vec![account_key(email)];
+        if let Some(ip) = ip {
+            keys.push(ip_key(ip));
+        }
+        keys
+    }
+
+    fn remaining_seconds(attempt: &LoginAttempt, now: chrono::DateTime<chrono::Utc>) -> i64 {
+        attempt
+            .blocked_until
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| (at.with_timezone(&chrono::Utc) - now).num_seconds() + 1)
+            .unwrap_or(0)
+    }
+
+    /// The longest block still running among a login's keys.
+    fn blocked(
+        attempts: &[LoginAttempt],
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<LoginBlocked> {
+        let mut blocked: Option<LoginBlocked> = None;
+        for attempt in attempts {
+            let remaining = Self::remaining_seconds(attempt, now);
+            if remaining > 0
+                && blocked
+                    .as_ref()
+                    .is_none_or(|b| remaining > b.retry_after_seconds)
+            {
+                blocked = Some(LoginBlocked {
+                    retry_after_seconds: remaining,
+                    locked: attempt.locked,
+                });
+            }
+        }
+        blocked
+    }
+
+    /// Until when a key that just failed is blocked, and whether that is a lockout.
+    fn block(
+        settings: &LoginThrottleSettings,
+        attempt: &LoginAttempt,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<(String, bool)> {
+        let (backoff_after, lockout_after) = if attempt.key.starts_with("ip:") {
+            (settings.ip_backoff_after, settings.ip_lockout_after)
+        } else {
+            (settings.backoff_after, settings.account_lockout_after)
+        };
+        let (seconds, locked) =
+            settings.block_after(attempt.failures, backoff_after, lockout_after)?;
+        Some((
+            (now + chrono::Duration::seconds(seconds)).to_rfc3339(),
+            locked,
+        ))
+    }
+
+    /// Refuses the attempt while the account or the client IP is backing off or locked.
+    pub async fn check(
+        repo: &LoginAttemptRepository,
+        email: &str,
+        ip: Option<&str>,
+    ) -> Result<(), LoginBlocked> {
+        let mut attempts = Vec::new();
+        for key in Self::keys(email, ip) {
+            // Don't lock everyone out when the database hiccups; the password check still runs
+            if let Ok(Some(attempt)) = repo.find(&key).await {
+                attempts.push(attempt);
+            }
+        }
+        match Self::blocked(&attempts, chrono::Utc::now()) {
+            Some(b) => Err(b),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a wrong password or second-factor code against the account and the IP.
+    pub async fn record_failure(
+        repo: &LoginAttemptRepository,
+        settings: &LoginThrottleSettings,
+        email: &str,
+        ip: Option<&str>,
+    ) {
+        let now = chrono::Utc::now();
+        let window_cutoff = (now - chrono::Duration::seconds(settings.window_seconds)).to_rfc3339();
+
+        for key in Self::keys(email, ip) {
+            let attempt = match repo.record_failure(&key, &window_cutoff).await {
+                Ok(Some(attempt)) => attempt,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!("Failed to record login failure for {}: {}", key, e);
+                    continue;
+                }
+            };
+            if let Some((until, locked)) = Self::block(settings, &attempt, now) {
+                if locked && !attempt.locked {
+                    tracing::warn!(
+                        "🔒 Login locked for {} after {} failures",
+                        key,
+                        attempt.failures
+                    );
+                }
+                let _ = repo.set_block(&key, &until, locked).await;
+            }
+        }
+    }
+
+    /// A completed login, second factor included, clears the account's counter. A correct
+    /// password alone doesn't, or it would reset the count of wrong codes after it. The IP
+    /// counter is left to expire so one known account can't be used to reset it.
+    pub async fn record_success(repo: &LoginAttemptRepository, email: &str) {
+        let _ = repo.clear(&account_key(email)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_then_caps() {
+        let settings = LoginThrottleSettings {
+            backoff_base_seconds: 2,
+            backoff_max_seconds: 20,
+            ..Default::default()
+        };
+        assert_eq!(settings.block_after(2, 3, 0), None);
+        assert_eq!(settings.block_after(3, 3, 0), Some((2, false)));
+        assert_eq!(settings.block_after(4, 3, 0), Some((4, false)));
+        assert_eq!(settings.block_after(6, 3, 0), Some((16, false)));
+        assert_eq!(settings.block_after(7, 3, 0), Some((20, false)));
+        assert_eq!(settings.block_after(500, 3, 0), Some((20, false)));
+    }
+
+    #[test]
+    fn lockout_replaces_backoff_at_threshold() {
+        let settings = LoginThrottleSettings::default();
+        assert_eq!(settings.block_after(9, 3, 10), Some((64, false)));
+        assert_eq!(
+            settings.block_after(10, 3, 10),
+            Some((settings.lockout_seconds, true))
+        );
+    }
+
+    #[test]
+    fn wrong_codes_after_correct_passwords_lock_the_account() {
+        let settings = LoginThrottleSettings::default();
+        let mut now = chrono::Utc::now();
+        let mut account = LoginAttempt {
+            id: None,
+            key: account_key("somchai@example.com"),
+            failures: 0,
+            window_started_at: now.to_rfc3339(),
+            last_failed_at: now.to_rfc3339(),
+            blocked_until: None,
+            locked: false,
+        };
+
+        // Each round: the password is right (nothing is recorded, so a fresh challenge starts
+        // from the same count), then the code is wrong
+        for round in 1..=settings.account_lockout_after {
+            let blocked = LoginThrottleService::blocked(std::slice::from_ref(&account), now);
+            assert!(blocked.is_none(), "blocked before round {}", round);
+
+            account.failures += 1;
+            if let Some((until, locked)) = LoginThrottleService::block(&settings, &account, now) {
+                account.blocked_until = Some(until);
+                account.locked = locked;
+            }
+            // Wait out any backoff; only the lockout should stop the next round
+            if !account.locked {
+                now += chrono::Duration::seconds(settings.backoff_max_seconds + 1);
+            }
+        }
+
+        let blocked = LoginThrottleService::blocked(std::slice::from_ref(&account), now)
+            .expect("account should be locked");
+        assert!(blocked.locked);
+        assert!(blocked.retry_after_seconds > settings.backoff_max_seconds);
+    }
+}
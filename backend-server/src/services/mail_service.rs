@@ -0,0 +1,153 @@
+use lettre::{
+    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use std::env;
+use std::path::PathBuf;
+use tracing::info;
+
+const DEFAULT_MAIL_FROM: &str = "Khun Phaen <no-reply@localhost>";
+const DEFAULT_MAIL_FILE_DIR: &str = "./mail-outbox";
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Outgoing mail transport, chosen with `MAIL_TRANSPORT` (`smtp`, `file` or `log`).
+/// `file` and `log` are meant for local development where no SMTP server is around.
+#[derive(Clone)]
+pub enum Mailer {
+    Smtp {
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: String,
+    },
+    File {
+        dir: PathBuf,
+        from: String,
+    },
+    Log {
+        from: String,
+    },
+}
+
+fn env_non_empty(key: &str) -> Option<String> {
+    env::var(key)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+impl Mailer {
+    pub fn from_env() -> Self {
+        let from = env_non_empty("MAIL_FROM").unwrap_or_else(|| DEFAULT_MAIL_FROM.to_string());
+        let kind = env_non_empty("MAIL_TRANSPORT")
+            .unwrap_or_else(|| "log".to_string())
+            .to_lowercase();
+
+        match kind.as_str() {
+            "smtp" => match build_smtp_transport() {
+                Ok(transport) => Mailer::Smtp { transport, from },
+                Err(e) => {
+                    tracing::warn!("Invalid SMTP configuration ({}), falling back to log", e);
+                    Mailer::Log { from }
+                }
+            },
+            "file" => Mailer::File {
+                dir: PathBuf::from(
+                    env_non_empty("MAIL_FILE_DIR")
+                        .unwrap_or_else(|| DEFAULT_MAIL_FILE_DIR.to_string()),
+                ),
+                from,
+            },
+            _ => Mailer::Log { from },
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Mailer::Smtp { .. } => "smtp",
+            Mailer::File { .. } => "file",
+            Mailer::Log { .. } => "log",
+        }
+    }
+
+    pub async fn send(&self, message: MailMessage) -> Result<(), String> {
+        match self {
+            Mailer::Smtp { transport, from } => {
+                let email = Message::builder()
+                    .from(
+                        from.parse()
+                            .map_err(|e| format!("Invalid MAIL_FROM: {}", e))?,
+                    )
+                    .to(message
+                        .to
+                        .parse()
+                        .map_err(|e| format!("Invalid recipient: {}", e))?)
+                    .subject(message.subject)
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(message.body)
+                    .map_err(|e| e.to_string())?;
+                transport
+                    .send(email)
+                    .await
+                    .map_err(|e| format!("SMTP error: {}", e))?;
+                Ok(())
+            }
+            Mailer::File { dir, from } => {
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .map_err(|e| format!("Mail outbox error: {}", e))?;
+                let path = dir.join(format!("{}.eml", uuid::Uuid::now_v7()));
+                let content = format!(
+                    "From: {}\nTo: {}\nSubject: {}\nDate: {}\n\n{}\n",
+                    from,
+                    message.to,
+                    message.subject,
+                    chrono::Utc::now().to_rfc2822(),
+                    message.body
+                );
+                tokio::fs::write(&path, content)
+                    .await
+                    .map_err(|e| format!("Mail outbox error: {}", e))?;
+                info!("📧 Mail to {} written to {}", message.to, path.display());
+                Ok(())
+            }
+            Mailer::Log { from } => {
+                info!(
+                    "📧 Mail from {} to {} | {}\n{}",
+                    from, message.to, message.subject, message.body
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+fn build_smtp_transport() -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+    let host = env_non_empty("SMTP_HOST").ok_or("SMTP_HOST is not set")?;
+    let security = env_non_empty("SMTP_SECURITY")
+        .unwrap_or_else(|| "starttls".to_string())
+        .to_lowercase();
+
+    let mut builder = match security.as_str() {
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).map_err(|e| e.to_string())?,
+        "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+        _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+            .map_err(|e| e.to_string())?,
+    };
+
+    if let Some(port) = env_non_empty("SMTP_PORT").and_then(|p| p.parse::<u16>().ok()) {
+        builder = builder.port(port);
+    }
+    if let (Some(user), Some(pass)) = (
+        env_non_empty("SMTP_USERNAME"),
+        env_non_empty("SMTP_PASSWORD"),
+    ) {
+        builder = builder.credentials(Credentials::new(user, pass));
+    }
+
+    Ok(builder.build())
+}
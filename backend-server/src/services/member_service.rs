@@ -0,0 +1,199 @@
+use crate::models::member::{InviteMemberRequest, WorkspaceMember, WorkspaceRole};
+use crate::models::workspace::Workspace;
+use crate::repositories::data_repo::DataRepository;
+use crate::repositories::member_repo::MemberRepository;
+use crate::repositories::profile_repo::ProfileRepository;
+use crate::repositories::user_repo::UserRepository;
+use mongodb::bson::oid::ObjectId;
+use tracing::info;
+
+pub struct MemberService;
+
+impl MemberService {
+    /// Effective role of `user_id` in `workspace`. The workspace owner is always `Owner`,
+    /// even if no membership document exists for them.
+    pub async fn resolve_role(
+        member_repo: &MemberRepository,
+        workspace: &Workspace,
+        user_id: &ObjectId,
+    ) -> Result<Option<WorkspaceRole>, String> {
+        if workspace.owner_id == *user_id {
+            return Ok(Some(WorkspaceRole::Owner));
+        }
+        let workspace_id = match workspace.id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let member = member_repo
+            .find_member(&workspace_id, user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(member.map(|m| m.role))
+    }
+
+    pub async fn list_members(
+        member_repo: &MemberRepository,
+        user_repo: &UserRepository,
+        profile_repo: &ProfileRepository,
+        workspace: &Workspace,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let workspace_id = workspace.id.ok_or("Workspace has no id")?;
+        let mut members = member_repo
+            .find_by_workspace(&workspace_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        if !members.iter().any(|m| m.user_id == workspace.owner_id) {
+            members.insert(
+                0,
+                WorkspaceMember {
+                    id: None,
+                    workspace_id,
+                    user_id: workspace.owner_id,
+                    role: WorkspaceRole::Owner,
+                    invited_by: None,
+                    created_at: Some(workspace.created_at.to_rfc3339()),
+                    updated_at: None,
+                },
+            );
+        }
+
+        let mut member_list = Vec::new();
+        for member in members {
+            let user = user_repo.find_by_id(&member.user_id).await.ok().flatten();
+            let profile = match &user {
+                Some(u) => profile_repo
+                    .find_by_user_id(&u.user_id)
+                    .await
+                    .ok()
+                    .flatten(),
+                None => None,
+            };
+            let role = if member.user_id == workspace.owner_id {
+                WorkspaceRole::Owner
+            } else {
+                member.role
+            };
+            member_list.push(serde_json::json!({
+                "user_id": member.user_id.to_hex(),
+                "email": user.as_ref().map(|u| u.email.clone()),
+                "role": role,
+                "invited_by": member.invited_by.map(|id| id.to_hex()),
+                "created_at": member.created_at,
+                "updated_at": member.updated_at,
+                "profile": profile,
+            }));
+        }
+
+        Ok(member_list)
+    }
+
+    pub async fn invite(
+        member_repo: &MemberRepository,
+        user_repo: &UserRepository,
+        workspace: &Workspace,
+        invited_by: &ObjectId,
+        payload: InviteMemberRequest,
+    ) -> Result<WorkspaceMember, String> {
+        if payload.role == WorkspaceRole::Owner {
+            return Err("Cannot invite a member as owner".to_string());
+        }
+        let workspace_id = workspace.id.ok_or("Workspace has no id")?;
+
+        let user = match (payload.user_id.as_deref(), payload.email.as_deref()) {
+            (Some(id), _) => {
+                let oid = ObjectId::parse_str(id).map_err(|_| "Invalid user ID".to_string())?;
+                user_repo
+                    .find_by_id(&oid)
+                    .await
+                    .map_err(|e| format!("Database error: {}", e))?
+            }
+            (None, Some(email)) => user_repo
+                .find_by_email(email.trim())
+                .await
+                .map_err(|e| format!("Database error: {}", e))?,
+            (None, None) => return Err("email or user_id is required".to_string()),
+        };
+        let user_oid = user
+            .and_then(|u| u.id)
+            .ok_or_else(|| "User not found".to_string())?;
+
+        if user_oid == workspace.owner_id {
+            return Err("User is already the workspace owner".to_string());
+        }
+
+        let existing = member_repo
+            .find_member(&workspace_id, &user_oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if existing.is_some() {
+            return Err("User is already a member of this workspace".to_string());
+        }
+
+        member_repo
+            .create(WorkspaceMember {
+                id: None,
+                workspace_id,
+                user_id: user_oid,
+                role: payload.role,
+                invited_by: Some(*invited_by),
+                created_at: None,
+                updated_at: None,
+            })
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// One-time migration: seeds editor memberships for users who previously reached a
+    /// workspace only through an assignee link, so dropping assignee-based access doesn't
+    /// lock anyone out.
+    pub async fn backfill_from_assignees(
+        member_repo: &MemberRepository,
+        data_repo: &DataRepository,
+    ) -> Result<u64, String> {
+        if member_repo
+            .is_assignee_backfill_done()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+        {
+            return Ok(0);
+        }
+
+        let assignees = data_repo
+            .find_user_linked_assignees()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let mut created = 0;
+        for assignee in assignees {
+            let user_oid = match assignee
+                .user_id
+                .as_deref()
+                .and_then(|id| ObjectId::parse_str(id).ok())
+            {
+                Some(id) => id,
+                None => continue,
+            };
+            if member_repo
+                .insert_if_missing(&assignee.workspace_id, &user_oid, WorkspaceRole::Editor)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+            {
+                created += 1;
+            }
+        }
+
+        member_repo
+            .mark_assignee_backfill_done()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        if created > 0 {
+            info!(
+                "👥 Backfilled {} workspace memberships from assignees",
+                created
+            );
+        }
+        Ok(created)
+    }
+}
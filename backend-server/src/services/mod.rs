@@ -1,6 +1,25 @@
+pub mod access_token_service;
+pub mod account_service;
+pub mod audit_service;
 pub mod auth_service;
+pub mod client_ip_service;
+pub mod document_service;
+pub mod event_feed_service;
+pub mod login_throttle_service;
+pub mod mail_service;
+pub mod member_service;
 pub mod milestone_service;
 pub mod notification_service;
+pub mod oidc_service;
+pub mod persist_service;
+pub mod presence_service;
+pub mod room_bus_service;
 pub mod room_service;
+pub mod session_service;
+pub mod snapshot_service;
 pub mod storage_service;
+pub mod two_factor_service;
+pub mod workspace_invite_service;
 pub mod workspace_service;
+pub mod ws_frame_service;
+pub mod ws_limit_service;
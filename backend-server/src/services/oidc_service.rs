@@ -0,0 +1,702 @@
+use crate::models::oidc::{ExternalIdentity, OidcCallbackRequest, OidcLoginState};
+use crate::models::profile::UserProfile;
+use crate::models::user::User;
+use crate::repositories::{
+    oidc_state_repo::OidcStateRepository, profile_repo::ProfileRepository,
+    user_repo::UserRepository,
+};
+use crate::services::session_service::{hash_token, random_secret};
+use base64::Engine;
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use tokio::sync::RwLock;
+
+const DEFAULT_SCOPES: &str = "openid email profile";
+const DEFAULT_PROVIDER_NAME: &str = "SSO";
+const LOGIN_STATE_TTL_SECONDS: i64 = 600;
+
+fn env_non_empty(key: &str) -> Option<String> {
+    env::var(key)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Which ID token / userinfo claims fill which local fields.
+#[derive(Debug, Clone)]
+pub struct OidcClaimMapping {
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub nickname: String,
+}
+
+impl Default for OidcClaimMapping {
+    fn default() -> Self {
+        Self {
+            email: "email".to_string(),
+            first_name: "given_name".to_string(),
+            last_name: "family_name".to_string(),
+            nickname: "preferred_username".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcSettings {
+    pub issuer_url: String,
+    pub client_id: String,
+    /// Public clients rely on PKCE alone and leave this unset
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+    pub scopes: String,
+    /// Label for the login button
+    pub provider_name: String,
+    /// Create unknown users on first login instead of rejecting them
+    pub auto_provision: bool,
+    /// Deployment role (`user` or `admin`) given to auto-provisioned users
+    pub default_role: String,
+    pub claims: OidcClaimMapping,
+}
+
+impl OidcSettings {
+    /// Returns `None` unless `OIDC_ISSUER_URL` and `OIDC_CLIENT_ID` are set.
+    pub fn from_env(app_base_url: &str) -> Option<Self> {
+        let issuer_url = env_non_empty("OIDC_ISSUER_URL")?
+            .trim_end_matches('/')
+            .to_string();
+        let client_id = env_non_empty("OIDC_CLIENT_ID")?;
+        let default_role = match env_non_empty("OIDC_DEFAULT_ROLE").as_deref() {
+            Some("admin") => "admin".to_string(),
+            Some("user") | None => "user".to_string(),
+            Some(other) => {
+                tracing::warn!("Unknown OIDC_DEFAULT_ROLE '{}', using 'user'", other);
+                "user".to_string()
+            }
+        };
+        let defaults = OidcClaimMapping::default();
+
+        Some(Self {
+            issuer_url,
+            client_id,
+            client_secret: env_non_empty("OIDC_CLIENT_SECRET"),
+            redirect_url: env_non_empty("OIDC_REDIRECT_URL")
+                .unwrap_or_else(|| format!("{}/login", app_base_url)),
+            scopes: env_non_empty("OIDC_SCOPES").unwrap_or_else(|| DEFAULT_SCOPES.to_string()),
+            provider_name: env_non_empty("OIDC_PROVIDER_NAME")
+                .unwrap_or_else(|| DEFAULT_PROVIDER_NAME.to_string()),
+            auto_provision: env_non_empty("OIDC_AUTO_PROVISION")
+                .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false),
+            default_role,
+            claims: OidcClaimMapping {
+                email: env_non_empty("OIDC_EMAIL_CLAIM").unwrap_or(defaults.email),
+                first_name: env_non_empty("OIDC_FIRST_NAME_CLAIM").unwrap_or(defaults.first_name),
+                last_name: env_non_empty("OIDC_LAST_NAME_CLAIM").unwrap_or(defaults.last_name),
+                nickname: env_non_empty("OIDC_NICKNAME_CLAIM").unwrap_or(defaults.nickname),
+            },
+        })
+    }
+}
+
+/// The parts of the provider's `/.well-known/openid-configuration` we use.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub jwks_uri: Option<String>,
+    #[serde(default)]
+    pub userinfo_endpoint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+    #[serde(default)]
+    access_token: Option<String>,
+}
+
+/// Claims from the provider, already mapped to local fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalClaims {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    /// `false` only when the provider explicitly says the address is unverified
+    pub email_verified: bool,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub nickname: Option<String>,
+}
+
+/// Client for one OpenID Connect provider. Discovery runs on first use and is cached,
+/// so the server still starts while the provider is unreachable.
+pub struct OidcProvider {
+    settings: OidcSettings,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+}
+
+/// S256 PKCE challenge for `code_verifier` (RFC 7636).
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier))
+}
+
+fn claim_string(claims: &serde_json::Value, name: &str) -> Option<String> {
+    claims
+        .get(name)
+        .and_then(|v| v.as_str())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+impl OidcProvider {
+    pub fn new(settings: OidcSettings) -> Self {
+        Self {
+            settings,
+            http: reqwest::Client::new(),
+            metadata: RwLock::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> &OidcSettings {
+        &self.settings
+    }
+
+    async fn metadata(&self) -> Result<ProviderMetadata, String> {
+        if let Some(metadata) = self.metadata.read().await.as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.settings.issuer_url
+        );
+        let metadata: ProviderMetadata = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| format!("OIDC discovery failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid OIDC discovery document: {}", e))?;
+        if metadata.issuer.trim_end_matches('/') != self.settings.issuer_url {
+            return Err(format!(
+                "OIDC issuer mismatch: expected {}, provider says {}",
+                self.settings.issuer_url, metadata.issuer
+            ));
+        }
+
+        *self.metadata.write().await = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_verifier: &str,
+    ) -> Result<String, String> {
+        let metadata = self.metadata().await?;
+        let challenge = pkce_challenge(code_verifier);
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.settings.client_id.as_str()),
+                ("redirect_uri", self.settings.redirect_url.as_str()),
+                ("scope", self.settings.scopes.as_str()),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| format!("Invalid authorization endpoint: {}", e))?;
+        Ok(url.to_string())
+    }
+
+    /// Redeems the authorization code, verifies the ID token and maps its claims.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<ExternalClaims, String> {
+        let metadata = self.metadata().await?;
+
+        let mut request = self.http.post(&metadata.token_endpoint).form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.settings.redirect_url.as_str()),
+            ("client_id", self.settings.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ]);
+        if let Some(secret) = &self.settings.client_secret {
+            request = request.basic_auth(&self.settings.client_id, Some(secret));
+        }
+        let tokens: TokenResponse = request
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| format!("OIDC token exchange failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid OIDC token response: {}", e))?;
+
+        let mut claims = self.verify_id_token(&metadata, &tokens.id_token).await?;
+        if claims.get("nonce").and_then(|v| v.as_str()) != Some(nonce) {
+            return Err("OIDC nonce mismatch".to_string());
+        }
+
+        // Some providers keep profile claims out of the ID token
+        if let (Some(endpoint), Some(access_token)) =
+            (&metadata.userinfo_endpoint, &tokens.access_token)
+        {
+            if claim_string(&claims, &self.settings.claims.email).is_none() {
+                if let Ok(userinfo) = self.userinfo(endpoint, access_token).await {
+                    if userinfo.get("sub") == claims.get("sub") {
+                        if let (Some(target), Some(extra)) =
+                            (claims.as_object_mut(), userinfo.as_object())
+                        {
+                            for (key, value) in extra {
+                                target.entry(key.clone()).or_insert_with(|| value.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.map_claims(&metadata, &claims)
+    }
+
+    async fn verify_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+    ) -> Result<serde_json::Value, String> {
+        let header = jsonwebtoken::decode_header(id_token)
+            .map_err(|e| format!("Invalid ID token: {}", e))?;
+
+        let key = match header.alg {
+            // HMAC-signed ID tokens use the client secret as the key (OIDC Core 10.1)
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                let secret =
+                    self.settings.client_secret.as_ref().ok_or_else(|| {
+                        "HMAC-signed ID token without a client secret".to_string()
+                    })?;
+                DecodingKey::from_secret(secret.as_bytes())
+            }
+            _ => {
+                let jwks_uri = metadata
+                    .jwks_uri
+                    .as_ref()
+                    .ok_or_else(|| "Provider has no jwks_uri".to_string())?;
+                let jwks: JwkSet = self
+                    .http
+                    .get(jwks_uri)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status())
+                    .map_err(|e| format!("Failed to fetch provider keys: {}", e))?
+                    .json()
+                    .await
+                    .map_err(|e| format!("Invalid provider keys: {}", e))?;
+                let jwk = match &header.kid {
+                    Some(kid) => jwks.find(kid),
+                    None if jwks.keys.len() == 1 => jwks.keys.first(),
+                    None => None,
+                }
+                .ok_or_else(|| "No provider key matches the ID token".to_string())?;
+                DecodingKey::from_jwk(jwk).map_err(|e| format!("Invalid provider key: {}", e))?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.settings.client_id]);
+        validation.leeway = 60;
+
+        jsonwebtoken::decode::<serde_json::Value>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| format!("Invalid ID token: {}", e))
+    }
+
+    async fn userinfo(
+        &self,
+        endpoint: &str,
+        access_token: &str,
+    ) -> Result<serde_json::Value, reqwest::Error> {
+        self.http
+            .get(endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    fn map_claims(
+        &self,
+        metadata: &ProviderMetadata,
+        claims: &serde_json::Value,
+    ) -> Result<ExternalClaims, String> {
+        let mapping = &self.settings.claims;
+        Ok(ExternalClaims {
+            issuer: metadata.issuer.clone(),
+            subject: claim_string(claims, "sub")
+                .ok_or_else(|| "ID token has no subject".to_string())?,
+            email: claim_string(claims, &mapping.email).map(|email| email.to_lowercase()),
+            email_verified: claims
+                .get("email_verified")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true),
+            first_name: claim_string(claims, &mapping.first_name),
+            last_name: claim_string(claims, &mapping.last_name),
+            nickname: claim_string(claims, &mapping.nickname),
+        })
+    }
+}
+
+/// What a completed callback did.
+pub enum OidcOutcome {
+    SignedIn(User),
+    Linked(User),
+}
+
+pub struct OidcService;
+
+impl OidcService {
+    /// Starts an authorization request and returns the provider URL to send the browser to.
+    /// With `link_user_id` the callback links that account instead of signing in.
+    pub async fn begin(
+        provider: &OidcProvider,
+        state_repo: &OidcStateRepository,
+        link_user_id: Option<ObjectId>,
+    ) -> Result<String, String> {
+        let state = random_secret(32);
+        let nonce = random_secret(32);
+        let code_verifier = random_secret(64);
+        let authorization_url = provider
+            .authorization_url(&state, &nonce, &code_verifier)
+            .await?;
+
+        let now = chrono::Utc::now();
+        state_repo
+            .create(&OidcLoginState {
+                id: None,
+                state_hash: hash_token(&state),
+                nonce,
+                code_verifier,
+                link_user_id,
+                created_at: now.to_rfc3339(),
+                expires_at: (now + chrono::Duration::seconds(LOGIN_STATE_TTL_SECONDS)).to_rfc3339(),
+            })
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(authorization_url)
+    }
+
+    /// Finishes the flow started by `begin`. `caller` is the signed-in user, if any; a link
+    /// request is only honoured for the same user that started it.
+    pub async fn complete(
+        provider: &OidcProvider,
+        state_repo: &OidcStateRepository,
+        user_repo: &UserRepository,
+        profile_repo: &ProfileRepository,
+        caller: Option<ObjectId>,
+        payload: &OidcCallbackRequest,
+    ) -> Result<OidcOutcome, String> {
+        let login_state = state_repo
+            .consume(&hash_token(&payload.state))
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Sign-in request expired, please try again".to_string())?;
+
+        let claims = provider
+            .exchange_code(
+                &payload.code,
+                &login_state.code_verifier,
+                &login_state.nonce,
+            )
+            .await?;
+
+        let linked_user = user_repo
+            .find_by_external_identity(&claims.issuer, &claims.subject)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        if let Some(link_user_id) = login_state.link_user_id {
+            if caller != Some(link_user_id) {
+                return Err("Sign in again to link your account".to_string());
+            }
+            if linked_user
+                .as_ref()
+                .is_some_and(|u| u.id != Some(link_user_id))
+            {
+                return Err("This identity is already linked to another account".to_string());
+            }
+            user_repo
+                .link_external_identity(&link_user_id, &external_identity(&claims))
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+            let user = user_repo
+                .find_by_id(&link_user_id)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or_else(|| "User not found".to_string())?;
+            return Ok(OidcOutcome::Linked(user));
+        }
+
+        let user = match linked_user {
+            Some(user) => user,
+            None => Self::provision(provider.settings(), user_repo, profile_repo, &claims).await?,
+        };
+        if !user.is_active {
+            return Err("Account is disabled".to_string());
+        }
+        Ok(OidcOutcome::SignedIn(user))
+    }
+
+    async fn provision(
+        settings: &OidcSettings,
+        user_repo: &UserRepository,
+        profile_repo: &ProfileRepository,
+        claims: &ExternalClaims,
+    ) -> Result<User, String> {
+        let email = claims
+            .email
+            .clone()
+            .ok_or_else(|| "The identity provider did not share an email address".to_string())?;
+
+        // Existing accounts are never taken over by email; the owner links them explicitly
+        if user_repo
+            .find_by_email(&email)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some()
+        {
+            return Err(
+                "An account with this email already exists. Sign in with your password and link it from your profile"
+                    .to_string(),
+            );
+        }
+        if !settings.auto_provision {
+            return Err("No account is linked to this identity".to_string());
+        }
+        if !claims.email_verified {
+            return Err("The identity provider has not verified this email address".to_string());
+        }
+
+        let user_id = uuid::Uuid::now_v7().to_string();
+        let user = User {
+            id: None,
+            user_id: user_id.clone(),
+            email,
+            role: settings.default_role.clone(),
+            password_hash: None,
+            created_at: chrono::Utc::now(),
+            setup_token: None,
+            setup_token_expires_at: None,
+            is_active: true,
+            discord_id: None,
+            two_factor: Default::default(),
+            external_identities: vec![external_identity(claims)],
+        };
+        let oid = user_repo
+            .create(user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        profile_repo
+            .create(UserProfile {
+                profile_id: uuid::Uuid::now_v7().to_string(),
+                user_id,
+                first_name: claims.first_name.clone(),
+                last_name: claims.last_name.clone(),
+                nickname: claims.nickname.clone(),
+                position: None,
+            })
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let user = user_repo
+            .find_by_id(&oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "User not found".to_string())?;
+        tracing::info!("👤 Provisioned {} from {}", user.email, claims.issuer);
+        Ok(user)
+    }
+}
+
+fn external_identity(claims: &ExternalClaims) -> ExternalIdentity {
+    ExternalIdentity {
+        issuer: claims.issuer.clone(),
+        subject: claims.subject.clone(),
+        email: claims.email.clone(),
+        linked_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::get, routing::post, Form, Json, Router};
+    use jsonwebtoken::{EncodingKey, Header};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const CLIENT_ID: &str = "khun-phaen";
+    const CLIENT_SECRET: &str = "mock-client-secret";
+
+    /// Minimal OIDC provider: discovery plus a token endpoint that checks PKCE and
+    /// returns an HS256 ID token.
+    #[derive(Clone, Default)]
+    struct MockProvider {
+        issuer: String,
+        code_challenge: Arc<Mutex<Option<String>>>,
+        nonce: Arc<Mutex<Option<String>>>,
+    }
+
+    async fn discovery(State(mock): State<MockProvider>) -> Json<serde_json::Value> {
+        Json(serde_json::json!({
+            "issuer": mock.issuer,
+            "authorization_endpoint": format!("{}/authorize", mock.issuer),
+            "token_endpoint": format!("{}/token", mock.issuer),
+        }))
+    }
+
+    async fn token(
+        State(mock): State<MockProvider>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+        let expected = mock.code_challenge.lock().unwrap().clone();
+        if form.get("code").map(String::as_str) != Some("mock-code")
+            || form.get("code_verifier").map(|v| pkce_challenge(v)) != expected
+        {
+            return Err(axum::http::StatusCode::BAD_REQUEST);
+        }
+        let now = chrono::Utc::now().timestamp();
+        let id_token = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &serde_json::json!({
+                "iss": mock.issuer,
+                "aud": CLIENT_ID,
+                "sub": "mock-subject",
+                "iat": now,
+                "exp": now + 300,
+                "nonce": mock.nonce.lock().unwrap().clone(),
+                "email": "Somchai@Example.com",
+                "email_verified": true,
+                "given_name": "Somchai",
+                "family_name": "Jaidee",
+                "preferred_username": "chai",
+            }),
+            &EncodingKey::from_secret(CLIENT_SECRET.as_bytes()),
+        )
+        .unwrap();
+        Ok(Json(
+            serde_json::json!({ "id_token": id_token, "token_type": "Bearer" }),
+        ))
+    }
+
+    async fn start_mock_provider() -> MockProvider {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mock = MockProvider {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            ..Default::default()
+        };
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/token", post(token))
+            .with_state(mock.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        mock
+    }
+
+    fn settings(issuer: &str) -> OidcSettings {
+        OidcSettings {
+            issuer_url: issuer.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some(CLIENT_SECRET.to_string()),
+            redirect_url: "http://localhost:5173/login".to_string(),
+            scopes: DEFAULT_SCOPES.to_string(),
+            provider_name: DEFAULT_PROVIDER_NAME.to_string(),
+            auto_provision: true,
+            default_role: "user".to_string(),
+            claims: OidcClaimMapping::default(),
+        }
+    }
+
+    fn query_param(url: &str, name: &str) -> Option<String> {
+        reqwest::Url::parse(url)
+            .ok()?
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+
+    #[test]
+    fn pkce_challenge_is_unpadded_base64url_sha256() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r7wW1gFWFOEjXk"),
+            "bwWFMyPfdG9qreDhH2lmftFx_dFeLDalzcT1gb_j68g"
+        );
+    }
+
+    #[tokio::test]
+    async fn code_flow_against_mock_provider() {
+        let mock = start_mock_provider().await;
+        let provider = OidcProvider::new(settings(&mock.issuer));
+
+        let url = provider
+            .authorization_url("state-1", "nonce-1", "verifier-with-enough-entropy")
+            .await
+            .unwrap();
+        assert!(url.starts_with(&format!("{}/authorize?", mock.issuer)));
+        assert_eq!(
+            query_param(&url, "code_challenge_method").as_deref(),
+            Some("S256")
+        );
+        *mock.code_challenge.lock().unwrap() = query_param(&url, "code_challenge");
+        *mock.nonce.lock().unwrap() = query_param(&url, "nonce");
+
+        let claims = provider
+            .exchange_code("mock-code", "verifier-with-enough-entropy", "nonce-1")
+            .await
+            .unwrap();
+        assert_eq!(
+            claims,
+            ExternalClaims {
+                issuer: mock.issuer.clone(),
+                subject: "mock-subject".to_string(),
+                email: Some("somchai@example.com".to_string()),
+                email_verified: true,
+                first_name: Some("Somchai".to_string()),
+                last_name: Some("Jaidee".to_string()),
+                nickname: Some("chai".to_string()),
+            }
+        );
+
+        // Wrong verifier is refused by the provider; wrong nonce is refused by us
+        assert!(provider
+            .exchange_code("mock-code", "another-verifier", "nonce-1")
+            .await
+            .is_err());
+        assert!(provider
+            .exchange_code("mock-code", "verifier-with-enough-entropy", "nonce-2")
+            .await
+            .is_err());
+    }
+}
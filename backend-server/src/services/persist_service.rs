@@ -0,0 +1,208 @@
+use crate::models::room_snapshot::RoomSnapshot;
+use crate::repositories::room_repo::RoomRepository;
+use crate::services::snapshot_service::SnapshotService;
+use crate::state::SharedState;
+use dashmap::DashMap;
+use mongodb::Database;
+use std::time::Duration as StdDuration;
+use tracing::warn;
+
+const DEFAULT_PERSIST_INTERVAL_MS: u64 = 1000;
+
+struct PendingWrite {
+    document: String,
+    /// Written after the document, oldest first
+    snapshots: Vec<RoomSnapshot>,
+    /// Bumped by every `mark`, so a finished write only clears the document it wrote
+    revision: u64,
+}
+
+/// Write-behind persistence of room documents. Syncs only mark their room dirty with the
+/// latest export; a single writer stores each dirty room at most once per `interval`, so a
+/// chatty client costs one write per interval and writes for a room never overtake each other.
+pub struct DocumentPersister {
+    pub interval: StdDuration,
+    dirty: DashMap<String, PendingWrite>,
+    /// Held while writing, so the timer, a rotate and shutdown never write the same room at once
+    writing: tokio::sync::Mutex<()>,
+}
+
+impl DocumentPersister {
+    pub fn from_env() -> Self {
+        let interval_ms = std::env::var("ROOM_PERSIST_INTERVAL_MS")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_PERSIST_INTERVAL_MS)
+            .max(10);
+        Self {
+            interval: StdDuration::from_millis(interval_ms),
+            dirty: DashMap::new(),
+            writing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Queues the room's latest document, replacing whatever was waiting for it.
+    pub fn mark(&self, room_code: &str, document: String, snapshot: Option<RoomSnapshot>) {
+        let mut pending = self
+            .dirty
+            .entry(room_code.to_string())
+            .or_insert_with(|| PendingWrite {
+                document: String::new(),
+                snapshots: Vec::new(),
+                revision: 0,
+            });
+        pending.document = document;
+        pending.snapshots.extend(snapshot);
+        pending.revision += 1;
+    }
+
+    /// The document still waiting to be written, or being written, which is newer than the
+    /// stored one.
+    pub fn pending_document(&self, room_code: &str) -> Option<String> {
+        self.dirty
+            .get(room_code)
+            .map(|pending| pending.document.clone())
+    }
+
+    /// Drops a queued write, for rooms whose stored document is going away.
+    pub fn discard(&self, room_code: &str) {
+        self.dirty.remove(room_code);
+    }
+
+    pub fn dirty_rooms(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Holds off writes, e.g. while a room is loaded, so a write can't land between reading
+    /// the stored document and checking for a queued one.
+    pub async fn hold_writes(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.writing.lock().await
+    }
+
+    /// Writes every dirty room. Returns how many were written.
+    pub async fn flush(&self, db: &Database) -> usize {
+        let _writing = self.writing.lock().await;
+        let room_codes: Vec<String> = self.dirty.iter().map(|e| e.key().clone()).collect();
+        let writes = room_codes.into_iter().filter_map(|room_code| {
+            let pending = self.take(&room_code)?;
+            Some(self.write(db, room_code, pending))
+        });
+        futures::future::join_all(writes)
+            .await
+            .into_iter()
+            .filter(|written| *written)
+            .count()
+    }
+
+    /// Writes one room now, e.g. before its stored document is moved.
+    pub async fn flush_room(&self, db: &Database, room_code: &str) -> Result<(), String> {
+        let _writing = self.writing.lock().await;
+        let Some(pending) = self.take(room_code) else {
+            return Ok(());
+        };
+        if self.write(db, room_code.to_string(), pending).await {
+            Ok(())
+        } else {
+            Err("Database error: room document not saved".to_string())
+        }
+    }
+
+    /// What to write for a room. The document stays queued until the write is saved, so a
+    /// room loaded meanwhile still finds it.
+    fn take(&self, room_code: &str) -> Option<PendingWrite> {
+        let mut pending = self.dirty.get_mut(room_code)?;
+        Some(PendingWrite {
+            document: pending.document.clone(),
+            snapshots: std::mem::take(&mut pending.snapshots),
+            revision: pending.revision,
+        })
+    }
+
+    /// Clears a saved write, unless a newer document was queued while it was in flight.
+    fn settle(&self, room_code: &str, revision: u64) {
+        self.dirty
+            .remove_if(room_code, |_, pending| pending.revision == revision);
+    }
+
+    async fn write(&self, db: &Database, room_code: String, pending: PendingWrite) -> bool {
+        let written = match RoomRepository::new(db)
+            .upsert_document(&room_code, &pending.document)
+            .await
+        {
+            Ok(()) => {
+                self.settle(&room_code, pending.revision);
+                true
+            }
+            Err(e) => {
+                // Still queued, so retried on the next flush
+                warn!("Failed to save document of room {}: {}", room_code, e);
+                false
+            }
+        };
+
+        for snapshot in &pending.snapshots {
+            if let Err(e) = SnapshotService::record(db, snapshot).await {
+                warn!("Failed to snapshot room {}: {}", room_code, e);
+            }
+        }
+        written
+    }
+}
+
+pub fn spawn_document_persister_task(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(state.document_persister.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            state.document_persister.flush(&state.db).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_syncs_coalesce_into_one_write() {
+        let persister = DocumentPersister::from_env();
+        persister.mark("room", "{\"v\":1}".to_string(), None);
+        persister.mark("room", "{\"v\":2}".to_string(), None);
+        persister.mark("other", "{}".to_string(), None);
+
+        assert_eq!(persister.dirty_rooms(), 2);
+        assert_eq!(
+            persister.pending_document("room").as_deref(),
+            Some("{\"v\":2}")
+        );
+
+        persister.discard("other");
+        assert_eq!(persister.dirty_rooms(), 1);
+        assert_eq!(persister.pending_document("other"), None);
+    }
+
+    #[test]
+    fn documents_stay_queued_until_their_write_is_saved() {
+        let persister = DocumentPersister::from_env();
+        persister.mark("room", "{\"v\":1}".to_string(), None);
+
+        let first = persister.take("room").unwrap();
+        assert_eq!(
+            persister.pending_document("room").as_deref(),
+            Some("{\"v\":1}")
+        );
+
+        persister.mark("room", "{\"v\":2}".to_string(), None);
+        persister.settle("room", first.revision);
+        assert_eq!(
+            persister.pending_document("room").as_deref(),
+            Some("{\"v\":2}")
+        );
+
+        let second = persister.take("room").unwrap();
+        persister.settle("room", second.revision);
+        assert_eq!(persister.pending_document("room"), None);
+    }
+}
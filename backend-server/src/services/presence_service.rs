@@ -0,0 +1,160 @@
+use crate::models::room::{PeerUser, Presence, PresenceStatus, RoomEvent};
+use crate::repositories::profile_repo::ProfileRepository;
+use crate::repositories::user_repo::UserRepository;
+use crate::services::room_bus_service::publish_to_room;
+use crate::state::SharedState;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use mongodb::Database;
+use std::time::Duration as StdDuration;
+
+/// A peer's presence goes out at most this often; updates in between are coalesced
+pub const PRESENCE_THROTTLE_MS: u64 = 250;
+/// `typing` is dropped when the client hasn't repeated it for this long
+const TYPING_TTL_SECONDS: i64 = 8;
+/// The open task and field are dropped when the client hasn't refreshed them for this long
+const ACTIVITY_TTL_SECONDS: i64 = 60;
+const EXPIRY_INTERVAL_SECONDS: u64 = 5;
+const MAX_VALUE_LEN: usize = 128;
+
+pub struct PresenceService;
+
+impl PresenceService {
+    /// Identity shown to other peers, looked up once per connection.
+    pub async fn resolve_user(db: &Database, user_id: &ObjectId) -> Option<PeerUser> {
+        let user = UserRepository::new(db).find_by_id(user_id).await.ok()??;
+        let nickname = ProfileRepository::new(db)
+            .find_by_user_id(&user.user_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|profile| profile.nickname.or(profile.first_name));
+
+        Some(PeerUser {
+            id: user_id.to_hex(),
+            email: user.email,
+            nickname,
+        })
+    }
+
+    /// Presence from a client's `update_presence`.
+    pub fn from_update(
+        task_id: Option<&String>,
+        field: Option<&String>,
+        typing: bool,
+        status: PresenceStatus,
+    ) -> Result<Presence, String> {
+        let clean = |value: Option<&String>| -> Result<Option<String>, String> {
+            match value.map(|v| v.trim()) {
+                Some(v) if v.chars().count() > MAX_VALUE_LEN => Err(format!(
+                    "Presence values are limited to {} characters",
+                    MAX_VALUE_LEN
+                )),
+                Some("") | None => Ok(None),
+                Some(v) => Ok(Some(v.to_string())),
+            }
+        };
+
+        let task_id = clean(task_id)?;
+        let field = clean(field)?;
+        Ok(Presence {
+            // Typing without a task or field to type into means nothing to the others
+            typing: typing && (task_id.is_some() || field.is_some()),
+            task_id,
+            field,
+            status,
+            updated_at: Some(Utc::now()),
+        })
+    }
+
+    /// Drops what the peer stopped refreshing. Returns whether anything changed.
+    pub fn expire(presence: &mut Presence, now: DateTime<Utc>) -> bool {
+        let Some(updated_at) = presence.updated_at else {
+            return false;
+        };
+        let age = now.signed_duration_since(updated_at).num_seconds();
+        let mut changed = false;
+
+        if presence.typing && age >= TYPING_TTL_SECONDS {
+            presence.typing = false;
+            changed = true;
+        }
+        if (presence.task_id.is_some() || presence.field.is_some()) && age >= ACTIVITY_TTL_SECONDS {
+            presence.task_id = None;
+            presence.field = None;
+            if presence.status == PresenceStatus::Active {
+                presence.status = PresenceStatus::Idle;
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Expires presence of the peers connected to this instance and tells their rooms.
+pub fn spawn_presence_expiry_task(state: SharedState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(StdDuration::from_secs(EXPIRY_INTERVAL_SECONDS));
+
+        loop {
+            interval.tick().await;
+
+            let now = Utc::now();
+            let mut expired = Vec::new();
+            for room in state.rooms.iter() {
+                for mut peer in room.peers.iter_mut() {
+                    if PresenceService::expire(&mut peer.presence, now) {
+                        expired.push((room.key().clone(), peer.id.clone(), peer.presence.clone()));
+                    }
+                }
+            }
+
+            for (room_code, peer_id, presence) in expired {
+                publish_to_room(
+                    &state,
+                    &room_code,
+                    RoomEvent::PresenceUpdate { peer_id, presence },
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_presence_expires_in_stages() {
+        let task = "665f0c".to_string();
+        let notes = "notes".to_string();
+        let mut presence =
+            PresenceService::from_update(Some(&task), Some(&notes), true, PresenceStatus::Active)
+                .unwrap();
+        let start = presence.updated_at.unwrap();
+
+        assert!(!PresenceService::expire(&mut presence, start));
+        assert!(PresenceService::expire(
+            &mut presence,
+            start + chrono::Duration::seconds(TYPING_TTL_SECONDS)
+        ));
+        assert!(!presence.typing);
+        assert_eq!(presence.task_id.as_deref(), Some("665f0c"));
+
+        assert!(PresenceService::expire(
+            &mut presence,
+            start + chrono::Duration::seconds(ACTIVITY_TTL_SECONDS)
+        ));
+        assert_eq!(presence.task_id, None);
+        assert_eq!(presence.status, PresenceStatus::Idle);
+        assert!(!PresenceService::expire(
+            &mut presence,
+            start + chrono::Duration::seconds(ACTIVITY_TTL_SECONDS * 2)
+        ));
+
+        let long = "x".repeat(MAX_VALUE_LEN + 1);
+        assert!(
+            PresenceService::from_update(Some(&long), None, false, PresenceStatus::Away).is_err()
+        );
+    }
+}
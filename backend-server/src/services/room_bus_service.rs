@@ -0,0 +1,400 @@
+use crate::models::room::{PeerInfo, RoomEvent};
+use crate::models::room_event::{RoomEventRecord, RoomPresence};
+use crate::repositories::room_event_repo::RoomEventRepository;
+use crate::repositories::room_host_repo::RoomHostRepository;
+use crate::repositories::room_presence_repo::RoomPresenceRepository;
+use crate::services::document_service::DocumentService;
+use crate::services::room_service::{set_host, settle_host, successor};
+use crate::state::{AppState, SharedState};
+use futures::StreamExt;
+use mongodb::Database;
+use std::env;
+use std::time::Duration as StdDuration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+const HEARTBEAT_SECONDS: u64 = 30;
+/// Presence of an instance that missed this many seconds of heartbeats is ignored
+const PRESENCE_STALE_SECONDS: i64 = 90;
+/// Events are only needed while they are being streamed; this is just a safety margin
+const EVENT_RETENTION_SECONDS: i64 = 3600;
+const WATCH_RETRY_SECONDS: u64 = 10;
+
+/// How room events reach peers, chosen with `ROOM_EVENT_BUS` (`memory` or `mongo`).
+/// `memory` only reaches peers connected to this process. `mongo` also hands every event to
+/// the other instances through a change stream on `room_events` and keeps presence in
+/// `room_presence`, so replicas behind a load balancer can share rooms.
+pub enum RoomEventBus {
+    Memory,
+    Mongo(SharedRoomBus),
+}
+
+pub struct SharedRoomBus {
+    pub instance_id: String,
+    /// Writes go through one task so other instances see them in publish order
+    writes: mpsc::UnboundedSender<(String, RoomEvent)>,
+}
+
+impl RoomEventBus {
+    pub fn from_env(db: &Database) -> Self {
+        let kind = env::var("ROOM_EVENT_BUS")
+            .map(|value| value.trim().to_lowercase())
+            .unwrap_or_default();
+
+        match kind.as_str() {
+            "mongo" => {
+                let instance_id = uuid::Uuid::new_v4().to_string();
+                let (writes, rx) = mpsc::unbounded_channel();
+                spawn_event_writer(db.clone(), instance_id.clone(), rx);
+                RoomEventBus::Mongo(SharedRoomBus {
+                    instance_id,
+                    writes,
+                })
+            }
+            "" | "memory" => RoomEventBus::Memory,
+            other => {
+                warn!("Unknown ROOM_EVENT_BUS '{}', using memory", other);
+                RoomEventBus::Memory
+            }
+        }
+    }
+
+    pub fn kind(&self) -> String {
+        match self {
+            RoomEventBus::Memory => "memory".to_string(),
+            RoomEventBus::Mongo(bus) => format!("mongo (instance {})", bus.instance_id),
+        }
+    }
+
+    /// Delivers `event` to this instance's peers through `tx` (if the room is loaded here)
+    /// and, with a shared bus, to every other instance.
+    pub fn publish(
+        &self,
+        tx: Option<&broadcast::Sender<RoomEvent>>,
+        room_code: &str,
+        event: RoomEvent,
+    ) {
+        match self {
+            RoomEventBus::Memory => {
+                if let Some(tx) = tx {
+                    let _ = tx.send(event);
+                }
+            }
+            RoomEventBus::Mongo(bus) => {
+                if let Some(tx) = tx {
+                    let _ = tx.send(event.clone());
+                }
+                let _ = bus.writes.send((room_code.to_string(), event));
+            }
+        }
+    }
+
+    /// Everyone in the room. `local` are the peers connected to this instance, which are
+    /// always included even if their presence hasn't been written yet.
+    pub async fn room_peers(
+        &self,
+        db: &Database,
+        room_code: &str,
+        local: Vec<PeerInfo>,
+    ) -> Vec<PeerInfo> {
+        if let RoomEventBus::Memory = self {
+            return local;
+        }
+
+        let seen_after =
+            (chrono::Utc::now() - chrono::Duration::seconds(PRESENCE_STALE_SECONDS)).to_rfc3339();
+        let mut peers = local;
+        match RoomPresenceRepository::new(db)
+            .find_by_room(room_code, &seen_after)
+            .await
+        {
+            Ok(remote) => {
+                for presence in remote {
+                    if !peers.iter().any(|peer| peer.id == presence.peer_id) {
+                        peers.push(presence.peer);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to load presence for room {}: {}", room_code, e),
+        }
+        peers
+    }
+}
+
+/// Publishes a server-originated event on a room, whether or not it is loaded here.
+pub fn publish_to_room(state: &AppState, room_code: &str, event: RoomEvent) {
+    let tx = state.rooms.get(room_code).map(|room| room.tx.clone());
+    state.room_bus.publish(tx.as_ref(), room_code, event);
+}
+
+/// Removes the room from this instance and tells its peers, here and on other instances,
+/// that it closed.
+pub fn close_room(state: &AppState, room_code: &str, reason: &str) {
+    let tx = state.rooms.remove(room_code).map(|(_, room)| room.tx);
+    state.room_bus.publish(
+        tx.as_ref(),
+        room_code,
+        RoomEvent::RoomClosed {
+            reason: reason.to_string(),
+        },
+    );
+}
+
+/// Makes `candidate`, a peer connected here, host of a room without one, unless someone
+/// else got there first. Every election goes through here: the in-memory bus decides under
+/// the room's lock, the shared bus with a conditional update on `room_hosts`, so two
+/// instances can't both elect a host. Returns the room's host.
+pub async fn elect_host(state: &AppState, room_code: &str, candidate: &str) -> Option<String> {
+    let claimed = match &state.room_bus {
+        RoomEventBus::Memory => None,
+        RoomEventBus::Mongo(bus) => {
+            let now = chrono::Utc::now();
+            let stale_before =
+                (now - chrono::Duration::seconds(PRESENCE_STALE_SECONDS)).to_rfc3339();
+            match RoomHostRepository::new(&state.db)
+                .claim(
+                    room_code,
+                    candidate,
+                    &bus.instance_id,
+                    &now.to_rfc3339(),
+                    &stale_before,
+                )
+                .await
+            {
+                Ok(holder) => Some(holder?),
+                Err(e) => {
+                    warn!("Failed to elect a host for room {}: {}", room_code, e);
+                    return state.rooms.get(room_code)?.host_id.clone();
+                }
+            }
+        }
+    };
+
+    let mut room = state.rooms.get_mut(room_code)?;
+    let holder = claimed
+        .or_else(|| room.host_id.clone())
+        .unwrap_or_else(|| candidate.to_string());
+    if holder == candidate && !room.peers.contains_key(candidate) {
+        // Left while the claim was on its way; passed on the way `remove_peer` would have
+        let next = successor(&room);
+        set_host(&mut room, next.clone());
+        let event = RoomEvent::HostChanged {
+            new_host_id: next.clone(),
+        };
+        state.room_bus.publish(Some(&room.tx), room_code, event);
+        return next;
+    }
+    if let Some(event) = settle_host(&mut room, holder, candidate) {
+        info!("👑 {} hosts room {}", candidate, room_code);
+        state.room_bus.publish(Some(&room.tx), room_code, event);
+    }
+    room.host_id.clone()
+}
+
+fn spawn_event_writer(
+    db: Database,
+    instance_id: String,
+    mut rx: mpsc::UnboundedReceiver<(String, RoomEvent)>,
+) {
+    tokio::spawn(async move {
+        let events = RoomEventRepository::new(&db);
+        let presence = RoomPresenceRepository::new(&db);
+        let hosts = RoomHostRepository::new(&db);
+
+        while let Some((room_code, event)) = rx.recv().await {
+            let now = chrono::Utc::now().to_rfc3339();
+
+            let presence_result = match &event {
+                RoomEvent::PeerJoined { peer } => {
+                    presence
+                        .upsert(&RoomPresence {
+                            id: None,
+                            room_code: room_code.clone(),
+                            peer_id: peer.id.clone(),
+                            instance_id: instance_id.clone(),
+                            peer: peer.clone(),
+                            seen_at: now.clone(),
+                        })
+                        .await
+                }
+                RoomEvent::PeerLeft { peer_id } => presence.remove(peer_id).await,
+                RoomEvent::PresenceUpdate {
+                    peer_id,
+                    presence: current,
+                } => presence.set_presence(peer_id, current).await,
+                _ => Ok(()),
+            };
+            if let Err(e) = presence_result {
+                warn!("Failed to update presence in room {}: {}", room_code, e);
+            }
+
+            // Before the event goes out, so instances that see a release can claim the room
+            let host_result = match &event {
+                RoomEvent::HostChanged { new_host_id } => {
+                    hosts
+                        .hand_over(&room_code, &instance_id, new_host_id.as_deref(), &now)
+                        .await
+                }
+                RoomEvent::RoomClosed { .. } => hosts.remove(&room_code).await,
+                _ => Ok(()),
+            };
+            if let Err(e) = host_result {
+                warn!("Failed to update the host of room {}: {}", room_code, e);
+            }
+
+            let record = RoomEventRecord {
+                id: None,
+                room_code,
+                origin: instance_id.clone(),
+                event: match serde_json::to_string(&event) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        warn!("Failed to encode room event: {}", e);
+                        continue;
+                    }
+                },
+                created_at: now,
+            };
+            if let Err(e) = events.insert(&record).await {
+                warn!("Failed to publish room event: {}", e);
+            }
+        }
+    });
+}
+
+/// Starts following other instances' events and the presence heartbeat. Does nothing for
+/// the in-memory bus.
+pub fn spawn_room_bus_tasks(state: SharedState) {
+    let instance_id = match &state.room_bus {
+        RoomEventBus::Mongo(bus) => bus.instance_id.clone(),
+        RoomEventBus::Memory => return,
+    };
+
+    let listener_state = state.clone();
+    let listener_instance = instance_id.clone();
+    tokio::spawn(async move {
+        let repo = RoomEventRepository::new(&listener_state.db);
+        let mut resume_token = None;
+
+        loop {
+            match repo
+                .watch_others(&listener_instance, resume_token.clone())
+                .await
+            {
+                Ok(mut stream) => {
+                    info!("📡 Following room events from other instances");
+                    while let Some(change) = stream.next().await {
+                        match change {
+                            Ok(change) => {
+                                if let Some(record) = change.full_document {
+                                    deliver_remote(&listener_state, record).await;
+                                }
+                                resume_token = stream.resume_token();
+                            }
+                            Err(e) => {
+                                warn!("Room event stream failed: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => warn!(
+                    "Cannot follow room events (change streams need a replica set): {}",
+                    e
+                ),
+            }
+            tokio::time::sleep(StdDuration::from_secs(WATCH_RETRY_SECONDS)).await;
+        }
+    });
+
+    tokio::spawn(async move {
+        let presence = RoomPresenceRepository::new(&state.db);
+        let events = RoomEventRepository::new(&state.db);
+        let hosts = RoomHostRepository::new(&state.db);
+        let mut interval = tokio::time::interval(StdDuration::from_secs(HEARTBEAT_SECONDS));
+
+        loop {
+            interval.tick().await;
+
+            let now = chrono::Utc::now();
+            if let Err(e) = presence
+                .touch_instance(&instance_id, &now.to_rfc3339())
+                .await
+            {
+                warn!("Failed to refresh room presence: {}", e);
+            }
+            let hosted: Vec<String> = state
+                .rooms
+                .iter()
+                .filter(|room| {
+                    room.host_id
+                        .as_ref()
+                        .is_some_and(|host_id| room.peers.contains_key(host_id))
+                })
+                .map(|room| room.key().clone())
+                .collect();
+            if let Err(e) = hosts.touch(&hosted, &instance_id, &now.to_rfc3339()).await {
+                warn!("Failed to refresh room hosts: {}", e);
+            }
+            let stale = (now - chrono::Duration::seconds(PRESENCE_STALE_SECONDS)).to_rfc3339();
+            if let Err(e) = presence.delete_stale(&stale).await {
+                warn!("Failed to remove stale room presence: {}", e);
+            }
+            let expired = (now - chrono::Duration::seconds(EVENT_RETENTION_SECONDS)).to_rfc3339();
+            if let Err(e) = events.delete_older_than(&expired).await {
+                warn!("Failed to remove old room events: {}", e);
+            }
+        }
+    });
+}
+
+/// Hands an event from another instance to the peers connected here. Document changes are
+/// also applied to this instance's copy of the room document so catch-up stays complete.
+/// Hosts chosen elsewhere are adopted; when one was released, this instance puts its own
+/// longest present peer up for election, which only one instance can win.
+async fn deliver_remote(state: &AppState, record: RoomEventRecord) {
+    let event: RoomEvent = match serde_json::from_str(&record.event) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("Ignoring unreadable room event: {}", e);
+            return;
+        }
+    };
+
+    if let RoomEvent::RoomClosed { .. } = event {
+        if let Some((_, room)) = state.rooms.remove(&record.room_code) {
+            let _ = room.tx.send(event);
+        }
+        return;
+    }
+
+    let candidate = {
+        let Some(mut room) = state.rooms.get_mut(&record.room_code) else {
+            return;
+        };
+        let mut candidate = None;
+        match &event {
+            RoomEvent::OperationsUpdate { operations, .. } => {
+                DocumentService::apply(&mut room.document, operations.clone());
+            }
+            RoomEvent::DocumentUpdate { document, .. } => {
+                if let Err(e) = DocumentService::merge(&mut room.document, document) {
+                    warn!("Ignoring remote document for {}: {}", record.room_code, e);
+                }
+            }
+            RoomEvent::HostChanged { new_host_id } => {
+                set_host(&mut room, new_host_id.clone());
+                if new_host_id.is_none() {
+                    candidate = successor(&room);
+                }
+            }
+            _ => {}
+        }
+        let _ = room.tx.send(event);
+        candidate
+    };
+
+    if let Some(candidate) = candidate {
+        elect_host(state, &record.room_code, &candidate).await;
+    }
+}
@@ -1,13 +1,241 @@
-use crate::models::room::Room;
+use crate::models::access_token::AccessTokenScope;
+use crate::models::member::WorkspaceRole;
+use crate::models::room::{Room, RoomEvent};
+use crate::repositories::member_repo::MemberRepository;
 use crate::repositories::room_repo::RoomRepository;
 use crate::repositories::workspace_repo::WorkspaceRepository;
-use crate::state::SharedState;
+use crate::services::member_service::MemberService;
+use crate::services::room_bus_service::publish_to_room;
+use crate::services::snapshot_service::SnapshotService;
+use crate::state::{AppState, SharedState};
 use dashmap::DashMap;
+use mongodb::bson::oid::ObjectId;
 use rand::Rng;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration as StdDuration;
 use tokio::sync::broadcast;
 use tracing::info;
 
+const DEFAULT_CHANNEL_CAPACITY: usize = 256;
+const DEFAULT_PING_INTERVAL_SECONDS: u64 = 30;
+const DEFAULT_PING_TIMEOUT_SECONDS: u64 = 90;
+
+/// Server-initiated WebSocket pings. A peer that sends nothing back, pongs included, for
+/// `timeout` is disconnected and removed from its room.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatSettings {
+    pub interval: StdDuration,
+    pub timeout: StdDuration,
+}
+
+impl HeartbeatSettings {
+    pub fn from_env() -> Self {
+        let seconds = |key: &str, default: u64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            seconds("WS_PING_INTERVAL_SECONDS", DEFAULT_PING_INTERVAL_SECONDS),
+            seconds("WS_PING_TIMEOUT_SECONDS", DEFAULT_PING_TIMEOUT_SECONDS),
+        )
+    }
+
+    /// The timeout always leaves room for at least one missed ping.
+    fn new(interval_seconds: u64, timeout_seconds: u64) -> Self {
+        let interval_seconds = interval_seconds.max(1);
+        Self {
+            interval: StdDuration::from_secs(interval_seconds),
+            timeout: StdDuration::from_secs(timeout_seconds.max(interval_seconds * 2)),
+        }
+    }
+}
+
+/// Room broadcast sizing and how often peers fall behind it. A peer lags when more events
+/// arrive than its channel holds before it reads them; raise `ROOM_CHANNEL_CAPACITY` if
+/// `lagged_receivers` keeps growing.
+pub struct RoomMetrics {
+    pub channel_capacity: usize,
+    lagged_receivers: AtomicU64,
+    skipped_events: AtomicU64,
+}
+
+impl RoomMetrics {
+    pub fn from_env() -> Self {
+        let channel_capacity = std::env::var("ROOM_CHANNEL_CAPACITY")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .filter(|capacity| *capacity > 0)
+            .unwrap_or(DEFAULT_CHANNEL_CAPACITY);
+        Self {
+            channel_capacity,
+            lagged_receivers: AtomicU64::new(0),
+            skipped_events: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_lag(&self, skipped: u64) {
+        self.lagged_receivers.fetch_add(1, Ordering::Relaxed);
+        self.skipped_events.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> serde_json::Value {
+        serde_json::json!({
+            "channel_capacity": self.channel_capacity,
+            "lagged_receivers": self.lagged_receivers.load(Ordering::Relaxed),
+            "skipped_events": self.skipped_events.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Removes a peer from the room and tells the others. Returns false if it was already gone.
+pub fn remove_peer(state: &AppState, room_code: &str, peer_id: &str) -> bool {
+    let Some(mut room) = state.rooms.get_mut(room_code) else {
+        return false;
+    };
+    if room.peers.remove(peer_id).is_none() {
+        return false;
+    }
+
+    let event = RoomEvent::PeerLeft {
+        peer_id: peer_id.to_string(),
+    };
+    state.room_bus.publish(Some(&room.tx), room_code, event);
+
+    info!("👤 Peer left: {} from room {}", peer_id, room_code);
+
+    if room.host_id.as_deref() == Some(peer_id) {
+        let next = successor(&room);
+        set_host(&mut room, next.clone());
+        match &next {
+            Some(host_id) => info!("👑 Host of room {} passed to {}", room_code, host_id),
+            None => info!("👑 Room {} has no host until someone joins", room_code),
+        }
+        let event = RoomEvent::HostChanged { new_host_id: next };
+        state.room_bus.publish(Some(&room.tx), room_code, event);
+    }
+
+    if room.peers.is_empty() {
+        room.empty_since = Some(chrono::Utc::now());
+        if state.room_idle_timeout_seconds == 0 {
+            info!("🕒 Room {} is empty; keeping indefinitely", room_code);
+        } else {
+            info!(
+                "🕒 Room {} is empty; keeping for {}s before cleanup",
+                room_code, state.room_idle_timeout_seconds
+            );
+        }
+    }
+    true
+}
+
+/// Disconnects a peer on whichever instance it is connected to and tells it why. A peer
+/// connected here leaves the room right away; elsewhere its own instance removes it.
+pub fn kick_peer(state: &AppState, room_code: &str, peer_id: &str, reason: &str) {
+    let event = RoomEvent::PeerKicked {
+        peer_id: peer_id.to_string(),
+        reason: reason.to_string(),
+    };
+    publish_to_room(state, room_code, event);
+    info!(
+        "🥾 Peer {} kicked from room {}: {}",
+        peer_id, room_code, reason
+    );
+    remove_peer(state, room_code, peer_id);
+}
+
+/// Drops an empty room from memory once its queued document write is saved, so the next
+/// join loads the document from the database again. Returns false if peers are connected.
+pub async fn evict_room(state: &SharedState, room_code: &str) -> Result<bool, String> {
+    if state
+        .rooms
+        .get(room_code)
+        .is_some_and(|room| !room.peers.is_empty())
+    {
+        return Ok(false);
+    }
+    state
+        .document_persister
+        .flush_room(&state.db, room_code)
+        .await?;
+    // Someone may have joined while the write was in flight
+    let evicted = state
+        .rooms
+        .remove_if(room_code, |_, room| room.peers.is_empty())
+        .is_some();
+    if evicted {
+        info!("📤 Room evicted from memory: {}", room_code);
+    }
+    Ok(evicted || !state.rooms.contains_key(room_code))
+}
+
+/// The peer that takes over from a departing host: whoever joined first, by peer id on a tie.
+pub fn successor(room: &Room) -> Option<String> {
+    room.peers
+        .iter()
+        .min_by(|a, b| a.joined_at.cmp(&b.joined_at).then_with(|| a.id.cmp(&b.id)))
+        .map(|peer| peer.id.clone())
+}
+
+/// Records the room's host and keeps every peer's `is_host` in line with it.
+pub fn set_host(room: &mut Room, host_id: Option<String>) {
+    for mut peer in room.peers.iter_mut() {
+        peer.is_host = host_id.as_deref() == Some(peer.id.as_str());
+    }
+    room.host_id = host_id;
+}
+
+/// Applies the outcome of an election to this instance's copy of the room. Returns the
+/// `HostChanged` to publish when `candidate` won; a host elected on another instance was
+/// announced there.
+pub fn settle_host(room: &mut Room, holder: String, candidate: &str) -> Option<RoomEvent> {
+    if room.host_id.as_deref() == Some(holder.as_str()) {
+        return None;
+    }
+    set_host(room, Some(holder.clone()));
+    (holder == candidate).then_some(RoomEvent::HostChanged {
+        new_host_id: Some(holder),
+    })
+}
+
+/// Safety net for sockets whose task stopped making progress (e.g. a send stuck on a dead
+/// connection): their peers stop being refreshed and are evicted here.
+pub fn spawn_peer_reaper_task(state: SharedState) {
+    tokio::spawn(async move {
+        let heartbeat = state.ws_heartbeat;
+        let mut interval = tokio::time::interval(heartbeat.interval);
+        let grace = chrono::Duration::from_std(heartbeat.timeout + heartbeat.interval)
+            .unwrap_or_else(|_| chrono::Duration::seconds(DEFAULT_PING_TIMEOUT_SECONDS as i64));
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = chrono::Utc::now() - grace;
+            let dead: Vec<(String, String)> = state
+                .rooms
+                .iter()
+                .flat_map(|room| {
+                    room.peers
+                        .iter()
+                        .filter(|peer| peer.last_seen.is_some_and(|seen| seen < cutoff))
+                        .map(|peer| (room.key().clone(), peer.id.clone()))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+
+            for (room_code, peer_id) in dead {
+                if remove_peer(&state, &room_code, &peer_id) {
+                    info!(
+                        "💀 Evicted unresponsive peer {} from room {}",
+                        peer_id, room_code
+                    );
+                }
+            }
+        }
+    });
+}
+
 pub fn spawn_room_cleanup_task(state: SharedState) {
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(StdDuration::from_secs(60));
@@ -34,7 +262,11 @@ pub fn spawn_room_cleanup_task(state: SharedState) {
                 .collect();
 
             for room_code in stale_rooms {
-                if state.rooms.remove(&room_code).is_some() {
+                // Someone may have joined since the scan
+                let removed = state.rooms.remove_if(&room_code, |_, room| {
+                    room.peers.is_empty() && room.empty_since.is_some()
+                });
+                if removed.is_some() {
                     info!("🗑️ Room removed after idle timeout: {}", room_code);
                 }
             }
@@ -57,6 +289,40 @@ pub fn generate_random_id() -> String {
     uuid::Uuid::new_v4().to_string()[..8].to_string()
 }
 
+/// Role the caller gets in a room. Workspace rooms use the caller's workspace role (capped by
+/// a personal access token); rooms without a workspace, made with `POST /api/rooms`, are open
+/// to any logged-in session as an editor. Non-members get the same error as an unknown code.
+pub async fn resolve_room_role(
+    state: &SharedState,
+    user_id: &ObjectId,
+    token_scope: Option<&AccessTokenScope>,
+    room_code: &str,
+) -> Result<WorkspaceRole, String> {
+    let workspace = WorkspaceRepository::new(&state.db)
+        .find_by_room_code(room_code)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let workspace = match workspace {
+        Some(workspace) => workspace,
+        None if token_scope.is_none() => return Ok(WorkspaceRole::Editor),
+        None => return Err("Room not found".to_string()),
+    };
+    if let Some(scope) = token_scope {
+        if !workspace.id.is_some_and(|id| scope.allows_workspace(&id)) {
+            return Err("Room not found".to_string());
+        }
+    }
+
+    let role =
+        MemberService::resolve_role(&MemberRepository::new(&state.db), &workspace, user_id).await?;
+    match (role, token_scope) {
+        (Some(role), Some(scope)) => Ok(scope.cap_role(role)),
+        (Some(role), None) => Ok(role),
+        (None, _) => Err("Room not found".to_string()),
+    }
+}
+
 pub async fn ensure_room_exists(state: &SharedState, room_code: &str) -> Result<(), String> {
     if state.rooms.contains_key(room_code) {
         return Ok(());
@@ -66,6 +332,9 @@ pub async fn ensure_room_exists(state: &SharedState, room_code: &str) -> Result<
     let room_repo = RoomRepository::new(&state.db);
     let workspace_repo = WorkspaceRepository::new(&state.db);
 
+    // A write finishing between reading the stored document and checking for a queued one
+    // would leave the room with the document that write replaced
+    let writes = state.document_persister.hold_writes().await;
     let existing_room_doc = room_repo.find_by_code(room_code).await.ok().flatten();
     let workspace_doc = workspace_repo
         .find_by_room_code(room_code)
@@ -73,44 +342,143 @@ pub async fn ensure_room_exists(state: &SharedState, room_code: &str) -> Result<
         .ok()
         .flatten();
 
-    // If it doesn't exist in either, we'll only allow it if it's a 6-char generated code
-    // actually, let's be more permissive for now and let the system create it if it's a valid request.
-    // However, the rule of thumb is: if it's a UUID, it MUST be in the workspace collection.
-
+    // Unknown codes are never auto-created. Workspace codes (UUIDs) must belong to a live
+    // workspace, so a code that was rotated away stops working even if a late sync wrote its
+    // document back; other codes need a persisted room from `POST /api/rooms`.
     let is_valid = if room_code.len() == 36 && room_code.contains('-') {
-        // Looks like a UUID
-        workspace_doc.is_some() || existing_room_doc.is_some()
+        workspace_doc.is_some()
     } else {
-        // Might be a legacy 6-char code or other
-        true
+        existing_room_doc.is_some()
     };
 
     if !is_valid {
         return Err("Invalid room code".to_string());
     }
 
-    let document_state = existing_room_doc.and_then(|d| {
-        d.get("document")
-            .and_then(|v| v.as_str().map(|s| s.to_string()))
-    });
+    // A write still queued for the room is newer than what's stored
+    let stored = state
+        .document_persister
+        .pending_document(room_code)
+        .or_else(|| existing_room_doc.and_then(|d| d.get("document")?.as_str().map(str::to_string)))
+        .unwrap_or_default();
+    drop(writes);
+    let document = SnapshotService::load_stored(&state.db, room_code, &stored).await?;
 
     let room_id = uuid::Uuid::new_v4().to_string();
-    let host_id = format!("host_{}", generate_random_id());
 
-    let (tx, _) = broadcast::channel(256);
+    let (tx, _) = broadcast::channel(state.room_metrics.channel_capacity);
 
     let room = Room {
         id: room_id,
-        host_id,
+        host_id: None,
         created_at: chrono::Utc::now(),
         tx,
         peers: DashMap::new(),
-        document_state,
+        document,
         last_sync: chrono::Utc::now(),
+        last_snapshot: None,
         empty_since: Some(chrono::Utc::now()),
     };
 
-    state.rooms.insert(room_code.to_string(), room);
+    // Another join may have loaded the room meanwhile; its peers stay
+    state.rooms.entry(room_code.to_string()).or_insert(room);
     info!("🆕 Room auto-initialized: {}", room_code);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heartbeat_timeout_allows_a_missed_ping() {
+        let settings = HeartbeatSettings::new(30, 10);
+        assert_eq!(settings.timeout, StdDuration::from_secs(60));
+
+        let settings = HeartbeatSettings::new(0, 0);
+        assert_eq!(settings.interval, StdDuration::from_secs(1));
+        assert_eq!(settings.timeout, StdDuration::from_secs(2));
+    }
+
+    fn room_with(peers: &[(&str, i64)]) -> Room {
+        let (tx, _) = broadcast::channel(1);
+        let room = Room {
+            id: "room".to_string(),
+            host_id: None,
+            created_at: chrono::Utc::now(),
+            tx,
+            peers: DashMap::new(),
+            document: None,
+            last_sync: chrono::Utc::now(),
+            last_snapshot: None,
+            empty_since: None,
+        };
+        let joined_at = chrono::Utc::now();
+        for (id, offset) in peers {
+            room.peers.insert(
+                id.to_string(),
+                crate::models::room::PeerInfo {
+                    id: id.to_string(),
+                    joined_at: joined_at + chrono::Duration::seconds(*offset),
+                    is_host: false,
+                    metadata: None,
+                    user: None,
+                    presence: Default::default(),
+                    last_seen: None,
+                },
+            );
+        }
+        room
+    }
+
+    #[test]
+    fn host_passes_to_longest_present_peer() {
+        let mut room = room_with(&[("peer_c", 5), ("peer_b", 1), ("peer_a", 1)]);
+
+        let next = successor(&room);
+        assert_eq!(next.as_deref(), Some("peer_a"));
+        set_host(&mut room, next);
+        assert!(room.peers.get("peer_a").unwrap().is_host);
+        assert!(!room.peers.get("peer_b").unwrap().is_host);
+
+        room.peers.clear();
+        assert_eq!(successor(&room), None);
+    }
+
+    #[test]
+    fn two_instances_elect_one_host() {
+        // One room loaded on two instances sharing a bus. `room_hosts` only takes a claim
+        // while the room has no host, like the conditional update in `RoomHostRepository`.
+        let mut hosts: Option<String> = None;
+        let mut claim = |candidate: &str| hosts.get_or_insert(candidate.to_string()).clone();
+        let mut first = room_with(&[("peer_a", 0)]);
+        let mut second = room_with(&[("peer_b", 0)]);
+
+        // Both peers enter a room without a host at the same time
+        let announced = settle_host(&mut first, claim("peer_a"), "peer_a");
+        assert!(matches!(
+            &announced,
+            Some(RoomEvent::HostChanged { new_host_id: Some(id) }) if id == "peer_a"
+        ));
+        assert!(settle_host(&mut second, claim("peer_b"), "peer_b").is_none());
+
+        assert_eq!(first.host_id.as_deref(), Some("peer_a"));
+        assert_eq!(second.host_id.as_deref(), Some("peer_a"));
+        assert!(first.peers.get("peer_a").unwrap().is_host);
+        assert!(!second.peers.get("peer_b").unwrap().is_host);
+
+        // The first instance's announcement changes nothing on the second
+        assert!(settle_host(&mut second, "peer_a".to_string(), "peer_b").is_none());
+    }
+
+    #[test]
+    fn lag_metrics_accumulate() {
+        let metrics = RoomMetrics::from_env();
+        metrics.record_lag(12);
+        metrics.record_lag(3);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot["lagged_receivers"], 2);
+        assert_eq!(snapshot["skipped_events"], 15);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::models::auth::Claims;
+use crate::models::session::{ClientInfo, Session, TokenPair};
+use crate::models::user::User;
+use crate::repositories::session_repo::SessionRepository;
+use crate::repositories::user_repo::UserRepository;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use mongodb::bson::oid::ObjectId;
+use sha2::{Digest, Sha256};
+use std::env;
+
+const DEFAULT_ACCESS_TOKEN_TTL_SECONDS: i64 = 15 * 60;
+const DEFAULT_REFRESH_TOKEN_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+const DEFAULT_SETUP_TOKEN_TTL_SECONDS: i64 = 72 * 60 * 60;
+const DEFAULT_PASSWORD_RESET_TTL_SECONDS: i64 = 30 * 60;
+const DEFAULT_EMAIL_CHANGE_TTL_SECONDS: i64 = 24 * 60 * 60;
+const DEFAULT_IMPERSONATION_TTL_SECONDS: i64 = 30 * 60;
+const DEFAULT_APP_BASE_URL: &str = "http://localhost:5173";
+
+#[derive(Debug, Clone)]
+pub struct AuthSettings {
+    pub access_token_ttl_seconds: i64,
+    pub refresh_token_ttl_seconds: i64,
+    pub setup_token_ttl_seconds: i64,
+    pub password_reset_ttl_seconds: i64,
+    pub email_change_ttl_seconds: i64,
+    /// Lifetime of an admin "act as user" token; it can't be refreshed
+    pub impersonation_ttl_seconds: i64,
+    /// Frontend URL used to build links in outgoing mail
+    pub app_base_url: String,
+}
+
+impl AuthSettings {
+    pub fn from_env() -> Self {
+        Self {
+            access_token_ttl_seconds: env_seconds(
+                "ACCESS_TOKEN_TTL_SECONDS",
+                DEFAULT_ACCESS_TOKEN_TTL_SECONDS,
+            ),
+            refresh_token_ttl_seconds: env_seconds(
+                "REFRESH_TOKEN_TTL_SECONDS",
+                DEFAULT_REFRESH_TOKEN_TTL_SECONDS,
+            ),
+            setup_token_ttl_seconds: env_seconds(
+                "SETUP_TOKEN_TTL_SECONDS",
+                DEFAULT_SETUP_TOKEN_TTL_SECONDS,
+            ),
+            password_reset_ttl_seconds: env_seconds(
+                "PASSWORD_RESET_TTL_SECONDS",
+                DEFAULT_PASSWORD_RESET_TTL_SECONDS,
+            ),
+            email_change_ttl_seconds: env_seconds(
+                "EMAIL_CHANGE_TTL_SECONDS",
+                DEFAULT_EMAIL_CHANGE_TTL_SECONDS,
+            ),
+            impersonation_ttl_seconds: env_seconds(
+                "IMPERSONATION_TTL_SECONDS",
+                DEFAULT_IMPERSONATION_TTL_SECONDS,
+            ),
+            app_base_url: env::var("APP_BASE_URL")
+                .ok()
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| DEFAULT_APP_BASE_URL.to_string()),
+        }
+    }
+}
+
+fn env_seconds(key: &str, default: i64) -> i64 {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(default)
+}
+
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn random_secret(len: usize) -> String {
+    rand::Rng::sample_iter(&mut rand::thread_rng(), &rand::distributions::Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Refresh tokens are `<session_id>.<secret>` so the session can be found without scanning hashes.
+fn split_refresh_token(token: &str) -> Option<(&str, &str)> {
+    let (session_id, secret) = token.split_once('.')?;
+    if session_id.is_empty() || secret.is_empty() {
+        return None;
+    }
+    Some((session_id, secret))
+}
+
+pub struct SessionService;
+
+impl SessionService {
+    pub fn encode_access_token(
+        user_id: &ObjectId,
+        role: &str,
+        session_id: &str,
+        settings: &AuthSettings,
+        jwt_secret: &str,
+    ) -> Result<String, String> {
+        let expiration = chrono::Utc::now()
+            .checked_add_signed(chrono::Duration::seconds(settings.access_token_ttl_seconds))
+            .expect("valid timestamp")
+            .timestamp() as usize;
+
+        let claims = Claims {
+            sub: user_id.to_hex(),
+            role: role.to_string(),
+            exp: expiration,
+            sid: Some(session_id.to_string()),
+            access_token: None,
+            impersonator: None,
+        };
+
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(jwt_secret.as_ref()),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Opens a new session (one per login/device) and returns its first token pair.
+    pub async fn issue(
+        session_repo: &SessionRepository,
+        user: &User,
+        settings: &AuthSettings,
+        jwt_secret: &str,
+        client: ClientInfo,
+    ) -> Result<TokenPair, String> {
+        let user_oid = user.id.ok_or("User has no id")?;
+        let session_id = uuid::Uuid::now_v7().to_string();
+        let refresh_token = format!("{}.{}", session_id, random_secret(48));
+        let now = chrono::Utc::now();
+        let expires_at = now + chrono::Duration::seconds(settings.refresh_token_ttl_seconds);
+
+        let _ = session_repo.delete_stale_for_user(&user_oid).await;
+
+        session_repo
+            .create(&Session {
+                id: None,
+                session_id: session_id.clone(),
+                user_id: user_oid,
+                refresh_token_hash: hash_token(&refresh_token),
+                user_agent: client.user_agent,
+                ip: client.ip,
+                created_at: now.to_rfc3339(),
+                last_used_at: now.to_rfc3339(),
+                expires_at: expires_at.to_rfc3339(),
+                revoked_at: None,
+                impersonator_id: None,
+            })
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let access_token =
+            Self::encode_access_token(&user_oid, &user.role, &session_id, settings, jwt_secret)?;
+
+        Ok(TokenPair {
+            session_id,
+            access_token,
+            refresh_token,
+            expires_in: settings.access_token_ttl_seconds,
+        })
+    }
+
+    /// Opens a short session on `user`'s account for an admin. The single access token
+    /// carries the admin id and expires with the session; no refresh token is handed out.
+    pub async fn issue_impersonation(
+        session_repo: &SessionRepository,
+        user: &User,
+        impersonator_id: &ObjectId,
+        settings: &AuthSettings,
+        jwt_secret: &str,
+        client: ClientInfo,
+    ) -> Result<(String, Session), String> {
+        let user_oid = user.id.ok_or("User has no id")?;
+        let now = chrono::Utc::now();
+        let expires_at = now + chrono::Duration::seconds(settings.impersonation_ttl_seconds);
+
+        let session = Session {
+            id: None,
+            session_id: uuid::Uuid::now_v7().to_string(),
+            user_id: user_oid,
+            // Never disclosed, so the session can't be refreshed
+            refresh_token_hash: hash_token(&random_secret(48)),
+            user_agent: client.user_agent,
+            ip: client.ip,
+            created_at: now.to_rfc3339(),
+            last_used_at: now.to_rfc3339(),
+            expires_at: expires_at.to_rfc3339(),
+            revoked_at: None,
+            impersonator_id: Some(*impersonator_id),
+        };
+        session_repo
+            .create(&session)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let claims = Claims {
+            sub: user_oid.to_hex(),
+            role: user.role.clone(),
+            exp: expires_at.timestamp() as usize,
+            sid: Some(session.session_id.clone()),
+            access_token: None,
+            impersonator: Some(impersonator_id.to_hex()),
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(jwt_secret.as_ref()),
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok((token, session))
+    }
+
+    /// Exchanges a refresh token for a new pair. The old refresh token stops working;
+    /// presenting it again is treated as theft and revokes the whole session.
+    pub async fn refresh(
+        session_repo: &SessionRepository,
+        user_repo: &UserRepository,
+        refresh_token: &str,
+        settings: &AuthSettings,
+        jwt_secret: &str,
+    ) -> Result<TokenPair, String> {
+        let (session_id, _) = split_refresh_token(refresh_token).ok_or("Invalid refresh token")?;
+
+        let session = session_repo
+            .find_active(session_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or("Session expired or revoked")?;
+
+        let current_hash = hash_token(refresh_token);
+        if session.refresh_token_hash != current_hash {
+            tracing::warn!(
+                "🔐 Refresh token reuse detected for session {}, revoking",
+                session_id
+            );
+            let _ = session_repo.revoke(session_id).await;
+            return Err("Session expired or revoked".to_string());
+        }
+
+        let user = user_repo
+            .find_by_id(&session.user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let user = match user {
+            Some(u) if u.is_active => u,
+            _ => {
+                let _ = session_repo.revoke(session_id).await;
+                return Err("Account is disabled".to_string());
+            }
+        };
+
+        let next_refresh_token = format!("{}.{}", session_id, random_secret(48));
+        let expires_at =
+            chrono::Utc::now() + chrono::Duration::seconds(settings.refresh_token_ttl_seconds);
+        let rotated = session_repo
+            .rotate(
+                session_id,
+                &current_hash,
+                &hash_token(&next_refresh_token),
+                &expires_at.to_rfc3339(),
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if !rotated {
+            // Lost a race with a concurrent refresh of the same token.
+            return Err("Session expired or revoked".to_string());
+        }
+
+        let access_token = Self::encode_access_token(
+            &session.user_id,
+            &user.role,
+            session_id,
+            settings,
+            jwt_secret,
+        )?;
+
+        Ok(TokenPair {
+            session_id: session_id.to_string(),
+            access_token,
+            refresh_token: next_refresh_token,
+            expires_in: settings.access_token_ttl_seconds,
+        })
+    }
+
+    pub async fn list_sessions(
+        session_repo: &SessionRepository,
+        user_id: &ObjectId,
+        current_session_id: Option<&str>,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let sessions = session_repo
+            .find_active_by_user(user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|s| {
+                serde_json::json!({
+                    "session_id": s.session_id,
+                    "user_agent": s.user_agent,
+                    "ip": s.ip,
+                    "created_at": s.created_at,
+                    "last_used_at": s.last_used_at,
+                    "expires_at": s.expires_at,
+                    "current": current_session_id == Some(s.session_id.as_str()),
+                    "impersonated": s.impersonator_id.is_some(),
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_token_splits_into_session_and_secret() {
+        assert_eq!(
+            split_refresh_token("0190-abc.s3cret"),
+            Some(("0190-abc", "s3cret"))
+        );
+        assert_eq!(split_refresh_token("no-dot"), None);
+        assert_eq!(split_refresh_token(".secret"), None);
+        assert_eq!(split_refresh_token("session."), None);
+    }
+
+    #[test]
+    fn token_hash_is_stable_hex() {
+        let hashed = hash_token("abc");
+        assert_eq!(hashed.len(), 64);
+        assert_eq!(hashed, hash_token("abc"));
+        assert_ne!(hashed, hash_token("abd"));
+    }
+}
@@ -0,0 +1,308 @@
+use crate::models::room::{Room, RoomEvent};
+use crate::models::room_snapshot::{
+    FieldDiff, RoomSnapshot, SnapshotAuthor, SnapshotDiff, TaskDiff,
+};
+use crate::repositories::room_repo::RoomRepository;
+use crate::repositories::room_snapshot_repo::RoomSnapshotRepository;
+use crate::services::document_service::DocumentService;
+use crate::services::room_service::ensure_room_exists;
+use crate::state::SharedState;
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::oid::ObjectId;
+use mongodb::Database;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use wasm_crdt::{CrdtDocument, CrdtTask};
+
+/// A room's document is snapshotted on sync at most this often
+const SNAPSHOT_INTERVAL_SECONDS: i64 = 300;
+/// Snapshots kept per room after compaction
+const SNAPSHOT_LIMIT: usize = 50;
+/// Everything younger than this is kept
+const KEEP_ALL_HOURS: i64 = 1;
+/// Up to this age one snapshot per hour is kept, after it one per day
+const KEEP_HOURLY_HOURS: i64 = 24;
+
+pub struct SnapshotService;
+
+impl SnapshotService {
+    /// Whether a sync of this room should be snapshotted. Marks the room when it should.
+    pub fn due(room: &mut Room, now: DateTime<Utc>) -> bool {
+        let due = room
+            .last_snapshot
+            .is_none_or(|at| now - at >= Duration::seconds(SNAPSHOT_INTERVAL_SECONDS));
+        if due {
+            room.last_snapshot = Some(now);
+        }
+        due
+    }
+
+    pub fn capture(
+        room_code: &str,
+        document: &CrdtDocument,
+        author: &SnapshotAuthor,
+        reason: &str,
+    ) -> RoomSnapshot {
+        let exported = document.export();
+        RoomSnapshot {
+            id: None,
+            room_code: room_code.to_string(),
+            size: exported.len() as i64,
+            document: exported,
+            author_peer_id: author.peer_id.clone(),
+            author_user_id: author.user_id.clone(),
+            reason: reason.to_string(),
+            task_count: document.tasks().len() as i64,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Loads a room's stored document. One that can't be read is kept as an "unreadable"
+    /// snapshot before the room starts empty, since the next persist overwrites it.
+    pub async fn load_stored(
+        db: &Database,
+        room_code: &str,
+        stored: &str,
+    ) -> Result<Option<CrdtDocument>, String> {
+        let error = match DocumentService::load(stored) {
+            Ok(document) => return Ok(document),
+            Err(e) => e,
+        };
+        let snapshot = RoomSnapshot {
+            id: None,
+            room_code: room_code.to_string(),
+            document: stored.to_string(),
+            author_peer_id: "server".to_string(),
+            author_user_id: None,
+            reason: "unreadable".to_string(),
+            size: stored.len() as i64,
+            task_count: 0,
+            created_at: Utc::now().to_rfc3339(),
+        };
+        Self::record(db, &snapshot).await?;
+        tracing::warn!(
+            "{} in room {}; kept it as a snapshot and started over",
+            error,
+            room_code
+        );
+        Ok(None)
+    }
+
+    /// Stores a snapshot and compacts the room's history.
+    pub async fn record(db: &Database, snapshot: &RoomSnapshot) -> Result<(), String> {
+        let repo = RoomSnapshotRepository::new(db);
+        repo.insert(snapshot)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let existing: Vec<(ObjectId, DateTime<Utc>)> = repo
+            .find_by_room(&snapshot.room_code)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .into_iter()
+            .filter_map(|s| {
+                let created_at = DateTime::parse_from_rfc3339(&s.created_at).ok()?;
+                Some((s.id?, created_at.with_timezone(&Utc)))
+            })
+            .collect();
+
+        let victims = compaction_victims(&existing, Utc::now());
+        if !victims.is_empty() {
+            repo.delete_ids(&victims)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// The room's document as peers see it: the live one if the room is open here,
+    /// otherwise the newest one written or waiting to be.
+    pub async fn current_document(state: &SharedState, room_code: &str) -> Result<String, String> {
+        if let Some(room) = state.rooms.get(room_code) {
+            return Ok(room
+                .document
+                .as_ref()
+                .map(|doc| doc.export())
+                .unwrap_or_default());
+        }
+        if let Some(pending) = state.document_persister.pending_document(room_code) {
+            return Ok(pending);
+        }
+        let stored = RoomRepository::new(&state.db)
+            .find_by_code(room_code)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(stored
+            .and_then(|room| room.get("document")?.as_str().map(str::to_string))
+            .unwrap_or_default())
+    }
+
+    /// What changed between `snapshot` and `current`, both exported documents.
+    pub fn diff(current: &str, snapshot: &str) -> Result<SnapshotDiff, String> {
+        let parse = |json: &str| -> Result<HashMap<u32, CrdtTask>, String> {
+            if json.trim().is_empty() {
+                return Ok(HashMap::new());
+            }
+            let tasks: HashMap<u32, CrdtTask> =
+                serde_json::from_str(json).map_err(|e| format!("Unreadable document: {}", e))?;
+            Ok(tasks
+                .into_iter()
+                .filter(|(_, task)| !task.deleted)
+                .collect())
+        };
+        let current = parse(current)?;
+        let snapshot = parse(snapshot)?;
+
+        let mut diff = SnapshotDiff::default();
+        let ids: BTreeSet<u32> = current.keys().chain(snapshot.keys()).copied().collect();
+        for task_id in ids {
+            let (then, now) = match (snapshot.get(&task_id), current.get(&task_id)) {
+                (None, Some(_)) => {
+                    diff.added.push(task_id);
+                    continue;
+                }
+                (Some(_), None) => {
+                    diff.removed.push(task_id);
+                    continue;
+                }
+                (Some(then), Some(now)) => (then, now),
+                (None, None) => continue,
+            };
+
+            let mut fields: BTreeMap<&String, (Option<&String>, Option<&String>)> = BTreeMap::new();
+            for (field, value) in &then.fields {
+                fields.entry(field).or_default().0 = Some(&value.value);
+            }
+            for (field, value) in &now.fields {
+                fields.entry(field).or_default().1 = Some(&value.value);
+            }
+            let fields: Vec<FieldDiff> = fields
+                .into_iter()
+                .filter(|(_, (then, now))| then != now)
+                .map(|(field, (then, now))| FieldDiff {
+                    field: field.clone(),
+                    snapshot: then.cloned(),
+                    current: now.cloned(),
+                })
+                .collect();
+            if !fields.is_empty() {
+                diff.changed.push(TaskDiff { task_id, fields });
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Brings the room's document back to `snapshot` and sends the result to every peer.
+    /// The document as it was is snapshotted first, so a restore can itself be undone.
+    /// Returns the ids of tasks that were deleted since and came back.
+    pub async fn restore(
+        state: &SharedState,
+        room_code: &str,
+        snapshot: &RoomSnapshot,
+        author: &SnapshotAuthor,
+    ) -> Result<Vec<u32>, String> {
+        ensure_room_exists(state, room_code).await?;
+
+        let (before, restored, undeleted) = {
+            let Some(mut room) = state.rooms.get_mut(room_code) else {
+                return Err("Room not found".to_string());
+            };
+            let before = room
+                .document
+                .as_ref()
+                .map(|doc| Self::capture(room_code, doc, author, "before_restore"));
+            let undeleted = DocumentService::revert(&mut room.document, &snapshot.document)?;
+            let Some(doc) = room.document.as_ref() else {
+                return Err("Room has no document".to_string());
+            };
+            let restored = doc.export();
+            let event = RoomEvent::DocumentUpdate {
+                from: "server".to_string(),
+                document: restored.clone(),
+                version: doc.version().clone(),
+            };
+            let now = Utc::now();
+            room.last_sync = now;
+            room.last_snapshot = Some(now);
+            state.room_bus.publish(Some(&room.tx), room_code, event);
+            (before, restored, undeleted)
+        };
+
+        if let Some(before) = before {
+            Self::record(&state.db, &before).await?;
+        }
+        state.document_persister.mark(room_code, restored, None);
+        state
+            .document_persister
+            .flush_room(&state.db, room_code)
+            .await?;
+        Ok(undeleted)
+    }
+}
+
+/// Snapshots to drop, given a room's snapshots newest first. Recent ones are all kept; older
+/// ones thin out to the newest per hour, then per day, and the oldest go past the limit.
+pub fn compaction_victims(
+    snapshots: &[(ObjectId, DateTime<Utc>)],
+    now: DateTime<Utc>,
+) -> Vec<ObjectId> {
+    let mut buckets = BTreeSet::new();
+    let mut kept = 0;
+    let mut victims = Vec::new();
+
+    for (id, created_at) in snapshots {
+        let age = now - *created_at;
+        let bucket = if age < Duration::hours(KEEP_ALL_HOURS) {
+            None
+        } else if age < Duration::hours(KEEP_HOURLY_HOURS) {
+            Some((3600, created_at.timestamp() / 3600))
+        } else {
+            Some((86400, created_at.timestamp() / 86400))
+        };
+        let keep = kept < SNAPSHOT_LIMIT && bucket.is_none_or(|bucket| buckets.insert(bucket));
+        if keep {
+            kept += 1;
+        } else {
+            victims.push(*id);
+        }
+    }
+    victims
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compaction_thins_out_old_snapshots() {
+        let now = Utc::now();
+        let mut snapshots = Vec::new();
+        // Every 10 minutes for three days
+        for i in 0..(3 * 24 * 6) {
+            snapshots.push((ObjectId::new(), now - Duration::minutes(10 * i)));
+        }
+
+        let victims = compaction_victims(&snapshots, now);
+        let kept: Vec<_> = snapshots
+            .iter()
+            .filter(|(id, _)| !victims.contains(id))
+            .collect();
+
+        assert!(kept.len() <= SNAPSHOT_LIMIT);
+        // The last hour is untouched
+        let recent = kept
+            .iter()
+            .filter(|(_, at)| now - *at < Duration::hours(KEEP_ALL_HOURS))
+            .count();
+        assert_eq!(recent, 6);
+        // Nothing older than a day shares a day with another kept snapshot
+        let old_days: Vec<_> = kept
+            .iter()
+            .filter(|(_, at)| now - *at >= Duration::hours(KEEP_HOURLY_HOURS))
+            .map(|(_, at)| at.timestamp() / 86400)
+            .collect();
+        let unique: BTreeSet<_> = old_days.iter().collect();
+        assert_eq!(old_days.len(), unique.len());
+
+        assert!(compaction_victims(&snapshots[..3], now).is_empty());
+    }
+}
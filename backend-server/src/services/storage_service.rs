@@ -78,7 +78,7 @@ fn encryption_key_bytes() -> [u8; 32] {
     hasher.finalize().into()
 }
 
-fn decrypt_value_if_needed(value: Option<String>) -> Option<String> {
+pub fn decrypt_value_if_needed(value: Option<String>) -> Option<String> {
     let value = clean_optional(value)?;
     if !value.starts_with(ENCRYPTED_PREFIX) {
         return Some(value);
@@ -0,0 +1,430 @@
+use crate::models::auth::LoginTwoFactorRequest;
+use crate::models::auth_token::{AuthToken, AuthTokenPurpose};
+use crate::models::user::{TwoFactorSettings, User};
+use crate::repositories::{auth_token_repo::AuthTokenRepository, user_repo::UserRepository};
+use crate::services::session_service::{hash_token, random_secret};
+use crate::services::storage_service::{decrypt_value_if_needed, encrypt_value_if_present};
+use bcrypt::verify;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use mongodb::bson::oid::ObjectId;
+use rand::RngCore;
+use sha1::Sha1;
+
+const TOTP_ISSUER: &str = "Khun Phaen";
+const TOTP_PERIOD_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+/// Accept one step either side of "now" to tolerate clock drift on the phone
+const TOTP_SKEW_STEPS: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+pub const LOGIN_CHALLENGE_TTL_SECONDS: i64 = 5 * 60;
+pub const LOGIN_CHALLENGE_MAX_ATTEMPTS: u32 = 5;
+
+/// New random 160-bit TOTP secret, base32 encoded for authenticator apps.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+/// `otpauth://` URI that authenticator apps import (usually via QR code).
+pub fn provisioning_uri(account: &str, secret: &str) -> String {
+    let issuer = percent_encode(TOTP_ISSUER);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        percent_encode(account),
+        secret,
+        issuer,
+        TOTP_DIGITS,
+        TOTP_PERIOD_SECONDS
+    )
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// RFC 6238 check (SHA-1, 6 digits, 30s). Returns the matching time step so callers
+/// can reject a code that was already used.
+pub fn verify_totp(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let expected: u32 = code.parse().ok()?;
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current = unix_time.div_euclid(TOTP_PERIOD_SECONDS);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .filter(|step| *step >= 0)
+        .find(|step| hotp(&key, *step as u64) == expected)
+}
+
+/// Recovery codes are shown once as `xxxxx-xxxxx`; only hashes of the normalized form are kept.
+fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = random_secret(10).to_lowercase();
+            format!("{}-{}", &raw[..5], &raw[5..])
+        })
+        .collect();
+    let hashes = codes
+        .iter()
+        .map(|code| hash_token(&normalize_recovery_code(code)))
+        .collect();
+    (codes, hashes)
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// What the client needs to finish a login that stopped after the password step.
+pub struct LoginChallenge {
+    pub challenge_token: String,
+    pub expires_in: i64,
+    /// Set when the user must enroll first: `(secret, otpauth_uri)`
+    pub setup: Option<(String, String)>,
+}
+
+pub struct TwoFactorService;
+
+impl TwoFactorService {
+    /// Hands out a new secret. It only takes effect once `confirm_setup` sees a valid code.
+    pub async fn begin_setup(
+        user_repo: &UserRepository,
+        user: &mut User,
+    ) -> Result<(String, String), String> {
+        if user.two_factor.enabled {
+            return Err("Two-factor authentication is already enabled".to_string());
+        }
+        let secret = generate_secret();
+        user.two_factor.pending_secret = encrypt_value_if_present(Some(secret.clone()));
+        user_repo
+            .update(user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let uri = provisioning_uri(&user.email, &secret);
+        Ok((secret, uri))
+    }
+
+    /// Turns 2FA on and returns the plaintext recovery codes (shown to the user once).
+    pub async fn confirm_setup(
+        user_repo: &UserRepository,
+        user: &mut User,
+        code: &str,
+    ) -> Result<Vec<String>, String> {
+        if user.two_factor.enabled {
+            return Err("Two-factor authentication is already enabled".to_string());
+        }
+        let encrypted = user
+            .two_factor
+            .pending_secret
+            .clone()
+            .ok_or_else(|| "Start two-factor setup first".to_string())?;
+        let secret = decrypt_value_if_needed(Some(encrypted.clone()))
+            .ok_or_else(|| "Start two-factor setup first".to_string())?;
+        let step = verify_totp(&secret, code, chrono::Utc::now().timestamp())
+            .ok_or_else(|| "Invalid verification code".to_string())?;
+
+        let (codes, hashes) = generate_recovery_codes();
+        user.two_factor = TwoFactorSettings {
+            enabled: true,
+            secret: Some(encrypted),
+            pending_secret: None,
+            recovery_code_hashes: hashes,
+            last_used_step: Some(step),
+            enabled_at: Some(chrono::Utc::now().to_rfc3339()),
+        };
+        user_repo
+            .update(user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(codes)
+    }
+
+    /// Checks an authenticator code or burns a recovery code.
+    pub async fn verify(
+        user_repo: &UserRepository,
+        user: &User,
+        code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<(), String> {
+        if !user.two_factor.enabled {
+            return Err("Two-factor authentication is not enabled".to_string());
+        }
+        let user_oid = user.id.ok_or("User has no id")?;
+
+        if let Some(code) = code.filter(|c| !c.trim().is_empty()) {
+            let secret = decrypt_value_if_needed(user.two_factor.secret.clone())
+                .ok_or_else(|| "Two-factor secret is unreadable".to_string())?;
+            let step = verify_totp(&secret, code, chrono::Utc::now().timestamp())
+                .ok_or_else(|| "Invalid verification code".to_string())?;
+            // Fails if this step (or a later one) was already used
+            if !user_repo
+                .advance_totp_step(&user_oid, step)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+            {
+                return Err("Invalid verification code".to_string());
+            }
+            return Ok(());
+        }
+
+        if let Some(recovery_code) = recovery_code.filter(|c| !c.trim().is_empty()) {
+            let code_hash = hash_token(&normalize_recovery_code(recovery_code));
+            if !user_repo
+                .consume_recovery_code(&user_oid, &code_hash)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+            {
+                return Err("Invalid recovery code".to_string());
+            }
+            return Ok(());
+        }
+
+        Err("Verification code is required".to_string())
+    }
+
+    pub async fn disable(
+        user_repo: &UserRepository,
+        user: &mut User,
+        password: &str,
+        code: Option<&str>,
+        recovery_code: Option<&str>,
+    ) -> Result<(), String> {
+        let password_hash = user
+            .password_hash
+            .as_ref()
+            .ok_or("User has no password set".to_string())?;
+        if !verify(password, password_hash).map_err(|e| e.to_string())? {
+            return Err("Invalid password".to_string());
+        }
+        Self::verify(user_repo, user, code, recovery_code).await?;
+
+        user.two_factor = TwoFactorSettings::default();
+        user_repo
+            .update(user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Replaces all recovery codes; requires a current authenticator code.
+    pub async fn regenerate_recovery_codes(
+        user_repo: &UserRepository,
+        user: &User,
+        code: &str,
+    ) -> Result<Vec<String>, String> {
+        Self::verify(user_repo, user, Some(code), None).await?;
+
+        let (codes, hashes) = generate_recovery_codes();
+        let user_oid = user.id.ok_or("User has no id")?;
+        user_repo
+            .set_recovery_codes(&user_oid, hashes)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(codes)
+    }
+
+    /// Admin escape hatch for a user who lost both their device and recovery codes.
+    pub async fn reset(user_repo: &UserRepository, id_str: &str) -> Result<User, String> {
+        let oid = ObjectId::parse_str(id_str).map_err(|_| "Invalid User ID format".to_string())?;
+        let mut user = user_repo
+            .find_by_id(&oid)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "User not found".to_string())?;
+        user.two_factor = TwoFactorSettings::default();
+        user_repo
+            .update(&user)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(user)
+    }
+
+    /// Called after a correct password. Users without 2FA who are required to have it get a
+    /// fresh secret to enroll with before a session is issued.
+    pub async fn start_login_challenge(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        user: &mut User,
+    ) -> Result<LoginChallenge, String> {
+        let user_oid = user.id.ok_or("User has no id")?;
+        let setup = if user.two_factor.enabled {
+            None
+        } else {
+            Some(Self::begin_setup(user_repo, user).await?)
+        };
+
+        token_repo
+            .invalidate_for_user(&user_oid, AuthTokenPurpose::TwoFactorLogin)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let challenge_token = random_secret(48);
+        let now = chrono::Utc::now();
+        token_repo
+            .create(&AuthToken {
+                id: None,
+                token_hash: hash_token(&challenge_token),
+                purpose: AuthTokenPurpose::TwoFactorLogin,
+                user_id: user_oid,
+                new_email: None,
+                created_at: now.to_rfc3339(),
+                expires_at: (now + chrono::Duration::seconds(LOGIN_CHALLENGE_TTL_SECONDS))
+                    .to_rfc3339(),
+                used_at: None,
+                failed_attempts: 0,
+            })
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(LoginChallenge {
+            challenge_token,
+            expires_in: LOGIN_CHALLENGE_TTL_SECONDS,
+            setup,
+        })
+    }
+
+    /// The user a pending login challenge belongs to, so the second step can be throttled
+    /// against the same account as the first.
+    pub async fn login_challenge_user(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        challenge_token: &str,
+    ) -> Result<User, String> {
+        let challenge = token_repo
+            .find_valid(
+                &hash_token(challenge_token),
+                AuthTokenPurpose::TwoFactorLogin,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Invalid or expired login challenge".to_string())?;
+
+        user_repo
+            .find_by_id(&challenge.user_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .filter(|u| u.is_active)
+            .ok_or_else(|| "Invalid or expired login challenge".to_string())
+    }
+
+    /// Second login step for the challenge's user (see `login_challenge_user`). Returns the
+    /// new recovery codes if this completed enrollment.
+    pub async fn complete_login_challenge(
+        user_repo: &UserRepository,
+        token_repo: &AuthTokenRepository,
+        user: &mut User,
+        payload: &LoginTwoFactorRequest,
+    ) -> Result<Option<Vec<String>>, String> {
+        let token_hash = hash_token(&payload.challenge_token);
+        let result = if user.two_factor.enabled {
+            Self::verify(
+                user_repo,
+                user,
+                payload.code.as_deref(),
+                payload.recovery_code.as_deref(),
+            )
+            .await
+            .map(|_| None)
+        } else {
+            let code = payload.code.as_deref().unwrap_or_default();
+            Self::confirm_setup(user_repo, user, code).await.map(Some)
+        };
+
+        let recovery_codes = match result {
+            Ok(codes) => codes,
+            Err(e) => {
+                let _ = token_repo
+                    .record_failed_attempt(&token_hash, LOGIN_CHALLENGE_MAX_ATTEMPTS)
+                    .await;
+                return Err(e);
+            }
+        };
+
+        token_repo
+            .consume(&token_hash, AuthTokenPurpose::TwoFactorLogin)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Invalid or expired login challenge".to_string())?;
+
+        Ok(recovery_codes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B uses the ASCII key "12345678901234567890"
+    fn rfc_secret() -> String {
+        BASE32_NOPAD.encode(b"12345678901234567890")
+    }
+
+    #[test]
+    fn matches_rfc6238_vectors() {
+        let secret = rfc_secret();
+        assert_eq!(verify_totp(&secret, "287082", 59), Some(1));
+        assert_eq!(verify_totp(&secret, "081804", 1111111109), Some(37037036));
+        assert_eq!(verify_totp(&secret, "050471", 1111111111), Some(37037037));
+    }
+
+    #[test]
+    fn allows_one_step_of_drift_only() {
+        let secret = rfc_secret();
+        // Code for step 1 (t=59) is still accepted one step later, but not two
+        assert_eq!(verify_totp(&secret, "287082", 89), Some(1));
+        assert_eq!(verify_totp(&secret, "287082", 120), None);
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        let secret = rfc_secret();
+        assert_eq!(verify_totp(&secret, "28708", 59), None);
+        assert_eq!(verify_totp(&secret, "28708a", 59), None);
+        assert_eq!(verify_totp(&secret, "287 082", 59), Some(1));
+    }
+
+    #[test]
+    fn recovery_codes_hash_their_normalized_form() {
+        let (codes, hashes) = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        let typed = codes[0].to_uppercase().replace('-', " ");
+        assert_eq!(hash_token(&normalize_recovery_code(&typed)), hashes[0]);
+    }
+
+    #[test]
+    fn provisioning_uri_escapes_label() {
+        let uri = provisioning_uri("a+b@example.com", "ABC");
+        assert_eq!(
+            uri,
+            "otpauth://totp/Khun%20Phaen:a%2Bb%40example.com?secret=ABC&issuer=Khun%20Phaen&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
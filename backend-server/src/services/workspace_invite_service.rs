@@ -0,0 +1,172 @@
+use crate::models::member::{WorkspaceMember, WorkspaceRole};
+use crate::models::workspace::Workspace;
+use crate::models::workspace_invite::{CreateWorkspaceInviteRequest, WorkspaceInvite};
+use crate::repositories::{
+    member_repo::MemberRepository, workspace_invite_repo::WorkspaceInviteRepository,
+    workspace_repo::WorkspaceRepository,
+};
+use crate::services::member_service::MemberService;
+use crate::services::session_service::{hash_token, random_secret};
+use mongodb::bson::oid::ObjectId;
+
+const DEFAULT_EXPIRY_HOURS: i64 = 24 * 7;
+const MAX_EXPIRY_HOURS: i64 = 24 * 90;
+const MAX_USES_LIMIT: u32 = 10_000;
+
+/// Result of accepting an invite link.
+pub struct InviteAcceptance {
+    pub workspace: Workspace,
+    pub role: WorkspaceRole,
+    /// True if the user was already a member; no use of the invite was taken
+    pub already_member: bool,
+}
+
+pub struct WorkspaceInviteService;
+
+impl WorkspaceInviteService {
+    /// Creates an invite link for `workspace_id`. The plaintext token is returned once and
+    /// never stored.
+    pub async fn create(
+        invite_repo: &WorkspaceInviteRepository,
+        workspace_id: &ObjectId,
+        created_by: &ObjectId,
+        payload: CreateWorkspaceInviteRequest,
+    ) -> Result<(WorkspaceInvite, String), String> {
+        let role = payload.role.unwrap_or(WorkspaceRole::Viewer);
+        if role == WorkspaceRole::Owner {
+            return Err("Cannot invite a member as owner".to_string());
+        }
+        if payload
+            .max_uses
+            .is_some_and(|max| max == 0 || max > MAX_USES_LIMIT)
+        {
+            return Err(format!("max_uses must be between 1 and {}", MAX_USES_LIMIT));
+        }
+        let expires_in_hours = payload.expires_in_hours.unwrap_or(DEFAULT_EXPIRY_HOURS);
+        if !(0..=MAX_EXPIRY_HOURS).contains(&expires_in_hours) {
+            return Err(format!(
+                "expires_in_hours must be between 0 and {}",
+                MAX_EXPIRY_HOURS
+            ));
+        }
+
+        let now = chrono::Utc::now();
+        let token = random_secret(32);
+        let invite = WorkspaceInvite {
+            id: None,
+            invite_id: uuid::Uuid::now_v7().to_string(),
+            workspace_id: *workspace_id,
+            token_hash: hash_token(&token),
+            role,
+            created_by: *created_by,
+            created_at: now.to_rfc3339(),
+            expires_at: (expires_in_hours > 0)
+                .then(|| (now + chrono::Duration::hours(expires_in_hours)).to_rfc3339()),
+            max_uses: payload.max_uses,
+            uses: 0,
+            revoked_at: None,
+        };
+        invite_repo
+            .create(&invite)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok((invite, token))
+    }
+
+    pub fn link(app_base_url: &str, token: &str) -> String {
+        format!("{}/invite/{}", app_base_url.trim_end_matches('/'), token)
+    }
+
+    pub fn to_json(invite: &WorkspaceInvite) -> serde_json::Value {
+        let now = chrono::Utc::now().to_rfc3339();
+        serde_json::json!({
+            "invite_id": invite.invite_id,
+            "role": invite.role,
+            "created_by": invite.created_by.to_hex(),
+            "created_at": invite.created_at,
+            "expires_at": invite.expires_at,
+            "max_uses": invite.max_uses,
+            "uses": invite.uses,
+            "revoked_at": invite.revoked_at,
+            "status": invite.status(&now),
+        })
+    }
+
+    /// Looks up an invite that can still be used, together with its workspace. `None` covers
+    /// unknown, revoked, expired and used-up links alike.
+    pub async fn find_usable(
+        invite_repo: &WorkspaceInviteRepository,
+        workspace_repo: &WorkspaceRepository,
+        token: &str,
+    ) -> Result<Option<(WorkspaceInvite, Workspace)>, String> {
+        let invite = match invite_repo
+            .find_by_hash(&hash_token(token))
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+        {
+            Some(invite) if invite.status(&chrono::Utc::now().to_rfc3339()) == "active" => invite,
+            _ => return Ok(None),
+        };
+        let workspace = workspace_repo
+            .find_by_id(&invite.workspace_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(workspace.map(|workspace| (invite, workspace)))
+    }
+
+    /// Adds `user_id` to the invite's workspace. Existing members keep their role and don't
+    /// count against `max_uses`.
+    pub async fn accept(
+        invite_repo: &WorkspaceInviteRepository,
+        workspace_repo: &WorkspaceRepository,
+        member_repo: &MemberRepository,
+        token: &str,
+        user_id: &ObjectId,
+    ) -> Result<Option<InviteAcceptance>, String> {
+        let (_, workspace) = match Self::find_usable(invite_repo, workspace_repo, token).await? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        if let Some(role) = MemberService::resolve_role(member_repo, &workspace, user_id).await? {
+            return Ok(Some(InviteAcceptance {
+                workspace,
+                role,
+                already_member: true,
+            }));
+        }
+
+        let invite = match invite_repo
+            .consume(&hash_token(token))
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+        {
+            Some(invite) => invite,
+            None => return Ok(None),
+        };
+        let workspace_id = workspace.id.ok_or("Workspace has no id")?;
+
+        if let Err(e) = member_repo
+            .create(WorkspaceMember {
+                id: None,
+                workspace_id,
+                user_id: *user_id,
+                role: invite.role,
+                invited_by: Some(invite.created_by),
+                created_at: None,
+                updated_at: None,
+            })
+            .await
+        {
+            let _ = invite_repo.release(&invite.invite_id).await;
+            return Err(format!("Database error: {}", e));
+        }
+
+        Ok(Some(InviteAcceptance {
+            workspace,
+            role: invite.role,
+            already_member: false,
+        }))
+    }
+}
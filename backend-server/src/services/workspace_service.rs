@@ -1,8 +1,12 @@
-use crate::models::room::Room;
+use crate::models::member::WorkspaceRole;
 use crate::models::workspace::{CreateWorkspaceRequest, UpdateWorkspaceRequest, Workspace};
+use crate::repositories::member_repo::MemberRepository;
 use crate::repositories::room_repo::RoomRepository;
+use crate::repositories::room_snapshot_repo::RoomSnapshotRepository;
+use crate::repositories::workspace_invite_repo::WorkspaceInviteRepository;
 use crate::repositories::workspace_repo::WorkspaceRepository;
-use dashmap::DashMap;
+use crate::services::room_bus_service::close_room;
+use crate::state::AppState;
 use mongodb::bson::oid::ObjectId;
 use uuid::Uuid;
 
@@ -33,14 +37,14 @@ impl WorkspaceService {
     pub async fn get_user_workspaces(
         repo: &WorkspaceRepository,
         owner_id: &ObjectId,
-        assigned_ws_ids: Vec<ObjectId>,
+        member_ws_ids: Vec<ObjectId>,
     ) -> Result<Vec<Workspace>, String> {
         let mut workspaces = repo
             .find_by_owner_id(owner_id)
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
-        for w_id in assigned_ws_ids {
+        for w_id in member_ws_ids {
             if !workspaces.iter().any(|w| w.id == Some(w_id)) {
                 if let Ok(Some(w)) = repo.find_by_id(&w_id).await {
                     workspaces.push(w);
@@ -53,6 +57,7 @@ impl WorkspaceService {
 
     pub async fn create_workspace(
         repo: &WorkspaceRepository,
+        member_repo: &MemberRepository,
         owner_id: &ObjectId,
         payload: CreateWorkspaceRequest,
     ) -> Result<Workspace, String> {
@@ -64,7 +69,7 @@ impl WorkspaceService {
             short_name: Self::resolve_short_name(&payload.name, payload.short_name.as_deref()),
             color: payload.color.clone(),
             icon: payload.icon.clone(),
-            owner_id: owner_id.clone(),
+            owner_id: *owner_id,
             room_code: room_code.clone(),
             created_at: chrono::Utc::now(),
             notification_config: None,
@@ -75,6 +80,13 @@ impl WorkspaceService {
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
+        if let Some(ws_id) = created_workspace.id {
+            member_repo
+                .insert_if_missing(&ws_id, owner_id, WorkspaceRole::Owner)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+
         Ok(created_workspace)
     }
 
@@ -110,7 +122,9 @@ impl WorkspaceService {
     pub async fn delete_workspace(
         workspace_repo: &WorkspaceRepository,
         room_repo: &RoomRepository,
-        rooms: &DashMap<String, Room>,
+        member_repo: &MemberRepository,
+        invite_repo: &WorkspaceInviteRepository,
+        state: &AppState,
         owner_id: &ObjectId,
         workspace_id: &ObjectId,
     ) -> Result<bool, String> {
@@ -133,13 +147,61 @@ impl WorkspaceService {
             .map_err(|e| format!("Database error: {}", e))?;
 
         if deleted {
-            // 3. Clean up room data from MongoDB (synced documents)
+            // 3. Clean up room data (synced documents), memberships and invites from MongoDB
             let _ = room_repo.delete_by_room_code(&room_code).await;
-
-            // 4. Remove in-memory room from DashMap
-            rooms.remove(&room_code);
+            let _ = RoomSnapshotRepository::new(&state.db)
+                .delete_by_room_code(&room_code)
+                .await;
+            let _ = member_repo.delete_by_workspace(workspace_id).await;
+            let _ = invite_repo.delete_by_workspace(workspace_id).await;
+
+            // 4. Remove in-memory room and disconnect its peers on every instance
+            close_room(state, &room_code, "workspace_deleted");
+            state.document_persister.discard(&room_code);
         }
 
         Ok(deleted)
     }
+
+    /// Gives the workspace a new room code. The synced document moves with it, and peers
+    /// still connected under the old code are told the room closed and disconnected.
+    /// Returns the new code, or `None` if the workspace isn't found or not owned by `owner_id`.
+    pub async fn rotate_room_code(
+        workspace_repo: &WorkspaceRepository,
+        room_repo: &RoomRepository,
+        state: &AppState,
+        owner_id: &ObjectId,
+        workspace: &Workspace,
+    ) -> Result<Option<String>, String> {
+        let workspace_id = workspace.id.ok_or("Workspace has no id")?;
+        let new_code = Uuid::new_v4().to_string();
+
+        let updated = workspace_repo
+            .update_room_code(&workspace_id, owner_id, &new_code)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if !updated {
+            return Ok(None);
+        }
+
+        // The room is closed first so no edit can be queued under the old code after the
+        // flush; the queued ones are saved there and move with the document
+        close_room(state, &workspace.room_code, "room_code_rotated");
+        state
+            .document_persister
+            .flush_room(&state.db, &workspace.room_code)
+            .await?;
+        room_repo
+            .rename(&workspace.room_code, &new_code)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        RoomSnapshotRepository::new(&state.db)
+            .rename(&workspace.room_code, &new_code)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        state.document_persister.discard(&workspace.room_code);
+
+        Ok(Some(new_code))
+    }
 }
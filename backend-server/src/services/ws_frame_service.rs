@@ -0,0 +1,121 @@
+use crate::models::message::{ErrorCode, ServerMessage};
+use axum::extract::ws::Message;
+use axum::http::HeaderMap;
+use sync_codec::{CodecError, FrameKind};
+
+pub use sync_codec::PROTOCOL as FRAME_PROTOCOL;
+
+/// How a connection's messages travel: JSON text frames, or binary `sync_codec` frames once
+/// `FRAME_PROTOCOL` is the subprotocol agreed on the upgrade. Clients may send either kind of
+/// frame regardless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wire {
+    Text,
+    Binary,
+}
+
+impl Wire {
+    /// Picks the subprotocol to echo, the first one the client offered that is `supported`,
+    /// and the wire format that goes with it, so the two always agree.
+    pub fn negotiate(
+        headers: &HeaderMap,
+        supported: &[&'static str],
+    ) -> (Self, Option<&'static str>) {
+        let selected = headers
+            .get(axum::http::header::SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .find_map(|offered| supported.iter().copied().find(|p| *p == offered))
+            });
+        let wire = if selected == Some(FRAME_PROTOCOL) {
+            Wire::Binary
+        } else {
+            Wire::Text
+        };
+        (wire, selected)
+    }
+
+    pub fn encode(self, msg: &ServerMessage) -> Message {
+        let json = serde_json::to_string(msg).unwrap();
+        match self {
+            Wire::Text => Message::Text(json),
+            Wire::Binary => Message::Binary(sync_codec::encode(frame_kind(msg), json.as_bytes())),
+        }
+    }
+}
+
+fn frame_kind(msg: &ServerMessage) -> FrameKind {
+    match msg {
+        ServerMessage::DocumentSync { .. } => FrameKind::Document,
+        ServerMessage::Operations { .. } => FrameKind::Operations,
+        _ => FrameKind::Message,
+    }
+}
+
+/// The JSON inside a client's binary frame. `max_len` applies to the decompressed message.
+pub fn decode_frame(frame: &[u8], max_len: usize) -> Result<String, (ErrorCode, String)> {
+    let (_, body) = sync_codec::decode(frame, max_len).map_err(|e| match e {
+        CodecError::TooLarge { .. } => (ErrorCode::MessageTooLarge, e.to_string()),
+        _ => (ErrorCode::InvalidMessage, e.to_string()),
+    })?;
+    String::from_utf8(body).map_err(|_| {
+        (
+            ErrorCode::InvalidMessage,
+            "Frame body is not UTF-8".to_string(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_crdt::VersionVector;
+
+    fn offer(protocols: &str) -> (Wire, Option<&'static str>) {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::header::SEC_WEBSOCKET_PROTOCOL,
+            protocols.parse().unwrap(),
+        );
+        Wire::negotiate(&headers, &[FRAME_PROTOCOL, "bearer"])
+    }
+
+    #[test]
+    fn binary_wire_is_opt_in_and_round_trips() {
+        assert_eq!(offer("bearer, token"), (Wire::Text, Some("bearer")));
+        assert_eq!(
+            offer(&format!("{}, bearer, token", FRAME_PROTOCOL)),
+            (Wire::Binary, Some(FRAME_PROTOCOL))
+        );
+
+        let msg = ServerMessage::DocumentSync {
+            document: "{\"1\":{}}".repeat(100),
+            version: VersionVector::new(),
+        };
+        let Message::Binary(frame) = Wire::Binary.encode(&msg) else {
+            panic!("expected a binary frame");
+        };
+        assert_eq!(frame[3], FrameKind::Document as u8);
+        let json = decode_frame(&frame, 1024 * 1024).unwrap();
+        assert_eq!(json, serde_json::to_string(&msg).unwrap());
+
+        let (code, _) = decode_frame(&frame, 16).unwrap_err();
+        assert_eq!(code, ErrorCode::MessageTooLarge);
+    }
+
+    #[test]
+    fn mixed_offer_follows_the_echoed_protocol() {
+        // The client is told `bearer`, so it must not get binary frames
+        assert_eq!(
+            offer(&format!("bearer, token, {}", FRAME_PROTOCOL)),
+            (Wire::Text, Some("bearer"))
+        );
+        assert_eq!(
+            Wire::negotiate(&HeaderMap::new(), &[FRAME_PROTOCOL, "bearer"]),
+            (Wire::Text, None)
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::models::message::{ClientMessage, ErrorCode};
+use std::time::Duration as StdDuration;
+use tokio::time::Instant;
+
+const DEFAULT_MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+const DEFAULT_BROADCAST_PER_SECOND: f64 = 20.0;
+const DEFAULT_SYNC_PER_SECOND: f64 = 5.0;
+const DEFAULT_MAX_STRIKES: u32 = 20;
+/// A peer's strikes are forgiven after this long without a new one
+const STRIKE_RESET: StdDuration = StdDuration::from_secs(60);
+/// Buckets hold this many seconds worth of messages, so short bursts pass
+const BURST_SECONDS: f64 = 2.0;
+
+/// Limits on what a single WebSocket connection may send.
+#[derive(Debug, Clone, Copy)]
+pub struct WsLimits {
+    pub max_message_bytes: usize,
+    pub broadcast_per_second: f64,
+    /// Shared by `sync_document` and `push_operations`
+    pub sync_per_second: f64,
+    /// Rejected messages tolerated before the connection is closed; 0 never closes it
+    pub max_strikes: u32,
+}
+
+impl WsLimits {
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(key: &str, default: T) -> T {
+            std::env::var(key)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(default)
+        }
+
+        Self {
+            max_message_bytes: var("WS_MAX_MESSAGE_BYTES", DEFAULT_MAX_MESSAGE_BYTES).max(1024),
+            broadcast_per_second: var("WS_BROADCAST_PER_SECOND", DEFAULT_BROADCAST_PER_SECOND)
+                .max(0.1),
+            sync_per_second: var("WS_SYNC_PER_SECOND", DEFAULT_SYNC_PER_SECOND).max(0.1),
+            max_strikes: var("WS_MAX_STRIKES", DEFAULT_MAX_STRIKES),
+        }
+    }
+}
+
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(per_second: f64, now: Instant) -> Self {
+        let capacity = (per_second * BURST_SECONDS).max(1.0);
+        Self {
+            capacity,
+            tokens: capacity,
+            per_second,
+            refilled_at: now,
+        }
+    }
+
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.refilled_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Per-connection enforcement of `WsLimits`.
+pub struct ConnectionLimiter {
+    limits: WsLimits,
+    broadcast: TokenBucket,
+    sync: TokenBucket,
+    strikes: u32,
+    last_strike: Option<Instant>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: WsLimits) -> Self {
+        let now = Instant::now();
+        Self {
+            limits,
+            broadcast: TokenBucket::new(limits.broadcast_per_second, now),
+            sync: TokenBucket::new(limits.sync_per_second, now),
+            strikes: 0,
+            last_strike: None,
+        }
+    }
+
+    pub fn check_size(&self, len: usize) -> Result<(), ErrorCode> {
+        if len > self.limits.max_message_bytes {
+            Err(ErrorCode::MessageTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn check_rate(&mut self, msg: &ClientMessage) -> Result<(), ErrorCode> {
+        let now = Instant::now();
+        let allowed = match msg {
+            ClientMessage::Broadcast { .. } => self.broadcast.try_take(now),
+            ClientMessage::SyncDocument { .. } | ClientMessage::PushOperations { .. } => {
+                self.sync.try_take(now)
+            }
+            _ => true,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(ErrorCode::RateLimited)
+        }
+    }
+
+    /// Counts a rejected message. Returns true once the peer should be disconnected.
+    pub fn strike(&mut self) -> bool {
+        let now = Instant::now();
+        if self
+            .last_strike
+            .is_some_and(|at| now.saturating_duration_since(at) >= STRIKE_RESET)
+        {
+            self.strikes = 0;
+        }
+        self.strikes += 1;
+        self.last_strike = Some(now);
+        self.limits.max_strikes > 0 && self.strikes >= self.limits.max_strikes
+    }
+}
+
+/// The start of `text` for logging, cut on a character boundary (Thai text is multi-byte).
+pub fn log_preview(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_bursts_then_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, start);
+        for _ in 0..4 {
+            assert!(bucket.try_take(start));
+        }
+        assert!(!bucket.try_take(start));
+        assert!(bucket.try_take(start + StdDuration::from_millis(500)));
+        assert!(!bucket.try_take(start + StdDuration::from_millis(500)));
+    }
+
+    #[test]
+    fn preview_never_splits_a_character() {
+        let thai = "สวัสดีครับ ทดสอบข้อความภาษาไทย";
+        assert_eq!(log_preview(thai, 5), "สวัสด");
+        assert_eq!(log_preview("short", 50), "short");
+    }
+}
@@ -1,20 +1,47 @@
 use dashmap::DashMap;
 use mongodb::Database;
+use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tokio::sync::RwLock;
 
 use crate::models::{message::SystemEvent, room::Room};
+use crate::services::client_ip_service::ProxySettings;
+use crate::services::event_feed_service::{RoomFeeds, StreamSession};
+use crate::services::login_throttle_service::LoginThrottleSettings;
+use crate::services::mail_service::Mailer;
+use crate::services::oidc_service::OidcProvider;
+use crate::services::persist_service::DocumentPersister;
+use crate::services::room_bus_service::RoomEventBus;
+use crate::services::room_service::{HeartbeatSettings, RoomMetrics};
+use crate::services::session_service::AuthSettings;
 use crate::services::storage_service::ActiveStorage;
+use crate::services::ws_limit_service::WsLimits;
 
 pub type SharedState = Arc<AppState>;
 
 pub struct AppState {
     pub db: Database,
     pub rooms: DashMap<String, Room>,
+    pub room_bus: RoomEventBus,
     pub room_idle_timeout_seconds: u64,
+    pub ws_heartbeat: HeartbeatSettings,
+    pub ws_limits: WsLimits,
+    /// Open WebSocket connections on this instance, joined to a room or not
+    pub ws_connections: AtomicUsize,
+    pub room_feeds: RoomFeeds,
+    /// Server-sent event streams open on this instance, by peer id
+    pub event_streams: DashMap<String, StreamSession>,
+    pub room_metrics: RoomMetrics,
+    pub document_persister: DocumentPersister,
     pub system_tx: broadcast::Sender<SystemEvent>,
     pub jwt_secret: String,
+    pub auth_settings: AuthSettings,
+    pub mailer: Mailer,
+    /// Present when single sign-on is configured
+    pub oidc: Option<OidcProvider>,
+    pub proxy: ProxySettings,
+    pub login_throttle: LoginThrottleSettings,
     pub storage: RwLock<ActiveStorage>,
 }
 
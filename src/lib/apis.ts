@@ -35,6 +35,87 @@ export const api = {
         body: JSON.stringify(payload),
       });
     },
+    loginTwoFactor: async (
+      challengeToken: string,
+      payload: { code?: string; recovery_code?: string },
+    ): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/login/2fa`, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        credentials: "include",
+        body: JSON.stringify({ challenge_token: challengeToken, ...payload }),
+      });
+    },
+    oidcConfig: async (): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/oidc/config`, {
+        headers: { Accept: "application/json" },
+        credentials: "include",
+      });
+    },
+    oidcAuthorize: async (): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/oidc/authorize`, {
+        headers: { Accept: "application/json" },
+        credentials: "include",
+      });
+    },
+    oidcCallback: async (code: string, state: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+      };
+      // Sent when linking, so the server can match the request to the signed-in user
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/oidc/callback`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+        body: JSON.stringify({ code, state }),
+      });
+    },
+    linkOidc: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/oidc/link`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+      });
+    },
+    unlinkOidc: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/oidc`, {
+        method: "DELETE",
+        headers,
+        credentials: "include",
+      });
+    },
     getSetupInfo: async (token: string): Promise<Response> => {
       return fetch(`${API_BASE_URL}/auth/setup-info?token=${token}`, {
         method: "GET",
@@ -53,9 +134,61 @@ export const api = {
         body: JSON.stringify({ token, password }),
       });
     },
+    forgotPassword: async (email: string): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/forgot-password`, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        credentials: "include",
+        body: JSON.stringify({ email }),
+      });
+    },
+    getResetInfo: async (token: string): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/reset-info?token=${token}`, {
+        method: "GET",
+        headers: { Accept: "application/json" },
+        credentials: "include",
+      });
+    },
+    resetPassword: async (
+      token: string,
+      password: string,
+    ): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/reset-password`, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        credentials: "include",
+        body: JSON.stringify({ token, password }),
+      });
+    },
+    verifyEmail: async (token: string): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/verify-email`, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        credentials: "include",
+        body: JSON.stringify({ token }),
+      });
+    },
+    refresh: async (): Promise<Response> => {
+      return fetch(`${API_BASE_URL}/auth/refresh`, {
+        method: "POST",
+        credentials: "include",
+      });
+    },
     logout: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {};
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
       return fetch(`${API_BASE_URL}/auth/logout`, {
         method: "POST",
+        headers,
         credentials: "include",
       });
     },
@@ -163,8 +296,284 @@ export const api = {
         body: JSON.stringify(payload),
       });
     },
+    twoFactorStatus: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/2fa`, {
+        headers,
+        credentials: "include",
+      });
+    },
+    setupTwoFactor: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/2fa/setup`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+      });
+    },
+    enableTwoFactor: async (code: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+      };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/2fa/enable`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+        body: JSON.stringify({ code }),
+      });
+    },
+    disableTwoFactor: async (payload: {
+      password: string;
+      code?: string;
+      recovery_code?: string;
+    }): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+      };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/2fa/disable`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+        body: JSON.stringify(payload),
+      });
+    },
+    regenerateRecoveryCodes: async (code: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+      };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/2fa/recovery-codes`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+        body: JSON.stringify({ code }),
+      });
+    },
+    listAccessTokens: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/tokens`, {
+        headers,
+        credentials: "include",
+      });
+    },
+    createAccessToken: async (payload: {
+      name: string;
+      workspace_ids: string[];
+      permission: "read" | "write";
+      expires_in_days?: number;
+    }): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+      };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/tokens`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+        body: JSON.stringify(payload),
+      });
+    },
+    revokeAccessToken: async (tokenId: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/me/tokens/${tokenId}`, {
+        method: "DELETE",
+        headers,
+        credentials: "include",
+      });
+    },
+    resetUserTwoFactor: async (id: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/users/${id}/2fa`, {
+        method: "DELETE",
+        headers,
+        credentials: "include",
+      });
+    },
+    unlockUser: async (id: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/users/${id}/unlock`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+      });
+    },
+    impersonateUser: async (id: string, reason?: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+      };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/users/${id}/impersonate`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+        body: JSON.stringify({ reason }),
+      });
+    },
+    stopImpersonation: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/auth/impersonation/stop`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+      });
+    },
   },
   admin: {
+    securityPolicy: async (): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/admin/security/policy`, {
+        headers,
+        credentials: "include",
+      });
+    },
+    updateSecurityPolicy: async (payload: Record<string, any>): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+      };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+
+      return fetch(`${API_BASE_URL}/admin/security/policy`, {
+        method: "PUT",
+        headers,
+        credentials: "include",
+        body: JSON.stringify(payload),
+      });
+    },
     storageConfig: async (): Promise<Response> => {
       let token = "";
       if (typeof document !== "undefined") {
@@ -563,6 +972,107 @@ export const api = {
         credentials: "include",
       });
     },
+    rotateRoomCode: async (id: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+      return fetch(`${API_BASE_URL}/workspaces/${id}/room-code/rotate`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+      });
+    },
+    getInvites: async (wsId: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+      return fetch(`${API_BASE_URL}/workspaces/${wsId}/invites`, {
+        headers,
+        credentials: "include",
+      });
+    },
+    createInvite: async (
+      wsId: string,
+      invite: { role?: string; expires_in_hours?: number; max_uses?: number },
+    ): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+      const headers: Record<string, string> = {
+        "Content-Type": "application/json",
+        Accept: "application/json",
+      };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+      return fetch(`${API_BASE_URL}/workspaces/${wsId}/invites`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+        body: JSON.stringify(invite),
+      });
+    },
+    revokeInvite: async (wsId: string, inviteId: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+      return fetch(`${API_BASE_URL}/workspaces/${wsId}/invites/${inviteId}`, {
+        method: "DELETE",
+        headers,
+        credentials: "include",
+      });
+    },
+    previewInvite: async (inviteToken: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+      return fetch(`${API_BASE_URL}/invites/${inviteToken}`, {
+        headers,
+        credentials: "include",
+      });
+    },
+    acceptInvite: async (inviteToken: string): Promise<Response> => {
+      let token = "";
+      if (typeof document !== "undefined") {
+        const match = document.cookie.match(
+          new RegExp("(^| )_khun_ph_token=([^;]+)"),
+        );
+        if (match) token = match[2];
+      }
+      const headers: Record<string, string> = { Accept: "application/json" };
+      if (token) headers["Authorization"] = `Bearer ${token}`;
+      return fetch(`${API_BASE_URL}/invites/${inviteToken}/accept`, {
+        method: "POST",
+        headers,
+        credentials: "include",
+      });
+    },
   },
 
   // Workspace-scoped data APIs
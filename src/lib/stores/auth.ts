@@ -16,6 +16,8 @@ export interface User {
   role: string;
   discord_id?: string;
   profile?: UserProfile;
+  /** Set while an admin is viewing the app as this user */
+  impersonator?: { id: string; email: string };
 }
 
 export const user = writable<User | null>(null);
@@ -29,12 +31,82 @@ export function getCookie(name: string) {
   return null;
 }
 
+let refreshTimer: ReturnType<typeof setTimeout> | null = null;
+
+/**
+ * Stores the access token returned by login/refresh and schedules the next refresh. The
+ * refresh token is an HttpOnly cookie the server sets, which scripts can't read.
+ */
+export function storeSession(data: { token: string; expires_in?: number }) {
+  if (!browser) return;
+  const expiresIn = data.expires_in || 60 * 15;
+  document.cookie = `_khun_ph_token=${data.token}; path=/; max-age=${expiresIn}; samesite=Lax`;
+
+  if (refreshTimer) clearTimeout(refreshTimer);
+  // Refresh a minute before the access token expires
+  refreshTimer = setTimeout(
+    () => {
+      refreshSession();
+    },
+    Math.max(expiresIn - 60, 10) * 1000,
+  );
+}
+
+export function clearSession() {
+  if (!browser) return;
+  if (refreshTimer) clearTimeout(refreshTimer);
+  refreshTimer = null;
+  // The refresh cookie is cleared by the server on logout
+  document.cookie = "_khun_ph_token=; path=/; max-age=0; samesite=Lax";
+}
+
+/**
+ * Switches to an impersonation token. The admin's refresh cookie is left alone, so
+ * refreshing the session afterwards returns to the admin's own account.
+ */
+export function startImpersonation(data: { token: string; expires_in?: number }) {
+  if (!browser) return;
+  if (refreshTimer) clearTimeout(refreshTimer);
+  refreshTimer = null;
+  const expiresIn = data.expires_in || 60 * 30;
+  document.cookie = `_khun_ph_token=${data.token}; path=/; max-age=${expiresIn}; samesite=Lax`;
+}
+
+export async function stopImpersonation() {
+  if (!browser) return;
+  try {
+    await api.auth.stopImpersonation();
+  } catch (e) {
+    console.error("Failed to end impersonation:", e);
+  }
+  document.cookie = "_khun_ph_token=; path=/; max-age=0; samesite=Lax";
+  await initAuth();
+}
+
+export async function refreshSession(): Promise<boolean> {
+  try {
+    const res = await api.auth.refresh();
+    if (!res.ok) {
+      clearSession();
+      return false;
+    }
+    storeSession(await res.json());
+    return true;
+  } catch (e) {
+    console.error("Session refresh failed:", e);
+    return false;
+  }
+}
+
 export async function initAuth() {
   if (!browser) return;
 
   authLoading.set(true);
   try {
-    const token = getCookie("_khun_ph_token");
+    let token = getCookie("_khun_ph_token");
+    if (!token && (await refreshSession())) {
+      token = getCookie("_khun_ph_token");
+    }
     if (token) {
       // First, set user from local storage for instant UI render
       const cachedEmail = localStorage.getItem("user_email");
@@ -57,7 +129,10 @@ export async function initAuth() {
       }
 
       // Then quietly verify with backend to ensure the token isn't expired/fake
-      const res = await api.auth.me();
+      let res = await api.auth.me();
+      if (res.status === 401 && (await refreshSession())) {
+        res = await api.auth.me();
+      }
       if (res.ok) {
         const data = await res.json();
         user.set({
@@ -67,6 +142,7 @@ export async function initAuth() {
           role: data.role || "user",
           discord_id: data.discord_id,
           profile: data.profile,
+          impersonator: data.impersonator || undefined,
         });
         localStorage.setItem("user_email", data.email);
         localStorage.setItem("user_id", data.id);
@@ -79,7 +155,7 @@ export async function initAuth() {
           localStorage.setItem("user_profile", JSON.stringify(data.profile));
       } else {
         // Token is invalid/expired according to backend
-        document.cookie = "_khun_ph_token=; path=/; max-age=0; samesite=Lax";
+        clearSession();
         localStorage.removeItem("user_email");
         localStorage.removeItem("user_id");
         user.set(null);
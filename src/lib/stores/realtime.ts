@@ -13,6 +13,9 @@ export type RealtimeStatus = "disconnected" | "connecting" | "connected";
 export const realtimeStatus = writable<RealtimeStatus>("disconnected");
 export const realtimePeers = writable<number>(0);
 
+/** Set when the server closes the room, e.g. after the owner rotated its room code */
+export const roomClosed = writable<string | null>(null);
+
 /** Fires whenever remote data changes so the UI can update instantly */
 export const dataChanged = writable<{
   entity: string;
@@ -38,24 +41,34 @@ export function connectRealtime(
 
   disconnectRealtime();
   currentRoomCode = roomCode;
+  roomClosed.set(null);
   _onDataChanged = onDataChanged || null;
 
   realtimeStatus.set("connecting");
 
   try {
-    ws = new WebSocket(WS_BASE);
+    // Browsers can't send an Authorization header on a WebSocket, so the token travels as
+    // the subprotocol pair ["bearer", token]
+    let token = "";
+    if (typeof document !== "undefined") {
+      const match = document.cookie.match(
+        new RegExp("(^| )_khun_ph_token=([^;]+)"),
+      );
+      if (match) token = match[2];
+    }
+    ws = token
+      ? new WebSocket(WS_BASE, ["bearer", token])
+      : new WebSocket(WS_BASE);
 
     ws.onopen = () => {
       console.log("🔗 Realtime: connected");
       realtimeStatus.set("connected");
 
-      // Join the room (MATCHES RUST EXPECTATIONS)
+      // Join the room (MATCHES RUST EXPECTATIONS); the server assigns the peer id and host
       ws?.send(
         JSON.stringify({
           action: "join",
           room_code: roomCode,
-          peer_id: generatePeerId(),
-          is_host: false,
           metadata: null,
         }),
       );
@@ -158,6 +171,17 @@ function handleMessage(msg: any) {
       if (count > 0) realtimePeers.set(count - 1);
       break;
 
+    case "room_closed":
+      // The old room code no longer works, so don't auto-reconnect with it
+      currentRoomCode = null;
+      roomClosed.set(msg.reason || "closed");
+      break;
+
+    case "kicked":
+      // The socket closes next; reconnecting joins the room again
+      console.warn(`Realtime: removed from the room by an admin (${msg.reason})`);
+      break;
+
     case "data":
       // Message comes wrapped inside the 'data' field
       try {
@@ -183,12 +207,3 @@ function handleMessage(msg: any) {
       break;
   }
 }
-
-function generatePeerId(): string {
-  return (
-    "peer_" +
-    Math.random().toString(36).substring(2, 8) +
-    "_" +
-    Date.now().toString(36)
-  );
-}
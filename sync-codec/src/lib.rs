@@ -0,0 +1,184 @@
+//! Binary WebSocket frame format shared by the browser (through `wasm-compress`) and the
+//! sync server, so both ends agree on it.
+//!
+//! A frame is a 4-byte header followed by the body:
+//!
+//! | byte | meaning                                   |
+//! |------|-------------------------------------------|
+//! | 0    | magic `b'K'`                              |
+//! | 1    | format version, currently `1`             |
+//! | 2    | [`Codec`] of the body                     |
+//! | 3    | [`FrameKind`] of the message              |
+//!
+//! The body is the UTF-8 JSON of the message, as it would be sent in a text frame. With
+//! [`Codec::Lz4`] it is an LZ4 block prefixed with the uncompressed length (u32, little
+//! endian), the same layout `wasm-compress` uses for its stored data.
+
+use lz4_flex::{compress_prepend_size, decompress_size_prepended};
+use std::fmt;
+
+/// `Sec-WebSocket-Protocol` value a client offers to send and receive binary frames.
+pub const PROTOCOL: &str = "khun-phaen.frame.v1";
+pub const HEADER_LEN: usize = 4;
+const MAGIC: u8 = b'K';
+const VERSION: u8 = 1;
+/// Smaller bodies are sent raw; LZ4 rarely pays off for them
+const MIN_COMPRESS_LEN: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Codec {
+    Raw = 0,
+    Lz4 = 1,
+}
+
+impl Codec {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Raw),
+            1 => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+}
+
+/// What the frame carries, so a receiver can route or log it without decompressing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameKind {
+    /// Any message not listed below
+    Message = 0,
+    /// `sync_document` from a client, `document_sync` from the server
+    Document = 1,
+    /// `push_operations` from a client, `operations` from the server
+    Operations = 2,
+}
+
+impl FrameKind {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Message),
+            1 => Some(Self::Document),
+            2 => Some(Self::Operations),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodecError {
+    TooShort,
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownCodec(u8),
+    UnknownKind(u8),
+    /// The body would decode to more than the receiver accepts
+    TooLarge { len: usize, max: usize },
+    Corrupt(String),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort => write!(f, "Frame is shorter than its header"),
+            Self::BadMagic => write!(f, "Not a sync frame"),
+            Self::UnsupportedVersion(v) => write!(f, "Unsupported frame version {}", v),
+            Self::UnknownCodec(c) => write!(f, "Unknown frame codec {}", c),
+            Self::UnknownKind(k) => write!(f, "Unknown frame kind {}", k),
+            Self::TooLarge { len, max } => {
+                write!(f, "Frame decodes to {} bytes, limit is {}", len, max)
+            }
+            Self::Corrupt(e) => write!(f, "Corrupt frame body: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+/// Encodes a message body, compressing it when that makes the frame smaller.
+pub fn encode(kind: FrameKind, body: &[u8]) -> Vec<u8> {
+    let compressed = (body.len() >= MIN_COMPRESS_LEN)
+        .then(|| compress_prepend_size(body))
+        .filter(|compressed| compressed.len() < body.len());
+    let (codec, payload) = match &compressed {
+        Some(compressed) => (Codec::Lz4, compressed.as_slice()),
+        None => (Codec::Raw, body),
+    };
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(&[MAGIC, VERSION, codec as u8, kind as u8]);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Decodes a frame into its kind and body. `max_len` bounds the decoded body, and is checked
+/// before anything is decompressed.
+pub fn decode(frame: &[u8], max_len: usize) -> Result<(FrameKind, Vec<u8>), CodecError> {
+    let Some((header, payload)) = frame.split_first_chunk::<HEADER_LEN>() else {
+        return Err(CodecError::TooShort);
+    };
+    let [magic, version, codec, kind] = *header;
+    if magic != MAGIC {
+        return Err(CodecError::BadMagic);
+    }
+    if version != VERSION {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+    let codec = Codec::from_byte(codec).ok_or(CodecError::UnknownCodec(codec))?;
+    let kind = FrameKind::from_byte(kind).ok_or(CodecError::UnknownKind(kind))?;
+
+    let body = match codec {
+        Codec::Raw => {
+            if payload.len() > max_len {
+                return Err(CodecError::TooLarge {
+                    len: payload.len(),
+                    max: max_len,
+                });
+            }
+            payload.to_vec()
+        }
+        Codec::Lz4 => {
+            let Some(size) = payload.first_chunk::<4>() else {
+                return Err(CodecError::TooShort);
+            };
+            let len = u32::from_le_bytes(*size) as usize;
+            if len > max_len {
+                return Err(CodecError::TooLarge { len, max: max_len });
+            }
+            decompress_size_prepended(payload)
+                .map_err(|e| CodecError::Corrupt(e.to_string()))?
+        }
+    };
+    Ok((kind, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip_and_compress_large_bodies() {
+        let small = br#"{"type":"ping"}"#;
+        let frame = encode(FrameKind::Message, small);
+        assert_eq!(frame[2], Codec::Raw as u8);
+        assert_eq!(decode(&frame, 1024).unwrap(), (FrameKind::Message, small.to_vec()));
+
+        let large = format!(r#"{{"type":"sync_document","document":"{}"}}"#, "งาน ".repeat(500));
+        let frame = encode(FrameKind::Document, large.as_bytes());
+        assert_eq!(frame[2], Codec::Lz4 as u8);
+        assert!(frame.len() < large.len());
+        let (kind, body) = decode(&frame, large.len()).unwrap();
+        assert_eq!(kind, FrameKind::Document);
+        assert_eq!(body, large.as_bytes());
+
+        assert_eq!(
+            decode(&frame, 100),
+            Err(CodecError::TooLarge {
+                len: large.len(),
+                max: 100
+            })
+        );
+        assert_eq!(decode(b"K\x01", 100), Err(CodecError::TooShort));
+        assert_eq!(decode(b"{\"type\"", 100), Err(CodecError::BadMagic));
+    }
+}
@@ -84,6 +84,32 @@ pub fn get_compression_ratio(original: &str, compressed: &str) -> f32 {
     ((original_len - compressed_len) as f32 / original_len as f32) * 100.0
 }
 
+/// Largest message `decode_frame` will inflate
+const MAX_FRAME_BODY: usize = 64 * 1024 * 1024;
+
+/// `Sec-WebSocket-Protocol` to offer for binary sync frames
+#[wasm_bindgen]
+pub fn frame_protocol() -> String {
+    sync_codec::PROTOCOL.to_string()
+}
+
+/// Encode a JSON sync message as a binary WebSocket frame (LZ4 when it helps).
+/// `kind`: 0 = other message, 1 = sync_document, 2 = push_operations
+#[wasm_bindgen]
+pub fn encode_frame(kind: u8, json: &str) -> Result<Vec<u8>, JsValue> {
+    let kind = sync_codec::FrameKind::from_byte(kind)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown frame kind {}", kind)))?;
+    Ok(sync_codec::encode(kind, json.as_bytes()))
+}
+
+/// Decode a binary WebSocket frame from the server back into its JSON message
+#[wasm_bindgen]
+pub fn decode_frame(frame: &[u8]) -> Result<String, JsValue> {
+    let (_, body) = sync_codec::decode(frame, MAX_FRAME_BODY)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    String::from_utf8(body).map_err(|e| JsValue::from_str(&format!("UTF-8 decode error: {}", e)))
+}
+
 /// Simple base64 encode
 fn base64_encode(data: &[u8]) -> String {
     const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
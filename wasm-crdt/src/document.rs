@@ -0,0 +1,754 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Highest Lamport counter seen from each node
+pub type VersionVector = BTreeMap<String, u64>;
+
+/// Operations kept for incremental sync. Peers that fall further behind get a snapshot.
+const HISTORY_LIMIT: usize = 10_000;
+
+/// How far past the local counter a remote timestamp may be. Counters only grow by one per
+/// edit, so anything further ahead is forged; accepting it would pin the field it touches.
+const MAX_COUNTER_LEAD: u64 = 1 << 32;
+
+/// Lamport Timestamp for ordering
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LamportTimestamp {
+    pub counter: u64,
+    pub node_id: String,
+}
+
+impl LamportTimestamp {
+    pub fn new(counter: u64, node_id: &str) -> Self {
+        Self {
+            counter,
+            node_id: node_id.to_string(),
+        }
+    }
+
+    pub fn increment(&mut self) {
+        self.counter = self.counter.saturating_add(1);
+    }
+}
+
+/// CRDT Operation for tasks
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Operation {
+    Insert {
+        task_id: u32,
+        field: String,
+        value: String,
+        timestamp: LamportTimestamp,
+    },
+    Update {
+        task_id: u32,
+        field: String,
+        value: String,
+        timestamp: LamportTimestamp,
+    },
+    Delete {
+        task_id: u32,
+        timestamp: LamportTimestamp,
+    },
+    /// Undoes a `Delete` with an older timestamp
+    Restore {
+        task_id: u32,
+        timestamp: LamportTimestamp,
+    },
+}
+
+impl Operation {
+    pub fn timestamp(&self) -> &LamportTimestamp {
+        match self {
+            Operation::Insert { timestamp, .. }
+            | Operation::Update { timestamp, .. }
+            | Operation::Delete { timestamp, .. }
+            | Operation::Restore { timestamp, .. } => timestamp,
+        }
+    }
+}
+
+/// True if `a` has seen everything `b` has
+pub fn dominates(a: &VersionVector, b: &VersionVector) -> bool {
+    b.iter()
+        .all(|(node, counter)| a.get(node).copied().unwrap_or(0) >= *counter)
+}
+
+/// CRDT Document for a Task
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrdtTask {
+    pub id: u32,
+    pub fields: HashMap<String, CrdtValue>,
+    pub deleted: bool,
+    pub created_at: LamportTimestamp,
+    pub updated_at: LamportTimestamp,
+    /// When the task was last brought back after a deletion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored_at: Option<LamportTimestamp>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrdtValue {
+    pub value: String,
+    pub timestamp: LamportTimestamp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DocumentStats {
+    pub node_id: String,
+    pub active_tasks: usize,
+    pub deleted_tasks: usize,
+    pub pending_operations: usize,
+    pub counter: u64,
+}
+
+/// CRDT Document Store
+#[derive(Clone, Debug)]
+pub struct CrdtDocument {
+    node_id: String,
+    counter: u64,
+    tasks: HashMap<u32, CrdtTask>,
+    operations: Vec<Operation>,
+    version: VersionVector,
+    /// Operations that changed this document, local or remote, oldest first
+    history: Vec<Operation>,
+    /// Version already covered before `history` starts (import or trimmed history)
+    history_base: VersionVector,
+}
+
+impl CrdtDocument {
+    pub fn new(node_id: String) -> Self {
+        Self {
+            node_id,
+            counter: 0,
+            tasks: HashMap::new(),
+            operations: Vec::new(),
+            version: VersionVector::new(),
+            history: Vec::new(),
+            history_base: VersionVector::new(),
+        }
+    }
+
+    fn new_timestamp(&mut self) -> LamportTimestamp {
+        self.counter = self.counter.saturating_add(1);
+        LamportTimestamp::new(self.counter, &self.node_id)
+    }
+
+    /// False for a remote timestamp too far ahead of this document to be genuine.
+    fn plausible(&self, timestamp: &LamportTimestamp) -> bool {
+        timestamp.counter <= self.counter.saturating_add(MAX_COUNTER_LEAD)
+    }
+
+    /// Lamport rule: after seeing a remote timestamp, local ones must sort after it.
+    fn observe(&mut self, timestamp: &LamportTimestamp) {
+        self.counter = self.counter.max(timestamp.counter);
+        let seen = self.version.entry(timestamp.node_id.clone()).or_insert(0);
+        *seen = (*seen).max(timestamp.counter);
+    }
+
+    fn record(&mut self, op: Operation) {
+        self.observe(&op.timestamp().clone());
+        self.history.push(op);
+
+        if self.history.len() > HISTORY_LIMIT {
+            let excess = self.history.len() - HISTORY_LIMIT;
+            for dropped in self.history.drain(..excess) {
+                let ts = dropped.timestamp();
+                let base = self.history_base.entry(ts.node_id.clone()).or_insert(0);
+                *base = (*base).max(ts.counter);
+            }
+        }
+    }
+
+    /// Insert or update a task field. Returns false if an existing value was newer.
+    pub fn upsert_field(&mut self, task_id: u32, field: String, value: String) -> bool {
+        let timestamp = self.new_timestamp();
+
+        let task = self.tasks.entry(task_id).or_insert_with(|| CrdtTask {
+            id: task_id,
+            fields: HashMap::new(),
+            deleted: false,
+            created_at: timestamp.clone(),
+            updated_at: timestamp.clone(),
+            restored_at: None,
+        });
+
+        // CRDT: Keep the value with higher timestamp (last-write-wins)
+        let should_update = match task.fields.get(&field) {
+            Some(existing) => timestamp > existing.timestamp,
+            None => true,
+        };
+
+        if should_update {
+            task.fields.insert(
+                field.clone(),
+                CrdtValue {
+                    value: value.clone(),
+                    timestamp: timestamp.clone(),
+                },
+            );
+            task.updated_at = timestamp.clone();
+
+            let op = if task.fields.len() == 1 && field == "title" {
+                Operation::Insert {
+                    task_id,
+                    field,
+                    value,
+                    timestamp,
+                }
+            } else {
+                Operation::Update {
+                    task_id,
+                    field,
+                    value,
+                    timestamp,
+                }
+            };
+
+            self.operations.push(op.clone());
+            self.record(op);
+        }
+        should_update
+    }
+
+    /// Delete a task (soft delete). Returns false if the task doesn't exist.
+    pub fn delete_task(&mut self, task_id: u32) -> bool {
+        let timestamp = self.new_timestamp();
+
+        match self.tasks.get_mut(&task_id) {
+            Some(task) => {
+                task.deleted = true;
+                task.updated_at = timestamp.clone();
+
+                let op = Operation::Delete { task_id, timestamp };
+                self.operations.push(op.clone());
+                self.record(op);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Bring back a deleted task. Returns false if it doesn't exist or isn't deleted.
+    pub fn restore_task(&mut self, task_id: u32) -> bool {
+        if !self.tasks.get(&task_id).is_some_and(|task| task.deleted) {
+            return false;
+        }
+        let timestamp = self.new_timestamp();
+        if let Some(task) = self.tasks.get_mut(&task_id) {
+            task.deleted = false;
+            task.updated_at = timestamp.clone();
+            task.restored_at = Some(timestamp.clone());
+        }
+
+        let op = Operation::Restore { task_id, timestamp };
+        self.operations.push(op.clone());
+        self.record(op);
+        true
+    }
+
+    /// Brings the document back to an earlier `export` by writing its values again as new
+    /// local edits, so the result wins over the current state wherever it is merged. Tasks
+    /// deleted since the snapshot are restored and their ids returned. Fields added since the
+    /// snapshot are kept, since fields can't be removed.
+    pub fn revert_to(&mut self, snapshot_json: &str) -> Result<Vec<u32>, String> {
+        let snapshot: HashMap<u32, CrdtTask> =
+            serde_json::from_str(snapshot_json).map_err(|e| format!("Parse error: {}", e))?;
+
+        let added_since: Vec<u32> = self
+            .tasks
+            .values()
+            .filter(|task| !task.deleted && snapshot.get(&task.id).is_none_or(|t| t.deleted))
+            .map(|task| task.id)
+            .collect();
+        for task_id in added_since {
+            self.delete_task(task_id);
+        }
+
+        let mut restored = Vec::new();
+        for (task_id, task) in snapshot.iter().filter(|(_, task)| !task.deleted) {
+            if self.restore_task(*task_id) {
+                restored.push(*task_id);
+            }
+            // Title first, so a re-created task starts with an Insert like a new one
+            let mut fields: Vec<(&String, &CrdtValue)> = task.fields.iter().collect();
+            fields.sort_by_key(|(field, _)| (field.as_str() != "title", field.as_str()));
+            for (field, value) in fields {
+                let unchanged = self
+                    .tasks
+                    .get(task_id)
+                    .and_then(|current| current.fields.get(field))
+                    .is_some_and(|current| current.value == value.value);
+                if !unchanged {
+                    self.upsert_field(*task_id, field.clone(), value.value.clone());
+                }
+            }
+        }
+
+        restored.sort_unstable();
+        Ok(restored)
+    }
+
+    /// All non-deleted tasks
+    pub fn tasks(&self) -> Vec<&CrdtTask> {
+        self.tasks.values().filter(|t| !t.deleted).collect()
+    }
+
+    pub fn task(&self, task_id: u32) -> Option<&CrdtTask> {
+        self.tasks.get(&task_id).filter(|t| !t.deleted)
+    }
+
+    /// Merge another exported document into this one. Values it adopts are added to the
+    /// history as operations so they still reach peers that sync incrementally. A document
+    /// with a timestamp implausibly far ahead is rejected whole.
+    pub fn merge(&mut self, other_json: &str) -> Result<(), String> {
+        let other: HashMap<u32, CrdtTask> =
+            serde_json::from_str(other_json).map_err(|e| format!("Parse error: {}", e))?;
+        let forged = other.values().any(|task| {
+            [&task.created_at, &task.updated_at]
+                .into_iter()
+                .chain(task.restored_at.as_ref())
+                .chain(task.fields.values().map(|value| &value.timestamp))
+                .any(|timestamp| !self.plausible(timestamp))
+        });
+        if forged {
+            return Err("Timestamp too far ahead".to_string());
+        }
+
+        for (task_id, other_task) in other {
+            self.observe(&other_task.updated_at);
+            for value in other_task.fields.values() {
+                self.observe(&value.timestamp);
+            }
+
+            let mut adopted = Vec::new();
+            match self.tasks.get_mut(&task_id) {
+                Some(local_task) => {
+                    // Merge fields using LWW (Last-Write-Wins)
+                    for (field, other_value) in &other_task.fields {
+                        let newer = match local_task.fields.get(field) {
+                            Some(local_value) => other_value.timestamp > local_value.timestamp,
+                            None => true,
+                        };
+                        if newer {
+                            local_task.fields.insert(field.clone(), other_value.clone());
+                            adopted.push(Operation::Update {
+                                task_id,
+                                field: field.clone(),
+                                value: other_value.value.clone(),
+                                timestamp: other_value.timestamp.clone(),
+                            });
+                        }
+                    }
+
+                    // Handle deletion, and restores newer than it
+                    if other_task.deleted && other_task.updated_at > local_task.updated_at {
+                        local_task.deleted = true;
+                        adopted.push(Operation::Delete {
+                            task_id,
+                            timestamp: other_task.updated_at.clone(),
+                        });
+                    }
+                    match &other_task.restored_at {
+                        Some(restored_at)
+                            if !other_task.deleted
+                                && local_task.deleted
+                                && *restored_at > local_task.updated_at =>
+                        {
+                            local_task.deleted = false;
+                            local_task.restored_at = Some(restored_at.clone());
+                            adopted.push(Operation::Restore {
+                                task_id,
+                                timestamp: restored_at.clone(),
+                            });
+                        }
+                        _ => {}
+                    }
+
+                    // Update timestamps
+                    if other_task.updated_at > local_task.updated_at {
+                        local_task.updated_at = other_task.updated_at.clone();
+                    }
+                }
+                None => {
+                    // Task doesn't exist locally, add it
+                    if !other_task.deleted {
+                        for (field, value) in &other_task.fields {
+                            adopted.push(Operation::Update {
+                                task_id,
+                                field: field.clone(),
+                                value: value.value.clone(),
+                                timestamp: value.timestamp.clone(),
+                            });
+                        }
+                        self.tasks.insert(task_id, other_task);
+                    }
+                }
+            }
+
+            for op in adopted {
+                self.record(op);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get document state as JSON for syncing
+    pub fn export(&self) -> String {
+        serde_json::to_string(&self.tasks).unwrap_or_default()
+    }
+
+    /// Import document state from JSON
+    pub fn import(&mut self, json: &str) -> Result<(), String> {
+        let tasks: HashMap<u32, CrdtTask> =
+            serde_json::from_str(json).map_err(|e| format!("Import error: {}", e))?;
+
+        // A snapshot carries no history; peers behind it have to take a snapshot too
+        self.tasks = HashMap::new();
+        self.version = VersionVector::new();
+        self.history.clear();
+        for task in tasks.values() {
+            self.observe(&task.created_at);
+            self.observe(&task.updated_at);
+            for value in task.fields.values() {
+                self.observe(&value.timestamp);
+            }
+        }
+        self.tasks = tasks;
+        self.history_base = self.version.clone();
+        Ok(())
+    }
+
+    /// Operations since last sync
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Apply remote operations given as a JSON array
+    pub fn apply_operations(&mut self, ops_json: &str) -> Result<(), String> {
+        let ops: Vec<Operation> =
+            serde_json::from_str(ops_json).map_err(|e| format!("Parse error: {}", e))?;
+        self.apply_remote_operations(ops);
+        Ok(())
+    }
+
+    /// Apply remote operations and return the ones that changed the document. Operations
+    /// that lost to a newer value are counted as seen but not kept; ones with a timestamp
+    /// implausibly far ahead are dropped unseen.
+    pub fn apply_remote_operations(&mut self, ops: Vec<Operation>) -> Vec<Operation> {
+        let mut applied = Vec::new();
+
+        for op in ops {
+            if !self.plausible(op.timestamp()) {
+                continue;
+            }
+            self.observe(op.timestamp());
+            let changed = match op.clone() {
+                Operation::Insert {
+                    task_id,
+                    field,
+                    value,
+                    timestamp,
+                }
+                | Operation::Update {
+                    task_id,
+                    field,
+                    value,
+                    timestamp,
+                } => self.apply_field_update(task_id, field, value, timestamp),
+                Operation::Delete { task_id, timestamp } => self.apply_deletion(task_id, timestamp),
+                Operation::Restore { task_id, timestamp } => self.apply_restore(task_id, timestamp),
+            };
+            if changed {
+                self.record(op.clone());
+                applied.push(op);
+            }
+        }
+
+        applied
+    }
+
+    /// Versions seen so far, per node
+    pub fn version(&self) -> &VersionVector {
+        &self.version
+    }
+
+    /// Operations a peer at `since` is missing, or `None` if the history no longer reaches
+    /// back that far and the peer needs a full snapshot (`export`) instead.
+    pub fn operations_since(&self, since: &VersionVector) -> Option<Vec<Operation>> {
+        if !dominates(since, &self.history_base) {
+            return None;
+        }
+        Some(
+            self.history
+                .iter()
+                .filter(|op| {
+                    let ts = op.timestamp();
+                    ts.counter > since.get(&ts.node_id).copied().unwrap_or(0)
+                })
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn apply_field_update(
+        &mut self,
+        task_id: u32,
+        field: String,
+        value: String,
+        timestamp: LamportTimestamp,
+    ) -> bool {
+        let task = self.tasks.entry(task_id).or_insert_with(|| CrdtTask {
+            id: task_id,
+            fields: HashMap::new(),
+            deleted: false,
+            created_at: timestamp.clone(),
+            updated_at: timestamp.clone(),
+            restored_at: None,
+        });
+
+        match task.fields.get(&field) {
+            Some(existing) if existing.timestamp >= timestamp => {
+                // Local value is newer (or this exact write), keep it
+                false
+            }
+            _ => {
+                task.fields.insert(field, CrdtValue { value, timestamp });
+                true
+            }
+        }
+    }
+
+    fn apply_deletion(&mut self, task_id: u32, timestamp: LamportTimestamp) -> bool {
+        match self.tasks.get_mut(&task_id) {
+            Some(task) if timestamp > task.updated_at => {
+                task.deleted = true;
+                task.updated_at = timestamp;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn apply_restore(&mut self, task_id: u32, timestamp: LamportTimestamp) -> bool {
+        match self.tasks.get_mut(&task_id) {
+            Some(task) if task.deleted && timestamp > task.updated_at => {
+                task.deleted = false;
+                task.updated_at = timestamp.clone();
+                task.restored_at = Some(timestamp);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Clear all operations (after successful sync)
+    pub fn clear_operations(&mut self) {
+        self.operations.clear();
+    }
+
+    pub fn stats(&self) -> DocumentStats {
+        DocumentStats {
+            node_id: self.node_id.clone(),
+            active_tasks: self.tasks.values().filter(|t| !t.deleted).count(),
+            deleted_tasks: self.tasks.values().filter(|t| t.deleted).count(),
+            pending_operations: self.operations.len(),
+            counter: self.counter,
+        }
+    }
+
+    /// Sync code (short hash of node_id)
+    pub fn sync_code(&self) -> String {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.node_id.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        // Convert to 6-digit alphanumeric
+        const CHARS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+        let mut result = String::new();
+        let mut num = hash;
+
+        for _ in 0..6 {
+            result.push(CHARS[(num % CHARS.len() as u64) as usize] as char);
+            num /= CHARS.len() as u64;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(node_id: &str) -> CrdtDocument {
+        CrdtDocument::new(node_id.to_string())
+    }
+
+    #[test]
+    fn operations_since_returns_only_the_missing_delta() {
+        let mut room = doc("server");
+        let mut alice = doc("alice");
+        let mut bob = doc("bob");
+
+        alice.upsert_field(1, "title".to_string(), "Write docs".to_string());
+        room.apply_remote_operations(alice.operations().to_vec());
+        bob.apply_remote_operations(room.operations_since(&VersionVector::new()).unwrap());
+        let base = bob.version().clone();
+
+        // Alice and Bob edit concurrently; each has seen only its own edit
+        alice.clear_operations();
+        alice.upsert_field(1, "status".to_string(), "done".to_string());
+        bob.upsert_field(2, "title".to_string(), "Fix login".to_string());
+        room.apply_remote_operations(alice.operations().to_vec());
+        room.apply_remote_operations(bob.operations().to_vec());
+        assert!(!dominates(alice.version(), bob.version()));
+        assert!(!dominates(bob.version(), alice.version()));
+        assert!(dominates(room.version(), alice.version()));
+        assert!(dominates(room.version(), bob.version()));
+
+        assert_eq!(room.operations_since(&base).unwrap().len(), 2);
+        assert!(room.operations_since(room.version()).unwrap().is_empty());
+
+        let for_alice = room.operations_since(alice.version()).unwrap();
+        assert_eq!(for_alice.len(), 1);
+        assert_eq!(for_alice[0].timestamp().node_id, "bob");
+        alice.apply_remote_operations(for_alice);
+
+        let for_bob = room.operations_since(bob.version()).unwrap();
+        assert_eq!(for_bob.len(), 1);
+        assert_eq!(for_bob[0].timestamp().node_id, "alice");
+        bob.apply_remote_operations(for_bob);
+
+        assert_eq!(alice.version(), room.version());
+        assert_eq!(bob.version(), room.version());
+        assert_eq!(alice.tasks().len(), 2);
+        assert_eq!(bob.task(1).unwrap().fields["status"].value, "done");
+    }
+
+    #[test]
+    fn trimmed_history_falls_back_to_the_full_document() {
+        let mut room = doc("server");
+        let mut peer = doc("peer");
+        room.upsert_field(1, "title".to_string(), "v0".to_string());
+        peer.import(&room.export()).unwrap();
+        let behind = peer.version().clone();
+
+        // The oldest operation the peer has is trimmed first, which it doesn't need
+        for n in 1..=HISTORY_LIMIT {
+            room.upsert_field(1, "title".to_string(), format!("v{}", n));
+        }
+        assert_eq!(room.operations_since(&behind).unwrap().len(), HISTORY_LIMIT);
+        room.upsert_field(1, "title".to_string(), "latest".to_string());
+        assert!(room.operations_since(&behind).is_none());
+
+        // Only up to date peers can follow operations; the rest take an export
+        let latest = room.version().clone();
+        assert_eq!(room.operations_since(&latest).unwrap().len(), 0);
+        peer.merge(&room.export()).unwrap();
+        assert_eq!(peer.version(), &latest);
+        assert_eq!(peer.task(1).unwrap().fields["title"].value, "latest");
+
+        // An imported document has no history of its own
+        let mut reloaded = doc("server");
+        reloaded.import(&room.export()).unwrap();
+        assert!(reloaded.operations_since(&behind).is_none());
+        assert!(reloaded.operations_since(&latest).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reapplying_operations_changes_nothing() {
+        let mut alice = doc("alice");
+        alice.upsert_field(1, "title".to_string(), "Write docs".to_string());
+        alice.upsert_field(1, "status".to_string(), "todo".to_string());
+        alice.upsert_field(2, "title".to_string(), "Fix login".to_string());
+        alice.delete_task(2);
+        alice.restore_task(2);
+        let ops = alice.operations().to_vec();
+
+        let mut room = doc("server");
+        assert_eq!(room.apply_remote_operations(ops.clone()).len(), ops.len());
+        let exported = room.export();
+        let version = room.version().clone();
+        let history = room.operations_since(&VersionVector::new()).unwrap().len();
+
+        assert!(room.apply_remote_operations(ops.clone()).is_empty());
+        // Out of order, an older value still loses to the newer one already applied
+        let mut reversed = ops;
+        reversed.reverse();
+        assert!(room.apply_remote_operations(reversed).is_empty());
+        room.merge(&alice.export()).unwrap();
+
+        assert_eq!(room.version(), &version);
+        let after: HashMap<u32, CrdtTask> = serde_json::from_str(&room.export()).unwrap();
+        let before: HashMap<u32, CrdtTask> = serde_json::from_str(&exported).unwrap();
+        assert_eq!(after.len(), before.len());
+        for (id, task) in &before {
+            assert_eq!(after[id].deleted, task.deleted);
+            assert_eq!(after[id].fields.len(), task.fields.len());
+        }
+        assert_eq!(
+            room.operations_since(&VersionVector::new()).unwrap().len(),
+            history
+        );
+    }
+
+    #[test]
+    fn revert_to_rewrites_values_as_newer_edits() {
+        let mut room = doc("server");
+        room.upsert_field(1, "title".to_string(), "Write docs".to_string());
+        room.upsert_field(2, "title".to_string(), "Fix login".to_string());
+        let snapshot = room.export();
+
+        room.upsert_field(1, "title".to_string(), "Changed".to_string());
+        room.upsert_field(3, "title".to_string(), "Added later".to_string());
+        room.delete_task(2);
+        let stale = room.export();
+
+        assert_eq!(room.revert_to(&snapshot).unwrap(), vec![2]);
+        room.merge(&stale).unwrap();
+        assert_eq!(room.task(1).unwrap().fields["title"].value, "Write docs");
+        assert!(room.task(2).is_some());
+        assert!(room.task(3).is_none());
+        assert!(room.revert_to("not json").is_err());
+    }
+
+    #[test]
+    fn timestamps_far_ahead_are_refused() {
+        let mut room = doc("server");
+        room.upsert_field(1, "title".to_string(), "Write docs".to_string());
+        let pinned = LamportTimestamp::new(u64::MAX, "mallory");
+        let forged = |timestamp: LamportTimestamp| {
+            vec![Operation::Update {
+                task_id: 1,
+                field: "title".to_string(),
+                value: "Pinned".to_string(),
+                timestamp,
+            }]
+        };
+
+        assert!(room
+            .apply_remote_operations(forged(pinned.clone()))
+            .is_empty());
+        let mut task = room.task(1).unwrap().clone();
+        task.fields.get_mut("title").unwrap().timestamp = pinned.clone();
+        task.updated_at = pinned;
+        let exported = serde_json::to_string(&HashMap::from([(1, task)])).unwrap();
+        assert!(room.merge(&exported).is_err());
+        assert_eq!(room.task(1).unwrap().fields["title"].value, "Write docs");
+        assert_eq!(room.stats().counter, 1);
+
+        // A counter already at the top stays there rather than wrapping around
+        let mut stored = doc("server");
+        stored.import(&exported).unwrap();
+        stored.upsert_field(1, "status".to_string(), "done".to_string());
+        assert_eq!(stored.stats().counter, u64::MAX);
+
+        let ahead = LamportTimestamp::new(1_000, "mallory");
+        assert_eq!(room.apply_remote_operations(forged(ahead)).len(), 1);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::document::{CrdtDocument, VersionVector};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str);
+}
+
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
+}
+
+/// JavaScript binding for [`CrdtDocument`], exported to JS as `CrdtDocument`
+#[wasm_bindgen(js_name = CrdtDocument)]
+pub struct WasmCrdtDocument {
+    inner: CrdtDocument,
+}
+
+#[wasm_bindgen(js_class = CrdtDocument)]
+impl WasmCrdtDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new(node_id: String) -> Self {
+        #[cfg(feature = "console_error_panic_hook")]
+        console_error_panic_hook::set_once();
+
+        console_log!("CRDT Document created for node: {}", node_id);
+
+        Self {
+            inner: CrdtDocument::new(node_id),
+        }
+    }
+
+    /// Insert or update a task field
+    pub fn upsert_field(&mut self, task_id: u32, field: String, value: String) {
+        let log_field = field.clone();
+        if self.inner.upsert_field(task_id, field, value) {
+            console_log!("Upserted field {} for task {}", log_field, task_id);
+        }
+    }
+
+    /// Delete a task (soft delete)
+    pub fn delete_task(&mut self, task_id: u32) {
+        if self.inner.delete_task(task_id) {
+            console_log!("Deleted task {}", task_id);
+        }
+    }
+
+    /// Bring back a deleted task
+    pub fn restore_task(&mut self, task_id: u32) {
+        if self.inner.restore_task(task_id) {
+            console_log!("Restored task {}", task_id);
+        }
+    }
+
+    /// Get all non-deleted tasks
+    pub fn get_tasks(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.inner.tasks()).unwrap_or(JsValue::NULL)
+    }
+
+    /// Get task by ID
+    pub fn get_task(&self, task_id: u32) -> JsValue {
+        match self.inner.task(task_id) {
+            Some(task) => serde_wasm_bindgen::to_value(task).unwrap_or(JsValue::NULL),
+            None => JsValue::NULL,
+        }
+    }
+
+    /// Merge another document into this one
+    pub fn merge(&mut self, other_json: &str) -> Result<(), JsValue> {
+        self.inner
+            .merge(other_json)
+            .map_err(|e| JsValue::from_str(&e))?;
+        console_log!("Merged document. Total tasks: {}", self.task_count());
+        Ok(())
+    }
+
+    /// Get document state as JSON for syncing
+    pub fn export(&self) -> String {
+        self.inner.export()
+    }
+
+    /// Import document state from JSON
+    pub fn import(&mut self, json: &str) -> Result<(), JsValue> {
+        self.inner.import(json).map_err(|e| JsValue::from_str(&e))?;
+        console_log!("Imported document with {} tasks", self.task_count());
+        Ok(())
+    }
+
+    /// Get operations since last sync
+    pub fn get_operations(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(self.inner.operations()).unwrap_or(JsValue::NULL)
+    }
+
+    /// Apply remote operations
+    pub fn apply_operations(&mut self, ops_json: &str) -> Result<(), JsValue> {
+        self.inner
+            .apply_operations(ops_json)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Get the version vector as JSON (`{"node_id": counter}`)
+    pub fn get_version(&self) -> String {
+        serde_json::to_string(self.inner.version()).unwrap_or_default()
+    }
+
+    /// Get operations a peer at `version_json` is missing, as a JSON array. Returns
+    /// `undefined` when only a full `export` can bring that peer up to date.
+    pub fn get_operations_since(&self, version_json: &str) -> Result<Option<String>, JsValue> {
+        let since: VersionVector = serde_json::from_str(version_json)
+            .map_err(|e| JsValue::from_str(&format!("Parse error: {}", e)))?;
+        Ok(self
+            .inner
+            .operations_since(&since)
+            .map(|ops| serde_json::to_string(&ops).unwrap_or_default()))
+    }
+
+    /// Clear all operations (after successful sync)
+    pub fn clear_operations(&mut self) {
+        self.inner.clear_operations();
+    }
+
+    fn task_count(&self) -> usize {
+        let stats = self.inner.stats();
+        stats.active_tasks + stats.deleted_tasks
+    }
+
+    /// Get document stats
+    pub fn stats(&self) -> JsValue {
+        serde_json::to_value(self.inner.stats())
+            .ok()
+            .and_then(|stats| serde_wasm_bindgen::to_value(&stats).ok())
+            .unwrap_or(JsValue::NULL)
+    }
+
+    /// Generate sync code (short hash of node_id)
+    pub fn get_sync_code(&self) -> String {
+        self.inner.sync_code()
+    }
+}
+
+/// Utility: Generate unique node ID
+/// Takes timestamp from JS (Date.now()) to avoid SystemTime issues in WASM
+#[wasm_bindgen]
+pub fn generate_node_id(timestamp_ms: u32) -> String {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    timestamp_ms.hash(&mut hasher);
+    format!("node_{:x}", hasher.finish())
+}